@@ -0,0 +1,62 @@
+//! Compares [`tokenize_batch`] with tokenizing texts one at a time, both with [`tokenize`],
+//! which loads the tokenizer for every call, and with a loop over the shared tokenizer from
+//! [`singletons`](oairs::tokenizers::singletons), which isolates the gain from tokenizing in
+//! parallel.
+//!
+//! ```sh
+//! cargo bench --bench tokenize
+//! cargo bench --bench tokenize --no-default-features  # without the `parallel` feature
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use oairs::tokenizers::{singletons::cl100k_base_singleton, tokenize, tokenize_batch, Tokenizer};
+
+const PARAGRAPH: &str = "The one who shuts his ears to the cry of the poor will himself also \
+    call out and not be answered. This is a test string to see how it tokenizes, repeated to \
+    make a document about the length of a few paragraphs that might be embedded. ";
+
+fn corpus(n: usize) -> Vec<String> {
+    (0..n)
+        .map(|i| format!("{i}. {}", PARAGRAPH.repeat(8)))
+        .collect()
+}
+
+fn bench_tokenize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+    group.sample_size(10);
+
+    // Loading the tokenizer dominates, so only a few texts are needed to see it.
+    let docs = corpus(10);
+    let texts: Vec<&str> = docs.iter().map(|s| s.as_str()).collect();
+    group.bench_with_input(BenchmarkId::new("each", 10), &texts, |b, texts| {
+        b.iter(|| {
+            texts
+                .iter()
+                .map(|text| tokenize(text, Tokenizer::CL100KBase).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    for n in [100, 1_000] {
+        let docs = corpus(n);
+        let texts: Vec<&str> = docs.iter().map(|s| s.as_str()).collect();
+
+        group.bench_with_input(BenchmarkId::new("sequential", n), &texts, |b, texts| {
+            let bpe = cl100k_base_singleton();
+            let bpe = bpe.lock();
+            b.iter(|| {
+                texts
+                    .iter()
+                    .map(|text| bpe.encode_ordinary(text))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("batch", n), &texts, |b, texts| {
+            b.iter(|| tokenize_batch(texts, Tokenizer::CL100KBase))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_tokenize);
+criterion_main!(benches);
@@ -1,21 +1,35 @@
 //! # save_json
-//! This crate provides a convenience trait, `SaveJson`, that can be derived for structs that
-//! implement `Serialize`. The trait will implement a `save_json` method for the struct, which
-//! takes a `&str` path and uses the `serde_json::to_writer_pretty` method to serialize the struct.
+//! This crate provides the derive macros for the `SaveJson` and `LoadJson` traits defined in
+//! `oairs`. `LoadJson` is the counterpart for structs that implement `Deserialize`, providing
+//! `load_json(path)`, `load_json_from(reader)`, and `from_json_str(s)`.
+//!
+//! Both traits have default implementations for all of their methods, so the `SaveJson` derive
+//! simply emits
+//! `impl ::oairs::SaveJson for T {}` for a struct that implements `Serialize`. This gives the
+//! struct the following methods:
+//!
+//! * `save_json(path)` - Serializes the struct as pretty-printed JSON to the file at `path` (a
+//!   `&str`, `Path`, `PathBuf`, etc.), writing to a temporary file first and renaming it into
+//!   place. Returns the path that was written.
+//! * `save_json_compact(path)` - The same as `save_json`, without pretty-printing.
+//! * `save_json_gz(path)` - Compact JSON, gzip-compressed (with the `gzip` feature of `oairs`).
+//! * `save_json_async(path)` - The same as `save_json`, using `tokio::fs` for the file IO.
+//! * `save_json_to(writer)` - Serializes the struct to any `std::io::Write`.
+//! * `to_json_string_pretty()` - Serializes the struct to a `String`.
 //!
 //! If the path does not end with ".json" it will be appended. If the path includes parents that do not
-//! exist it will try to create them.
+//! exist it will try to create them. A path without parents, like `"results.json"`, is written to
+//! the current directory.
 //!
 //! Fails if it cannot create the path (or its parents) or if serialization fails on
 //! [the specified conditions](https://docs.rs/serde_json/latest/serde_json/fn.to_writer_pretty.html#errors).
-//!  
-//! # Arguments
-//! * `path` - `&str` that represents the path to the file to be created.
 //!
 //! # Example
 //! The following example assumes that we are using the `oairs` library to send a request to the
 //! OpenAI API. The `response` is a `reqwest::Response` with the `json` feature.
 //! ```rust,no_run
+//! use oairs::SaveJson;
+//!
 //! #[derive(Debug, Serialize, Deserialize, SaveJson)]
 //! pub struct Completion {
 //!     pub id: String,
@@ -30,8 +44,8 @@
 //! let completion: Completion = response.json().await?;
 //!
 //! let path = "some/path/completion.json";
-//! match completion.save_json(&path) {
-//!    Ok(_) => println!("Saved completion to {}", path),
+//! match completion.save_json(path) {
+//!    Ok(written) => println!("Saved completion to {}", written.display()),
 //!    Err(e) => println!("Failed to save completion to {}: {}", path, e),
 //! };
 //! ```
@@ -46,66 +60,26 @@ pub fn derive(input: TokenStream) -> TokenStream {
     impl_save_json_macro_derive(&ast)
 }
 
-// TODO: Check this with FileResponse
+#[proc_macro_derive(LoadJson)]
+pub fn derive_load(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    impl_load_json_macro_derive(&ast)
+}
+
 fn impl_save_json_macro_derive(ast: &syn::DeriveInput) -> TokenStream {
     let name = &ast.ident;
-    quote!{
-        impl #name {
-            pub fn save_json(&self, path: &str) -> Result<(), std::io::Error> {
-
-                let path = if path.ends_with(".json") {
-                    path.to_string()
-                } else {
-                    format!("{}.json", path)
-                };
-                let path = std::path::Path::new(&path);
-                let parent = path.parent().unwrap();
-                if !parent.exists() {
-                    match std::fs::create_dir_all(parent) {
-                        Ok(_) => (),
-                        Err(e) => {
-                            // By default, we get the error 'failed to create whole tree', which
-                            // probably isn't very helpful to many users when the error will simply
-                            // point to the function call.
-                            let e = format!(
-                                "Path's parent directory, {}, does not exist and could not be created. Possibly invalid characters or non-existent? {}",
-                                parent.display(),
-                                e
-                            );
-                            return Err(
-                                std::io::Error::new(std::io::ErrorKind::Other, e)
-                            )
-                        },
-                    };
-                }
-
-                // In my testing, `:` in the filename itself won't be caught as an error by
-                // File::create. However, (on Windows at least) a path like "te:st2.json" will create a 
-                // file named `te` and return a handle to a path that doesn't exist, which won't be caught 
-                // by serde_json. The user will end up with an empty file named `te` and no indication
-                // that an error has occured.
-                let filename = path.file_name().unwrap().to_str().unwrap();
-                if filename.contains(":") {
-                    return Err(
-                            std::io::Error::new
-                            (std::io::ErrorKind::InvalidInput, "filename contains invalid character: :")
-                        );
-                }
-
-                let file = match std::fs::File::create(&path) {
-                    Ok(f) => f,
-                    Err(e) => return Err(e),
-                };
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::oairs::SaveJson for #name #ty_generics #where_clause {}
+    }
+    .into()
+}
 
-                match serde_json::to_writer_pretty(&file, &self) {
-                    Ok(_) => Ok(()),
-                    Err(e) => {
-                        println!("Error: {}", e);
-                        let e = std::io::Error::new(std::io::ErrorKind::Other, e.to_string());
-                        return Err(e)
-                    },
-                }
-            }
-        }
-    }.into()
+fn impl_load_json_macro_derive(ast: &syn::DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::oairs::LoadJson for #name #ty_generics #where_clause {}
+    }
+    .into()
 }
@@ -0,0 +1,356 @@
+//! Splitting WAV files that are too large to transcribe in one request, and stitching the
+//! transcriptions of the chunks back together.
+//!
+//! The API rejects audio files larger than [`MAX_FILE_SIZE`]. [`split_wav`] slices a PCM WAV
+//! file into complete WAV files of at most a given duration (or [`split_wav_to_size`], at most
+//! a given size), optionally cutting at the quietest point near each boundary so words aren't
+//! cut in half. Each [`AudioChunk`] records where it starts in the original, which
+//! [`VerboseTranscription::stitch`] uses to correct the timestamps of the chunks'
+//! transcriptions.
+//!
+//! Other formats (e.g. mp3) have to be converted to WAV first, e.g. with
+//! `ffmpeg -i input.mp3 -ac 1 -ar 16000 output.wav`.
+//!
+//! # Example
+//! ```rust,no_run
+//! let wav = std::fs::read("interview.wav")?;
+//! let chunks = split_wav_to_size(&wav, MAX_FILE_SIZE, Some(Duration::from_secs(5)))?;
+//!
+//! let mut parts = Vec::new();
+//! for chunk in &chunks {
+//!     let transcription: VerboseTranscription = transcribe(&chunk.wav).await?;
+//!     parts.push((chunk.offset, transcription));
+//! }
+//! VerboseTranscription::stitch(parts).save_srt("interview.srt")?;
+//! ```
+
+use std::time::Duration;
+
+use super::{response::VerboseTranscription, *};
+
+/// The largest audio file the API accepts, 25 MB.
+pub const MAX_FILE_SIZE: u64 = 25 * 1024 * 1024;
+
+// The length of the windows compared when searching for the quietest point to cut at.
+const SILENCE_WINDOW: Duration = Duration::from_millis(20);
+
+/// A slice of a WAV file, itself a complete WAV file.
+#[derive(Debug, Clone)]
+pub struct AudioChunk {
+    /// Where the chunk starts in the original file, in seconds.
+    pub offset: f64,
+    /// In seconds.
+    pub duration: f64,
+    pub wav: Vec<u8>,
+}
+
+impl AudioChunk {
+    pub fn save(&self, path: &str) -> Result<(), std::io::Error> {
+        std::fs::write(path, &self.wav)
+    }
+}
+
+/// Splits a PCM WAV file into chunks of at most `max_duration`.
+///
+/// With `silence_search`, each chunk (but the last) ends at the quietest point within
+/// `silence_search` of `max_duration`, rather than exactly at it. The search needs 16-bit
+/// samples; for other sample formats the chunks are cut at `max_duration`.
+///
+/// Returns a `ParseError` if `wav` isn't a WAV file, and a `ParamError` if `max_duration` is
+/// shorter than a sample.
+pub fn split_wav(
+    wav: &[u8],
+    max_duration: Duration,
+    silence_search: Option<Duration>,
+) -> Result<Vec<AudioChunk>, OairsError> {
+    let wav = Wav::parse(wav)?;
+    let max_frames = wav.frames_in(max_duration);
+    if max_frames == 0 {
+        return Err(OairsError::new(
+            "max_duration is shorter than a sample".to_string(),
+            ErrorType::ParamError,
+            Some("max_duration".to_string()),
+            None,
+        ));
+    }
+    let search_frames = silence_search
+        .filter(|_| wav.bits_per_sample == 16)
+        .map(|search| wav.frames_in(search).min(max_frames - 1));
+
+    let total_frames = wav.data.len() / wav.block_align;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < total_frames {
+        let mut end = (start + max_frames).min(total_frames);
+        if let (Some(search), true) = (search_frames, end < total_frames) {
+            end = wav.quietest_frame(end - search, end);
+        }
+        chunks.push(wav.chunk(start, end));
+        start = end;
+    }
+    Ok(chunks)
+}
+
+/// Splits a PCM WAV file into chunks of at most `max_bytes` (e.g. [`MAX_FILE_SIZE`]). See
+/// [`split_wav`].
+pub fn split_wav_to_size(
+    wav: &[u8],
+    max_bytes: u64,
+    silence_search: Option<Duration>,
+) -> Result<Vec<AudioChunk>, OairsError> {
+    let parsed = Wav::parse(wav)?;
+    let header = parsed.chunk(0, 0).wav.len() as u64;
+    let max_frames = max_bytes.saturating_sub(header) / parsed.block_align as u64;
+    let max_duration = Duration::from_secs_f64(max_frames as f64 / parsed.sample_rate as f64);
+    split_wav(wav, max_duration, silence_search)
+}
+
+/// Splits the PCM WAV file at `path`. See [`split_wav`].
+pub fn split_wav_file(
+    path: &str,
+    max_duration: Duration,
+    silence_search: Option<Duration>,
+) -> Result<Vec<AudioChunk>, OairsError> {
+    let wav = std::fs::read(path)?;
+    split_wav(&wav, max_duration, silence_search)
+}
+
+impl VerboseTranscription {
+    /// Joins the transcriptions of consecutive [`AudioChunk`]s, each paired with the chunk's
+    /// `offset`, into one. The offset is added to the timestamps of each chunk's segments, and
+    /// the segments are renumbered. The language and task are those of the first chunk.
+    pub fn stitch<I>(parts: I) -> VerboseTranscription
+    where
+        I: IntoIterator<Item = (f64, VerboseTranscription)>,
+    {
+        let mut stitched: Option<VerboseTranscription> = None;
+        for (offset, part) in parts {
+            let stitched = stitched.get_or_insert_with(|| VerboseTranscription {
+                task: part.task.clone(),
+                language: part.language.clone(),
+                duration: 0.0,
+                text: String::new(),
+                segments: Vec::new(),
+            });
+            let text = part.text.trim();
+            if !text.is_empty() {
+                if !stitched.text.is_empty() {
+                    stitched.text.push(' ');
+                }
+                stitched.text.push_str(text);
+            }
+            for mut segment in part.segments {
+                segment.id = stitched.segments.len() as u32;
+                segment.start += offset;
+                segment.end += offset;
+                stitched.segments.push(segment);
+            }
+            stitched.duration = stitched.duration.max(offset + part.duration);
+        }
+        stitched.unwrap_or(VerboseTranscription {
+            task: None,
+            language: String::new(),
+            duration: 0.0,
+            text: String::new(),
+            segments: Vec::new(),
+        })
+    }
+}
+
+// The parts of a WAV file needed to slice it.
+struct Wav<'a> {
+    fmt: &'a [u8],
+    data: &'a [u8],
+    channels: usize,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    block_align: usize,
+}
+
+impl<'a> Wav<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Wav<'a>, OairsError> {
+        let error =
+            |message: &str| OairsError::new(message.to_string(), ErrorType::ParseError, None, None);
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(error("Not a WAV file"));
+        }
+
+        let (mut fmt, mut data) = (None, None);
+        let mut pos = 12;
+        while pos + 8 <= bytes.len() {
+            let id = &bytes[pos..pos + 4];
+            let size = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let body = &bytes[pos + 8..(pos + 8 + size).min(bytes.len())];
+            match id {
+                b"fmt " => fmt = Some(body),
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            // Chunks are padded to an even size.
+            pos += 8 + size + size % 2;
+        }
+
+        let fmt = fmt
+            .filter(|f| f.len() >= 16)
+            .ok_or(error("WAV file has no fmt chunk"))?;
+        let data = data.ok_or(error("WAV file has no data chunk"))?;
+        let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+        let wav = Wav {
+            fmt,
+            data,
+            channels: u16_at(2) as usize,
+            sample_rate: u32::from_le_bytes(fmt[4..8].try_into().unwrap()),
+            bits_per_sample: u16_at(14),
+            block_align: u16_at(12) as usize,
+        };
+        if wav.channels == 0 || wav.sample_rate == 0 || wav.block_align == 0 {
+            return Err(error("Invalid WAV format"));
+        }
+        Ok(wav)
+    }
+
+    fn frames_in(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * self.sample_rate as f64) as usize
+    }
+
+    // The start of the quietest window between the frames `from` and `to`, or `to` if there's
+    // no room for a window.
+    fn quietest_frame(&self, from: usize, to: usize) -> usize {
+        let window = self.frames_in(SILENCE_WINDOW).max(1);
+        let mut quietest = (u64::MAX, to);
+        let mut frame = from.max(1);
+        while frame + window <= to {
+            let bytes = &self.data[frame * self.block_align..(frame + window) * self.block_align];
+            let loudness: u64 = bytes
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]).unsigned_abs() as u64)
+                .sum();
+            if loudness < quietest.0 {
+                quietest = (loudness, frame);
+            }
+            frame += window;
+        }
+        quietest.1
+    }
+
+    fn chunk(&self, start: usize, end: usize) -> AudioChunk {
+        let data = &self.data[start * self.block_align..end * self.block_align];
+        let fmt_padding = self.fmt.len() % 2;
+        let riff_size = 4 + 8 + self.fmt.len() + fmt_padding + 8 + data.len();
+
+        let mut wav = Vec::with_capacity(8 + riff_size);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(riff_size as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&(self.fmt.len() as u32).to_le_bytes());
+        wav.extend_from_slice(self.fmt);
+        wav.resize(wav.len() + fmt_padding, 0);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(data);
+
+        let rate = self.sample_rate as f64;
+        AudioChunk {
+            offset: start as f64 / rate,
+            duration: (end - start) as f64 / rate,
+            wav,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::response::TranscriptionSegment, *};
+
+    // A mono 16-bit WAV of `samples` at 1 kHz.
+    fn wav(samples: &[i16]) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        for field in [1u16, 1] {
+            fmt.extend_from_slice(&field.to_le_bytes());
+        }
+        fmt.extend_from_slice(&1000u32.to_le_bytes());
+        fmt.extend_from_slice(&2000u32.to_le_bytes());
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+
+        let wav = Wav {
+            fmt: &fmt,
+            data: &data,
+            channels: 1,
+            sample_rate: 1000,
+            bits_per_sample: 16,
+            block_align: 2,
+        };
+        wav.chunk(0, samples.len()).wav
+    }
+
+    #[test]
+    fn split() {
+        // 2.5 seconds, silent from 1.7 to 1.8 seconds.
+        let samples: Vec<i16> = (0..2500)
+            .map(|i| if (1700..1800).contains(&i) { 0 } else { 1000 })
+            .collect();
+        let original = wav(&samples);
+
+        let chunks = split_wav(&original, Duration::from_secs(1), None).unwrap();
+        assert_eq!(
+            chunks.iter().map(|c| c.offset).collect::<Vec<_>>(),
+            vec![0.0, 1.0, 2.0]
+        );
+        assert_eq!(chunks[2].duration, 0.5);
+        let data: Vec<u8> = chunks
+            .iter()
+            .flat_map(|c| Wav::parse(&c.wav).unwrap().data.to_vec())
+            .collect();
+        assert_eq!(data, Wav::parse(&original).unwrap().data);
+
+        let chunks = split_wav(
+            &original,
+            Duration::from_secs(2),
+            Some(Duration::from_millis(500)),
+        )
+        .unwrap();
+        assert_eq!(chunks[1].offset, 1.7);
+
+        let chunks = split_wav_to_size(&original, 2044, None).unwrap();
+        assert!(chunks.iter().all(|c| c.wav.len() <= 2044));
+        assert_eq!(chunks.len(), 3);
+
+        assert!(split_wav(b"not a wav", Duration::from_secs(1), None).is_err());
+    }
+
+    #[test]
+    fn stitch() {
+        let part = |text: &str, duration: f64| VerboseTranscription {
+            task: Some("transcribe".to_string()),
+            language: "english".to_string(),
+            duration,
+            text: text.to_string(),
+            segments: vec![TranscriptionSegment {
+                id: 0,
+                seek: 0,
+                start: 0.5,
+                end: duration,
+                text: text.to_string(),
+                tokens: vec![],
+                temperature: 0.0,
+                avg_logprob: -0.2,
+                compression_ratio: 1.0,
+                no_speech_prob: 0.0,
+            }],
+        };
+
+        let stitched = VerboseTranscription::stitch(vec![
+            (0.0, part(" One.", 10.0)),
+            (10.0, part("Two.", 4.0)),
+        ]);
+
+        assert_eq!(stitched.text, "One. Two.");
+        assert_eq!(stitched.duration, 14.0);
+        assert_eq!(stitched.segments[1].id, 1);
+        assert_eq!(stitched.segments[1].start, 10.5);
+        assert_eq!(stitched.segments[1].end, 14.0);
+    }
+}
@@ -1,3 +1,8 @@
+mod chunk;
+pub mod response;
+
+pub use self::chunk::*;
+
 use crate::error::{ErrorType, OairsError};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -0,0 +1,199 @@
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::{LoadJson, SaveJson};
+
+/// The format of a transcription or translation. The default is `json`, which deserializes
+/// into a [`Transcription`]; `verbose_json` deserializes into a [`VerboseTranscription`]. The
+/// other formats are returned as plain text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionFormat {
+    #[default]
+    Json,
+    VerboseJson,
+    Text,
+    Srt,
+    Vtt,
+}
+
+impl std::fmt::Display for TranscriptionFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+impl Serialize for TranscriptionFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_str())
+    }
+}
+
+impl TranscriptionFormat {
+    pub fn to_str(&self) -> &str {
+        match self {
+            TranscriptionFormat::Json => "json",
+            TranscriptionFormat::VerboseJson => "verbose_json",
+            TranscriptionFormat::Text => "text",
+            TranscriptionFormat::Srt => "srt",
+            TranscriptionFormat::Vtt => "vtt",
+        }
+    }
+
+    /// The file extension for a response in this format, e.g. `srt`.
+    pub fn extension(&self) -> &str {
+        match self {
+            TranscriptionFormat::Json | TranscriptionFormat::VerboseJson => "json",
+            TranscriptionFormat::Text => "txt",
+            TranscriptionFormat::Srt => "srt",
+            TranscriptionFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// A transcription or translation in the `json` format.
+#[derive(Debug, Clone, Serialize, Deserialize, SaveJson, LoadJson)]
+pub struct Transcription {
+    pub text: String,
+}
+
+/// A transcription or translation in the `verbose_json` format, with timestamped segments.
+#[derive(Debug, Clone, Serialize, Deserialize, SaveJson, LoadJson)]
+pub struct VerboseTranscription {
+    #[serde(default)]
+    pub task: Option<String>,
+    pub language: String,
+    /// In seconds.
+    pub duration: f64,
+    pub text: String,
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptionSegment {
+    pub id: u32,
+    #[serde(default)]
+    pub seek: u64,
+    /// In seconds.
+    pub start: f64,
+    /// In seconds.
+    pub end: f64,
+    pub text: String,
+    #[serde(default)]
+    pub tokens: Vec<usize>,
+    #[serde(default)]
+    pub temperature: f64,
+    pub avg_logprob: f64,
+    #[serde(default)]
+    pub compression_ratio: f64,
+    #[serde(default)]
+    pub no_speech_prob: f64,
+}
+
+impl VerboseTranscription {
+    /// The segments as SubRip (`.srt`) subtitles.
+    pub fn to_srt(&self) -> String {
+        let mut srt = String::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            let _ = write!(
+                srt,
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                timestamp(segment.start, ','),
+                timestamp(segment.end, ','),
+                segment.text.trim()
+            );
+        }
+        srt
+    }
+
+    /// The segments as WebVTT (`.vtt`) subtitles.
+    pub fn to_vtt(&self) -> String {
+        let mut vtt = String::from("WEBVTT\n\n");
+        for segment in &self.segments {
+            let _ = write!(
+                vtt,
+                "{} --> {}\n{}\n\n",
+                timestamp(segment.start, '.'),
+                timestamp(segment.end, '.'),
+                segment.text.trim()
+            );
+        }
+        vtt
+    }
+
+    /// Save the segments as SubRip subtitles (see [`to_srt`](Self::to_srt)) to `path`. If the
+    /// path includes parents that do not exist it will try to create them.
+    pub fn save_srt(&self, path: &str) -> Result<(), std::io::Error> {
+        save(path, self.to_srt())
+    }
+
+    /// Save the segments as WebVTT subtitles (see [`to_vtt`](Self::to_vtt)) to `path`. If the
+    /// path includes parents that do not exist it will try to create them.
+    pub fn save_vtt(&self, path: &str) -> Result<(), std::io::Error> {
+        save(path, self.to_vtt())
+    }
+}
+
+fn save(path: &str, content: String) -> Result<(), std::io::Error> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)
+}
+
+// `HH:MM:SS,mmm` for SRT and `HH:MM:SS.mmm` for VTT.
+fn timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERBOSE: &str = r#"{
+        "task": "transcribe",
+        "language": "english",
+        "duration": 3725.5,
+        "text": "Hello there. General Kenobi.",
+        "segments": [
+            {"id": 0, "seek": 0, "start": 0.0, "end": 2.5, "text": " Hello there.",
+             "tokens": [50364, 2425], "temperature": 0.0, "avg_logprob": -0.25,
+             "compression_ratio": 0.8, "no_speech_prob": 0.01},
+            {"id": 1, "seek": 0, "start": 3723.25, "end": 3725.5, "text": " General Kenobi.",
+             "tokens": [6996], "temperature": 0.0, "avg_logprob": -0.3,
+             "compression_ratio": 0.8, "no_speech_prob": 0.02}
+        ]
+    }"#;
+
+    #[test]
+    fn subtitles() {
+        let transcription = VerboseTranscription::from_json_str(VERBOSE).unwrap();
+
+        assert_eq!(
+            transcription.to_srt(),
+            "1\n00:00:00,000 --> 00:00:02,500\nHello there.\n\n\
+             2\n01:02:03,250 --> 01:02:05,500\nGeneral Kenobi.\n\n"
+        );
+        assert_eq!(
+            transcription.to_vtt(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nHello there.\n\n\
+             01:02:03.250 --> 01:02:05.500\nGeneral Kenobi.\n\n"
+        );
+        assert_eq!(
+            serde_json::to_string(&TranscriptionFormat::VerboseJson).unwrap(),
+            "\"verbose_json\""
+        );
+    }
+}
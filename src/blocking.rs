@@ -0,0 +1,478 @@
+//! A blocking [`Client`], for programs that don't run an async runtime, e.g. CLI tools and build
+//! scripts. Requires the `blocking` feature.
+//!
+//! The blocking client has the same methods as the async [`Client`](crate::client::Client) and
+//! returns the same builders, wrapped in a [`Request`] whose `send` blocks until the response
+//! arrives. The responses deserialize into the same response structs.
+//!
+//! # Example
+//! ```rust,no_run
+//! use oairs::blocking::Client;
+//!
+//! let client = Client::new(key);
+//! let messages = Messages::new(vec![Msg::user("Hello!")]);
+//! let completion: ChatCompletion = client
+//!     .chat_completion(ChatModel::default(), &messages)
+//!     .configure(|chat| {
+//!         chat.max_tokens(100);
+//!     })
+//!     .send_parsed()?;
+//! ```
+
+use std::ops::{Deref, DerefMut};
+
+use reqwest::{blocking::multipart::Form, header::AUTHORIZATION};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    client::{auth_header, bearer, HttpMethod, PreparedBody, PreparedRequest},
+    completions::{ChatBuilder, CompletionBuilder, Messages},
+    credentials::KeyRing,
+    edits::EditBuilder,
+    embeddings::EmbeddingBuilder,
+    files::ListFilesBuilder,
+    fine_tunes::{FineTunesBuilder, ListEventsBuilder},
+    images::{ImageBuilder, ImageEdit, ImageGen, ImageInput, ImageVariation},
+    meta::parse_body,
+    Cancel, Delete, Gettable, Keyed,
+};
+
+use super::*;
+
+// The fields of a multipart form whose values are the paths of files to upload.
+const FILE_FIELDS: [&str; 3] = ["file", "image", "mask"];
+
+/// A builder (or, for requests without parameters, a `Client<Gettable>` etc.) whose request can
+/// be sent by the blocking [`Client`]. Implemented with the builder's `dry_run`.
+pub trait Prepare {
+    fn prepare(&self) -> Result<PreparedRequest, OairsError>;
+}
+
+macro_rules! impl_prepare {
+    ($($typ:ty),+ $(,)?) => {
+        $(
+            impl Prepare for $typ {
+                fn prepare(&self) -> Result<PreparedRequest, OairsError> {
+                    self.dry_run()
+                }
+            }
+        )+
+    };
+}
+
+impl_prepare!(
+    ChatBuilder<Sendable>,
+    CompletionBuilder<Sendable>,
+    EditBuilder<Sendable>,
+    EmbeddingBuilder<Sendable>,
+    FineTunesBuilder<'_, Sendable>,
+    ModerationBuilder<Sendable>,
+    ImageBuilder<ImageGen>,
+    ListFilesBuilder<Sendable>,
+    ListEventsBuilder<Sendable>,
+    crate::client::Client<Gettable>,
+    crate::client::Client<Delete>,
+    crate::client::Client<Cancel>,
+);
+
+// The blocking client uploads the files named in the prepared form, so an image in memory can't
+// be sent.
+macro_rules! impl_prepare_image {
+    ($($typ:ty),+ $(,)?) => {
+        $(
+            impl Prepare for $typ {
+                fn prepare(&self) -> Result<PreparedRequest, OairsError> {
+                    if self.image().is_in_memory() {
+                        return Err(in_memory_image());
+                    }
+                    self.dry_run()
+                }
+            }
+        )+
+    };
+}
+
+impl_prepare_image!(ImageBuilder<ImageEdit>, ImageBuilder<ImageVariation>);
+
+impl Prepare for crate::client::Client<Sendable> {
+    fn prepare(&self) -> Result<PreparedRequest, OairsError> {
+        if self.is_in_memory() {
+            return Err(OairsError::new(
+                "The blocking client can only upload a file from its path, not from memory"
+                    .to_string(),
+                ErrorType::ParamError,
+                Some("file".to_string()),
+                None,
+            ));
+        }
+        self.dry_run()
+    }
+}
+
+fn in_memory_image() -> OairsError {
+    OairsError::new(
+        "The blocking client can only upload an image from a file, not an ImageInput::Png"
+            .to_string(),
+        ErrorType::ParamError,
+        Some("image".to_string()),
+        None,
+    )
+}
+
+/// The blocking counterpart of the async [`Client`](crate::client::Client). See the
+/// [module docs](self).
+///
+/// Requests are sent as the builder's `dry_run` prepares them, so the options that act when the
+/// async `send` runs (a [`ResponseCache`](crate::cache::ResponseCache), a token budget,
+/// [`archive_to`](ChatBuilder::archive_to), and [`save_raw_to`](ChatBuilder::save_raw_to))
+/// don't apply.
+#[derive(Debug)]
+pub struct Client {
+    inner: crate::client::Client<Keyed>,
+    http: reqwest::blocking::Client,
+}
+
+impl From<crate::client::Client<Keyed>> for Client {
+    /// Send the requests of an async client, with its key (or key ring) and base url, in
+    /// blocking mode.
+    fn from(inner: crate::client::Client<Keyed>) -> Self {
+        let user_agent = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+        let http = reqwest::blocking::Client::builder()
+            .user_agent(user_agent)
+            .build()
+            .expect("the blocking client's TLS backend should initialize");
+        Client { inner, http }
+    }
+}
+
+impl Client {
+    pub fn new<K: Into<String> + std::fmt::Debug>(key: K) -> Client {
+        Client::from(crate::client::Client::new(key))
+    }
+
+    /// See [`Client::with_key_ring`](crate::client::Client::with_key_ring).
+    pub fn with_key_ring(key_ring: KeyRing) -> Client {
+        Client::from(crate::client::Client::with_key_ring(key_ring))
+    }
+
+    /// See [`Client::with_base_url`](crate::client::Client::with_base_url).
+    pub fn with_base_url<B: Into<String>>(mut self, base_url: B) -> Client {
+        self.inner = self.inner.with_base_url(base_url);
+        self
+    }
+
+    /// The async client the requests are built with.
+    pub fn inner(&self) -> &crate::client::Client<Keyed> {
+        &self.inner
+    }
+
+    pub fn chat_completion(
+        &self,
+        model: ChatModel,
+        msgs: &Messages,
+    ) -> Request<'_, ChatBuilder<Sendable>> {
+        self.request(self.inner.chat_completion(model, msgs))
+    }
+
+    pub fn completion(&self, model: CompletionModel) -> Request<'_, CompletionBuilder<Sendable>> {
+        self.request(self.inner.completion(model))
+    }
+
+    pub fn create_edit<I: Into<String>>(
+        &self,
+        model: EditModel,
+        instruction: I,
+    ) -> Request<'_, EditBuilder<Sendable>> {
+        self.request(self.inner.create_edit(model, instruction))
+    }
+
+    pub fn create_embeddings<T>(
+        &self,
+        model: EmbeddingModel,
+        inputs: &[T],
+    ) -> Request<'_, EmbeddingBuilder<Sendable>>
+    where
+        T: Into<String> + std::fmt::Display,
+    {
+        self.request(self.inner.create_embeddings(model, inputs))
+    }
+
+    pub fn create_fine_tune<'a>(
+        &self,
+        training_file_id: &'a str,
+    ) -> Request<'_, FineTunesBuilder<'a, Sendable>> {
+        self.request(self.inner.create_fine_tune(training_file_id))
+    }
+
+    pub fn create_moderation<S: Into<String>>(
+        &self,
+        input: S,
+    ) -> Request<'_, ModerationBuilder<Sendable>> {
+        self.request(self.inner.create_moderation(input))
+    }
+
+    pub fn create_moderations(
+        &self,
+        inputs: Vec<String>,
+    ) -> Request<'_, ModerationBuilder<Sendable>> {
+        self.request(self.inner.create_moderations(inputs))
+    }
+
+    pub fn create_image<P: Into<String>>(&self, prompt: P) -> Request<'_, ImageBuilder<ImageGen>> {
+        self.request(self.inner.create_image(prompt))
+    }
+
+    /// The image must be a file: sending an in-memory [`ImageInput`] returns a `ParamError`.
+    pub fn create_image_edit<I, P>(
+        &self,
+        image: I,
+        prompt: P,
+    ) -> Request<'_, ImageBuilder<ImageEdit>>
+    where
+        I: Into<ImageInput>,
+        P: Into<String>,
+    {
+        self.request(self.inner.create_image_edit(image, prompt))
+    }
+
+    /// The image must be a file: sending an in-memory [`ImageInput`] returns a `ParamError`.
+    pub fn create_image_variation<I>(&self, image: I) -> Request<'_, ImageBuilder<ImageVariation>>
+    where
+        I: Into<ImageInput>,
+    {
+        self.request(self.inner.create_image_variation(image))
+    }
+
+    pub fn upload_file<F: Into<String>>(
+        &self,
+        file: F,
+        purpose: Purpose,
+    ) -> Request<'_, crate::client::Client<Sendable>> {
+        self.request(self.inner.upload_file(file, purpose))
+    }
+
+    pub fn list_files(&self) -> Request<'_, ListFilesBuilder<Sendable>> {
+        self.request(self.inner.list_files())
+    }
+
+    pub fn retrieve_file(&self, file_id: &str) -> Request<'_, crate::client::Client<Gettable>> {
+        self.request(self.inner.retrieve_file(file_id))
+    }
+
+    pub fn retrieve_file_content(
+        &self,
+        file_id: &str,
+    ) -> Request<'_, crate::client::Client<Gettable>> {
+        self.request(self.inner.retrieve_file_content(file_id))
+    }
+
+    pub fn delete_file(&self, file_id: &str) -> Request<'_, crate::client::Client<Delete>> {
+        self.request(self.inner.delete_file(file_id))
+    }
+
+    pub fn list_fine_tunes(&self) -> Request<'_, crate::client::Client<Gettable>> {
+        self.request(self.inner.list_fine_tunes())
+    }
+
+    pub fn retrieve_fine_tune_info(
+        &self,
+        fine_tune_id: &str,
+    ) -> Request<'_, crate::client::Client<Gettable>> {
+        self.request(self.inner.retrieve_fine_tune_info(fine_tune_id))
+    }
+
+    pub fn list_fine_tune_events(
+        &self,
+        fine_tune_id: &str,
+    ) -> Request<'_, ListEventsBuilder<Sendable>> {
+        self.request(self.inner.list_fine_tune_events(fine_tune_id))
+    }
+
+    pub fn cancel_fine_tune(
+        &self,
+        fine_tune_id: &str,
+    ) -> Request<'_, crate::client::Client<Cancel>> {
+        self.request(self.inner.cancel_fine_tune(fine_tune_id))
+    }
+
+    pub fn delete_fine_tune_model(
+        &self,
+        model: &str,
+    ) -> Request<'_, crate::client::Client<Delete>> {
+        self.request(self.inner.delete_fine_tune_model(model))
+    }
+
+    pub fn list_models(&self) -> Request<'_, crate::client::Client<Gettable>> {
+        self.request(self.inner.list_models())
+    }
+
+    pub fn retrieve_model<R>(&self, model: &R) -> Request<'_, crate::client::Client<Gettable>>
+    where
+        R: RetrievableModel,
+    {
+        self.request(self.inner.retrieve_model(model))
+    }
+
+    fn request<B: Prepare>(&self, builder: B) -> Request<'_, B> {
+        Request {
+            client: self,
+            builder,
+        }
+    }
+
+    fn execute(
+        &self,
+        prepared: PreparedRequest,
+    ) -> Result<reqwest::blocking::Response, OairsError> {
+        let key = auth_header(&bearer(prepared.key.as_str()))?;
+
+        let request = match prepared.method {
+            HttpMethod::Get => self.http.get(&prepared.url),
+            HttpMethod::Post => self.http.post(&prepared.url),
+            HttpMethod::Delete => self.http.delete(&prepared.url),
+        }
+        .header(AUTHORIZATION, key);
+
+        let request = match prepared.body {
+            PreparedBody::Empty => request,
+            PreparedBody::Json(json) => request.json(&json),
+            PreparedBody::Form(fields) => {
+                let mut form = Form::new();
+                for (name, value) in fields {
+                    form = match FILE_FIELDS.contains(&name.as_str()) {
+                        true => form.file(name, &value).map_err(|e| {
+                            OairsError::new(
+                                format!("Unable to read file: {e}"),
+                                ErrorType::FileError,
+                                Some(value.clone()),
+                                None,
+                            )
+                        })?,
+                        false => form.text(name, value),
+                    };
+                }
+                request.multipart(form)
+            }
+        };
+
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(e) => return Err(parse_reqwest_error(e)),
+        };
+        let status = response.status();
+        if status == reqwest::StatusCode::OK {
+            return Ok(response);
+        }
+        let headers = response.headers().clone();
+        let body = response.text().unwrap_or_default();
+        Err(parse_api_error_body(&body, status, &headers))
+    }
+}
+
+/// A request built by the blocking [`Client`]. Derefs to the builder, so its parameters are set
+/// as they are for the async client, either on a binding or in
+/// [`configure`](Request::configure):
+///
+/// ```rust,no_run
+/// let mut request = client.completion(CompletionModel::default());
+/// request.prompt("Say this is a test").max_tokens(7);
+/// let completion: Completion = request.send_parsed()?;
+/// ```
+///
+/// Note that a chain of the builder's setters ends on the builder itself, whose `send` is async.
+#[derive(Debug)]
+pub struct Request<'c, B> {
+    client: &'c Client,
+    builder: B,
+}
+
+impl<B> Deref for Request<'_, B> {
+    type Target = B;
+
+    fn deref(&self) -> &B {
+        &self.builder
+    }
+}
+
+impl<B> DerefMut for Request<'_, B> {
+    fn deref_mut(&mut self) -> &mut B {
+        &mut self.builder
+    }
+}
+
+impl<'c, B: Prepare> Request<'c, B> {
+    /// Set the builder's parameters in `f`, keeping the request chainable.
+    pub fn configure<F: FnOnce(&mut B)>(mut self, f: F) -> Self {
+        f(&mut self.builder);
+        self
+    }
+
+    /// Sends the request, blocking until the response arrives.
+    pub fn send(&self) -> Result<reqwest::blocking::Response, OairsError> {
+        self.client.execute(self.builder.prepare()?)
+    }
+
+    /// Sends the request and deserializes the response into `T`, e.g. a
+    /// [`ChatCompletion`](crate::completions::response::ChatCompletion). As with the builders'
+    /// `send_parsed`, a body that doesn't match `T` is an error with the status and the start of
+    /// the body.
+    pub fn send_parsed<T: DeserializeOwned>(&self) -> Result<T, OairsError> {
+        let response = self.send()?;
+        let status = response.status();
+        match response.bytes() {
+            Ok(body) => parse_body(status, &body),
+            Err(e) => Err(parse_reqwest_error(e)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::{
+        client::HttpMethod,
+        completions::{response::ChatCompletion, Messages, Msg},
+        models::{response::ModelsList, ChatModel},
+        test_util::{fixtures::RATE_LIMIT_ERROR, MockApi, TEST_API_KEY},
+    };
+
+    #[tokio::test]
+    async fn blocking_client() {
+        let api = MockApi::start().await;
+        api.mock_error(HttpMethod::Get, "/files/file-bad", 429, RATE_LIMIT_ERROR)
+            .await;
+
+        let base_url = api.base_url();
+        tokio::task::spawn_blocking(move || {
+            let client = crate::blocking::Client::new(TEST_API_KEY).with_base_url(base_url);
+            let models: ModelsList = client.list_models().send_parsed().unwrap();
+            assert_eq!(models.data.len(), 2);
+
+            let messages = Messages::new(vec![Msg::user("Test")]);
+            let completion: ChatCompletion = client
+                .chat_completion(ChatModel::default(), &messages)
+                .configure(|chat| {
+                    chat.max_tokens(5);
+                })
+                .send_parsed()
+                .unwrap();
+            assert_eq!(
+                completion.response_message().content(),
+                "This is a test response."
+            );
+
+            let error = client.retrieve_file("file-bad").send().unwrap_err();
+            assert!(error.is_rate_limit());
+        })
+        .await
+        .unwrap();
+
+        let requests = api.requests("/chat/completions").await;
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["max_tokens"], 5);
+        let authorization = requests[0].headers.get(&"authorization".into()).unwrap();
+        assert_eq!(
+            authorization.last().as_str(),
+            format!("Bearer {TEST_API_KEY}")
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Contains the opt-in [`TokenBudget`], which lets a [`Client`](crate::client::Client) hold
+//! back requests that would exceed a rate of tokens, rather than sending them to be rejected
+//! with a `429`. See [`Client::with_budget`](crate::client::Client::with_budget).
+
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use super::*;
+
+/// The rate of tokens a [`TokenBudget`] allows, and what happens to a request that would
+/// exceed it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    tokens: usize,
+    per: Duration,
+    reject: bool,
+}
+
+impl Budget {
+    /// Allow `tokens` per minute, e.g. the tokens-per-minute rate limit of your account.
+    pub fn tokens_per_minute(tokens: usize) -> Budget {
+        Budget::tokens_per(tokens, Duration::from_secs(60))
+    }
+
+    /// Allow `tokens` within any window of `per`.
+    pub fn tokens_per(tokens: usize, per: Duration) -> Budget {
+        Budget {
+            tokens,
+            per,
+            reject: false,
+        }
+    }
+
+    /// Return a `RateLimit` error for a request that would exceed the budget, instead of
+    /// waiting until it fits.
+    pub fn reject(mut self) -> Self {
+        self.reject = true;
+        self
+    }
+
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    pub fn per(&self) -> Duration {
+        self.per
+    }
+}
+
+/// A sliding window of the tokens spent by requests, shared by the builders of a
+/// [`Client`](crate::client::Client). Before a chat, completion, edit, or embedding request is
+/// sent, its tokens are estimated (the prompt's tokens plus `max_tokens` for each choice; see
+/// e.g. [`ChatBuilder::estimated_tokens`](crate::completions::ChatBuilder::estimated_tokens)).
+/// If spending them would exceed the [`Budget`], the request waits until enough of the earlier
+/// requests have left the window, or is rejected if the budget was set to
+/// [`reject`](Budget::reject).
+///
+/// The estimate is made on the client, so it complements rather than replaces the handling of
+/// `429` responses, e.g. with [`OairsError::is_rate_limit`].
+///
+/// Cloning a `TokenBudget` is cheap, and the clones share their window.
+#[derive(Clone)]
+pub struct TokenBudget {
+    budget: Budget,
+    spent: Arc<Mutex<VecDeque<(Instant, usize)>>>,
+}
+
+impl std::fmt::Debug for TokenBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenBudget")
+            .field("budget", &self.budget)
+            .field("spent", &self.spent())
+            .finish()
+    }
+}
+
+impl TokenBudget {
+    pub fn new(budget: Budget) -> TokenBudget {
+        TokenBudget {
+            budget,
+            spent: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn budget(&self) -> Budget {
+        self.budget
+    }
+
+    /// The tokens spent within the current window.
+    pub fn spent(&self) -> usize {
+        let mut spent = self.spent.lock();
+        self.expire(&mut spent, Instant::now());
+        spent.iter().map(|(_, tokens)| tokens).sum()
+    }
+
+    /// Spends `tokens`, waiting until they fit within the budget. Returns a `ParamError` if
+    /// `tokens` exceeds the whole budget, and a `RateLimit` error if they don't fit and the
+    /// budget was set to [`reject`](Budget::reject).
+    pub async fn acquire(&self, tokens: usize) -> Result<(), OairsError> {
+        if tokens > self.budget.tokens {
+            return Err(OairsError::new(
+                format!(
+                    "The request's estimated {} tokens exceed the budget of {} tokens per {:?}",
+                    tokens, self.budget.tokens, self.budget.per
+                ),
+                ErrorType::ParamError,
+                Some("budget".to_string()),
+                None,
+            ));
+        }
+
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut spent = self.spent.lock();
+                self.expire(&mut spent, now);
+
+                // How long until enough of the spent tokens leave the window for `tokens` to fit.
+                let mut excess = (spent.iter().map(|(_, t)| t).sum::<usize>() + tokens)
+                    .saturating_sub(self.budget.tokens);
+                let mut wait = Duration::ZERO;
+                for (at, spent_tokens) in spent.iter() {
+                    if excess == 0 {
+                        break;
+                    }
+                    excess = excess.saturating_sub(*spent_tokens);
+                    wait = (*at + self.budget.per).saturating_duration_since(now);
+                }
+                if wait.is_zero() {
+                    spent.push_back((now, tokens));
+                    return Ok(());
+                }
+                wait
+            };
+
+            if self.budget.reject {
+                return Err(OairsError::new(
+                    format!(
+                        "The request's estimated {} tokens would exceed the budget of {} tokens \
+                         per {:?}. Retry in {:?}",
+                        tokens, self.budget.tokens, self.budget.per, wait
+                    ),
+                    ErrorType::RateLimit,
+                    Some("budget".to_string()),
+                    None,
+                ));
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn expire(&self, spent: &mut VecDeque<(Instant, usize)>, now: Instant) {
+        while let Some((at, _)) = spent.front() {
+            if now.duration_since(*at) < self.budget.per {
+                break;
+            }
+            spent.pop_front();
+        }
+    }
+}
+
+/// Spends the tokens estimated by `tokens` from `budget`, if any. The estimate is only made
+/// when there's a budget.
+pub(crate) async fn spend<F>(budget: Option<&TokenBudget>, tokens: F) -> Result<(), OairsError>
+where
+    F: FnOnce() -> Result<usize, OairsError>,
+{
+    match budget {
+        Some(budget) => budget.acquire(tokens()?).await,
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn token_budget() {
+        let per = Duration::from_millis(200);
+        let budget = TokenBudget::new(Budget::tokens_per(100, per));
+
+        budget.acquire(60).await.unwrap();
+        tokio::time::sleep(per / 2).await;
+        budget.acquire(40).await.unwrap();
+        assert_eq!(budget.spent(), 100);
+        assert!(budget.acquire(101).await.is_err());
+
+        // Waits for the first 60 tokens to leave the window.
+        let start = Instant::now();
+        budget.acquire(50).await.unwrap();
+        assert!(start.elapsed() >= per / 4);
+        assert_eq!(budget.spent(), 90);
+
+        let rejecting = TokenBudget::new(Budget::tokens_per(100, per).reject());
+        rejecting.acquire(100).await.unwrap();
+        let error = rejecting.acquire(1).await.unwrap_err();
+        assert!(error.is_rate_limit());
+    }
+}
@@ -0,0 +1,261 @@
+//! Contains the opt-in [`ResponseCache`], which lets a [`Client`](crate::client::Client) return
+//! a stored response for a duplicate request to a deterministic endpoint instead of sending it
+//! again. See [`Client::with_cache`](crate::client::Client::with_cache).
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::client::{handle_request, HttpMethod};
+
+use super::*;
+
+/// How a [`ResponseCache`] stores responses.
+///
+/// Requests are identified by a hash of their url and JSON body (the API key isn't part of it),
+/// so the same request sent from different builders, or with a different key, is a duplicate.
+/// Only successful responses are cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    ttl: Duration,
+    max_entries: Option<usize>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy::content_hash()
+    }
+}
+
+impl CachePolicy {
+    /// Identify requests by the hash of their content, keeping responses for an hour with no
+    /// limit on the number of entries.
+    pub fn content_hash() -> CachePolicy {
+        CachePolicy {
+            ttl: Duration::from_secs(60 * 60),
+            max_entries: None,
+        }
+    }
+
+    /// How long a response is returned for duplicate requests.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The maximum number of responses to keep. When full, the oldest response is evicted.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct CachedResponse {
+    status: reqwest::StatusCode,
+    headers: reqwest::header::HeaderMap,
+    body: Vec<u8>,
+    stored_at: Instant,
+}
+
+impl CachedResponse {
+    fn to_response(&self) -> reqwest::Response {
+        let mut response = ::http::Response::new(self.body.clone());
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        reqwest::Response::from(response)
+    }
+}
+
+/// An in-memory cache of responses, used by the [`Client`](crate::client::Client) for the
+/// deterministic endpoints: embeddings and moderations. (Tokenization is done locally by the
+/// [`tokenizers`](crate::tokenizers) module, so it needs no cache.)
+///
+/// Cloning a `ResponseCache` is cheap, and the clones share their entries.
+#[derive(Clone)]
+pub struct ResponseCache {
+    policy: CachePolicy,
+    entries: Arc<Mutex<HashMap<u64, CachedResponse>>>,
+}
+
+impl std::fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseCache")
+            .field("policy", &self.policy)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+impl ResponseCache {
+    pub fn new(policy: CachePolicy) -> ResponseCache {
+        ResponseCache {
+            policy,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn policy(&self) -> CachePolicy {
+        self.policy
+    }
+
+    /// The number of responses stored, including any that have expired but not yet been
+    /// evicted.
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every stored response.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    fn key(url: &str, json: &serde_json::Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        json.to_string().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn get(&self, key: u64) -> Option<reqwest::Response> {
+        let mut entries = self.entries.lock();
+        match entries.get(&key) {
+            Some(entry) if entry.stored_at.elapsed() < self.policy.ttl => Some(entry.to_response()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    async fn store(
+        &self,
+        key: u64,
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, OairsError> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = match response.bytes().await {
+            Ok(body) => body.to_vec(),
+            Err(e) => return Err(parse_reqwest_error(e)),
+        };
+        let entry = CachedResponse {
+            status,
+            headers,
+            body,
+            stored_at: Instant::now(),
+        };
+        let response = entry.to_response();
+
+        let mut entries = self.entries.lock();
+        let ttl = self.policy.ttl;
+        entries.retain(|_, entry| entry.stored_at.elapsed() < ttl);
+        if let Some(max) = self.policy.max_entries {
+            while entries.len() >= max.max(1) {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.stored_at)
+                    .map(|(key, _)| *key);
+                match oldest {
+                    Some(oldest) => entries.remove(&oldest),
+                    None => break,
+                };
+            }
+        }
+        entries.insert(key, entry);
+
+        Ok(response)
+    }
+}
+
+/// Sends a `POST` request with a JSON body, returning the cached response for a duplicate
+/// request if there is a `cache`.
+pub(crate) async fn send_cached(
+    cache: Option<&ResponseCache>,
+    key: &str,
+    url: &str,
+    json: serde_json::Value,
+) -> Result<reqwest::Response, OairsError> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return handle_request(key, url, HttpMethod::Post, Some(json), None).await,
+    };
+
+    let cache_key = ResponseCache::key(url, &json);
+    if let Some(response) = cache.get(cache_key) {
+        return Ok(response);
+    }
+    let response = handle_request(key, url, HttpMethod::Post, Some(json), None).await?;
+    cache.store(cache_key, response).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &str) -> reqwest::Response {
+        reqwest::Response::from(::http::Response::new(body.as_bytes().to_vec()))
+    }
+
+    #[tokio::test]
+    async fn response_cache() {
+        let cache = ResponseCache::new(CachePolicy::content_hash().max_entries(2));
+        let json = serde_json::json!({"input": ["a"]});
+        let key = ResponseCache::key("url", &json);
+
+        assert!(cache.get(key).is_none());
+        cache.store(key, response("first")).await.unwrap();
+        let cached = cache.get(key).unwrap();
+        assert_eq!(cached.text().await.unwrap(), "first");
+
+        assert_ne!(
+            key,
+            ResponseCache::key("url", &serde_json::json!({"input": ["b"]}))
+        );
+        assert_ne!(key, ResponseCache::key("other", &json));
+
+        cache.store(1, response("second")).await.unwrap();
+        cache.store(2, response("third")).await.unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(key).is_none());
+
+        let cache = ResponseCache::new(CachePolicy::content_hash().ttl(Duration::ZERO));
+        cache.store(key, response("expired")).await.unwrap();
+        assert!(cache.get(key).is_none());
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{embeddings::response::Embedding, test_util::MockApi};
+
+    #[tokio::test]
+    async fn response_cache() {
+        let api = MockApi::start().await;
+        let client = api
+            .client()
+            .with_cache(crate::cache::CachePolicy::content_hash());
+
+        for input in ["a", "a", "b"] {
+            let response = client
+                .create_embeddings(crate::EmbeddingModel::default(), &[input])
+                .send()
+                .await
+                .unwrap();
+            response.json::<Embedding>().await.unwrap();
+        }
+
+        assert_eq!(api.requests("/embeddings").await.len(), 2);
+        assert_eq!(client.cache().unwrap().len(), 2);
+    }
+}
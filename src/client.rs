@@ -2,18 +2,42 @@
 //! sending a request. The Client struct is the main point of entry for interacting with the
 //! endpoints of the OpenAI API.
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+use std::{
+    future::Future,
+    sync::{Arc, OnceLock},
+};
+
+use parking_lot::Mutex;
 use reqwest::{header, multipart::Part};
 
+#[cfg(feature = "tokenizers")]
+use crate::budget::{Budget, TokenBudget};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    cache::{CachePolicy, ResponseCache},
+    files::DownloadFileBuilder,
+    meta::{RequestTiming, TimingStart},
+    record_replay::Mode,
+};
 use crate::{
-    completions::{ChatBuilder, CompletionBuilder, Messages},
+    completions::{ChatBuilder, CompletionBuilder, Messages, SafeChatBuilder},
+    credentials::{ApiKey, KeyRing},
     edits::EditBuilder,
     embeddings::EmbeddingBuilder,
+    files::{response::FileInfo, DeleteFilesBuilder, ListFilesBuilder},
     fine_tunes::{FineTunesBuilder, ListEventsBuilder},
-    images::{ImageBuilder, ImageEdit, ImageGen, ImageVariation},
+    images::{ImageBuilder, ImageEdit, ImageGen, ImageInput, ImageVariation},
+    meta::deserialize_response,
+    models::{response::ModelsList, AvailableModels},
 };
 
 use super::*;
 
+mod handles;
+pub use handles::*;
+
 // ASIDE:
 //
 // I've wrestled back and forth between various designs. Initially I attempted to make the design as
@@ -42,12 +66,32 @@ use super::*;
 // This is the only `POST` method that takes a form and since there are only two fields, it seemed
 // another foolish consistency to give it its own struct. Therefore, doing `client.upload_file(...)`
 // will return a `Client<Sendable>`.
+//
+// As the list of methods grew, the flat methods became hard to browse, so the resource handles
+// (`client.files()`, `client.fine_tunes()`, ...) came back as a way to find them. The handles do the
+// work and the flat methods delegate to them; neither is meant to replace the other.
 
 /// The main point of entry for interacting with the endpoints of the OpenAI API. Parameters
 /// **required** by the API are always passed in as arguments to the relevant method. Optional
 /// parameters, if any, are chained. Executing the request is always done by awaiting the
 /// `send()` method.
 ///
+/// Cloning a `Client` is cheap, and the clones share its key ring, cache, and budget. Every
+/// request is sent with the same pooled HTTP client, so connections are reused across clones,
+/// builders, and tasks.
+///
+/// # Example
+/// ```rust,no_run
+/// let client = Client::new(&key);
+/// let tasks: Vec<_> = inputs
+///     .into_iter()
+///     .map(|input| {
+///         let client = client.clone();
+///         tokio::spawn(async move { client.create_moderation(input).send_parsed().await })
+///     })
+///     .collect();
+/// ```
+///
 /// # Example
 /// ```rust,no_run
 /// let key = std::env::var("OPENAI_API_KEY").unwrap();
@@ -107,25 +151,84 @@ use super::*;
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Client<State = Unkeyed> {
     #[serde(skip)]
-    key: String,
+    key: Arc<str>,
+
+    #[serde(skip)]
+    url: Option<Cow<'static, str>>,
+
+    #[serde(skip)]
+    endpoints: Endpoints,
+
+    #[serde(skip)]
+    key_ring: Option<KeyRing>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    cache: Option<ResponseCache>,
+
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    budget: Option<TokenBudget>,
 
+    // The models listed by the first call to `available_models`.
     #[serde(skip)]
-    url: Option<String>,
+    available_models: Arc<Mutex<Option<AvailableModels>>>,
 
     // These fields only used for the form of uploading a file.
     #[serde(skip_serializing_if = "Option::is_none", alias = "file")]
     upload_filename: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", alias = "purpose")]
     file_purpose: Option<Purpose>,
+    // The contents of a file uploaded from memory, named by `upload_filename`.
+    #[serde(skip)]
+    upload_bytes: Option<Vec<u8>>,
 
     #[serde(skip)]
     state: PhantomData<State>,
 }
 
+// Implemented by hand since the derive would require `State: Clone`.
+impl<State> Clone for Client<State> {
+    fn clone(&self) -> Self {
+        Client {
+            key: self.key.clone(),
+            url: self.url.clone(),
+            endpoints: self.endpoints.clone(),
+            key_ring: self.key_ring.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            cache: self.cache.clone(),
+            #[cfg(feature = "tokenizers")]
+            budget: self.budget.clone(),
+            available_models: self.available_models.clone(),
+            upload_filename: self.upload_filename.clone(),
+            file_purpose: self.file_purpose.clone(),
+            upload_bytes: self.upload_bytes.clone(),
+            state: PhantomData,
+        }
+    }
+}
+
 impl Client<Unkeyed> {
     pub fn new<K: Into<String> + std::fmt::Debug>(key: K) -> Client<Keyed> {
         Client {
-            key: format!("Bearer {}", key.into()),
+            key: format!("Bearer {}", key.into()).into(),
+            state: PhantomData::<Keyed>,
+            ..Default::default()
+        }
+    }
+
+    /// Create a client that takes the key for each request from a [`KeyRing`], spreading the
+    /// requests across its keys. The key is chosen when the request (or its builder) is
+    /// created, so a builder keeps the same key however many times it is sent.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let ring = KeyRing::from_env_var("OPENAI_API_KEYS", Rotation::RoundRobin).unwrap();
+    /// let client = Client::with_key_ring(ring);
+    /// ```
+    pub fn with_key_ring(key_ring: KeyRing) -> Client<Keyed> {
+        Client {
+            key_ring: Some(key_ring),
             state: PhantomData::<Keyed>,
             ..Default::default()
         }
@@ -133,6 +236,93 @@ impl Client<Unkeyed> {
 }
 
 impl Client<Keyed> {
+    /// The `Authorization` header value (`Bearer` and the key) for the next request: the
+    /// client's key, or the next key of its [`KeyRing`]. For an
+    /// [`OairsRequest`](crate::http::OairsRequest) of an endpoint the crate doesn't cover.
+    pub fn next_key(&self) -> String {
+        match &self.key_ring {
+            Some(ring) => format!("Bearer {}", ring.next_key().as_str()),
+            None => self.key.to_string(),
+        }
+    }
+
+    /// The [`Endpoints`] used to build the url of each request.
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Send requests to `base_url` instead of the OpenAI API, e.g. for a proxy, an
+    /// API-compatible server, or a mock server in tests. See [`Endpoints::new`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let client = Client::new(key).with_base_url("http://127.0.0.1:8080/v1");
+    /// ```
+    pub fn with_base_url<B: Into<String>>(mut self, base_url: B) -> Client<Keyed> {
+        self.endpoints.set_base_url(base_url);
+        self
+    }
+
+    /// Cache the responses to embedding and moderation requests, returning the stored response
+    /// when an identical request is sent again within the policy's TTL, instead of paying for
+    /// it twice. Requests are identified by a hash of their url and JSON body; see
+    /// [`CachePolicy`]. Requests to every other endpoint are always sent.
+    ///
+    /// Not available for `wasm32`, where reqwest can't rebuild a stored response.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let client = Client::new(key)
+    ///     .with_cache(CachePolicy::content_hash().ttl(Duration::from_secs(600)));
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_cache(mut self, policy: CachePolicy) -> Client<Keyed> {
+        self.cache = Some(ResponseCache::new(policy));
+        self
+    }
+
+    /// The [`ResponseCache`] set with [`with_cache`](Self::with_cache), if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    /// Hold back chat, completion, edit, and embedding requests that would exceed `budget`,
+    /// e.g. your account's tokens-per-minute rate limit. Each request's tokens are estimated
+    /// before it's sent; see [`TokenBudget`]. The budget is shared by every builder created
+    /// by the client (and its clones).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let client = Client::new(key).with_budget(Budget::tokens_per_minute(90_000));
+    /// ```
+    #[cfg(feature = "tokenizers")]
+    pub fn with_budget(mut self, budget: Budget) -> Client<Keyed> {
+        self.budget = Some(TokenBudget::new(budget));
+        self
+    }
+
+    /// The [`TokenBudget`] set with [`with_budget`](Self::with_budget), if any.
+    #[cfg(feature = "tokenizers")]
+    pub fn budget(&self) -> Option<&TokenBudget> {
+        self.budget.as_ref()
+    }
+
+    /// The [`KeyRing`] the client was created with, if any.
+    pub fn key_ring(&self) -> Option<&KeyRing> {
+        self.key_ring.as_ref()
+    }
+
+    /// If the client was created with a [`KeyRing`] using
+    /// [`Rotation::OnRateLimit`](crate::credentials::Rotation::OnRateLimit), switch to
+    /// its next key when `error` is a rate limit error. Returns whether the key was switched.
+    pub fn rotate_on_error(&self, error: &OairsError) -> bool {
+        match &self.key_ring {
+            Some(ring) => ring.rotate_on_error(error),
+            None => false,
+        }
+    }
+
     /// "Immediately cancel a fine-tune job."
     /// - [OpenAI API docs](https://platform.openai.com/docs/api-reference/fine-tunes/cancel)
     ///
@@ -157,12 +347,8 @@ impl Client<Keyed> {
     /// };
     /// // ...
     /// ```
-    pub fn cancel_fine_tune(self, fine_tune_id: &str) -> Client<Cancel> {
-        Client {
-            key: self.key,
-            url: Some(cancel_ft_url(fine_tune_id)),
-            ..Default::default()
-        }
+    pub fn cancel_fine_tune(&self, fine_tune_id: &str) -> Client<Cancel> {
+        self.fine_tunes().cancel(fine_tune_id)
     }
 
     /// For creating a chat completion with various GPT chat models (including GPT 4).
@@ -241,7 +427,19 @@ impl Client<Keyed> {
     /// }
     /// ```
     pub fn chat_completion(&self, model: ChatModel, msgs: &Messages) -> ChatBuilder<Sendable> {
-        ChatBuilder::create(&self.key, model, msgs)
+        let mut builder = ChatBuilder::create(self.next_key(), model, msgs);
+        builder.set_url(self.endpoints.chat_completions());
+        #[cfg(feature = "tokenizers")]
+        builder.set_budget(self.budget.clone());
+        builder
+    }
+
+    /// Like [`chat_completion`](Client::chat_completion), but the latest user messages (those
+    /// after the last assistant message) are run through the moderations endpoint first, and a
+    /// `ModerationBlocked` error (see [`OairsError::moderation_blocked`]) is returned instead of
+    /// calling the chat endpoint if any are flagged. See [`SafeChatBuilder`].
+    pub fn safe_chat_completion(&self, model: ChatModel, msgs: &Messages) -> SafeChatBuilder<'_> {
+        SafeChatBuilder::new(self, self.chat_completion(model, msgs), msgs)
     }
 
     /// "Given a prompt, the model will return one or more predicted completions, and can also return
@@ -290,7 +488,11 @@ impl Client<Keyed> {
     /// }
     /// ```
     pub fn completion(&self, model: CompletionModel) -> CompletionBuilder<Sendable> {
-        CompletionBuilder::create(&self.key, model)
+        let mut builder = CompletionBuilder::create(self.next_key(), model);
+        builder.set_url(self.endpoints.completions());
+        #[cfg(feature = "tokenizers")]
+        builder.set_budget(self.budget.clone());
+        builder
     }
 
     /// "Given a prompt and an instruction, the model will return an edited version of the prompt."
@@ -341,7 +543,13 @@ impl Client<Keyed> {
         model: EditModel,
         instruction: I,
     ) -> EditBuilder<Sendable> {
-        EditBuilder::create(&self.key, model, instruction.into())
+        let mut builder = EditBuilder::create(self.next_key(), model, instruction.into());
+        builder
+            .set_url(self.endpoints.edits())
+            .set_chat_url(self.endpoints.chat_completions());
+        #[cfg(feature = "tokenizers")]
+        builder.set_budget(self.budget.clone());
+        builder
     }
 
     /// "Creates an embedding vector representing the input text." -
@@ -406,7 +614,13 @@ impl Client<Keyed> {
     where
         T: Into<String> + std::fmt::Display,
     {
-        EmbeddingBuilder::new(&self.key, model, inputs)
+        let mut builder = EmbeddingBuilder::new(self.next_key(), model, inputs);
+        builder.set_url(self.endpoints.embeddings());
+        #[cfg(not(target_arch = "wasm32"))]
+        builder.set_cache(self.cache.clone());
+        #[cfg(feature = "tokenizers")]
+        builder.set_budget(self.budget.clone());
+        builder
     }
 
     /// Fine-tune a model based on a training file.
@@ -459,7 +673,7 @@ impl Client<Keyed> {
         &self,
         training_file_id: &'a str,
     ) -> FineTunesBuilder<'a, Sendable> {
-        FineTunesBuilder::create(&self.key, training_file_id)
+        self.fine_tunes().create(training_file_id)
     }
 
     /// "Classifies if text violates OpenAI's Content Policy." -
@@ -494,7 +708,11 @@ impl Client<Keyed> {
     /// ```
     pub fn create_moderation<S: Into<String>>(&self, input: S) -> ModerationBuilder<Sendable> {
         let inputs = vec![input.into()];
-        ModerationBuilder::create(&self.key, inputs)
+        let mut builder = ModerationBuilder::create(self.next_key(), inputs);
+        builder.set_url(self.endpoints.moderations());
+        #[cfg(not(target_arch = "wasm32"))]
+        builder.set_cache(self.cache.clone());
+        builder
     }
 
     /// Same as [`create_moderation`] but takes a vector of strings.
@@ -514,6 +732,10 @@ impl Client<Keyed> {
     /// Awaiting `send()` will return `Result<reqwest::Response, OairsError>`. A successful
     /// response can be deserialized into a [`ModerationResult`](self::response::ModerationResult).
     ///
+    /// For a large number of inputs, awaiting `send_batched(chunk_size, max_concurrency)` will
+    /// split them across multiple requests and return a single `ModerationResult` with the
+    /// inputs attached.
+    ///
     /// # Example
     /// ```rust,no_run
     /// let prompts = vec!["This is a test.".to_string(), "this is another test.".to_string()];
@@ -528,7 +750,11 @@ impl Client<Keyed> {
     /// };
     /// ```
     pub fn create_moderations(&self, inputs: Vec<String>) -> ModerationBuilder<Sendable> {
-        ModerationBuilder::create(&self.key, inputs)
+        let mut builder = ModerationBuilder::create(self.next_key(), inputs);
+        builder.set_url(self.endpoints.moderations());
+        #[cfg(not(target_arch = "wasm32"))]
+        builder.set_cache(self.cache.clone());
+        builder
     }
 
     /// "Creates an image given a prompt." -
@@ -581,23 +807,25 @@ impl Client<Keyed> {
     /// image.save_json(&filename).unwrap();
     /// ```
     pub fn create_image<P: Into<String>>(&self, prompt: P) -> ImageBuilder<ImageGen> {
-        ImageBuilder::create_image(&self.key, prompt)
+        self.images().generate(prompt)
     }
 
     /// Image format must be `RGBA`, `LA`, or `L`, (`RGB` will return an error from the API)."
-    pub fn create_image_edit<I, P>(&self, image_path: I, prompt: P) -> ImageBuilder<ImageEdit>
+    ///
+    /// The image can be a path to a PNG file or a PNG image in memory; see [`ImageInput`].
+    pub fn create_image_edit<I, P>(&self, image: I, prompt: P) -> ImageBuilder<ImageEdit>
     where
-        I: Into<String>,
+        I: Into<ImageInput>,
         P: Into<String>,
     {
-        ImageBuilder::create_edit(&self.key, image_path, prompt)
+        self.images().edit(image, prompt)
     }
 
     /// Create a variation of an image.
     ///
     /// # Arguments
-    /// * `image_path` - A `&str` or `String` representing path to the image to use for the
-    ///  variation.
+    /// * `image` - The image to use for the variation: a `&str` or `String` path, the bytes of a
+    ///  PNG image, or (with the `image` feature) an `image::DynamicImage`. See [`ImageInput`].
     ///
     /// # Optional parameters
     /// The following can be set with the corresponding methods:
@@ -617,11 +845,69 @@ impl Client<Keyed> {
     /// [`ResponseFormat::Url`](images::response::ResponseFormat).
     ///
     /// * `user` - The unique id for a user, for detecting abuse.
-    pub fn create_image_variation<I>(&self, image_path: I) -> ImageBuilder<ImageVariation>
+    pub fn create_image_variation<I>(&self, image: I) -> ImageBuilder<ImageVariation>
     where
-        I: Into<String>,
+        I: Into<ImageInput>,
     {
-        ImageBuilder::create_variation(&self.key, image_path)
+        self.images().variation(image)
+    }
+
+    /// Send a request to an endpoint that doesn't (yet) have its own method, using the same
+    /// authorization and error handling as every other request.
+    ///
+    /// # Arguments
+    /// * `method` - The [`HttpMethod`] of the request.
+    /// * `path` - The path of the endpoint, relative to the base url (e.g., "assistants" or
+    ///   "/v1/assistants"). A full url (starting with "http") is used as is.
+    /// * `json` - An optional json body.
+    ///
+    /// # Returns
+    /// [`CustomRequest`] that can be used to execute the request by awaiting `send()`, or by
+    /// awaiting `send_json::<T>()` to deserialize a successful response into your own type.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// #[derive(Deserialize)]
+    /// struct Assistant {
+    ///     id: String,
+    /// }
+    ///
+    /// let body = serde_json::json!({ "model": "gpt-4", "name": "Helper" });
+    /// let assistant: Assistant = client
+    ///     .custom_request(HttpMethod::Post, "assistants", Some(body))
+    ///     .send_json()
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    pub fn custom_request<P: AsRef<str>>(
+        &self,
+        method: HttpMethod,
+        path: P,
+        json: Option<serde_json::Value>,
+    ) -> CustomRequest {
+        CustomRequest {
+            key: self.next_key(),
+            url: self.endpoints.custom(path.as_ref()),
+            method,
+            json,
+            form: None,
+        }
+    }
+
+    /// The same as [`custom_request`](Client::custom_request), but sends a multipart form
+    /// as a `POST` request.
+    pub fn custom_form_request<P: AsRef<str>>(
+        &self,
+        path: P,
+        form: reqwest::multipart::Form,
+    ) -> CustomRequest {
+        CustomRequest {
+            key: self.next_key(),
+            url: self.endpoints.custom(path.as_ref()),
+            method: HttpMethod::Post,
+            json: None,
+            form: Some(form),
+        }
     }
 
     /// Delete a file that belongs to your organization.
@@ -650,12 +936,54 @@ impl Client<Keyed> {
     /// };
     /// // ...
     /// ```
-    pub fn delete_file(self, file_id: &str) -> Client<Delete> {
-        Client {
-            key: self.key,
-            url: Some(delete_file_url(file_id)),
-            ..Default::default()
-        }
+    pub fn delete_file(&self, file_id: &str) -> Client<Delete> {
+        self.files().delete(file_id)
+    }
+
+    /// Delete each of the given files, sending up to 4 requests at a time (see
+    /// [`DeleteFilesBuilder::max_concurrency`]).
+    ///
+    /// # Returns
+    /// `DeleteFilesBuilder<Sendable>` that can be used to execute the deletions by awaiting
+    /// `send()`, or to see what would be deleted by awaiting `dry_run()`.
+    ///
+    /// `Result<DeleteResults, OairsError>` is returned by awaiting `send()`, with the result of
+    /// each deletion.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let results = client
+    ///     .delete_files(["file-abc", "file-def"])
+    ///     .send()
+    ///     .await?;
+    /// for (file_id, error) in results.errors() {
+    ///     eprintln!("{file_id}: {error}");
+    /// }
+    /// ```
+    pub fn delete_files<I, S>(&self, file_ids: I) -> DeleteFilesBuilder<Sendable>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files().delete_many(file_ids)
+    }
+
+    /// Delete every file for which `filter` returns `true`. The files are listed (every page)
+    /// when the builder is sent, then deleted as with [`delete_files`](Client::delete_files).
+    /// Awaiting `dry_run()` lists and filters the files without deleting them.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let stale = |file: &FileInfo| file.created_at < cutoff;
+    /// let builder = client.delete_files_where(stale);
+    /// println!("Deleting {} files", builder.dry_run().await?.len());
+    /// let results = builder.send().await?;
+    /// ```
+    pub fn delete_files_where<F>(&self, filter: F) -> DeleteFilesBuilder<Sendable>
+    where
+        F: Fn(&FileInfo) -> bool + Send + Sync + 'static,
+    {
+        self.files().delete_where(filter)
     }
 
     /// "Delete a fine-tuned model. You must have the Owner role in your organization." -
@@ -685,20 +1013,63 @@ impl Client<Keyed> {
     ///    let delete_response = response.json::<DeleteResponse>().await.unwrap();
     /// // ...
     /// ```
-    pub fn delete_fine_tune_model(self, model: &str) -> Client<Delete> {
-        Client {
-            key: self.key,
-            url: Some(delete_ft_model_url(model)),
-            ..Default::default()
-        }
+    pub fn delete_fine_tune_model(&self, model: &str) -> Client<Delete> {
+        self.fine_tunes().delete_model(model)
+    }
+
+    /// Download the content of the specified file to disk.
+    ///
+    /// The response body is streamed to `path` rather than buffered in memory, which makes
+    /// this a better fit than [`retrieve_file_content`](crate::client::Client::retrieve_file_content)
+    /// for large fine-tune results files. The number of bytes written is checked against
+    /// [`FileInfo::bytes`](crate::files::response::FileInfo).
+    ///
+    /// # Arguments
+    /// * file_id - The ID of the file to download. Will have a format similar to "file-xxxxxxx...".
+    /// * path - Where to write the file. An existing file will be overwritten.
+    ///
+    /// # Optional Arguments
+    /// * `on_progress` - A callback receiving the bytes written so far and the total bytes.
+    ///
+    /// # Returns
+    /// `DownloadFileBuilder<Sendable>` that can be used to execute the download by awaiting `send()`.
+    ///
+    /// `Result<u64, OairsError>` is returned after `send()` is awaited, with the number of bytes written.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let file_id = "file-xewFBtsiD2hbU47Du1G37zJE";
+    /// let bytes = client
+    ///     .download_file(file_id, "results.csv")
+    ///     .on_progress(|written, total| println!("{written}/{total}"))
+    ///     .send()
+    ///     .await
+    ///     .unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn download_file<P: Into<std::path::PathBuf>>(
+        &self,
+        file_id: &str,
+        path: P,
+    ) -> DownloadFileBuilder<Sendable> {
+        self.files().download(file_id, path)
     }
 
     /// Get a list of files that you've uploaded to the server or that have been generated by OpenAI
     /// (e.g., as part of a fine-tune request).
     /// [OpenAI API Docs](https://platform.openai.com/docs/api-reference/files/list)
     ///
+    /// # Optional Arguments
+    /// The following query parameters can be set via chaining:
+    /// * `purpose` - Only return files with the given [`Purpose`].
+    /// * `after` - Pagination cursor, the id of the last file of the previous page.
+    /// * `limit` - The maximum number of files per page.
+    /// * `order` - [`Order::Asc`](crate::files::Order) or [`Order::Desc`](crate::files::Order)
+    ///   by `created_at`.
+    ///
     /// # Returns
-    /// `Client<Gettable>` that can be used to execute the request by awaiting `send()`.
+    /// `ListFilesBuilder<Sendable>` that can be used to set query parameters and execute the request
+    /// by awaiting `send()`, or to get a stream of every file via `into_stream()`.
     ///
     /// `Result<reqwest::Response, OairsError>` is returned by awaiting `send()`. A successful response
     /// can be deserialized using the [`FileList`] struct.
@@ -712,12 +1083,8 @@ impl Client<Keyed> {
     /// };
     /// // ...
     /// ```
-    pub fn list_files(&self) -> Client<Gettable> {
-        Client {
-            key: self.key.to_owned(),
-            url: Some(list_files_url().to_string()),
-            ..Default::default()
-        }
+    pub fn list_files(&self) -> ListFilesBuilder<Sendable> {
+        self.files().list()
     }
 
     /// "List your organization's fine-tuning jobs" -
@@ -739,11 +1106,7 @@ impl Client<Keyed> {
     /// // ...
     /// ```
     pub fn list_fine_tunes(&self) -> Client<Gettable> {
-        Client {
-            key: self.key.to_owned(),
-            url: Some(list_fine_tunes_url().to_string()),
-            ..Default::default()
-        }
+        self.fine_tunes().list()
     }
 
     /// "Get fine-grained status updates for a fine-tune job." -
@@ -786,7 +1149,7 @@ impl Client<Keyed> {
     /// // ...
     /// ```
     pub fn list_fine_tune_events(&self, fine_tune_id: &str) -> ListEventsBuilder<Sendable> {
-        ListEventsBuilder::new(&self.key, fine_tune_id)
+        self.fine_tunes().events(fine_tune_id)
     }
 
     /// List all available models and their associated information.
@@ -820,11 +1183,51 @@ impl Client<Keyed> {
     ///   Err(e) => panic!("{}", e),
     /// }
     pub fn list_models(&self) -> Client<Gettable> {
-        Client {
-            key: self.key.to_owned(),
-            url: Some(list_models_url().to_string()),
-            ..Default::default()
-        }
+        self.models().list()
+    }
+
+    /// The models the client's key can access, sorted into the model enums; see
+    /// [`AvailableModels`]. The models are listed with [`list_models`](Self::list_models) on
+    /// the first call, and the list is kept for the client's lifetime, so later calls (and
+    /// [`available_chat_models`](Self::available_chat_models) and
+    /// [`model_exists`](Self::model_exists)) don't make a request. Call
+    /// [`clear_available_models`](Self::clear_available_models) to list them again.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let available = client.available_models().await?;
+    /// let model = match available.chat.contains(&ChatModel::Gpt4) {
+    ///     true => ChatModel::Gpt4,
+    ///     false => ChatModel::GptTurbo,
+    /// };
+    /// ```
+    pub async fn available_models(&self) -> Result<AvailableModels, OairsError> {
+        self.models().available().await
+    }
+
+    /// The chat models the client's key can access. See
+    /// [`available_models`](Self::available_models).
+    pub async fn available_chat_models(&self) -> Result<Vec<ChatModel>, OairsError> {
+        Ok(self.available_models().await?.chat)
+    }
+
+    /// Whether the client's key can access `model`. See
+    /// [`available_models`](Self::available_models).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// if !client.model_exists(&ChatModel::Gpt432k).await? {
+    ///     // fall back to chunking the prompt
+    /// }
+    /// ```
+    pub async fn model_exists<R: RetrievableModel>(&self, model: &R) -> Result<bool, OairsError> {
+        self.models().exists(model).await
+    }
+
+    /// Forget the models listed by [`available_models`](Self::available_models), so the next
+    /// call lists them again, e.g. after gaining access to a new model.
+    pub fn clear_available_models(&self) {
+        self.models().clear_available();
     }
 
     /// Retrieve information about the specified file.
@@ -855,11 +1258,7 @@ impl Client<Keyed> {
     /// file_info.save_json(&filename).unwrap();
     /// ```
     pub fn retrieve_file(&self, file_id: &str) -> Client<Gettable> {
-        Client {
-            key: self.key.to_owned(),
-            url: Some(retrieve_file_url(file_id)),
-            ..Default::default()
-        }
+        self.files().retrieve(file_id)
     }
 
     /// Retrieve the content of the specified file.
@@ -968,11 +1367,7 @@ impl Client<Keyed> {
     /// }
     /// ```
     pub fn retrieve_file_content(&self, file_id: &str) -> Client<Gettable> {
-        Client {
-            key: self.key.to_owned(),
-            url: Some(retrieve_file_content_url(file_id)),
-            ..Default::default()
-        }
+        self.files().content(file_id)
     }
 
     /// "Gets info about the fine-tune job." -
@@ -1004,11 +1399,7 @@ impl Client<Keyed> {
     /// // ...
     /// ```
     pub fn retrieve_fine_tune_info(&self, fine_tune_id: &str) -> Client<Gettable> {
-        Client {
-            key: self.key.to_owned(),
-            url: Some(retrieve_ft_info_url(fine_tune_id)),
-            ..Default::default()
-        }
+        self.fine_tunes().retrieve(fine_tune_id)
     }
 
     /// Retrieve information about a model.
@@ -1023,6 +1414,7 @@ impl Client<Keyed> {
     ///   - [`CompletionModel`]
     ///   - [`EmbeddingModel`]
     ///   - [`FineTuneModel`]
+    ///   - [`ImageModel`]
     ///
     /// If you need to retrieve information about a model that is not included in the above enums,
     /// you can use either the [`custom_model!`] or [`ft_model!`] macros to create a custom/fine-tuned
@@ -1071,11 +1463,7 @@ impl Client<Keyed> {
     where
         R: RetrievableModel,
     {
-        Client {
-            key: self.key.to_owned(),
-            url: Some(retrieve_model_url(model.to_str())),
-            ..Default::default()
-        }
+        self.models().retrieve(model)
     }
 
     /// "Upload a file that contains document(s) to be used across various endpoints/features. Currently,
@@ -1083,11 +1471,10 @@ impl Client<Keyed> {
     /// [OpenAI API Docs](https://platform.openai.com/docs/api-reference/files/upload)
     ///
     /// # Arguments
-    /// * `file` - A `&str` path to the file you want to upload. Since currently the only purpose for
-    /// uploading a file is to use it for fine-tuning, the file should be `.jsonl`.
-    /// * `purpose` - A `Purpose`. Currently, the only available purpose by the API is 'fine-tune'
-    /// (hence, [`Purpose::FineTune`]). The enum constraint is to allow for future expansion of the API
-    /// and more generally to ensure that the purpose is properly set.
+    /// * `file` - A `&str` path to the file you want to upload. A file for fine-tuning should be
+    /// `.jsonl`.
+    /// * `purpose` - A [`Purpose`], e.g. [`Purpose::FineTune`]. A purpose without its own variant
+    /// can be given as `Purpose::Other`.
     ///
     /// # Returns
     /// `FileBuilder<'a, Create>` that can be used to execute the request by awaiting `send()`.
@@ -1109,13 +1496,25 @@ impl Client<Keyed> {
     /// };
     /// ```
     pub fn upload_file<F: Into<String>>(&self, file: F, purpose: Purpose) -> Client<Sendable> {
-        Client {
-            key: self.key.clone(),
-            url: Some(upload_file_url().to_string()),
-            upload_filename: Some(file.into()),
-            file_purpose: Some(purpose),
-            ..Default::default()
-        }
+        self.files().upload(file, purpose)
+    }
+
+    /// Like [`upload_file`](Self::upload_file), but uploads `bytes` from memory as a file named
+    /// `filename`, e.g. for `wasm32`, where there's no file system to read from. The MIME type
+    /// is inferred from the extension of `filename`, as with [`file_part`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let jsonl = b"{\"prompt\": \"Hello\", \"completion\": \" World\"}\n".to_vec();
+    /// let res = client.upload_bytes("train.jsonl", jsonl, Purpose::FineTune).send().await?;
+    /// ```
+    pub fn upload_bytes<F: Into<String>>(
+        &self,
+        filename: F,
+        bytes: Vec<u8>,
+        purpose: Purpose,
+    ) -> Client<Sendable> {
+        self.files().upload_bytes(filename, bytes, purpose)
     }
 }
 
@@ -1124,15 +1523,37 @@ impl Client<Cancel> {
     /// Executes the `POST` request. Returns a `Result` with either a `reqwest::Response` or an
     /// `OairsError`.
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let url = self.url.clone().unwrap();
-        handle_request(&self.key, &url, HttpMethod::Post, None, None).await
+        let url = self.url.as_deref().unwrap();
+        handle_request(&self.key, url, HttpMethod::Post, None, None).await
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        let url = self.url.as_deref().unwrap();
+        Ok(PreparedRequest::new(
+            &self.key,
+            url,
+            HttpMethod::Post,
+            PreparedBody::Empty,
+        ))
     }
 }
 
 impl<'a> Client<Gettable> {
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let url = self.url.clone().unwrap();
-        handle_request(&self.key, &url, HttpMethod::Get, None, None).await
+        let url = self.url.as_deref().unwrap();
+        handle_request(&self.key, url, HttpMethod::Get, None, None).await
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        let url = self.url.as_deref().unwrap();
+        Ok(PreparedRequest::new(
+            &self.key,
+            url,
+            HttpMethod::Get,
+            PreparedBody::Empty,
+        ))
     }
 }
 
@@ -1145,13 +1566,46 @@ impl Client<Sendable> {
         let path = self.upload_filename.as_ref().unwrap();
         let purpose = self.file_purpose.as_ref().unwrap();
 
-        let file_part = get_file_part(path)?;
+        let file_part = match &self.upload_bytes {
+            Some(bytes) => bytes_part(path, bytes.clone(), None)?,
+            None => get_file_part(path)?,
+        };
         let form = reqwest::multipart::Form::new()
             .text("purpose", purpose.to_string())
             .part("file", file_part);
 
-        let url = self.url.clone().unwrap();
-        handle_request(&self.key, &url, HttpMethod::Post, None, Some(form)).await
+        let url = self.url.as_deref().unwrap();
+        handle_request(&self.key, url, HttpMethod::Post, None, Some(form)).await
+    }
+
+    /// Whether the file is uploaded from memory, with [`upload_bytes`](Client::upload_bytes).
+    pub fn is_in_memory(&self) -> bool {
+        self.upload_bytes.is_some()
+    }
+
+    /// Checks that the file exists (unless it's uploaded from memory) and returns the request
+    /// that [`send`](Self::send) would make, without reading the file or sending anything.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        let path = self.upload_filename.as_ref().unwrap();
+        let purpose = self.file_purpose.as_ref().unwrap();
+        let file = match &self.upload_bytes {
+            Some(bytes) => format!("<{} bytes of {path}>", bytes.len()),
+            None => {
+                check_file(path)?;
+                path.clone()
+            }
+        };
+
+        let fields = vec![
+            ("purpose".to_string(), purpose.to_string()),
+            ("file".to_string(), file),
+        ];
+        Ok(PreparedRequest::new(
+            &self.key,
+            self.url.as_deref().unwrap(),
+            HttpMethod::Post,
+            PreparedBody::Form(fields),
+        ))
     }
 }
 
@@ -1160,14 +1614,260 @@ impl Client<Delete> {
     /// [`Response`](https://docs.rs/reqwest/0.11.14/reqwest/struct.Response.html)
     ///  or an `OairsError`.
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let url = self.url.clone().unwrap();
-        handle_request(&self.key, &url, HttpMethod::Delete, None, None).await
+        let url = self.url.as_deref().unwrap();
+        handle_request(&self.key, url, HttpMethod::Delete, None, None).await
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        let url = self.url.as_deref().unwrap();
+        Ok(PreparedRequest::new(
+            &self.key,
+            url,
+            HttpMethod::Delete,
+            PreparedBody::Empty,
+        ))
+    }
+}
+
+/// A request to an arbitrary endpoint. Normally you would get this from
+/// [`Client::custom_request`] or [`Client::custom_form_request`].
+#[derive(Debug)]
+pub struct CustomRequest {
+    key: String,
+    url: Cow<'static, str>,
+    method: HttpMethod,
+    json: Option<serde_json::Value>,
+    form: Option<reqwest::multipart::Form>,
+}
+
+impl CustomRequest {
+    /// Executes the request. Returns a `Result` with either a
+    /// [`Response`](https://docs.rs/reqwest/0.11.14/reqwest/struct.Response.html)
+    /// or an `OairsError`.
+    pub async fn send(self) -> Result<reqwest::Response, OairsError> {
+        handle_request(&self.key, &self.url, self.method, self.json, self.form).await
+    }
+
+    /// Executes the request and deserializes a successful response into `T`, with the status
+    /// and the start of the body in the error if it doesn't match.
+    pub async fn send_json<T: serde::de::DeserializeOwned>(self) -> Result<T, OairsError> {
+        deserialize_response::<T>(self.send().await?).await
+    }
+}
+
+/// A request that can be sent, implemented by each builder (and [`Client`] state) whose `send`
+/// returns the `reqwest::Response`, so that generic code, e.g. a retry wrapper or middleware,
+/// can take any of them, e.g. [`RetryPolicy::send`](crate::retry::RetryPolicy::send). The
+/// trait's `send` is the type's own `send`. Its future is `Send`, except for `wasm32`, where
+/// reqwest's futures aren't.
+///
+/// (The type-state struct [`Sendable`] has the name this trait would otherwise have.)
+///
+/// # Example
+/// ```rust,no_run
+/// use oairs::client::SendRequest;
+///
+/// async fn send_with_retries<R: SendRequest>(
+///     request: &R,
+///     retries: u32,
+/// ) -> Result<reqwest::Response, OairsError> {
+///     let mut attempt = 0;
+///     loop {
+///         match request.send().await {
+///             Err(e) if e.is_transient() && request.is_idempotent() && attempt < retries => {
+///                 attempt += 1
+///             }
+///             result => return result,
+///         }
+///     }
+/// }
+///
+/// let res = send_with_retries(&client.chat_completion(model, &messages), 3).await?;
+/// let res = send_with_retries(&client.list_models(), 3).await?;
+/// ```
+pub trait SendRequest {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send(&self) -> impl Future<Output = Result<reqwest::Response, OairsError>> + Send;
+    #[cfg(target_arch = "wasm32")]
+    fn send(&self) -> impl Future<Output = Result<reqwest::Response, OairsError>>;
+
+    /// Whether sending the request again after a failed attempt can't change or cost anything
+    /// the first attempt didn't: `true` for `GET` and `DELETE` requests, and for embeddings and
+    /// moderations, which return the same result for the same content.
+    fn is_idempotent(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! impl_send_request {
+    ($idempotent:literal => $($typ:ty),+ $(,)?) => {
+        $(
+            impl SendRequest for $typ {
+                #[cfg(not(target_arch = "wasm32"))]
+                fn send(&self) -> impl Future<Output = Result<reqwest::Response, OairsError>> + Send {
+                    <$typ>::send(self)
+                }
+
+                #[cfg(target_arch = "wasm32")]
+                fn send(&self) -> impl Future<Output = Result<reqwest::Response, OairsError>> {
+                    <$typ>::send(self)
+                }
+
+                fn is_idempotent(&self) -> bool {
+                    $idempotent
+                }
+            }
+        )+
+    };
+}
+
+impl_send_request!(
+    true =>
+    Client<Gettable>,
+    Client<Delete>,
+    EmbeddingBuilder<Sendable>,
+    ModerationBuilder<Sendable>,
+    ListEventsBuilder<Sendable>,
+    ListFilesBuilder<Sendable>,
+);
+
+impl_send_request!(
+    false =>
+    Client<Cancel>,
+    Client<Sendable>,
+    ChatBuilder<Sendable>,
+    CompletionBuilder<Sendable>,
+    EditBuilder<Sendable>,
+    FineTunesBuilder<'_, Sendable>,
+    ImageBuilder<ImageGen>,
+    ImageBuilder<ImageEdit>,
+    ImageBuilder<ImageVariation>,
+);
+
+/// A request that has been validated and built but not sent, returned by the `dry_run` method
+/// of the builders. The `Authorization` header is redacted, so a `PreparedRequest` is safe to
+/// log.
+///
+/// # Example
+/// ```rust,no_run
+/// let prepared = client
+///     .chat_completion(model, &messages)
+///     .max_tokens(100)
+///     .dry_run()?;
+/// println!("{prepared}");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreparedRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: PreparedBody,
+    // The key the request is sent with, which `headers` only shows redacted. The blocking client
+    // authenticates with it, so a builder's `api_key` override applies there too.
+    pub(crate) key: ApiKey,
+}
+
+/// The body of a [`PreparedRequest`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreparedBody {
+    Empty,
+    Json(serde_json::Value),
+    /// The fields of a multipart form, as `(name, value)` pairs. A file field's value is its
+    /// path; its contents aren't read.
+    Form(Vec<(String, String)>),
+}
+
+impl PreparedRequest {
+    pub(crate) fn new(key: &str, url: &str, method: HttpMethod, body: PreparedBody) -> Self {
+        let user_agent = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+        let key = key.strip_prefix("Bearer ").unwrap_or(key);
+        let mut headers = vec![
+            (
+                "Authorization".to_string(),
+                format!("Bearer {}", crate::credentials::redact(key)),
+            ),
+            ("User-Agent".to_string(), user_agent.to_string()),
+        ];
+        match &body {
+            PreparedBody::Json(_) => {
+                headers.push(("Content-Type".to_string(), "application/json".to_string()))
+            }
+            PreparedBody::Form(_) => headers.push((
+                "Content-Type".to_string(),
+                "multipart/form-data".to_string(),
+            )),
+            PreparedBody::Empty => (),
+        }
+
+        PreparedRequest {
+            method,
+            url: url.to_string(),
+            headers,
+            body,
+            key: ApiKey::new(key),
+        }
+    }
+
+    /// The JSON body, if the request has one.
+    pub fn json(&self) -> Option<&serde_json::Value> {
+        match &self.body {
+            PreparedBody::Json(json) => Some(json),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PreparedRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:?} {}", self.method, self.url)?;
+        for (name, value) in &self.headers {
+            writeln!(f, "{name}: {value}")?;
+        }
+        match &self.body {
+            PreparedBody::Empty => Ok(()),
+            PreparedBody::Json(json) => {
+                let pretty = serde_json::to_string_pretty(json).map_err(|_| std::fmt::Error)?;
+                write!(f, "\n{pretty}")
+            }
+            PreparedBody::Form(fields) => {
+                writeln!(f)?;
+                for (name, value) in fields {
+                    writeln!(f, "{name}={value}")?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
+// Returns an error if `path` can't be uploaded, for the `dry_run` of a multipart request.
+pub(crate) fn check_file(path: &str) -> Result<(), OairsError> {
+    let message = match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => return Ok(()),
+        Ok(_) => format!("Not a file: {path}"),
+        Err(e) => format!("{path}: {e}"),
+    };
+    Err(OairsError::new(
+        message,
+        ErrorType::FileError,
+        Some(path.to_string()),
+        None,
+    ))
+}
+
 // Below: Some helper functions for handling the request and response
 
-pub(crate) enum HttpMethod {
+// The `Authorization` header value for `key`, with or without a `Bearer ` prefix.
+pub(crate) fn bearer(key: &str) -> String {
+    match key.starts_with("Bearer ") {
+        true => key.to_string(),
+        false => format!("Bearer {key}"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
     Get,
     Post,
     Delete,
@@ -1180,75 +1880,220 @@ pub(crate) async fn handle_request(
     json: Option<serde_json::Value>,
     form: Option<reqwest::multipart::Form>,
 ) -> Result<reqwest::Response, OairsError> {
-    let client = match build_client(key) {
-        Ok(c) => c,
-        Err(e) => return Err(e),
+    #[cfg(not(target_arch = "wasm32"))]
+    let cassette = crate::record_replay::current().map(|layer| {
+        (
+            layer.mode(),
+            layer.cassette(url, http_method, json.as_ref()),
+        )
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    let cassette = match cassette {
+        Some((Mode::Replay, cassette)) => return cassette.replay().await,
+        Some((Mode::Record, cassette)) => Some(cassette),
+        None => None,
+    };
+    let request = set_method(shared_client()?, url.to_string(), http_method, key)?;
+    let request = if let Some(json) = json {
+        request
+            .header("Content-Type", "application/json")
+            .json(&json)
+    } else if let Some(form) = form {
+        // `multipart` sets the Content-Type header along with the form's boundary. Setting it
+        // here as well sends a second Content-Type without the boundary, and the API can't
+        // parse the form.
+        request.multipart(form)
+    } else {
+        request
     };
 
-    let request = match set_method(client, url.to_string(), http_method) {
-        Ok(r) => r,
-        Err(e) => return Err(e),
+    // The browser doesn't expose the timing of a `fetch` (nor does `Instant` work there), so
+    // responses aren't timed for `wasm32`.
+    #[cfg(target_arch = "wasm32")]
+    let response = send(request).await?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let response = {
+        let dns = Arc::new(Mutex::new(None));
+        let started = Instant::now();
+        let mut response = DNS_TIMING.scope(dns.clone(), send(request)).await?;
+        let timing = RequestTiming {
+            dns: *dns.lock(),
+            ttfb: started.elapsed(),
+            total: None,
+        };
+        if let Some(cassette) = cassette {
+            response = cassette.record(response).await?;
+        }
+        response
+            .extensions_mut()
+            .insert(TimingStart { started, timing });
+        response
     };
+    #[cfg(feature = "tracing")]
+    for warning in crate::meta::ApiWarning::from_headers(response.headers()) {
+        tracing::warn!(url, header = %warning.header, "{}", warning.message);
+    }
+    Ok(response)
+}
 
-    if let Some(json) = json {
-        send(
-            request
-                .header("Content-Type", "application/json")
-                .json(&json),
-        )
-        .await
-    } else if let Some(form) = form {
-        send(
-            request
-                .header("Content-Type", "multipart/form-data")
-                .multipart(form),
-        )
-        .await
-    } else {
-        send(request).await
+/// Runs `fut` to completion, or until `cancel` is cancelled, in which case it's dropped (along
+/// with any request it was making) and a `Cancelled` error is returned.
+pub(crate) async fn until_cancelled<T, F>(
+    cancel: Option<&CancellationToken>,
+    fut: F,
+) -> Result<T, OairsError>
+where
+    F: std::future::Future<Output = Result<T, OairsError>>,
+{
+    match cancel {
+        Some(token) => match token.run_until_cancelled(fut).await {
+            Some(result) => result,
+            None => Err(cancelled(None)),
+        },
+        None => fut.await,
     }
 }
 
-// (Just following reqwest example for the most part)
-pub(crate) fn build_client(key: &str) -> Result<reqwest::Client, OairsError> {
+// The `reqwest::Client` shared by every request, so its connection pool is reused across
+// requests, builders, and clones of a `Client`. The key is set on each request instead of as a
+// default header.
+static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+fn shared_client() -> Result<&'static reqwest::Client, OairsError> {
+    if let Some(client) = HTTP_CLIENT.get() {
+        return Ok(client);
+    }
     let user_agent = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+    let client = reqwest::Client::builder().user_agent(user_agent);
+    #[cfg(not(target_arch = "wasm32"))]
+    let client = client.dns_resolver(Arc::new(TimedResolver));
+    let client = client.build().map_err(builder_error)?;
+    Ok(HTTP_CLIENT.get_or_init(|| client))
+}
 
-    let mut headers = header::HeaderMap::new();
-    // Using an unwrap here because I assume the OpenAI API won't generate a key
-    // that uses non-visible ASCII characters
-    let mut auth_value = header::HeaderValue::from_str(key).unwrap();
-    auth_value.set_sensitive(true);
-    headers.insert(header::AUTHORIZATION, auth_value);
+#[cfg(not(target_arch = "wasm32"))]
+tokio::task_local! {
+    // Where the `TimedResolver` records the DNS lookup of the request being sent, for its
+    // `RequestTiming`. Unset (and the lookup not timed) when a connection is made outside of
+    // `handle_request`, or not made at all because a pooled one was reused.
+    static DNS_TIMING: Arc<Mutex<Option<Duration>>>;
+}
 
-    match reqwest::Client::builder()
-        .user_agent(user_agent)
-        .default_headers(headers)
-        .build()
-    {
-        Ok(c) => Ok(c),
-        Err(e) => Err(builder_error(e)),
+// Resolves the host of a request like reqwest's default resolver, timing the lookup for the
+// request's `RequestTiming`. The browser resolves hosts itself for `wasm32`.
+#[cfg(not(target_arch = "wasm32"))]
+struct TimedResolver;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl reqwest::dns::Resolve for TimedResolver {
+    fn resolve(&self, name: hyper::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        let dns = DNS_TIMING.try_with(|dns| dns.clone()).ok();
+        Box::pin(async move {
+            let started = Instant::now();
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if let Some(dns) = dns {
+                *dns.lock() = Some(started.elapsed());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
     }
 }
 
 pub(crate) fn set_method(
-    client: reqwest::Client,
+    client: &reqwest::Client,
     url: String,
     http_method: HttpMethod,
+    key: &str,
 ) -> Result<reqwest::RequestBuilder, OairsError> {
     let request = match http_method {
         HttpMethod::Get => client.get(url),
         HttpMethod::Post => client.post(url),
         HttpMethod::Delete => client.delete(url),
     };
+    Ok(request.header(header::AUTHORIZATION, auth_header(key)?))
+}
 
-    Ok(request)
+/// The `Authorization` header for `key` (including the `Bearer ` prefix), marked as sensitive.
+/// The key can come from anywhere, e.g. a builder's `api_key` or a file read by
+/// [`ApiKey::from_file`], so a key with characters a header can't hold is a `ParamError`.
+pub(crate) fn auth_header(key: &str) -> Result<header::HeaderValue, OairsError> {
+    let mut auth_value = header::HeaderValue::from_str(key).map_err(|e| {
+        OairsError::new(
+            format!("Invalid API key: {e}"),
+            ErrorType::ParamError,
+            Some("key".to_string()),
+            None,
+        )
+    })?;
+    auth_value.set_sensitive(true);
+    Ok(auth_value)
 }
 
 pub(crate) fn get_file_part(path: &str) -> Result<Part, OairsError> {
+    file_part(path, None)
+}
+
+/// Reads the file at `path` into a multipart form `Part`, e.g. for a
+/// [`custom_form_request`](Client::custom_form_request). The part is named with the file's
+/// basename, and its MIME type is `mime`, or else inferred from the extension with
+/// [`mime_type`]. An unrecognized extension gets no MIME type.
+///
+/// Returns a `FileError` if the file can't be read, or a `ParamError` if `mime` isn't a valid
+/// MIME type.
+///
+/// # Example
+/// ```rust,no_run
+/// let part = file_part("recordings/meeting.ogg", Some("audio/ogg"))?;
+/// let form = reqwest::multipart::Form::new().part("file", part);
+/// ```
+pub fn file_part(path: &str, mime: Option<&str>) -> Result<Part, OairsError> {
     let file = std::fs::read(path)?;
-    let file_part = Part::bytes(file).file_name(path.to_string());
+    bytes_part(path, file, mime)
+}
+
+/// Like [`file_part`], but with the contents of the file at `path` already in memory.
+pub fn bytes_part(path: &str, bytes: Vec<u8>, mime: Option<&str>) -> Result<Part, OairsError> {
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    let part = Part::bytes(bytes).file_name(file_name);
 
-    Ok(file_part)
+    match mime.or_else(|| mime_type(path)) {
+        Some(mime) => part.mime_str(mime).map_err(|e| {
+            OairsError::new(
+                format!("Invalid MIME type {mime}: {e}"),
+                ErrorType::ParamError,
+                Some(mime.to_string()),
+                None,
+            )
+        }),
+        None => Ok(part),
+    }
+}
+
+/// The MIME type of a file the API accepts, inferred from the extension of `path`, e.g.
+/// `audio/mpeg` for `.mp3`, `image/png` for `.png`, or `application/jsonl` for `.jsonl`.
+/// `None` for any other extension.
+pub fn mime_type(path: &str) -> Option<&'static str> {
+    let extension = std::path::Path::new(path)
+        .extension()?
+        .to_str()?
+        .to_lowercase();
+    let mime = match extension.as_str() {
+        "mp3" | "mpeg" | "mpga" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "wav" => "audio/wav",
+        "webm" => "audio/webm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "jsonl" => "application/jsonl",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        "txt" => "text/plain",
+        _ => return None,
+    };
+    Some(mime)
 }
 
 pub(crate) async fn send(
@@ -1271,3 +2116,305 @@ pub(crate) async fn check_status(
         _ => Err(parse_api_error(response, sc).await),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run() {
+        let client = Client::new("sk-abcdefghijklmnop");
+        let prepared = client
+            .chat_completion(ChatModel::default(), &Messages::default())
+            .dry_run()
+            .unwrap();
+
+        assert_eq!(prepared.url, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(prepared.json().unwrap()["model"], "gpt-3.5-turbo");
+        assert!(prepared
+            .headers
+            .contains(&("Authorization".to_string(), "Bearer sk-...mnop".to_string())));
+        assert!(!prepared.to_string().contains("abcdefghijkl"));
+
+        let error = client
+            .create_image_variation("no/such/image.png")
+            .dry_run()
+            .unwrap_err();
+        assert_eq!(error.error_type, ErrorType::FileError.to_string());
+
+        let prepared = client
+            .upload_file("Cargo.toml", Purpose::FineTune)
+            .dry_run()
+            .unwrap();
+        assert_eq!(
+            prepared.body,
+            PreparedBody::Form(vec![
+                ("purpose".to_string(), "fine-tune".to_string()),
+                ("file".to_string(), "Cargo.toml".to_string()),
+            ])
+        );
+
+        let prepared = client
+            .upload_bytes("no/such/train.jsonl", vec![0; 4], Purpose::FineTune)
+            .dry_run()
+            .unwrap();
+        assert_eq!(
+            prepared.body,
+            PreparedBody::Form(vec![
+                ("purpose".to_string(), "fine-tune".to_string()),
+                (
+                    "file".to_string(),
+                    "<4 bytes of no/such/train.jsonl>".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_api_key() {
+        let client = Client::new("sk-abcdefghijklmnop");
+        let messages = Messages::new(vec![crate::completions::Msg::user("Test")]);
+        let error = client
+            .chat_completion(ChatModel::default(), &messages)
+            .api_key("sk-abc\ndef")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(error.error_type, ErrorType::ParamError.to_string());
+        assert_eq!(error.param.as_deref(), Some("key"));
+    }
+
+    #[test]
+    fn resource_handles() {
+        let client = Client::new("sk-abcdefghijklmnop").with_base_url("http://localhost:8080/v1");
+        assert_eq!(
+            client.files().content("file-abc").dry_run().unwrap(),
+            client.retrieve_file_content("file-abc").dry_run().unwrap()
+        );
+        assert_eq!(
+            client.fine_tunes().cancel("ft-abc").dry_run().unwrap().url,
+            "http://localhost:8080/v1/fine-tunes/ft-abc/cancel"
+        );
+        assert_eq!(
+            client
+                .models()
+                .retrieve(&ChatModel::Gpt4)
+                .dry_run()
+                .unwrap()
+                .url,
+            "http://localhost:8080/v1/models/gpt-4"
+        );
+        assert_eq!(
+            client.images().generate("A cat").dry_run().unwrap(),
+            client.create_image("A cat").dry_run().unwrap()
+        );
+    }
+
+    #[test]
+    fn file_mime_type() {
+        assert_eq!(mime_type("audio/speech.MP3"), Some("audio/mpeg"));
+        assert_eq!(mime_type("mask.png"), Some("image/png"));
+        assert_eq!(mime_type("data/train.jsonl"), Some("application/jsonl"));
+        assert_eq!(mime_type("README"), None);
+        assert!(file_part("Cargo.toml", Some("not a mime type")).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use super::*;
+    use crate::{
+        completions::Msg,
+        files::response::FileInfo,
+        images::response::Image,
+        test_util::{fixtures::FILE_CONTENT, MockApi, TEST_API_KEY},
+    };
+
+    #[tokio::test]
+    async fn multipart_requests() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let dir = std::env::temp_dir().join(format!("oairs-multipart-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let training = dir.join("training.jsonl");
+        std::fs::write(&training, FILE_CONTENT).unwrap();
+        let image = dir.join("image.png");
+        std::fs::write(&image, b"not really a png").unwrap();
+        let (training, image) = (training.to_str().unwrap(), image.to_str().unwrap());
+
+        let info = client
+            .upload_file(training, crate::files::Purpose::FineTune)
+            .send()
+            .await
+            .unwrap();
+        info.json::<FileInfo>().await.unwrap();
+        let edit = client
+            .create_image_edit(image, "Add a hat")
+            .send()
+            .await
+            .unwrap();
+        edit.json::<Image>().await.unwrap();
+        let variation = client.create_image_variation(image).send().await.unwrap();
+        variation.json::<Image>().await.unwrap();
+
+        for endpoint in ["/files", "/images/edits", "/images/variations"] {
+            let requests = api.requests(endpoint).await;
+            let content_type = requests[0].headers.get(&"content-type".into()).unwrap();
+            assert_eq!(content_type.iter().count(), 1, "{endpoint}");
+            let content_type = content_type.last().as_str();
+            assert!(
+                content_type.starts_with("multipart/form-data; boundary="),
+                "{endpoint}: {content_type}"
+            );
+            let boundary = content_type.split("boundary=").nth(1).unwrap();
+            let body = String::from_utf8_lossy(&requests[0].body);
+            assert!(body.contains(boundary), "{endpoint}");
+        }
+        let body = String::from_utf8_lossy(&api.requests("/files").await[0].body).to_string();
+        assert!(body.contains("name=\"purpose\"\r\n\r\nfine-tune"));
+        assert!(body.contains(FILE_CONTENT));
+        assert!(body
+            .to_lowercase()
+            .contains("filename=\"training.jsonl\"\r\ncontent-type: application/jsonl"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn upload_bytes() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let upload = client.upload_bytes(
+            "training.jsonl",
+            FILE_CONTENT.as_bytes().to_vec(),
+            crate::files::Purpose::FineTune,
+        );
+        assert!(upload.is_in_memory());
+
+        let info = upload.send().await.unwrap();
+        info.json::<FileInfo>().await.unwrap();
+
+        let body = String::from_utf8_lossy(&api.requests("/files").await[0].body).to_string();
+        assert!(body.contains(FILE_CONTENT));
+        assert!(body
+            .to_lowercase()
+            .contains("filename=\"training.jsonl\"\r\ncontent-type: application/jsonl"));
+    }
+
+    #[tokio::test]
+    async fn custom_request_send_json() {
+        let api = MockApi::start().await;
+        let client = api.client();
+
+        let list: ModelsList = client
+            .custom_request(HttpMethod::Get, "models", None)
+            .send_json()
+            .await
+            .unwrap();
+        assert!(!list.data.is_empty());
+
+        let error = client
+            .custom_request(HttpMethod::Get, "models", None)
+            .send_json::<FileInfo>()
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, Some(200));
+        assert!(error.message.contains("FileInfo"), "{}", error.message);
+        assert!(error.message.contains("Body: {"), "{}", error.message);
+    }
+
+    #[tokio::test]
+    async fn send_request_trait() {
+        async fn status<R: crate::client::SendRequest>(request: &R) -> u16 {
+            request.send().await.unwrap().status().as_u16()
+        }
+
+        let api = MockApi::start().await;
+        let client = api.client();
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let chat = client.chat_completion(crate::ChatModel::default(), &messages);
+        assert_eq!(status(&chat).await, 200);
+        let embeddings = client.create_embeddings(crate::EmbeddingModel::default(), &["Test"]);
+        assert_eq!(status(&embeddings).await, 200);
+        assert_eq!(api.requests("/embeddings").await.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn client_shared_across_tasks() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let tasks: Vec<_> = (0..4)
+            .map(|i| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    client
+                        .create_moderation(format!("Input {i}"))
+                        .send_parsed()
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap().is_ok());
+        }
+
+        let requests = api.requests("/moderations").await;
+        assert_eq!(requests.len(), 4);
+        for request in requests {
+            let authorization = request.headers.get(&"authorization".into()).unwrap();
+            assert_eq!(
+                authorization.last().as_str(),
+                format!("Bearer {TEST_API_KEY}")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn per_builder_api_key() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let authorization = |request: &wiremock::Request| {
+            let values = request.headers.get(&"authorization".into()).unwrap();
+            values.last().as_str().to_string()
+        };
+
+        let mut chat = client.chat_completion(crate::ChatModel::default(), &messages);
+        chat.send().await.unwrap();
+        chat.api_key("sk-project-b").send().await.unwrap();
+        let requests = api.requests("/chat/completions").await;
+        assert_eq!(
+            authorization(&requests[0]),
+            format!("Bearer {TEST_API_KEY}")
+        );
+        assert_eq!(authorization(&requests[1]), "Bearer sk-project-b");
+
+        #[cfg(feature = "blocking")]
+        {
+            let base_url = api.base_url();
+            tokio::task::spawn_blocking(move || {
+                let client = crate::blocking::Client::new(TEST_API_KEY).with_base_url(base_url);
+                let messages = Messages::new(vec![Msg::user("Test")]);
+                client
+                    .chat_completion(ChatModel::default(), &messages)
+                    .configure(|chat| {
+                        chat.api_key("sk-project-d");
+                    })
+                    .send()
+                    .unwrap();
+            })
+            .await
+            .unwrap();
+            let requests = api.requests("/chat/completions").await;
+            assert_eq!(authorization(&requests[2]), "Bearer sk-project-d");
+        }
+
+        let mut delete = client.delete_files_where(|file: &FileInfo| file.purpose == "fine-tune");
+        delete.api_key(crate::credentials::ApiKey::new("sk-project-c"));
+        assert!(delete.send().await.unwrap().all_succeeded());
+        let list = api.requests("/files").await;
+        let deleted = api.requests("/files/file-XjGxS3KTG0uNmNOK362iJua3").await;
+        assert_eq!(authorization(&list[0]), "Bearer sk-project-c");
+        assert_eq!(authorization(&deleted[0]), "Bearer sk-project-c");
+    }
+}
@@ -0,0 +1,288 @@
+//! Resource-scoped views of a [`Client`], grouping its methods by the endpoint they call:
+//! [`Client::files`], [`Client::fine_tunes`], [`Client::images`], and [`Client::models`].
+//!
+//! The flat methods of the client (e.g. [`Client::upload_file`]) delegate to these handles, so
+//! either form sends the same request.
+
+use super::*;
+
+/// The file endpoints of a [`Client`]. See [`Client::files`].
+#[derive(Debug, Clone, Copy)]
+pub struct FilesHandle<'a> {
+    client: &'a Client<Keyed>,
+}
+
+impl<'a> FilesHandle<'a> {
+    /// See [`Client::upload_file`].
+    pub fn upload<F: Into<String>>(&self, file: F, purpose: Purpose) -> Client<Sendable> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.files()),
+            endpoints: self.client.endpoints.clone(),
+            upload_filename: Some(file.into()),
+            file_purpose: Some(purpose),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::upload_bytes`].
+    pub fn upload_bytes<F: Into<String>>(
+        &self,
+        filename: F,
+        bytes: Vec<u8>,
+        purpose: Purpose,
+    ) -> Client<Sendable> {
+        Client {
+            upload_bytes: Some(bytes),
+            ..self.upload(filename, purpose)
+        }
+    }
+
+    /// See [`Client::list_files`].
+    pub fn list(&self) -> ListFilesBuilder<Sendable> {
+        let mut builder = ListFilesBuilder::create(self.client.next_key());
+        builder.set_url(self.client.endpoints.files());
+        builder
+    }
+
+    /// See [`Client::retrieve_file`].
+    pub fn retrieve(&self, file_id: &str) -> Client<Gettable> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.file(file_id)),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::retrieve_file_content`].
+    pub fn content(&self, file_id: &str) -> Client<Gettable> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.file_content(file_id)),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::download_file`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn download<P: Into<std::path::PathBuf>>(
+        &self,
+        file_id: &str,
+        path: P,
+    ) -> DownloadFileBuilder<Sendable> {
+        let mut builder = DownloadFileBuilder::create(self.client.next_key(), file_id, path);
+        builder.set_endpoints(&self.client.endpoints);
+        builder
+    }
+
+    /// See [`Client::delete_file`].
+    pub fn delete(&self, file_id: &str) -> Client<Delete> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.file(file_id)),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::delete_files`].
+    pub fn delete_many<I, S>(&self, file_ids: I) -> DeleteFilesBuilder<Sendable>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let file_ids = file_ids.into_iter().map(Into::into).collect();
+        let mut builder = DeleteFilesBuilder::create(self.client.next_key(), file_ids);
+        builder.set_endpoints(&self.client.endpoints);
+        builder
+    }
+
+    /// See [`Client::delete_files_where`].
+    pub fn delete_where<F>(&self, filter: F) -> DeleteFilesBuilder<Sendable>
+    where
+        F: Fn(&FileInfo) -> bool + Send + Sync + 'static,
+    {
+        let mut builder =
+            DeleteFilesBuilder::create_where(self.client.next_key(), self.list(), Arc::new(filter));
+        builder.set_endpoints(&self.client.endpoints);
+        builder
+    }
+}
+
+/// The fine-tune endpoints of a [`Client`]. See [`Client::fine_tunes`].
+#[derive(Debug, Clone, Copy)]
+pub struct FineTunesHandle<'a> {
+    client: &'a Client<Keyed>,
+}
+
+impl<'a> FineTunesHandle<'a> {
+    /// See [`Client::create_fine_tune`].
+    pub fn create<'b>(&self, training_file_id: &'b str) -> FineTunesBuilder<'b, Sendable> {
+        let mut builder = FineTunesBuilder::create(self.client.next_key(), training_file_id);
+        builder.set_url(self.client.endpoints.fine_tunes());
+        builder
+    }
+
+    /// See [`Client::list_fine_tunes`].
+    pub fn list(&self) -> Client<Gettable> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.fine_tunes()),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::retrieve_fine_tune_info`].
+    pub fn retrieve(&self, fine_tune_id: &str) -> Client<Gettable> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.fine_tune(fine_tune_id)),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::list_fine_tune_events`].
+    pub fn events(&self, fine_tune_id: &str) -> ListEventsBuilder<Sendable> {
+        let mut builder = ListEventsBuilder::new(&self.client.next_key(), fine_tune_id);
+        builder.set_url(self.client.endpoints.fine_tune_events(fine_tune_id));
+        builder
+    }
+
+    /// See [`Client::cancel_fine_tune`].
+    pub fn cancel(&self, fine_tune_id: &str) -> Client<Cancel> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.cancel_fine_tune(fine_tune_id)),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::delete_fine_tune_model`].
+    pub fn delete_model(&self, model: &str) -> Client<Delete> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.model(model)),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The image endpoints of a [`Client`]. See [`Client::images`].
+#[derive(Debug, Clone, Copy)]
+pub struct ImagesHandle<'a> {
+    client: &'a Client<Keyed>,
+}
+
+impl<'a> ImagesHandle<'a> {
+    /// See [`Client::create_image`].
+    pub fn generate<P: Into<String>>(&self, prompt: P) -> ImageBuilder<ImageGen> {
+        let mut builder = ImageBuilder::create_image(self.client.next_key(), prompt);
+        builder.set_url(self.client.endpoints.image_generations());
+        builder
+    }
+
+    /// See [`Client::create_image_edit`].
+    pub fn edit<I, P>(&self, image: I, prompt: P) -> ImageBuilder<ImageEdit>
+    where
+        I: Into<ImageInput>,
+        P: Into<String>,
+    {
+        let mut builder = ImageBuilder::create_edit(self.client.next_key(), image, prompt);
+        builder.set_url(self.client.endpoints.image_edits());
+        builder
+    }
+
+    /// See [`Client::create_image_variation`].
+    pub fn variation<I: Into<ImageInput>>(&self, image: I) -> ImageBuilder<ImageVariation> {
+        let mut builder = ImageBuilder::create_variation(self.client.next_key(), image);
+        builder.set_url(self.client.endpoints.image_variations());
+        builder
+    }
+}
+
+/// The model endpoints of a [`Client`]. See [`Client::models`].
+#[derive(Debug, Clone, Copy)]
+pub struct ModelsHandle<'a> {
+    client: &'a Client<Keyed>,
+}
+
+impl<'a> ModelsHandle<'a> {
+    /// See [`Client::list_models`].
+    pub fn list(&self) -> Client<Gettable> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.models()),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::retrieve_model`].
+    pub fn retrieve<R: RetrievableModel>(&self, model: &R) -> Client<Gettable> {
+        Client {
+            key: self.client.next_key().into(),
+            url: Some(self.client.endpoints.model(model.to_str())),
+            endpoints: self.client.endpoints.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// See [`Client::available_models`].
+    pub async fn available(&self) -> Result<AvailableModels, OairsError> {
+        if let Some(models) = self.client.available_models.lock().as_ref() {
+            return Ok(models.clone());
+        }
+        let res = self.list().send().await?;
+        let list = deserialize_response::<ModelsList>(res).await?;
+        let models = AvailableModels::from_ids(list.data.iter().map(|model| &model.id));
+        *self.client.available_models.lock() = Some(models.clone());
+        Ok(models)
+    }
+
+    /// See [`Client::model_exists`].
+    pub async fn exists<R: RetrievableModel>(&self, model: &R) -> Result<bool, OairsError> {
+        Ok(self.available().await?.contains(model.to_str()))
+    }
+
+    /// See [`Client::clear_available_models`].
+    pub fn clear_available(&self) {
+        *self.client.available_models.lock() = None;
+    }
+}
+
+impl Client<Keyed> {
+    /// The file endpoints: upload, list, retrieve, download, and delete.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let files = client.files();
+    /// let info = files.upload("data/train.jsonl", Purpose::FineTune).send().await?;
+    /// let list = files.list().send_parsed().await?;
+    /// ```
+    pub fn files(&self) -> FilesHandle<'_> {
+        FilesHandle { client: self }
+    }
+
+    /// The fine-tune endpoints: create, list, retrieve, events, and cancel, as well as deleting
+    /// a fine-tuned model.
+    pub fn fine_tunes(&self) -> FineTunesHandle<'_> {
+        FineTunesHandle { client: self }
+    }
+
+    /// The image endpoints: generate, edit, and variation.
+    pub fn images(&self) -> ImagesHandle<'_> {
+        ImagesHandle { client: self }
+    }
+
+    /// The model endpoints: list and retrieve, as well as the cached list of
+    /// [`available`](ModelsHandle::available) models.
+    pub fn models(&self) -> ModelsHandle<'_> {
+        ModelsHandle { client: self }
+    }
+}
@@ -0,0 +1,278 @@
+//! Contains [`batch`], [`batch_with_retries`] and [`batch_adaptive`], for sending one completion
+//! request per prompt concurrently, and the [`BatchResults`] they return.
+
+use futures::{stream, StreamExt};
+
+use super::{response::Completion, *};
+use crate::{
+    meta::deserialize_response,
+    request_set::{AdaptiveConcurrency, Throttle},
+    retry::RetryPolicy,
+};
+
+/// Sends a completion request for each of the `prompts`, with the settings (model, `max_tokens`,
+/// `temperature`, etc.) of `settings`, up to `max_concurrency` at a time. Any prompts set on
+/// `settings` are ignored.
+///
+/// Unlike [`CompletionBuilder::prompts`], which sends every prompt in one request, each prompt
+/// gets its own request, so one failing prompt doesn't fail the rest. Requests that are rate
+/// limited are retried with exponential backoff, as the default [`RetryPolicy`] allows. A
+/// request that timed out or lost its connection may already have been billed, so it isn't
+/// retried; see [`batch_with_retries`] to retry it anyway.
+///
+/// Returns a `ParamError` if `max_concurrency` is 0 or `settings` is set to stream.
+///
+/// # Example
+/// ```rust,no_run
+/// let mut settings = client.completion(CompletionModel::TextDavinci003);
+/// settings.max_tokens(100).temperature(Temperature::new(0.2));
+///
+/// let results = completions::batch(&settings, prompts, 4).await?;
+/// for (prompt, result) in results.iter() {
+///     match result {
+///         Ok(completion) => println!("{prompt}: {}", completion.choices[0].text),
+///         Err(e) => eprintln!("{prompt}: {e}"),
+///     }
+/// }
+/// println!("Total tokens: {}", results.usage().total_tokens);
+/// ```
+pub async fn batch(
+    settings: &CompletionBuilder<Sendable>,
+    prompts: Vec<String>,
+    max_concurrency: usize,
+) -> Result<BatchResults, OairsError> {
+    batch_with_retries(settings, prompts, max_concurrency, &RetryPolicy::new()).await
+}
+
+/// Like [`batch`], but retries failed requests as `policy` allows. Completions aren't
+/// idempotent, so only rate limited requests are retried unless the policy is set to
+/// [`retry_unsafe`](RetryPolicy::retry_unsafe).
+///
+/// # Example
+/// ```rust,no_run
+/// let mut policy = RetryPolicy::new();
+/// policy.max_retries(5).retry_unsafe(true);
+///
+/// let results = completions::batch_with_retries(&settings, prompts, 4, &policy).await?;
+/// ```
+pub async fn batch_with_retries(
+    settings: &CompletionBuilder<Sendable>,
+    prompts: Vec<String>,
+    max_concurrency: usize,
+    policy: &RetryPolicy,
+) -> Result<BatchResults, OairsError> {
+    if max_concurrency == 0 {
+        return Err(OairsError::new(
+            "max_concurrency must be greater than 0".to_string(),
+            ErrorType::ParamError,
+            Some("max_concurrency".to_string()),
+            None,
+        ));
+    }
+    send_batch(settings, prompts, max_concurrency, policy, None).await
+}
+
+/// Like [`batch`], but adjusts the number of requests in flight as they're sent, backing off
+/// when they're rate limited or slow down, instead of sending a fixed number at a time. See
+/// [`AdaptiveConcurrency`]. Each retry counts towards the concurrency as a request of its own.
+///
+/// Returns a `ParamError` if `adaptive` is invalid or `settings` is set to stream.
+///
+/// # Example
+/// ```rust,no_run
+/// let mut adaptive = AdaptiveConcurrency::new();
+/// adaptive.max_concurrency(32);
+///
+/// let results = completions::batch_adaptive(&settings, prompts, adaptive).await?;
+/// ```
+pub async fn batch_adaptive(
+    settings: &CompletionBuilder<Sendable>,
+    prompts: Vec<String>,
+    adaptive: AdaptiveConcurrency,
+) -> Result<BatchResults, OairsError> {
+    adaptive.validate()?;
+    let throttle = Throttle::new(adaptive);
+    let policy = RetryPolicy::new();
+    send_batch(settings, prompts, throttle.max(), &policy, Some(&throttle)).await
+}
+
+async fn send_batch(
+    settings: &CompletionBuilder<Sendable>,
+    prompts: Vec<String>,
+    max_concurrency: usize,
+    policy: &RetryPolicy,
+    throttle: Option<&Throttle>,
+) -> Result<BatchResults, OairsError> {
+    if settings.is_stream() {
+        return Err(OairsError::new(
+            "Streamed completions can't be batched".to_string(),
+            ErrorType::ParamError,
+            Some("stream".to_string()),
+            None,
+        ));
+    }
+
+    let builders: Vec<CompletionBuilder<Sendable>> = prompts
+        .iter()
+        .map(|prompt| settings.with_prompt(prompt.clone()))
+        .collect();
+    let results = stream::iter(builders.iter())
+        .map(|builder| send_with_retries(builder, policy, throttle))
+        .buffered(max_concurrency)
+        .collect()
+        .await;
+
+    Ok(BatchResults { prompts, results })
+}
+
+async fn send_with_retries(
+    builder: &CompletionBuilder<Sendable>,
+    policy: &RetryPolicy,
+    throttle: Option<&Throttle>,
+) -> Result<Completion, OairsError> {
+    policy
+        .run(false, || async {
+            let permit = match throttle {
+                Some(throttle) => Some(throttle.acquire().await),
+                None => None,
+            };
+            let result = match builder.send().await {
+                Ok(res) => deserialize_response::<Completion>(res).await,
+                Err(e) => Err(e),
+            };
+            if let Some(permit) = permit {
+                permit.record(&result);
+            }
+            result
+        })
+        .await
+}
+
+/// The result of each request sent by [`batch`], in the order of the prompts.
+#[derive(Debug)]
+pub struct BatchResults {
+    prompts: Vec<String>,
+    results: Vec<Result<Completion, OairsError>>,
+}
+
+impl BatchResults {
+    pub fn prompts(&self) -> &[String] {
+        &self.prompts
+    }
+
+    pub fn results(&self) -> &[Result<Completion, OairsError>] {
+        &self.results
+    }
+
+    /// Pairs each prompt with the result of its request.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Result<Completion, OairsError>)> {
+        self.prompts
+            .iter()
+            .map(|p| p.as_str())
+            .zip(self.results.iter())
+    }
+
+    /// The completions of the requests that succeeded, with the index of their prompt.
+    pub fn completions(&self) -> impl Iterator<Item = (usize, &Completion)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().ok().map(|c| (i, c)))
+    }
+
+    /// The errors of the requests that failed, with the index of their prompt.
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &OairsError)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().err().map(|e| (i, e)))
+    }
+
+    pub fn all_succeeded(&self) -> bool {
+        self.results.iter().all(|r| r.is_ok())
+    }
+
+    /// The [`Usage`] of all the requests that succeeded, summed.
+    pub fn usage(&self) -> Usage {
+        let mut usage = Usage {
+            prompt_tokens: 0,
+            completion_tokens: Some(0),
+            total_tokens: 0,
+        };
+        for (_, completion) in self.completions() {
+            usage += completion.usage;
+        }
+        usage
+    }
+
+    /// Consumes the results, returning them in the order of the prompts.
+    pub fn into_results(self) -> Vec<Result<Completion, OairsError>> {
+        self.results
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::*;
+    use crate::test_util::{
+        fixtures::RATE_LIMIT_ERROR,
+        wiremock::{
+            matchers::{body_string_contains, method, path},
+            Mock, ResponseTemplate,
+        },
+        MockApi,
+    };
+
+    #[tokio::test]
+    async fn batch_completions() {
+        let api = MockApi::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/completions"))
+            .and(body_string_contains("bad prompt"))
+            .respond_with(ResponseTemplate::new(400).set_body_string(RATE_LIMIT_ERROR))
+            .mount(api.server())
+            .await;
+
+        let client = api.client();
+        let mut settings = client.completion(CompletionModel::default());
+        settings.max_tokens(5);
+        let prompts = vec![
+            "one".to_string(),
+            "bad prompt".to_string(),
+            "three".to_string(),
+        ];
+
+        let results = batch(&settings, prompts, 2).await.unwrap();
+
+        assert_eq!(api.requests("/completions").await.len(), 3);
+        assert!(!results.all_succeeded());
+        assert_eq!(
+            results.completions().map(|(i, _)| i).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert_eq!(results.errors().next().unwrap().0, 1);
+        assert_eq!(results.usage().total_tokens, 26);
+        assert!(batch(&settings, vec![], 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn batch_adaptive_completions() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let mut settings = client.completion(CompletionModel::default());
+        settings.max_tokens(5);
+        let prompts = (0..6).map(|i| i.to_string()).collect();
+
+        let results = batch_adaptive(&settings, prompts, AdaptiveConcurrency::new())
+            .await
+            .unwrap();
+
+        assert_eq!(api.requests("/completions").await.len(), 6);
+        assert!(results.all_succeeded());
+        assert_eq!(results.prompts()[5], "5");
+
+        let mut invalid = AdaptiveConcurrency::new();
+        invalid.min_concurrency(8).max_concurrency(2);
+        assert!(batch_adaptive(&settings, vec![], invalid).await.is_err());
+    }
+}
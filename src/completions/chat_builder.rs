@@ -1,43 +1,109 @@
 // TODO: Clean up type-state pattern!
 
-use serde::ser::SerializeSeq;
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use super::{response::ChatCompletion, *};
+use serde::ser::{SerializeMap, SerializeSeq};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bytes::Bytes;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::{stream, Stream};
+use tokio::io::AsyncWrite;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::{sync::mpsc, task::JoinHandle};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::{chat_stream::ChannelSink, response::ChatArchiveRecord};
+use super::{
+    chat_stream::{
+        count_tokens, read_stream, CallbackSink, ChatStreamState, StreamEnd, TokenSink, WriterSink,
+    },
+    response::ChatCompletion,
+    *,
+};
+#[cfg(feature = "tokenizers")]
 use crate::{
-    client::{handle_request, HttpMethod},
+    budget::{spend, TokenBudget},
+    text::redact::Redactor,
     tokenizers::{tokenize, Tokenizer},
 };
+use crate::{
+    client::{bearer, handle_request, until_cancelled, HttpMethod, PreparedBody, PreparedRequest},
+    meta::{deserialize_response, with_meta, ResponseMeta},
+    utils::raw::tee_raw,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{meta::TimingStart, record_replay, sse};
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChatBuilder<Buildable> {
     #[serde(skip)]
     key: String,
     #[serde(skip)]
-    url: String,
+    url: Cow<'static, str>,
     model: ChatModel,
     messages: Messages,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<Temperature>,
     #[serde(skip_serializing_if = "Option::is_none")]
     top_p: Option<TopP>,
-    n: u8,
-    stream: bool,
+    /// Defaults to 1 if `n` is not specified.
     #[serde(skip_serializing_if = "Option::is_none")]
-    stop: Option<Vec<String>>,
+    n: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<StopSequences>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u16>,
     /// Defaults to 0.0 if `presence_penalty` is not specified.
-    presence_penalty: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
     /// Defaults to 0.0 if `frequency_penalty` is not specified.
-    frequency_penalty: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     logit_bias: Option<HashMap<String, f32>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    archive_dir: Option<PathBuf>,
+    #[serde(skip)]
+    raw_dir: Option<PathBuf>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    budget: Option<TokenBudget>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    redactor: Option<Redactor>,
+    #[serde(skip)]
+    reconnect: u8,
+    #[serde(skip)]
+    validate_messages: bool,
+    #[serde(skip)]
+    auto_continue: u8,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    check_context: bool,
+    #[serde(skip)]
+    cancel: Option<CancellationToken>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
     #[serde(skip)]
     state: PhantomData<Buildable>,
 }
 
+/// The deltas buffered by [`ChatBuilder::send_sse`] while the client is slow to read them.
+#[cfg(not(target_arch = "wasm32"))]
+const SSE_BUFFER: usize = 32;
+
+/// The instruction sent after a truncated reply by [`ChatBuilder::auto_continue`].
+const CONTINUE_INSTRUCTION: &str =
+    "Continue exactly where you left off, without repeating anything you already wrote.";
+
 impl ChatBuilder<Buildable> {
     pub fn create<K>(key: K, model: ChatModel, msgs: &Messages) -> ChatBuilder<Sendable>
     where
@@ -45,10 +111,9 @@ impl ChatBuilder<Buildable> {
     {
         ChatBuilder {
             key: key.into(),
-            url: chat_completion_url().to_string(),
+            url: Endpoints::default().chat_completions(),
             model,
             messages: msgs.to_owned(),
-            n: 1,
             state: PhantomData::<Sendable>,
             ..Default::default()
         }
@@ -56,6 +121,126 @@ impl ChatBuilder<Buildable> {
 }
 
 impl ChatBuilder<Sendable> {
+    /// The tokens the request is estimated to use, as spent from a [`TokenBudget`]: the tokens
+    /// of the messages' content, plus `max_tokens` (if set) for each of the `n` choices.
+    #[cfg(feature = "tokenizers")]
+    pub fn estimated_tokens(&self) -> Result<usize, OairsError> {
+        let mut tokens = 0;
+        for msg in &self.messages.data {
+            tokens += tokenize(msg.content(), self.model.tokenizer())?.len();
+        }
+        Ok(tokens + self.max_tokens.unwrap_or(0) as usize * self.choices() as usize)
+    }
+
+    /// The tokens the messages count against the model's context window, as the API counts
+    /// them: the tokens of each message's role, content, and name, plus 3 for each message's
+    /// delimiters and 3 to prime the reply.
+    #[cfg(feature = "tokenizers")]
+    pub fn prompt_tokens(&self) -> Result<usize, OairsError> {
+        let tokenizer = self.model.tokenizer();
+        let mut tokens = 3;
+        for msg in &self.messages.data {
+            tokens += 3;
+            tokens += tokenize(&msg.role().to_string(), tokenizer)?.len();
+            tokens += tokenize(msg.content(), tokenizer)?.len();
+            if let Some(name) = msg.name() {
+                tokens += tokenize(name, tokenizer)?.len() + 1;
+            }
+        }
+        Ok(tokens)
+    }
+
+    // Returns a `ParamError` if the messages and `max_tokens` don't fit in the model's context
+    // window, when `check_context` is set.
+    #[cfg(feature = "tokenizers")]
+    fn check_context_window(&self) -> Result<(), OairsError> {
+        if !self.check_context {
+            return Ok(());
+        }
+        let window = self.model.max_context_tokens();
+        let prompt = self.prompt_tokens()?;
+        let (message, param) = match self.max_tokens {
+            Some(max_tokens) if prompt + max_tokens as usize > window => (
+                format!(
+                    "The messages ({prompt} tokens) and max_tokens ({max_tokens}) need {} tokens, but {} has a context window of {window} tokens",
+                    prompt + max_tokens as usize,
+                    self.model
+                ),
+                "max_tokens",
+            ),
+            _ if prompt >= window => (
+                format!(
+                    "The messages need {prompt} tokens, but {} has a context window of {window} tokens",
+                    self.model
+                ),
+                "messages",
+            ),
+            _ => return Ok(()),
+        };
+        Err(OairsError::new(
+            message,
+            ErrorType::ParamError,
+            Some(param.to_string()),
+            None,
+        ))
+    }
+
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key (or the next key of its
+    /// [`KeyRing`](crate::credentials::KeyRing)), e.g. to bill it to another project. Everything
+    /// else comes from the client as usual, e.g. its base url, cache, and budget. The key can
+    /// be given with or without a `Bearer ` prefix, or as an
+    /// [`ApiKey`](crate::credentials::ApiKey).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let client = Client::new(ApiKey::from_env().unwrap());
+    /// let project_b = ApiKey::from_env_var("PROJECT_B_API_KEY").unwrap();
+    ///
+    /// let res = client
+    ///     .chat_completion(ChatModel::Gpt4, &messages)
+    ///     .api_key(project_b)
+    ///     .send()
+    ///     .await?;
+    /// ```
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
+    #[cfg(feature = "tokenizers")]
+    pub(crate) fn set_budget(&mut self, budget: Option<TokenBudget>) -> &mut Self {
+        self.budget = budget;
+        self
+    }
+
+    // The number of choices the API returns: `n`, or 1 if it isn't set.
+    fn choices(&self) -> u8 {
+        self.n.unwrap_or(1)
+    }
+
+    /// A copy of the builder's settings (model, `temperature`, `max_tokens`, etc.) with `msgs`
+    /// in place of its messages, so one configured request can be sent for many conversations.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let mut template = client.chat_completion(ChatModel::default(), &Messages::default());
+    /// template.temperature(Temperature::new(0.0)).max_tokens(50);
+    ///
+    /// for conversation in &conversations {
+    ///     let response = template.clone_with_messages(conversation).send().await?;
+    /// }
+    /// ```
+    pub fn clone_with_messages(&self, msgs: &Messages) -> ChatBuilder<Sendable> {
+        let mut b = self.clone();
+        b.messages = msgs.clone();
+        b
+    }
+
     /// The amount of randomness for the model to use when generating the
     /// completion. The valid range is 0 to 2. A value of 2 can lead to
     /// incoherent completions.
@@ -75,7 +260,7 @@ impl ChatBuilder<Sendable> {
     /// Number of chat completions to generate per prompt.
     /// [OpenAI API docs](https://platform.openai.com/docs/api-reference/chat/create#chat/create-n)
     pub fn n(&mut self, n: u8) -> &mut Self {
-        self.n = n;
+        self.n = Some(n);
         self
     }
 
@@ -83,13 +268,15 @@ impl ChatBuilder<Sendable> {
     /// as they become available, with the stream terminated by a `data: [DONE]` message."
     /// - [OpenAI API docs](https://platform.openai.com/docs/api-reference/chat/create#chat/create-stream)
     pub fn stream(&mut self, truth_value: bool) -> &mut Self {
-        self.stream = truth_value;
+        self.stream = Some(truth_value);
         self
     }
 
     /// "Up to 4 sequences where the API will stop generating further tokens."
     /// - [OpenAI API docs](https://platform.openai.com/docs/api-reference/chat/create#chat/create-stop)
-    pub fn stop(&mut self, stop: Vec<String>) -> &mut Self {
+    ///
+    /// See [`StopSequences`].
+    pub fn stop(&mut self, stop: StopSequences) -> &mut Self {
         self.stop = Some(stop);
         self
     }
@@ -109,7 +296,7 @@ impl ChatBuilder<Sendable> {
         if !(-2.0..=2.0).contains(&penalty) {
             panic!("Presence penalty must be between -2.0 and 2.0");
         }
-        self.presence_penalty = penalty;
+        self.presence_penalty = Some(penalty);
         self
     }
 
@@ -121,7 +308,7 @@ impl ChatBuilder<Sendable> {
         if !(-2.0..=2.0).contains(&penalty) {
             panic!("Frequency penalty must be between -2.0 and 2.0");
         }
-        self.frequency_penalty = penalty;
+        self.frequency_penalty = Some(penalty);
         self
     }
 
@@ -136,6 +323,8 @@ impl ChatBuilder<Sendable> {
     ///
     /// cf. [documentation](https://platform.openai.com/docs/api-reference/chat/create#chat/create-logit_bias)
     ///
+    /// [`LogitBiasBuilder`] can be used to build the map from words instead of token ids.
+    ///
     /// # Example
     /// 100.0 does not make it certain that a token will occur, nor does -100.0
     /// make it certain that a token will *not* occur.
@@ -205,10 +394,534 @@ impl ChatBuilder<Sendable> {
         self
     }
 
+    /// After each successful request, save the request payload together with the
+    /// [`ChatCompletion`] as a [`ChatArchiveRecord`] in `dir`, for auditing chat usage. The
+    /// directory is created if it doesn't exist, and each record is written to its own file,
+    /// named after the time the response was received and the id of the completion (e.g.,
+    /// `chat_1679000000000_chatcmpl-abc123.json`).
+    ///
+    /// A streamed response is archived by [`stream_to_writer`](ChatBuilder::stream_to_writer),
+    /// but not by [`send`](ChatBuilder::send), since the caller consumes the stream.
+    ///
+    /// Not available for `wasm32`, which has no file system.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn archive_to<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.archive_dir = Some(dir.into());
+        self
+    }
+
+    /// Save the body of each response to a file in `dir` exactly as it was received, e.g. to
+    /// debug a response that fails to deserialize. The directory is created if it doesn't exist,
+    /// and each body is written to its own file, named after the time the response was received
+    /// and its request id (e.g., `chat_1679000000000_req-abc123.json`). See also
+    /// [`save_raw_response`](crate::utils::save_raw_response).
+    ///
+    /// As with [`archive_to`](ChatBuilder::archive_to), a streamed response isn't saved by
+    /// [`send`](ChatBuilder::send). Not available for `wasm32`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_raw_to<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.raw_dir = Some(dir.into());
+        self
+    }
+
+    /// If the stream of [`stream_to_writer`](ChatBuilder::stream_to_writer) drops before it's
+    /// done, resume it up to `attempts` times by re-sending the request with the content
+    /// received so far appended as an assistant message, so the model continues where it left
+    /// off. The continuation is written to the writer and appended to the content. Defaults to
+    /// 0, in which case a dropped stream returns a `StreamInterrupted` error carrying the
+    /// content received so far (see [`OairsError::partial`]).
+    ///
+    /// A resumed request's `max_tokens` is what's left after the tokens received so far (counted
+    /// with the tokenizer, so it isn't reduced without the `tokenizers` feature). If nothing is
+    /// left, the stream isn't resumed, and the reply ends with a `finish_reason` of `"length"`.
+    ///
+    /// Only the first choice can be resumed, so a stream with `n` greater than 1 is never
+    /// resumed.
+    pub fn reconnect(&mut self, attempts: u8) -> &mut Self {
+        self.reconnect = attempts;
+        self
+    }
+
+    /// If `true`, the messages are checked with [`Messages::validate`] before the request is
+    /// sent (or prepared by [`dry_run`](Self::dry_run)), and any issues are returned as a
+    /// `ParamError` listing them. Defaults to `false`.
+    pub fn validate_messages(&mut self, truth_value: bool) -> &mut Self {
+        self.validate_messages = truth_value;
+        self
+    }
+
+    /// If a reply is cut off by `max_tokens` (its `finish_reason` is `"length"`), re-sends the
+    /// request up to `max_rounds` times, with the reply so far appended as an assistant message
+    /// followed by a user message asking the model to continue. The continuations are appended
+    /// to the first choice of the returned [`ChatCompletion`], which has the `finish_reason` of
+    /// the last round and the summed `usage` of every round. Defaults to 0 (not continued).
+    ///
+    /// Applies to [`send_parsed`](Self::send_parsed) and [`send_with_meta`](Self::send_with_meta),
+    /// and only when `n` is 1.
+    pub fn auto_continue(&mut self, max_rounds: u8) -> &mut Self {
+        self.auto_continue = max_rounds;
+        self
+    }
+
+    /// If `true`, the messages are counted with the model's tokenizer before the request is
+    /// sent (or prepared by [`dry_run`](Self::dry_run)), and a `ParamError` with the counts is
+    /// returned if they and `max_tokens` exceed the model's
+    /// [context window](ChatModel::max_context_tokens), instead of the API's
+    /// `context_length_exceeded` error. See [`prompt_tokens`](Self::prompt_tokens). Defaults to
+    /// `false`.
+    #[cfg(feature = "tokenizers")]
+    pub fn check_context(&mut self, truth_value: bool) -> &mut Self {
+        self.check_context = truth_value;
+        self
+    }
+
+    /// Stops the request when `token` is cancelled, e.g. when the user presses a stop button
+    /// in a UI, with a `Cancelled` error (see [`OairsError::is_cancelled`]). A stream, e.g. of
+    /// [`stream_to_writer`](Self::stream_to_writer), ends after the last delta that arrived
+    /// before, and the content of the first choice received so far is available from
+    /// [`OairsError::partial`]. Cancelling `token` cancels every request sent with it (or a
+    /// [child token](CancellationToken::child_token)).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let token = CancellationToken::new();
+    /// let (mut tokens, completion) = client
+    ///     .chat_completion(model, &messages)
+    ///     .cancel_on(token.clone())
+    ///     .send_with_channel(32);
+    /// stop_button.on_click(move || token.cancel());
+    ///
+    /// while let Some(delta) = tokens.recv().await {
+    ///     print!("{delta}");
+    /// }
+    /// match completion.await.unwrap() {
+    ///     Ok(completion) => messages.push_response(&completion),
+    ///     Err(e) if e.is_cancelled() => messages.push(Msg::assistant(e.partial().unwrap_or(""))),
+    ///     Err(e) => return Err(e),
+    /// }
+    /// ```
+    pub fn cancel_on(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Adds `key` to the request body with `value`, for a parameter this builder doesn't have a
+    /// setter for yet, e.g. one the API added after this version of the crate. Replaces a
+    /// parameter of the same name set by the builder.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let completion = client
+    ///     .chat_completion(model, &messages)
+    ///     .extra_param("seed", serde_json::json!(42))
+    ///     .send_parsed()
+    ///     .await?;
+    /// ```
+    pub fn extra_param<K: Into<String>>(&mut self, key: K, value: serde_json::Value) -> &mut Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Redact the content of the messages with `redactor` before sending, e.g. to keep emails or phone numbers
+    /// out of the request. The placeholders are recorded in `redactor`'s map (clones share it),
+    /// so the response can be re-hydrated with [`Redactor::rehydrate`]. The request is redacted
+    /// as [`to_request_json`](Self::to_request_json) builds it, so [`dry_run`](Self::dry_run)
+    /// shows what would be sent.
+    #[cfg(feature = "tokenizers")]
+    pub fn redact_with(&mut self, redactor: &Redactor) -> &mut Self {
+        self.redactor = Some(redactor.clone());
+        self
+    }
+
+    /// The JSON body that [`send`](Self::send) sends, e.g. for logging or snapshot-testing a
+    /// request, or for debugging a request the API rejects.
+    pub fn to_request_json(&self) -> serde_json::Value {
+        #[allow(unused_mut)]
+        let mut json = serde_json::to_value(self).unwrap();
+        #[cfg(feature = "tokenizers")]
+        if let (Some(redactor), Some(messages)) = (&self.redactor, json["messages"].as_array_mut())
+        {
+            for message in messages {
+                redactor.redact_json(&mut message["content"]);
+            }
+        }
+        json
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        if self.validate_messages {
+            self.messages.check()?;
+        }
+        #[cfg(feature = "tokenizers")]
+        self.check_context_window()?;
+        Ok(PreparedRequest::new(
+            &self.key,
+            &self.url,
+            HttpMethod::Post,
+            PreparedBody::Json(self.to_request_json()),
+        ))
+    }
+
+    /// Executes the `POST` request. If [`archive_to`](ChatBuilder::archive_to) or
+    /// [`save_raw_to`](ChatBuilder::save_raw_to) was set and the request succeeded, the body is
+    /// read to archive or save it, and the returned `reqwest::Response` is rebuilt from the
+    /// status, headers, and body of the original.
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let json = serde_json::to_value(self).unwrap();
-        handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None).await
+        if self.validate_messages {
+            self.messages.check()?;
+        }
+        #[cfg(feature = "tokenizers")]
+        self.check_context_window()?;
+        #[cfg(feature = "tokenizers")]
+        spend(self.budget.as_ref(), || self.estimated_tokens()).await?;
+        let json = self.to_request_json();
+        let res = until_cancelled(
+            self.cancel.as_ref(),
+            handle_request(
+                &self.key,
+                &self.url,
+                HttpMethod::Post,
+                Some(json.clone()),
+                None,
+            ),
+        )
+        .await?;
+        if self.stream == Some(true) {
+            return Ok(res);
+        }
+        let res = tee_raw(self.raw_dir.as_deref(), "chat", res).await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(dir) = &self.archive_dir {
+            return archive_response(dir, json, res).await;
+        }
+        Ok(res)
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into a [`ChatCompletion`]. If
+    /// the body doesn't match, e.g. after a change to the API, the error includes the status,
+    /// the start of the body, and the type it was deserialized into. Not for a
+    /// [`stream`](Self::stream)ed request.
+    pub async fn send_parsed(&self) -> Result<ChatCompletion, OairsError> {
+        let res = self.send().await?;
+        let completion = until_cancelled(self.cancel.as_ref(), deserialize_response(res)).await?;
+        Ok(self.continue_truncated(completion).await?.0)
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into a [`ChatCompletion`] and
+    /// returns it with the [`ResponseMeta`] read from the response's headers, e.g. the request
+    /// id. Not for a [`stream`](Self::stream)ed request; see
+    /// [`stream_to_writer`](Self::stream_to_writer).
+    pub async fn send_with_meta(&self) -> Result<(ChatCompletion, ResponseMeta), OairsError> {
+        let res = self.send().await?;
+        let (completion, meta) = until_cancelled(self.cancel.as_ref(), with_meta(res)).await?;
+        let (completion, continued_meta) = self.continue_truncated(completion).await?;
+        Ok((completion, continued_meta.unwrap_or(meta)))
+    }
+
+    /// Continues a truncated `completion` as set by [`auto_continue`](Self::auto_continue),
+    /// returning it with the [`ResponseMeta`] of the last continuation, if there was one.
+    async fn continue_truncated(
+        &self,
+        mut completion: ChatCompletion,
+    ) -> Result<(ChatCompletion, Option<ResponseMeta>), OairsError> {
+        let mut meta = None;
+        let mut rounds = 0;
+        while rounds < self.auto_continue && self.choices() == 1 && completion.is_truncated() {
+            rounds += 1;
+            let partial = completion.text().unwrap_or_default().to_string();
+            let mut messages = self.messages.clone();
+            messages.push(Msg::Assistant(partial.clone()));
+            messages.push(Msg::User(CONTINUE_INSTRUCTION.to_string()));
+
+            let res = self.clone_with_messages(&messages).send().await?;
+            let (continuation, continuation_meta) =
+                until_cancelled(self.cancel.as_ref(), with_meta::<ChatCompletion>(res)).await?;
+            meta = Some(continuation_meta);
+            completion.usage += continuation.usage;
+            if let (Some(choice), Some(next)) = (
+                completion.choices.first_mut(),
+                continuation.choices.into_iter().next(),
+            ) {
+                choice.message.set_content(partial + next.message.content());
+                choice.finish_reason = next.finish_reason;
+            }
+        }
+        Ok((completion, meta))
+    }
+
+    /// Streams the response (regardless of whether [`stream`](ChatBuilder::stream) was set),
+    /// writing the content of each delta to `writer` as it arrives, and returns the assembled
+    /// [`ChatCompletion`] once the stream is done. Useful for printing a response live while
+    /// also keeping the whole of it.
+    ///
+    /// If `role_prefix` is `true`, the role is written before the content (e.g., "assistant: ").
+    /// Only the first choice is written to `writer` when `n` is greater than 1, but every choice
+    /// is included in the returned `ChatCompletion`. Since the API doesn't return `usage` for a
+    /// streamed response, it is calculated with the tokenizer (or left at 0 without the
+    /// `tokenizers` feature).
+    ///
+    /// If the stream ends before `data: [DONE]`, it's resumed as set by
+    /// [`reconnect`](ChatBuilder::reconnect), or a `StreamInterrupted` error is returned with
+    /// the content of the first choice received so far.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let mut stdout = tokio::io::stdout();
+    /// let completion = client
+    ///     .chat_completion(model, &messages)
+    ///     .stream_to_writer(&mut stdout, true)
+    ///     .await
+    ///     .unwrap();
+    /// messages.push_response(&completion);
+    /// ```
+    pub async fn stream_to_writer<W>(
+        &self,
+        mut writer: W,
+        role_prefix: bool,
+    ) -> Result<ChatCompletion, OairsError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.stream_into(&mut WriterSink(&mut writer), role_prefix)
+            .await
+    }
+
+    /// Streams the response like [`stream_to_writer`](Self::stream_to_writer), calling
+    /// `on_token` with the content of each delta of the first choice as it arrives, e.g. to
+    /// forward it to a UI, and returns the assembled [`ChatCompletion`] once the stream is done.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let completion = client
+    ///     .chat_completion(model, &messages)
+    ///     .on_token(|delta| print!("{delta}"))
+    ///     .await?;
+    /// ```
+    pub async fn on_token<F>(&self, on_token: F) -> Result<ChatCompletion, OairsError>
+    where
+        F: FnMut(&str),
+    {
+        self.stream_into(&mut CallbackSink(on_token), false).await
+    }
+
+    /// Streams the response like [`stream_to_writer`](Self::stream_to_writer) in a spawned
+    /// task, sending the content of each delta of the first choice to the returned receiver as
+    /// it arrives. The channel holds up to `buffer` deltas, and closes once the stream is done.
+    /// The task returns the assembled [`ChatCompletion`], or the error that ended the stream.
+    ///
+    /// The stream continues if the receiver is dropped, so the completion can still be awaited.
+    ///
+    /// Not available for `wasm32`, where the request's future can't be sent to another thread.
+    ///
+    /// # Panics
+    /// If `buffer` is 0, or if called outside of a Tokio runtime.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let (mut tokens, completion) = client
+    ///     .chat_completion(model, &messages)
+    ///     .send_with_channel(32);
+    /// while let Some(delta) = tokens.recv().await {
+    ///     websocket.send(delta).await?;
+    /// }
+    /// let completion = completion.await.unwrap()?;
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn send_with_channel(
+        &self,
+        buffer: usize,
+    ) -> (
+        mpsc::Receiver<String>,
+        JoinHandle<Result<ChatCompletion, OairsError>>,
+    ) {
+        let (sender, receiver) = mpsc::channel(buffer);
+        let builder = self.clone();
+        let layer = record_replay::current();
+        let task = tokio::spawn(async move {
+            let mut sink = ChannelSink(sender);
+            record_replay::scoped(layer, builder.stream_into(&mut sink, false)).await
+        });
+        (receiver, task)
+    }
+
+    /// Streams the response like [`send_with_channel`](Self::send_with_channel), re-emitting
+    /// it as server-sent events for a web backend to return to a browser: the content of each
+    /// delta of the first choice as a `data:` event of a JSON string, then `data: [DONE]`, with
+    /// a `: keep-alive` comment after every `heartbeat` without a delta. An error ends the
+    /// stream with an `error` event. See [`sse`](crate::sse). Not available for `wasm32`, as
+    /// with `send_with_channel`.
+    ///
+    /// # Panics
+    /// If called outside of a Tokio runtime.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let events = client
+    ///     .chat_completion(model, &messages)
+    ///     .send_sse(Duration::from_secs(15));
+    /// let body = axum::body::Body::from_stream(events.map(Ok::<_, Infallible>));
+    /// ([(header::CONTENT_TYPE, oairs::sse::CONTENT_TYPE)], body)
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn send_sse(&self, heartbeat: Duration) -> impl Stream<Item = Bytes> + Send + 'static {
+        let (deltas, task) = self.send_with_channel(SSE_BUFFER);
+        let deltas = stream::unfold((deltas, Some(task)), |(mut deltas, task)| async move {
+            if let Some(delta) = deltas.recv().await {
+                return Some((Ok(delta), (deltas, task)));
+            }
+            let error = match task?.await {
+                Ok(Ok(_)) => return None,
+                Ok(Err(e)) => e,
+                Err(e) => OairsError::new(e.to_string(), ErrorType::Other, None, None),
+            };
+            Some((Err(error), (deltas, None)))
+        });
+        sse::encode(deltas, heartbeat)
+    }
+
+    // Streams the response into `sink`, resuming as set by `reconnect`, and assembles the
+    // completion.
+    async fn stream_into<S: TokenSink>(
+        &self,
+        sink: &mut S,
+        role_prefix: bool,
+    ) -> Result<ChatCompletion, OairsError> {
+        if self.validate_messages {
+            self.messages.check()?;
+        }
+        #[cfg(feature = "tokenizers")]
+        self.check_context_window()?;
+        let mut json = self.to_request_json();
+        json["stream"] = serde_json::Value::Bool(true);
+        #[cfg(feature = "tokenizers")]
+        spend(self.budget.as_ref(), || self.estimated_tokens()).await?;
+        let cancel = self.cancel.as_ref();
+        let request = handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None);
+        let res = until_cancelled(cancel, request).await?;
+
+        let mut state = ChatStreamState::default();
+        let mut end = read_stream(res, &mut state, sink, role_prefix, cancel).await?;
+        let mut attempts = 0;
+        loop {
+            let reason = match end {
+                StreamEnd::Done => break,
+                StreamEnd::Cancelled => return Err(cancelled(Some(state.content(0).to_string()))),
+                StreamEnd::Interrupted(reason) => reason,
+            };
+            let partial = state.content(0).to_string();
+            if attempts == self.reconnect || self.choices() > 1 {
+                return Err(stream_interrupted(partial, &reason));
+            }
+            attempts += 1;
+
+            let mut messages = self.messages.clone();
+            messages.push(Msg::Assistant(partial.clone()));
+            let mut resumed = self.clone_with_messages(&messages);
+            // The resumed reply continues this one, so it only gets what's left of `max_tokens`.
+            if let Some(max_tokens) = self.max_tokens {
+                let remaining = (max_tokens as usize).saturating_sub(count_tokens(&partial));
+                if remaining == 0 {
+                    state.finish(0, "length");
+                    break;
+                }
+                resumed.max_tokens = Some(remaining as u16);
+            }
+            #[cfg(feature = "tokenizers")]
+            spend(resumed.budget.as_ref(), || resumed.estimated_tokens()).await?;
+            let mut json = resumed.to_request_json();
+            json["stream"] = serde_json::Value::Bool(true);
+            let request = handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None);
+            let res = match until_cancelled(cancel, request).await {
+                Ok(res) => res,
+                Err(e) if e.is_cancelled() => return Err(cancelled(Some(partial))),
+                Err(e) => return Err(stream_interrupted(partial, &e.message)),
+            };
+            end = read_stream(res, &mut state, sink, false, cancel).await?;
+        }
+
+        let prompt_tokens = self
+            .messages
+            .data
+            .iter()
+            .map(|msg| count_tokens(msg.content()))
+            .sum();
+        let completion = state.into_completion(prompt_tokens);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(dir) = &self.archive_dir {
+            return archive(dir, self.to_request_json(), completion).await;
+        }
+        Ok(completion)
+    }
+}
+
+/// Reads the body of `res` to archive it with `request` in `dir`, returning a response rebuilt
+/// from the status, headers, and body of the original.
+#[cfg(not(target_arch = "wasm32"))]
+async fn archive_response(
+    dir: &std::path::Path,
+    request: serde_json::Value,
+    res: reqwest::Response,
+) -> Result<reqwest::Response, OairsError> {
+    let status = res.status();
+    let headers = res.headers().clone();
+    let timing = res.extensions().get::<TimingStart>().copied();
+    let body = match res.bytes().await {
+        Ok(body) => body,
+        Err(e) => return Err(parse_reqwest_error(e)),
+    };
+    let completion = match serde_json::from_slice::<ChatCompletion>(&body) {
+        Ok(completion) => completion,
+        Err(e) => {
+            return Err(OairsError::new(
+                format!("Unable to deserialize chat completion for archiving: {e}"),
+                ErrorType::DeserializationError,
+                None,
+                None,
+            ))
+        }
+    };
+    archive(dir, request, completion).await?;
+
+    let mut rebuilt = ::http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+    if let Some(timing) = timing {
+        rebuilt.extensions_mut().insert(timing);
+    }
+    Ok(reqwest::Response::from(rebuilt))
+}
+
+/// Writes the request and response to `dir` as a [`ChatArchiveRecord`], returning the response.
+#[cfg(not(target_arch = "wasm32"))]
+async fn archive(
+    dir: &std::path::Path,
+    request: serde_json::Value,
+    response: ChatCompletion,
+) -> Result<ChatCompletion, OairsError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let path = dir.join(format!("chat_{}_{}.json", timestamp, response.id));
+
+    let record = ChatArchiveRecord {
+        timestamp,
+        request,
+        response,
+    };
+    if let Err(e) = record.save_json_async(&path).await {
+        return Err(OairsError::new(
+            format!("Unable to archive chat completion: {e}"),
+            ErrorType::FileError,
+            Some(path.display().to_string()),
+            None,
+        ));
     }
+
+    Ok(record.response)
 }
 
 // TODO: Implement this
@@ -227,6 +940,8 @@ impl ChatBuilder<Sendable> {
 /// (Default)
 /// * `User` - designates the user or developer, providing instruction or a
 /// prompt.
+/// * `Function` - designates the result of a function the model called, sent
+///   back to the model.
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Role {
@@ -234,6 +949,7 @@ pub enum Role {
     #[default]
     System,
     User,
+    Function,
 }
 
 impl std::fmt::Display for Role {
@@ -242,6 +958,7 @@ impl std::fmt::Display for Role {
             Role::Assistant => write!(f, "assistant"),
             Role::System => write!(f, "system"),
             Role::User => write!(f, "user"),
+            Role::Function => write!(f, "function"),
         }
     }
 }
@@ -254,6 +971,7 @@ impl std::str::FromStr for Role {
             "assistant" => Ok(Role::Assistant),
             "system" => Ok(Role::System),
             "user" => Ok(Role::User),
+            "function" => Ok(Role::Function),
             _ => Err(format!("{} is not a valid role", s)),
         }
     }
@@ -265,6 +983,7 @@ impl Role {
             Role::Assistant => "assistant",
             Role::System => "system",
             Role::User => "user",
+            Role::Function => "function",
         }
     }
 
@@ -284,9 +1003,11 @@ impl Role {
             b"{\"role\":\"assistant\"}" => Ok(Role::Assistant),
             b"{\"role\":\"system\"}" => Ok(Role::System),
             b"{\"role\":\"user\"}" => Ok(Role::User),
+            b"{\"role\":\"function\"}" => Ok(Role::Function),
             b"assistant" => Ok(Role::Assistant),
             b"system" => Ok(Role::System),
             b"user" => Ok(Role::User),
+            b"function" => Ok(Role::Function),
             _ => Err(format!("{:?} is not a valid role", s)),
         }
     }
@@ -295,17 +1016,74 @@ impl Role {
 /// For building a type that can be serialized into what the API expects of a
 /// message. It's also possible to deserialize a message from the API into a
 /// `Msg`. Cf. [`Messages`]
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-#[serde(tag = "role", content = "content", rename_all = "lowercase")]
+///
+/// A `Function` message carries the result of a function the model called,
+/// under the function's `name`, and is serialized as
+/// `{"role": "function", "name": ..., "content": ...}`. The model's call itself is
+/// a `FunctionCall` message from the assistant, serialized as
+/// `{"role": "assistant", "content": null, "function_call": {...}}`.
+#[derive(Clone, Debug, PartialEq)]
 pub enum Msg {
     Assistant(String),
     System(String),
     User(String),
-    #[serde(skip_serializing)]
-    Response {
-        role: Role,
-        content: String,
-    },
+    Function { name: String, content: String },
+    FunctionCall(FunctionCall),
+    Response { role: Role, content: String },
+}
+
+/// A call the model made to one of the functions it was given, in place of a reply: the
+/// function's `name` and its `arguments` as a JSON string. Cf. [`Msg::FunctionCall`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+impl Serialize for Msg {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("role", self.role_as_str())?;
+        match self {
+            Msg::Function { name, .. } => map.serialize_entry("name", name)?,
+            Msg::FunctionCall(call) => {
+                map.serialize_entry("content", &None::<String>)?;
+                map.serialize_entry("function_call", call)?;
+                return map.end();
+            }
+            _ => (),
+        }
+        map.serialize_entry("content", self.content())?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Msg {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RawMsg {
+            role: Role,
+            name: Option<String>,
+            // `null` when the model calls a function instead of replying.
+            content: Option<String>,
+            function_call: Option<FunctionCall>,
+        }
+
+        let raw = RawMsg::deserialize(deserializer)?;
+        let content = raw.content.unwrap_or_default();
+        match (raw.role, raw.name, raw.function_call) {
+            (Role::Assistant, _, Some(call)) => Ok(Msg::FunctionCall(call)),
+            (Role::Function, Some(name), _) => Ok(Msg::Function { name, content }),
+            (Role::Function, None, _) => Err(serde::de::Error::missing_field("name")),
+            (role, _, _) => Ok(Msg::new(role, content)),
+        }
+    }
 }
 
 impl std::fmt::Display for Msg {
@@ -314,62 +1092,110 @@ impl std::fmt::Display for Msg {
             Msg::Assistant(s) => write!(f, "assistant: {}", s),
             Msg::System(s) => write!(f, "system: {}", s),
             Msg::User(s) => write!(f, "user: {}", s),
+            Msg::Function { name, content } => write!(f, "function ({}): {}", name, content),
+            Msg::FunctionCall(call) => write!(f, "assistant: {}({})", call.name, call.arguments),
             Msg::Response { role, content } => write!(f, "{}: {}", role, content),
         }
     }
 }
 
+impl<S: Into<String>> From<(Role, S)> for Msg {
+    fn from((role, content): (Role, S)) -> Self {
+        Msg::new(role, content)
+    }
+}
+
 impl std::str::FromStr for Msg {
     type Err = String;
 
-    /// The argument must be in the format of `"{"role": "role", "content":
-    /// "content"}"` (white space doesn't matter), the sort of format we would
-    /// need to pass to the API (or returned by the API).
+    /// Parses a message from JSON, in either the object form the API uses (and returns), e.g.
+    /// `{"role": "user", "content": "Hello"}`, or the form `Msg` would have if it derived
+    /// `Serialize`, e.g. `{"User": "Hello"}` or `{"Function": {"name": ..., "content": ...}}`.
     ///
     /// # Example
     /// ```rust,no_run
     /// let s = r#"{"role": "user", "content": "Hello, world!"}"#;
     /// let msg: Msg = Msg::from_str(s).unwrap();
     ///
-    /// assert_eq!(msg.role(), "user");
+    /// assert_eq!(msg.role(), Role::User);
     /// assert_eq!(msg.content(), "Hello, world!");
-    /// assert_eq!(msg, Msg::User("Hello, world!".into()));
+    /// assert_eq!(msg, Msg::from_str(r#"{"User": "Hello, world!"}"#).unwrap());
     /// ```
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut split = s.splitn(2, ',');
-        // `role` will look like the following: "{\"role\": \"user\""
-        let role = split.next().ok_or("No role found")?;
-        // `content` will look like the following: "\"content\": \"Hello\"}"
-        let content = split.next().ok_or("No content found")?.trim();
-
-        let mut split = role.splitn(2, ':');
-        let role = split.next().ok_or("No content found")?.trim();
+        // The variants of `Msg`, as serde represents an enum by default.
+        #[derive(Deserialize)]
+        enum Tagged {
+            Assistant(String),
+            System(String),
+            User(String),
+            Function { name: String, content: String },
+            FunctionCall(FunctionCall),
+            Response { role: Role, content: String },
+        }
 
-        let mut split = content.splitn(2, ':');
-        let content = split.next().ok_or("No content found")?.trim();
+        let value: serde_json::Value =
+            serde_json::from_str(s).map_err(|e| format!("{s} is not valid JSON: {e}"))?;
+        if value.get("role").is_some() {
+            return serde_json::from_value(value).map_err(|e| format!("Invalid message: {e}"));
+        }
 
-        // At this point, content value will be something like "\"Hello\"}"
-        // We want to strip the quotes and the closing curly brace
-        let content = content[1..content.len() - 2].to_string();
+        let msg = match serde_json::from_value(value) {
+            Ok(Tagged::Assistant(content)) => Msg::Assistant(content),
+            Ok(Tagged::System(content)) => Msg::System(content),
+            Ok(Tagged::User(content)) => Msg::User(content),
+            Ok(Tagged::Function { name, content }) => Msg::Function { name, content },
+            Ok(Tagged::FunctionCall(call)) => Msg::FunctionCall(call),
+            Ok(Tagged::Response { role, content }) => Msg::Response { role, content },
+            Err(e) => return Err(format!("Invalid message: {e}")),
+        };
+        Ok(msg)
+    }
+}
 
+impl Msg {
+    /// A message from `role`, e.g. `Msg::new(Role::User, "Hello")`. A `Function` message is
+    /// given an empty name; use [`Msg::function`] to name the function.
+    pub fn new<S: Into<String>>(role: Role, content: S) -> Msg {
+        let content = content.into();
         match role {
-            "assistant" => Ok(Msg::Assistant(content)),
-            "\"assistant\"" => Ok(Msg::Assistant(content)),
-            "system" => Ok(Msg::System(content)),
-            "\"system\"" => Ok(Msg::System(content)),
-            "user" => Ok(Msg::User(content)),
-            "\"user\"" => Ok(Msg::User(content)),
-            _ => Err(format!("{} is not a valid role", role)),
+            Role::Assistant => Msg::Assistant(content),
+            Role::System => Msg::System(content),
+            Role::User => Msg::User(content),
+            Role::Function => Msg::Function {
+                name: String::new(),
+                content,
+            },
         }
     }
-}
 
-impl Msg {
+    /// The result of calling the function `name`, to send back to the model, e.g.
+    /// `Msg::function("get_weather", r#"{"temperature": 22}"#)`.
+    pub fn function<N: Into<String>, S: Into<String>>(name: N, content: S) -> Msg {
+        Msg::Function {
+            name: name.into(),
+            content: content.into(),
+        }
+    }
+
+    pub fn system<S: Into<String>>(content: S) -> Msg {
+        Msg::System(content.into())
+    }
+
+    pub fn user<S: Into<String>>(content: S) -> Msg {
+        Msg::User(content.into())
+    }
+
+    pub fn assistant<S: Into<String>>(content: S) -> Msg {
+        Msg::Assistant(content.into())
+    }
+
     pub fn role(&self) -> Role {
         match self {
             Msg::Assistant(_) => Role::Assistant,
             Msg::System(_) => Role::System,
             Msg::User(_) => Role::User,
+            Msg::Function { .. } => Role::Function,
+            Msg::FunctionCall(_) => Role::Assistant,
             Msg::Response { role, .. } => role.clone(),
         }
     }
@@ -379,24 +1205,53 @@ impl Msg {
             Msg::Assistant(_) => "assistant",
             Msg::System(_) => "system",
             Msg::User(_) => "user",
+            Msg::Function { .. } => "function",
+            Msg::FunctionCall(_) => "assistant",
             Msg::Response { role, .. } => role.to_str(),
         }
     }
 
+    /// The name of the function, for a `Function` message.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Msg::Function { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The function the model called, for a `FunctionCall` message.
+    pub fn function_call(&self) -> Option<&FunctionCall> {
+        match self {
+            Msg::FunctionCall(call) => Some(call),
+            _ => None,
+        }
+    }
+
+    /// The content of the message, which is empty for a `FunctionCall`.
     pub fn content(&self) -> &str {
         match self {
             Msg::Assistant(s) => s,
             Msg::System(s) => s,
             Msg::User(s) => s,
+            Msg::Function { content, .. } => content,
+            Msg::FunctionCall(_) => "",
             Msg::Response { content, .. } => content,
         }
     }
 
+    /// Replaces the content of the message. A `FunctionCall` becomes an `Assistant` message
+    /// with `content`, since a call has none.
     pub fn set_content(&mut self, content: String) {
         match self {
-            Msg::Assistant(_) => *self = Msg::Assistant(content),
+            Msg::Assistant(_) | Msg::FunctionCall(_) => *self = Msg::Assistant(content),
             Msg::System(_) => *self = Msg::System(content),
             Msg::User(_) => *self = Msg::User(content),
+            Msg::Function { name, .. } => {
+                *self = Msg::Function {
+                    name: name.clone(),
+                    content,
+                }
+            }
             Msg::Response { role, .. } => {
                 *self = Msg::Response {
                     role: role.clone(),
@@ -407,22 +1262,24 @@ impl Msg {
     }
 
     /// Assumes no special tokens.
+    #[cfg(feature = "tokenizers")]
     pub fn tokens(&self) -> Result<Vec<usize>, OairsError> {
-        match self {
-            Msg::Assistant(s) => tokenize(s, Tokenizer::CL100KBase),
-            Msg::System(s) => tokenize(s, Tokenizer::CL100KBase),
-            Msg::User(s) => tokenize(s, Tokenizer::CL100KBase),
-            Msg::Response { content, .. } => tokenize(content, Tokenizer::CL100KBase),
-        }
+        tokenize(self.content(), Tokenizer::CL100KBase)
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize, SaveJson)]
+/// The messages of a conversation. Serialized as the array the API (and the Python SDK) uses,
+/// e.g. `[{"role": "system", "content": "Be brief."}, {"role": "user", "content": "Hi"}]`,
+/// and deserialized from the same.
+#[derive(Clone, Debug, Default, SaveJson, LoadJson)]
 pub struct Messages {
-    data: Vec<Msg>,
-    #[serde(skip)]
+    pub(super) data: Vec<Msg>,
     save_with_tokens: bool,
+    #[cfg(feature = "tokenizers")]
     tokens: Vec<Vec<usize>>,
+    // When each message was added and, for responses, the model and usage. Kept parallel to
+    // `data` for the transcript (see `transcript.rs`).
+    pub(super) meta: Vec<MsgMeta>,
 }
 
 impl std::fmt::Display for Messages {
@@ -455,6 +1312,60 @@ impl Serialize for Messages {
     }
 }
 
+impl<'de> Deserialize<'de> for Messages {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Messages::new(Vec::<Msg>::deserialize(deserializer)?))
+    }
+}
+
+/// Converts a JSON array of messages, e.g. one parsed from a prompt stored for the Python SDK.
+/// Returns a `DeserializationError` if the value isn't an array of messages.
+impl TryFrom<serde_json::Value> for Messages {
+    type Error = OairsError;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value).map_err(|e| {
+            OairsError::new(
+                format!("Unable to convert JSON into Messages: {e}"),
+                ErrorType::DeserializationError,
+                None,
+                None,
+            )
+        })
+    }
+}
+
+impl<S: Into<String>> From<Vec<(Role, S)>> for Messages {
+    fn from(msgs: Vec<(Role, S)>) -> Self {
+        Messages::new(msgs.into_iter().map(Msg::from).collect())
+    }
+}
+
+/// Converts `(role, content)` pairs, e.g. `vec![("system", "Be brief."), ("user", "Hi")]`.
+/// Returns a `ParamError` for a role that isn't `system`, `user`, `assistant`, or `function`.
+impl TryFrom<Vec<(&str, &str)>> for Messages {
+    type Error = OairsError;
+
+    fn try_from(msgs: Vec<(&str, &str)>) -> Result<Self, Self::Error> {
+        let msgs = msgs
+            .into_iter()
+            .map(|(role, content)| match role.parse::<Role>() {
+                Ok(role) => Ok(Msg::new(role, content)),
+                Err(e) => Err(OairsError::new(
+                    e,
+                    ErrorType::ParamError,
+                    Some("role".to_string()),
+                    None,
+                )),
+            })
+            .collect::<Result<Vec<Msg>, OairsError>>()?;
+        Ok(Messages::new(msgs))
+    }
+}
+
 impl Messages {
     pub fn new(msgs: Vec<Msg>) -> Messages {
         let mut messages = Messages::default();
@@ -462,32 +1373,68 @@ impl Messages {
         messages
     }
 
+    /// Parses the JSON array format the API and the Python SDK use for messages, e.g.
+    /// `[{"role": "user", "content": "Hi"}]`. A `function` message must have a `name`, and a
+    /// `null` content is read as an empty string.
+    pub fn from_json_str(s: &str) -> Result<Messages, OairsError> {
+        <Messages as LoadJson>::from_json_str(s)
+    }
+
+    /// The messages as a compact JSON array, the format [`Messages::from_json_str`] reads.
+    ///
+    /// A [`Msg::Response`] is written with its role, like any other message, so it's read back
+    /// as the message for that role, e.g. a `Msg::Assistant`.
+    pub fn to_json_string(&self) -> Result<String, OairsError> {
+        serde_json::to_string(self).map_err(|e| {
+            OairsError::new(
+                format!("Unable to serialize Messages: {e}"),
+                ErrorType::SerializationError,
+                None,
+                None,
+            )
+        })
+    }
+
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
 
-    pub fn push(&mut self, msg: Msg) {
-        self.data.push(msg);
+    /// Pushes a [`Msg`], or anything that converts into one, e.g. `(Role::User, "Hello")`.
+    pub fn push<M: Into<Msg>>(&mut self, msg: M) {
+        self.data.push(msg.into());
+        self.meta.push(MsgMeta::now());
     }
 
     pub fn extend(&mut self, msgs: Vec<Msg>) {
+        self.meta.extend(msgs.iter().map(|_| MsgMeta::now()));
         self.data.extend(msgs);
     }
 
+    /// Pushes the message of each choice, recording the response's model and usage for the
+    /// transcript (see [`save_transcript`](Messages::save_transcript)). The usage is recorded
+    /// with the first choice only, so that summing the usage of a transcript doesn't count a
+    /// response more than once.
     pub fn push_response(&mut self, chat_response: &ChatCompletion) {
-        self.data.extend(
-            chat_response
-                .choices
-                .iter()
-                .map(|choice| choice.message.clone()),
-        );
+        for (i, choice) in chat_response.choices.iter().enumerate() {
+            self.data.push(choice.message.clone());
+            self.meta.push(MsgMeta {
+                model: chat_response.model.clone(),
+                usage: (i == 0).then_some(chat_response.usage),
+                ..MsgMeta::now()
+            });
+        }
     }
 
     pub fn clear(&mut self) {
         self.data.clear();
+        self.meta.clear();
     }
 
-    pub fn save_with_tokens(&mut self, filename: &str) -> Result<(), std::io::Error> {
+    #[cfg(feature = "tokenizers")]
+    pub fn save_with_tokens<P: AsRef<std::path::Path>>(
+        &mut self,
+        filename: P,
+    ) -> Result<PathBuf, std::io::Error> {
         // TODO: Handle potential error in tokenization
         self.data.iter().for_each(|msg| {
             let tokens = msg.tokens().unwrap();
@@ -497,3 +1444,482 @@ impl Messages {
         self.save_json(filename)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_request_json() {
+        let messages = Messages::new(vec![
+            Msg::System("Be brief.".to_string()),
+            Msg::User("Hi".to_string()),
+        ]);
+        let mut builder = ChatBuilder::create("key", ChatModel::Gpt4, &messages);
+        builder.temperature(Temperature::new(0.5)).max_tokens(10);
+
+        assert_eq!(
+            builder.to_request_json(),
+            serde_json::json!({
+                "model": "gpt-4",
+                "messages": [
+                    {"role": "system", "content": "Be brief."},
+                    {"role": "user", "content": "Hi"}
+                ],
+                "temperature": 0.5,
+                "max_tokens": 10
+            })
+        );
+
+        builder
+            .extra_param("seed", serde_json::json!(42))
+            .extra_param("max_tokens", serde_json::json!(20));
+        let json = builder.to_request_json();
+        assert_eq!(json["seed"], 42);
+        assert_eq!(json["max_tokens"], 20);
+
+        // The example request in the API reference.
+        let messages = Messages::new(vec![Msg::User("Hello!".to_string())]);
+        let builder = ChatBuilder::create("key", ChatModel::GptTurbo, &messages);
+        assert_eq!(
+            builder.to_request_json(),
+            serde_json::json!({
+                "model": "gpt-3.5-turbo",
+                "messages": [{"role": "user", "content": "Hello!"}]
+            })
+        );
+    }
+
+    #[cfg(feature = "tokenizers")]
+    #[test]
+    fn check_context() {
+        let messages = Messages::new(vec![
+            Msg::System("Be brief.".to_string()),
+            Msg::User("Hi".to_string()),
+        ]);
+        let mut builder = ChatBuilder::create("key", ChatModel::GptTurbo, &messages);
+        assert_eq!(builder.prompt_tokens().unwrap(), 15);
+
+        builder.max_tokens(4090);
+        assert!(builder.dry_run().is_ok());
+        let error = builder.check_context(true).dry_run().unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("max_tokens"));
+        assert_eq!(
+            error.message,
+            "The messages (15 tokens) and max_tokens (4090) need 4105 tokens, but gpt-3.5-turbo has a context window of 4096 tokens"
+        );
+        assert!(builder.max_tokens(4081).dry_run().is_ok());
+    }
+
+    #[cfg(feature = "tokenizers")]
+    #[test]
+    fn redact_with() {
+        let messages = Messages::new(vec![
+            Msg::system("Be brief."),
+            Msg::user("Email bob@example.com, then jane@example.com."),
+        ]);
+        let redactor = Redactor::pii();
+        let mut builder = ChatBuilder::create("key", ChatModel::GptTurbo, &messages);
+        builder.redact_with(&redactor);
+
+        assert_eq!(
+            builder.to_request_json()["messages"],
+            serde_json::json!([
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Email [EMAIL_1], then [EMAIL_2]."}
+            ])
+        );
+        assert_eq!(
+            redactor.rehydrate("Sent to [EMAIL_2]."),
+            "Sent to jane@example.com."
+        );
+    }
+
+    #[test]
+    fn clone_with_messages() {
+        let mut template = ChatBuilder::create("key", ChatModel::Gpt4, &Messages::default());
+        template.temperature(Temperature::new(0.5)).max_tokens(10);
+        let messages = Messages::new(vec![Msg::user("Hi")]);
+
+        let fork = template.clone_with_messages(&messages);
+
+        let mut expected = template.to_request_json();
+        expected["messages"] = serde_json::json!([{"role": "user", "content": "Hi"}]);
+        assert_eq!(fork.to_request_json(), expected);
+        assert_eq!(
+            template.clone().to_request_json()["messages"],
+            serde_json::json!([])
+        );
+    }
+
+    #[tokio::test]
+    async fn archive_chat_completion() {
+        let dir = std::env::temp_dir().join(format!("oairs-archive-{}", std::process::id()));
+        let response: ChatCompletion = serde_json::from_str(
+            r#"{"id":"chatcmpl-abc","object":"chat.completion","created":1,"model":"gpt-3.5-turbo-0301","choices":[{"index":0,"message":{"role":"assistant","content":"Hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":5,"completion_tokens":1,"total_tokens":6}}"#,
+        )
+        .unwrap();
+        let mut builder = ChatBuilder::create("key", ChatModel::default(), &Messages::default());
+        builder.archive_to(&dir);
+        let request = serde_json::to_value(&builder).unwrap();
+
+        let response = archive(&dir, request.clone(), response).await.unwrap();
+        let path = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let record = ChatArchiveRecord::load_json(path.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(path.ends_with(format!("chat_{}_chatcmpl-abc.json", record.timestamp)));
+        assert_eq!(record.request, request);
+        assert_eq!(record.response.id, response.id);
+        assert_eq!(record.response.response_message().content(), "Hi");
+    }
+
+    #[test]
+    fn message_construction() {
+        let expected = Messages::new(vec![
+            Msg::System("Be brief.".to_string()),
+            Msg::User("Hi".to_string()),
+        ]);
+
+        let mut pushed = Messages::default();
+        pushed.push(Msg::system("Be brief."));
+        pushed.push((Role::User, String::from("Hi")));
+        let from_roles = Messages::from(vec![(Role::System, "Be brief."), (Role::User, "Hi")]);
+        let from_strs = Messages::try_from(vec![("system", "Be brief."), ("user", "Hi")]).unwrap();
+
+        for messages in [pushed, from_roles, from_strs] {
+            assert_eq!(messages.data, expected.data);
+        }
+        assert!(Messages::try_from(vec![("narrator", "Hi")]).is_err());
+    }
+
+    #[test]
+    fn msg_from_str() {
+        let cases = [
+            (
+                r#"{"role": "user", "content": "Hello, world: it's me"}"#,
+                Msg::user("Hello, world: it's me"),
+            ),
+            (
+                r#"{"content":"a,b:c}{\"quoted\"}","role":"system"}"#,
+                Msg::system(r#"a,b:c}{"quoted"}"#),
+            ),
+            (
+                r#"{"role": "assistant", "content": "Grüße, 世界 👋\nnew line"}"#,
+                Msg::assistant("Grüße, 世界 👋\nnew line"),
+            ),
+            (
+                r#"{"role": "function", "name": "f", "content": "{\"a\": 1}"}"#,
+                Msg::function("f", r#"{"a": 1}"#),
+            ),
+            (r#"{"User": "Hi, there: 👋"}"#, Msg::user("Hi, there: 👋")),
+            (
+                r#"{"Function": {"name": "f", "content": "1,2"}}"#,
+                Msg::function("f", "1,2"),
+            ),
+            (
+                r#"{"Response": {"role": "assistant", "content": "ok: done"}}"#,
+                Msg::Response {
+                    role: Role::Assistant,
+                    content: "ok: done".to_string(),
+                },
+            ),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<Msg>().unwrap(), expected, "{s}");
+        }
+
+        for msg in [Msg::user("a, b: c"), Msg::function("f", "é")] {
+            let json = serde_json::to_string(&msg).unwrap();
+            assert_eq!(json.parse::<Msg>().unwrap(), msg);
+        }
+        for invalid in [
+            r#"{"role": "narrator", "content": "Hi"}"#,
+            r#"{"Narrator": "Hi"}"#,
+            r#"{"role": "user", "content": "Hi""#,
+            r#""Hi""#,
+        ] {
+            assert!(invalid.parse::<Msg>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn messages_json_interop() {
+        let json = r#"[
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "What's the weather?"},
+            {"role": "assistant", "content": null},
+            {"role": "function", "name": "get_weather", "content": "{\"temperature\": 22}"},
+            {"role": "assistant", "content": "It's 22 degrees."}
+        ]"#;
+        let mut messages = Messages::from_json_str(json).unwrap();
+        assert_eq!(messages.data[2], Msg::assistant(""));
+        assert_eq!(
+            messages.data[3],
+            Msg::function("get_weather", r#"{"temperature": 22}"#)
+        );
+
+        messages.push(Msg::Response {
+            role: Role::Assistant,
+            content: "Anything else?".to_string(),
+        });
+        let value: serde_json::Value =
+            serde_json::from_str(&messages.to_json_string().unwrap()).unwrap();
+        assert_eq!(
+            value[5],
+            serde_json::json!({"role": "assistant", "content": "Anything else?"})
+        );
+
+        let round_trip = Messages::try_from(value).unwrap();
+        assert_eq!(round_trip.data[..5], messages.data[..5]);
+        assert_eq!(round_trip.data[5], Msg::assistant("Anything else?"));
+        assert!(Messages::try_from(serde_json::json!({"role": "user"})).is_err());
+        assert!(Messages::from_json_str(r#"[{"role": "function", "content": ""}]"#).is_err());
+    }
+
+    #[test]
+    fn function_message() {
+        let msg = Msg::function("get_weather", r#"{"temperature": 22}"#);
+        let json = serde_json::json!({
+            "role": "function",
+            "name": "get_weather",
+            "content": "{\"temperature\": 22}"
+        });
+        assert_eq!(serde_json::to_value(&msg).unwrap(), json);
+        assert_eq!(serde_json::from_value::<Msg>(json).unwrap(), msg);
+        assert_eq!(msg.role(), Role::Function);
+        assert_eq!(msg.name(), Some("get_weather"));
+
+        let nameless = serde_json::json!({"role": "function", "content": "22"});
+        assert!(serde_json::from_value::<Msg>(nameless).is_err());
+        let empty = serde_json::json!({"role": "assistant", "content": null});
+        assert_eq!(
+            serde_json::from_value::<Msg>(empty).unwrap(),
+            Msg::assistant("")
+        );
+    }
+
+    #[test]
+    fn function_call_message() {
+        // The model's reply when it calls a function.
+        let json = serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "function_call": {"name": "get_weather", "arguments": "{\"city\": \"Paris\"}"}
+        });
+        let msg = serde_json::from_value::<Msg>(json.clone()).unwrap();
+        let call = FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: r#"{"city": "Paris"}"#.to_string(),
+        };
+        assert_eq!(msg, Msg::FunctionCall(call.clone()));
+        assert_eq!(msg.role(), Role::Assistant);
+        assert_eq!(msg.function_call(), Some(&call));
+        assert_eq!(serde_json::to_value(&msg).unwrap(), json);
+
+        let response: ChatCompletion = serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-3.5-turbo-0613",
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15},
+            "choices": [{"index": 0, "message": json, "finish_reason": "function_call"}]
+        }))
+        .unwrap();
+        let mut messages = Messages::new(vec![Msg::user("What's the weather in Paris?")]);
+        messages.push_response(&response);
+        let sent = serde_json::to_value(&messages).unwrap();
+        assert_eq!(sent[1]["function_call"]["name"], "get_weather");
+        assert_eq!(sent[1]["content"], serde_json::Value::Null);
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use super::*;
+    use crate::test_util::{
+        fixtures::{CHAT_COMPLETION, CHAT_COMPLETION_STREAM},
+        wiremock::{
+            matchers::{method, path},
+            Mock, ResponseTemplate,
+        },
+        MockApi,
+    };
+
+    #[tokio::test]
+    async fn stream_reconnect() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        // The first two events of the stream, without `data: [DONE]`.
+        let truncated: String = CHAT_COMPLETION_STREAM
+            .split_inclusive("\n\n")
+            .take(2)
+            .collect();
+        let stream = |body: &str| {
+            ResponseTemplate::new(200).set_body_raw(body.as_bytes(), "text/event-stream")
+        };
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(stream(&truncated))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(api.server())
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(stream(CHAT_COMPLETION_STREAM))
+            .with_priority(2)
+            .mount(api.server())
+            .await;
+
+        let mut builder = client.chat_completion(crate::ChatModel::default(), &messages);
+        let mut written = Vec::new();
+        let error = builder
+            .stream_to_writer(&mut written, false)
+            .await
+            .unwrap_err();
+        assert!(error.is_stream_interrupted());
+        assert_eq!(error.partial(), Some("This is a test"));
+
+        written.clear();
+        let completion = builder
+            .reconnect(2)
+            .max_tokens(20)
+            .stream_to_writer(&mut written, false)
+            .await
+            .unwrap();
+        let content = "This is a testThis is a test response.";
+        assert_eq!(completion.choices[0].message.content(), content);
+        assert_eq!(String::from_utf8(written).unwrap(), content);
+
+        let requests = api.requests("/chat/completions").await;
+        assert_eq!(requests.len(), 3);
+        let resumed: serde_json::Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert_eq!(resumed["messages"][1]["content"], "This is a test");
+        #[cfg(feature = "tokenizers")]
+        {
+            // "This is a test" is 4 tokens.
+            assert_eq!(resumed["max_tokens"], 16);
+
+            Mock::given(method("POST"))
+                .and(path("/v1/chat/completions"))
+                .respond_with(stream(&truncated))
+                .up_to_n_times(1)
+                .with_priority(1)
+                .mount(api.server())
+                .await;
+            let completion = builder
+                .max_tokens(4)
+                .stream_to_writer(Vec::new(), false)
+                .await
+                .unwrap();
+            assert_eq!(completion.choices[0].message.content(), "This is a test");
+            assert_eq!(
+                completion.choices[0].finish_reason.as_deref(),
+                Some("length")
+            );
+            assert_eq!(api.requests("/chat/completions").await.len(), 4);
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_continue() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let truncated = CHAT_COMPLETION
+            .replace("This is a test response.", "This is a ")
+            .replace("\"stop\"", "\"length\"");
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(truncated.as_bytes(), "application/json"),
+            )
+            .up_to_n_times(1)
+            .mount(api.server())
+            .await;
+
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let completion = client
+            .chat_completion(crate::ChatModel::default(), &messages)
+            .max_tokens(4)
+            .auto_continue(2)
+            .send_parsed()
+            .await
+            .unwrap();
+        assert_eq!(
+            completion.text(),
+            Some("This is a This is a test response.")
+        );
+        assert_eq!(completion.finish_reason(), Some("stop"));
+        assert_eq!(completion.usage.total_tokens, 40);
+
+        let requests = api.requests("/chat/completions").await;
+        assert_eq!(requests.len(), 2);
+        let continued: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(continued["messages"][1]["role"], "assistant");
+        assert_eq!(continued["messages"][1]["content"], "This is a ");
+        assert_eq!(continued["messages"][2]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn stream_tokens() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock_stream("/chat/completions", CHAT_COMPLETION_STREAM)
+            .await;
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let builder = client.chat_completion(crate::ChatModel::default(), &messages);
+
+        let mut deltas = Vec::new();
+        let completion = builder
+            .on_token(|delta| deltas.push(delta.to_string()))
+            .await
+            .unwrap();
+        assert!(deltas.len() > 1);
+        assert_eq!(deltas.concat(), completion.choices[0].message.content());
+
+        let (mut receiver, task) = builder.send_with_channel(1);
+        let mut received = Vec::new();
+        while let Some(delta) = receiver.recv().await {
+            received.push(delta);
+        }
+        assert_eq!(received, deltas);
+        let completion = task.await.unwrap().unwrap();
+        assert_eq!(completion.choices[0].message.content(), deltas.concat());
+    }
+
+    #[tokio::test]
+    async fn cancel_stream() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock_stream("/chat/completions", CHAT_COMPLETION_STREAM)
+            .await;
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let token = crate::CancellationToken::new();
+        let mut builder = client.chat_completion(crate::ChatModel::default(), &messages);
+        builder.cancel_on(token.clone());
+
+        let mut deltas = Vec::new();
+        let error = builder
+            .on_token(|delta| {
+                deltas.push(delta.to_string());
+                token.cancel();
+            })
+            .await
+            .unwrap_err();
+        assert!(error.is_cancelled());
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(error.partial(), Some(deltas[0].as_str()));
+
+        let error = builder.send_parsed().await.unwrap_err();
+        assert!(error.is_cancelled());
+        assert_eq!(error.partial(), None);
+        assert_eq!(api.requests("/chat/completions").await.len(), 1);
+    }
+}
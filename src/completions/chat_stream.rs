@@ -0,0 +1,334 @@
+//! Helpers for consuming a streamed chat completion, shared by the methods of
+//! [`ChatBuilder`] that stream the response. [`read_stream`] and its sinks also read the
+//! streamed completions of [`CompletionBuilder`].
+
+use futures::StreamExt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::sync::mpsc;
+
+use super::{
+    response::{ChatChoice, ChatCompletion},
+    *,
+};
+#[cfg(feature = "tokenizers")]
+use crate::tokenizers::{tokenize, Tokenizer};
+use crate::utils::stream_parsers::take_sse_data;
+
+// Unlike `ChatCompletionChunk`, keeps the `role` and `content` of a delta apart.
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawChunk {
+    id: String,
+    created: u64,
+    model: String,
+    choices: Vec<RawChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawChoice {
+    index: usize,
+    delta: RawDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct RawDelta {
+    role: Option<Role>,
+    content: Option<String>,
+}
+
+/// A single piece of a streamed response.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Delta {
+    Role { index: usize, role: Role },
+    Content { index: usize, content: String },
+}
+
+/// Accumulates the chunks of a streamed chat completion so that a [`ChatCompletion`] can be
+/// assembled once the stream is done.
+#[derive(Debug, Default)]
+pub(crate) struct ChatStreamState {
+    id: String,
+    created: u64,
+    model: String,
+    choices: Vec<(Option<Role>, String, Option<String>)>,
+}
+
+impl ChatStreamState {
+    /// Parses the `data` of a server-sent event. Returns `Ok(None)` for `[DONE]`.
+    pub(crate) fn parse(data: &[u8]) -> Result<Option<RawChunk>, OairsError> {
+        if data == b"[DONE]" {
+            return Ok(None);
+        }
+        match serde_json::from_slice::<RawChunk>(data) {
+            Ok(chunk) => Ok(Some(chunk)),
+            Err(e) => Err(OairsError::new(
+                format!(
+                    "Unable to deserialize chat completion chunk: {}. Chunk: {}",
+                    e,
+                    String::from_utf8_lossy(data)
+                ),
+                ErrorType::DeserializationError,
+                None,
+                None,
+            )),
+        }
+    }
+
+    /// Adds the chunk to the state and returns its deltas.
+    pub(crate) fn apply(&mut self, chunk: RawChunk) -> Vec<Delta> {
+        if self.id.is_empty() {
+            self.id = chunk.id;
+            self.created = chunk.created;
+            self.model = chunk.model;
+        }
+
+        let mut deltas = Vec::new();
+        for choice in chunk.choices {
+            if self.choices.len() <= choice.index {
+                self.choices
+                    .resize(choice.index + 1, (None, String::new(), None));
+            }
+            let (role, content, finish_reason) = &mut self.choices[choice.index];
+
+            if let Some(r) = choice.delta.role {
+                *role = Some(r.clone());
+                deltas.push(Delta::Role {
+                    index: choice.index,
+                    role: r,
+                });
+            }
+            if let Some(c) = choice.delta.content {
+                content.push_str(&c);
+                deltas.push(Delta::Content {
+                    index: choice.index,
+                    content: c,
+                });
+            }
+            if choice.finish_reason.is_some() {
+                *finish_reason = choice.finish_reason;
+            }
+        }
+        deltas
+    }
+
+    /// The content received so far for the choice at `index`.
+    pub(crate) fn content(&self, index: usize) -> &str {
+        self.choices
+            .get(index)
+            .map_or("", |(_, content, _)| content.as_str())
+    }
+
+    /// Sets the `finish_reason` of the choice at `index`, e.g. `"length"` for a stream that
+    /// wasn't resumed because it had used up `max_tokens`.
+    pub(crate) fn finish(&mut self, index: usize, reason: &str) {
+        if let Some((_, _, finish_reason)) = self.choices.get_mut(index) {
+            *finish_reason = Some(reason.to_string());
+        }
+    }
+
+    /// Assembles the [`ChatCompletion`]. As with
+    /// [`ChatCompletionChunk::to_chat_response`](super::response::ChatCompletionChunk::to_chat_response),
+    /// the `object` is `chat.completion.chunk` to indicate that the response was streamed, and
+    /// the `usage` is calculated with the tokenizer since the API doesn't return it (see
+    /// [`count_tokens`]).
+    pub(crate) fn into_completion(self, prompt_tokens: usize) -> ChatCompletion {
+        let mut completion_tokens = 0;
+        let choices = self
+            .choices
+            .into_iter()
+            .enumerate()
+            .map(|(index, (role, content, finish_reason))| {
+                completion_tokens += count_tokens(&content);
+                ChatChoice {
+                    index: index as u8,
+                    message: Msg::Response {
+                        role: role.unwrap_or(Role::Assistant),
+                        content,
+                    },
+                    finish_reason,
+                }
+            })
+            .collect();
+
+        ChatCompletion {
+            input: None,
+            id: self.id,
+            object: "chat.completion.chunk".to_string(),
+            created: self.created,
+            model: Some(self.model),
+            choices,
+            usage: Usage {
+                prompt_tokens,
+                completion_tokens: Some(completion_tokens),
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+        }
+    }
+}
+
+/// The state of a streamed response, which `read_stream` adds each event to.
+pub(crate) trait StreamState {
+    /// Parses the `data` of a server-sent event and adds it to the state, returning its deltas,
+    /// or `Ok(None)` for `[DONE]`.
+    fn push(&mut self, data: &[u8]) -> Result<Option<Vec<Delta>>, OairsError>;
+}
+
+impl StreamState for ChatStreamState {
+    fn push(&mut self, data: &[u8]) -> Result<Option<Vec<Delta>>, OairsError> {
+        Ok(Self::parse(data)?.map(|chunk| self.apply(chunk)))
+    }
+}
+
+/// How a stream read by `read_stream` ended.
+pub(crate) enum StreamEnd {
+    /// With `data: [DONE]`.
+    Done,
+    /// Before `data: [DONE]`, for the given reason.
+    Interrupted(String),
+    /// By the request's `CancellationToken`.
+    Cancelled,
+}
+
+/// Reads the server-sent events of `res` into `state`, writing the first choice to `sink`,
+/// until the stream ends or `cancel` is cancelled.
+pub(crate) async fn read_stream<T: StreamState, S: TokenSink>(
+    res: reqwest::Response,
+    state: &mut T,
+    sink: &mut S,
+    role_prefix: bool,
+    cancel: Option<&CancellationToken>,
+) -> Result<StreamEnd, OairsError> {
+    let mut buffer = Vec::new();
+    let mut body = res.bytes_stream();
+    loop {
+        let next = match cancel {
+            Some(token) => match token.run_until_cancelled(body.next()).await {
+                Some(next) => next,
+                None => return Ok(StreamEnd::Cancelled),
+            },
+            None => body.next().await,
+        };
+        match next {
+            Some(Ok(b)) => buffer.extend_from_slice(&b),
+            Some(Err(e)) => return Ok(StreamEnd::Interrupted(e.to_string())),
+            None => break,
+        }
+
+        while let Some(data) = take_sse_data(&mut buffer) {
+            // Events that arrived in the same chunk as the cancellation aren't written either.
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return Ok(StreamEnd::Cancelled);
+            }
+            let deltas = match state.push(&data)? {
+                Some(deltas) => deltas,
+                None => return Ok(StreamEnd::Done),
+            };
+            for delta in deltas {
+                match delta {
+                    Delta::Role { index: 0, role } if role_prefix => {
+                        sink.write(format!("{role}: ")).await?;
+                    }
+                    Delta::Content { index: 0, content } => sink.write(content).await?,
+                    _ => (),
+                }
+            }
+            sink.flush().await?;
+        }
+    }
+    Ok(StreamEnd::Interrupted(
+        "the connection closed before `data: [DONE]`".to_string(),
+    ))
+}
+
+// Where `read_stream` writes the first choice of a streamed response as it arrives.
+pub(crate) trait TokenSink {
+    async fn write(&mut self, text: String) -> Result<(), OairsError>;
+
+    async fn flush(&mut self) -> Result<(), OairsError> {
+        Ok(())
+    }
+}
+
+pub(crate) struct WriterSink<'a, W>(pub(crate) &'a mut W);
+
+impl<W: AsyncWrite + Unpin> TokenSink for WriterSink<'_, W> {
+    async fn write(&mut self, text: String) -> Result<(), OairsError> {
+        Ok(self.0.write_all(text.as_bytes()).await?)
+    }
+
+    async fn flush(&mut self) -> Result<(), OairsError> {
+        Ok(self.0.flush().await?)
+    }
+}
+
+pub(crate) struct CallbackSink<F>(pub(crate) F);
+
+impl<F: FnMut(&str)> TokenSink for CallbackSink<F> {
+    async fn write(&mut self, text: String) -> Result<(), OairsError> {
+        (self.0)(&text);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct ChannelSink(pub(crate) mpsc::Sender<String>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TokenSink for ChannelSink {
+    async fn write(&mut self, text: String) -> Result<(), OairsError> {
+        // A dropped receiver doesn't stop the stream, so the completion can still be awaited.
+        let _ = self.0.send(text).await;
+        Ok(())
+    }
+}
+
+/// The number of tokens of `text` with the tokenizer of the chat models, for the `usage` of
+/// responses the API doesn't count. Always 0 without the `tokenizers` feature.
+#[cfg(feature = "tokenizers")]
+pub(crate) fn count_tokens(text: &str) -> usize {
+    tokenize(text, Tokenizer::CL100KBase)
+        .map(|t| t.len())
+        .unwrap_or(0)
+}
+
+#[cfg(not(feature = "tokenizers"))]
+pub(crate) fn count_tokens(_text: &str) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_stream_state() {
+        let events: [&[u8]; 4] = [
+            br#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-3.5-turbo-0301","choices":[{"delta":{"role":"assistant"},"index":0,"finish_reason":null}]}"#,
+            br#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-3.5-turbo-0301","choices":[{"delta":{"content":"Hello"},"index":0,"finish_reason":null}]}"#,
+            br#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-3.5-turbo-0301","choices":[{"delta":{},"index":0,"finish_reason":"stop"}]}"#,
+            b"[DONE]",
+        ];
+
+        let mut state = ChatStreamState::default();
+        let mut deltas = Vec::new();
+        for data in events {
+            match ChatStreamState::parse(data).unwrap() {
+                Some(chunk) => deltas.extend(state.apply(chunk)),
+                None => break,
+            }
+        }
+        let completion = state.into_completion(5);
+
+        assert_eq!(
+            deltas[1],
+            Delta::Content {
+                index: 0,
+                content: "Hello".to_string()
+            }
+        );
+        assert_eq!(completion.choices[0].message.content(), "Hello");
+        assert_eq!(completion.choices[0].finish_reason.as_deref(), Some("stop"));
+        assert_eq!(completion.usage.prompt_tokens, 5);
+    }
+}
@@ -4,9 +4,28 @@
 
 // TODO: Clean up type-state pattern!
 
-use crate::client::handle_request;
+use std::path::PathBuf;
 
-use super::*;
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "tokenizers")]
+use crate::{
+    budget::{spend, TokenBudget},
+    text::redact::Redactor,
+    tokenizers::tokenize,
+};
+use crate::{
+    client::{bearer, handle_request, until_cancelled, HttpMethod, PreparedBody, PreparedRequest},
+    meta::{deserialize_response, with_meta, ResponseMeta},
+    utils::raw::tee_raw,
+};
+
+use super::{
+    chat_stream::{read_stream, CallbackSink, StreamEnd, TokenSink, WriterSink},
+    completion_stream::CompletionStreamState,
+    response::Completion,
+    *,
+};
 
 /// Struct responsible for building a completion create request. Normally you would
 /// not use this struct directly, but would interact with it through the `Client`
@@ -25,15 +44,15 @@ use super::*;
 ///      Err(e) => panic!("Error: {}", e),
 /// }
 /// ```
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionBuilder<State = Buildable> {
     #[serde(skip)]
     key: String,
     #[serde(skip)]
-    url: String,
+    url: Cow<'static, str>,
     model: CompletionModel,
     #[serde(skip_serializing_if = "Option::is_none")]
-    prompt: Option<Vec<String>>,
+    prompt: Option<Prompt>,
     #[serde(skip_serializing_if = "Option::is_none")]
     suffix: Option<String>,
     /// Defaults to 16 if `max_tokens` is not specified.
@@ -44,26 +63,44 @@ pub struct CompletionBuilder<State = Buildable> {
     #[serde(skip_serializing_if = "Option::is_none")]
     top_p: Option<TopP>,
     /// Default is 1 if `n` is not specified.
-    n: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u8>,
     /// Defaults to `false` if `stream` is not specified.
-    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     logprobs: Option<u8>,
-    // Defaults to `false` if `echo` is not specified.
-    echo: bool,
+    /// Defaults to `false` if `echo` is not specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    echo: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    stop: Option<Vec<String>>,
+    stop: Option<StopSequences>,
     /// Defaults to 0.0 if `presence_penalty` is not specified.
-    presence_penalty: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
     /// Defaults to 0.0 if `frequency_penalty` is not specified.
-    frequency_penalty: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
     /// Defaults to 1 if `best_of` is not specified.
-    best_of: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best_of: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     logit_bias: Option<HashMap<String, f32>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<String>,
     #[serde(skip)]
+    raw_dir: Option<PathBuf>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    budget: Option<TokenBudget>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    redactor: Option<Redactor>,
+    #[serde(skip)]
+    cancel: Option<CancellationToken>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+    #[serde(skip)]
     state: PhantomData<State>,
 }
 
@@ -74,30 +111,101 @@ impl CompletionBuilder<Buildable> {
     ) -> CompletionBuilder<Sendable> {
         CompletionBuilder {
             key: key.into(),
-            url: completion_url().to_string(),
+            url: Endpoints::default().completions(),
             model,
-            n: 1,
-            best_of: 1,
             ..Default::default()
         }
     }
 }
 
 impl<'a> CompletionBuilder<Sendable> {
+    // The tokens of the prompts.
+    #[cfg(feature = "tokenizers")]
+    fn count_prompt_tokens(&self) -> Result<usize, OairsError> {
+        Ok(match &self.prompt {
+            Some(Prompt::Texts(texts)) => {
+                let mut tokens = 0;
+                for text in texts {
+                    tokens += tokenize(text, self.model.tokenizer())?.len();
+                }
+                tokens
+            }
+            Some(Prompt::Tokens(tokens)) => tokens.len(),
+            Some(Prompt::TokenBatches(batches)) => batches.iter().map(|b| b.len()).sum(),
+            None => 0,
+        })
+    }
+
+    /// The tokens the request is estimated to use, as spent from a [`TokenBudget`]: the tokens
+    /// of the prompts, plus `max_tokens` (16 if not set) for each of the `n` choices of each
+    /// prompt.
+    #[cfg(feature = "tokenizers")]
+    pub fn estimated_tokens(&self) -> Result<usize, OairsError> {
+        let prompt_tokens = self.count_prompt_tokens()?;
+        let prompts = self.prompt.as_ref().map_or(1, |p| p.len().max(1));
+        let max_tokens = self.max_tokens.unwrap_or(16) as usize;
+        Ok(prompt_tokens + max_tokens * self.n.unwrap_or(1) as usize * prompts)
+    }
+
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
+    #[cfg(feature = "tokenizers")]
+    pub(crate) fn set_budget(&mut self, budget: Option<TokenBudget>) -> &mut Self {
+        self.budget = budget;
+        self
+    }
+
     /// The text for which you wish to generate a completion. For generating
     /// completions from multiple prompts, use the `prompts()` method.
     pub fn prompt(&mut self, prompt: &'a str) -> &mut Self {
-        self.prompt = Some(vec![prompt.to_string()]);
+        self.prompt = Some(Prompt::Texts(vec![prompt.to_string()]));
         self
     }
 
     // TODO: Ideally incompatible params would be prevented by further refining the type-state. Look into builder crates!
-    /// The texts for which you wish to generate completions.
-    ///
-    /// # Panics
-    /// If `suffix` is also set.
+    /// The texts for which you wish to generate completions. Can't be used with `suffix`; see
+    /// [`validate`](Self::validate).
     pub fn prompts(&mut self, prompts: Vec<String>) -> &mut Self {
-        self.prompt = Some(prompts);
+        self.prompt = Some(Prompt::Texts(prompts));
+        self
+    }
+
+    /// A prompt given as token ids, e.g. from [`tokenize`](crate::tokenizers::tokenize) with
+    /// the model's tokenizer. Useful for controlling exactly what the model sees, such as
+    /// truncating a prompt to fit the context length without re-tokenizing. Replaces any
+    /// prompt set with `prompt()` or `prompts()`.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let model = CompletionModel::TextDavinci003;
+    /// let mut tokens = tokenize(&document, model.tokenizer()).unwrap();
+    /// tokens.truncate(3000);
+    ///
+    /// let completion = client.completion(model)
+    ///     .prompt_tokens(tokens)
+    ///     .max_tokens(500)
+    ///     .send()
+    ///     .await;
+    /// ```
+    pub fn prompt_tokens(&mut self, tokens: Vec<usize>) -> &mut Self {
+        self.prompt = Some(Prompt::Tokens(tokens));
+        self
+    }
+
+    /// Multiple prompts given as token ids. See [`prompt_tokens`](Self::prompt_tokens). Can't be
+    /// used with `suffix`; see [`validate`](Self::validate).
+    pub fn prompts_tokens(&mut self, prompts: Vec<Vec<usize>>) -> &mut Self {
+        self.prompt = Some(Prompt::TokenBatches(prompts));
         self
     }
 
@@ -108,9 +216,8 @@ impl<'a> CompletionBuilder<Sendable> {
     /// something that (might) naturally end with your suffix. See the examples
     /// section which should better illustrate the function.
     ///
-    /// # Panics
-    /// If `echo` is also set to `true` or if multiple prompts have been set (via
-    /// `prompts()`).
+    /// Can't be used with `echo` or with multiple prompts (via `prompts()`); see
+    /// [`validate`](Self::validate).
     ///
     /// # Examples
     /// ```rust
@@ -200,12 +307,6 @@ impl<'a> CompletionBuilder<Sendable> {
     /// Carol: The aliens who live on the dark side of the moon."
     /// </pre>
     pub fn suffix<S: Into<String> + std::fmt::Debug>(&mut self, suffix: S) -> &mut Self {
-        if self.echo {
-            panic!("Cannot set suffix if echo is set to true");
-        }
-        if self.prompt.is_some() && self.prompt.as_ref().unwrap().len() > 1 {
-            panic!("Cannot set suffix if multiple prompts have been set");
-        }
         self.suffix = Some(suffix.into());
         self
     }
@@ -237,7 +338,7 @@ impl<'a> CompletionBuilder<Sendable> {
 
     /// Number of completions to generate per prompt.
     pub fn n(&mut self, n: u8) -> &mut Self {
-        self.n = n;
+        self.n = Some(n);
         self
     }
 
@@ -246,7 +347,7 @@ impl<'a> CompletionBuilder<Sendable> {
     /// terminated by a `data: [DONE]` message." -
     /// [OpenAI API docs](https://platform.openai.com/docs/api-reference/completions/create#completions/create-stream)
     pub fn stream(&mut self, truth_value: bool) -> &mut Self {
-        self.stream = truth_value;
+        self.stream = Some(truth_value);
         self
     }
 
@@ -266,14 +367,16 @@ impl<'a> CompletionBuilder<Sendable> {
     /// The prompt(s) will be appended to the beginning of the completion, followed
     /// by two newlines.
     pub fn echo(&mut self, truth_value: bool) -> &mut Self {
-        self.echo = truth_value;
+        self.echo = Some(truth_value);
         self
     }
 
     /// "Up to 4 sequences where the API will stop generating further tokens. The
     /// returned text will not contain the stop sequence." -
     /// [OpenAI API docs](https://platform.openai.com/docs/api-reference/completions/create#completions/create-stop)
-    pub fn stop(&mut self, stop: Vec<String>) -> &mut Self {
+    ///
+    /// See [`StopSequences`].
+    pub fn stop(&mut self, stop: StopSequences) -> &mut Self {
         self.stop = Some(stop);
         self
     }
@@ -286,7 +389,7 @@ impl<'a> CompletionBuilder<Sendable> {
         if !(-2.0..=2.0).contains(&penalty) {
             panic!("Presence penalty must be between -2.0 and 2.0");
         }
-        self.presence_penalty = penalty;
+        self.presence_penalty = Some(penalty);
         self
     }
 
@@ -298,7 +401,7 @@ impl<'a> CompletionBuilder<Sendable> {
         if !(-2.0..=2.0).contains(&penalty) {
             panic!("Frequency penalty must be between -2.0 and 2.0");
         }
-        self.frequency_penalty = penalty;
+        self.frequency_penalty = Some(penalty);
         self
     }
 
@@ -308,7 +411,7 @@ impl<'a> CompletionBuilder<Sendable> {
     ///
     /// cf. [documentation](https://platform.openai.com/docs/api-reference/completions/create#completions/create-best_of)
     pub fn best_of(&mut self, num: u32) -> &mut Self {
-        self.best_of = num;
+        self.best_of = Some(num);
         self
     }
 
@@ -323,6 +426,8 @@ impl<'a> CompletionBuilder<Sendable> {
     ///
     /// cf. [documentation](https://platform.openai.com/docs/api-reference/completions/create#completions/create-logit_bias)
     ///
+    /// [`LogitBiasBuilder`] can be used to build the map from words instead of token ids.
+    ///
     /// # Example
     /// 100.0 does not make it certain that a token will occur, nor does -100.0 make
     /// it certain that a token will *not* occur.
@@ -384,16 +489,270 @@ impl<'a> CompletionBuilder<Sendable> {
         self
     }
 
-    pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let json = serde_json::to_value(self).unwrap();
-        handle_request(
+    /// Save the body of each response (unless [`stream`](Self::stream)ed) to a file in `dir`,
+    /// as with [`ChatBuilder::save_raw_to`](crate::completions::ChatBuilder::save_raw_to).
+    /// Not available for `wasm32`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_raw_to<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.raw_dir = Some(dir.into());
+        self
+    }
+
+    /// Stops the request when `token` is cancelled, with a `Cancelled` error, as with
+    /// [`ChatBuilder::cancel_on`](crate::completions::ChatBuilder::cancel_on). For a
+    /// [`stream`](Self::stream)ed request sent with [`send`](Self::send), only the request
+    /// itself is cancelled, not the reading of the returned response; use
+    /// [`stream_to_writer`](Self::stream_to_writer) or [`on_token`](Self::on_token) to also
+    /// stop the stream, with the text received so far in the error.
+    pub fn cancel_on(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// A copy of the builder's settings (model, `temperature`, `max_tokens`, etc.) with `prompt`
+    /// in place of its prompts, so one configured request can be sent for many inputs. See also
+    /// [`batch`](super::batch).
+    pub fn with_prompt<S: Into<String>>(&self, prompt: S) -> CompletionBuilder<Sendable> {
+        let mut builder = self.clone();
+        builder.prompt = Some(Prompt::Texts(vec![prompt.into()]));
+        builder
+    }
+
+    pub(crate) fn is_stream(&self) -> bool {
+        self.stream == Some(true)
+    }
+
+    /// Checks the parameters against the combinations the completions endpoint rejects:
+    /// `best_of` less than `n`, `logprobs` above 5, `stream` with `best_of` > 1, and `suffix`
+    /// with `echo` or multiple prompts, as well as penalties out of range. Returns every issue
+    /// at once, or an empty `Vec` if there are none.
+    ///
+    /// [`send`](Self::send) and [`dry_run`](Self::dry_run) return
+    /// the issues as a `ParamError` before making the request.
+    pub fn validate(&self) -> Vec<ParamIssue> {
+        let n = self.n.unwrap_or(1);
+        let best_of = self.best_of.unwrap_or(1);
+        let mut issues = Vec::new();
+        if n == 0 || best_of == 0 {
+            issues.push(ParamIssue::ZeroChoices);
+        } else if self.best_of.is_some() && best_of < n as u32 {
+            issues.push(ParamIssue::BestOfLessThanN { best_of, n });
+        }
+        if let Some(logprobs) = self.logprobs {
+            if logprobs > 5 {
+                issues.push(ParamIssue::LogprobsTooHigh(logprobs));
+            }
+        }
+        if self.is_stream() && best_of > 1 {
+            issues.push(ParamIssue::StreamWithBestOf(best_of));
+        }
+        if self.suffix.is_some() {
+            if self.echo == Some(true) {
+                issues.push(ParamIssue::SuffixWithEcho);
+            }
+            let prompts = self.prompt.as_ref().map_or(1, |p| p.len());
+            if prompts > 1 {
+                issues.push(ParamIssue::SuffixWithMultiplePrompts(prompts));
+            }
+        }
+        if let Some(presence) = self.presence_penalty {
+            if !(-2.0..=2.0).contains(&presence) {
+                issues.push(ParamIssue::PresencePenalty(presence));
+            }
+        }
+        if let Some(frequency) = self.frequency_penalty {
+            if !(-2.0..=2.0).contains(&frequency) {
+                issues.push(ParamIssue::FrequencyPenalty(frequency));
+            }
+        }
+        issues
+    }
+
+    /// [`validate`](Self::validate), as a `ParamError` listing the issues, with the parameter
+    /// of the first.
+    pub(crate) fn check(&self) -> Result<(), OairsError> {
+        let issues = self.validate();
+        let param = match issues.first() {
+            Some(issue) => issue.param().to_string(),
+            None => return Ok(()),
+        };
+        let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        Err(OairsError::new(
+            format!("Invalid parameters: {}", issues.join("; ")),
+            ErrorType::ParamError,
+            Some(param),
+            None,
+        ))
+    }
+
+    /// Adds `key` to the request body with `value`, as with
+    /// [`ChatBuilder::extra_param`](crate::completions::ChatBuilder::extra_param).
+    pub fn extra_param<K: Into<String>>(&mut self, key: K, value: serde_json::Value) -> &mut Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Redact the prompts (but not token ids) with `redactor` before sending, e.g. to keep emails or phone numbers
+    /// out of the request. The placeholders are recorded in `redactor`'s map (clones share it),
+    /// so the response can be re-hydrated with [`Redactor::rehydrate`]. The request is redacted
+    /// as [`to_request_json`](Self::to_request_json) builds it, so [`dry_run`](Self::dry_run)
+    /// shows what would be sent.
+    #[cfg(feature = "tokenizers")]
+    pub fn redact_with(&mut self, redactor: &Redactor) -> &mut Self {
+        self.redactor = Some(redactor.clone());
+        self
+    }
+
+    /// The JSON body that [`send`](Self::send) sends, with the prompt serialized as text or
+    /// token ids.
+    pub fn to_request_json(&self) -> serde_json::Value {
+        #[allow(unused_mut)]
+        let mut json = serde_json::to_value(self).unwrap();
+        #[cfg(feature = "tokenizers")]
+        if let Some(redactor) = &self.redactor {
+            redactor.redact_json(&mut json["prompt"]);
+        }
+        json
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        self.check()?;
+        Ok(PreparedRequest::new(
             &self.key,
             &self.url,
-            client::HttpMethod::Post,
-            Some(json),
-            None,
+            HttpMethod::Post,
+            PreparedBody::Json(self.to_request_json()),
+        ))
+    }
+
+    pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
+        self.check()?;
+        #[cfg(feature = "tokenizers")]
+        spend(self.budget.as_ref(), || self.estimated_tokens()).await?;
+        let json = self.to_request_json();
+        let res = until_cancelled(
+            self.cancel.as_ref(),
+            handle_request(
+                &self.key,
+                &self.url,
+                client::HttpMethod::Post,
+                Some(json),
+                None,
+            ),
         )
-        .await
+        .await?;
+        match self.is_stream() {
+            true => Ok(res),
+            false => tee_raw(self.raw_dir.as_deref(), "completion", res).await,
+        }
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into a [`Completion`], with
+    /// the status and the start of the body in the error if it doesn't match.
+    pub async fn send_parsed(&self) -> Result<Completion, OairsError> {
+        let res = self.send().await?;
+        until_cancelled(self.cancel.as_ref(), deserialize_response(res)).await
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into a [`Completion`] and returns it
+    /// with the [`ResponseMeta`] read from the response's headers, e.g. the request id.
+    pub async fn send_with_meta(&self) -> Result<(Completion, ResponseMeta), OairsError> {
+        let res = self.send().await?;
+        until_cancelled(self.cancel.as_ref(), with_meta(res)).await
+    }
+
+    /// Streams the response, whether or not [`stream`](Self::stream) is set, writing the text
+    /// of the first choice to `writer` as it arrives, and returns the assembled [`Completion`]
+    /// once the stream is done, as with
+    /// [`ChatBuilder::stream_to_writer`](crate::completions::ChatBuilder::stream_to_writer).
+    /// Since the API doesn't return `usage` for a streamed response, it is calculated with the
+    /// model's tokenizer (or left at 0 without the `tokenizers` feature).
+    ///
+    /// If the stream ends before `data: [DONE]`, a `StreamInterrupted` error is returned with
+    /// the text of the first choice received so far (see [`OairsError::partial`]).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let mut stdout = tokio::io::stdout();
+    /// let completion = client
+    ///     .completion(CompletionModel::TextDavinci003)
+    ///     .prompt("Once upon a time")
+    ///     .stream_to_writer(&mut stdout)
+    ///     .await?;
+    /// ```
+    pub async fn stream_to_writer<W>(&self, mut writer: W) -> Result<Completion, OairsError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.stream_into(&mut WriterSink(&mut writer)).await
+    }
+
+    /// Streams the response like [`stream_to_writer`](Self::stream_to_writer), calling
+    /// `on_token` with the text of each chunk of the first choice as it arrives.
+    pub async fn on_token<F>(&self, on_token: F) -> Result<Completion, OairsError>
+    where
+        F: FnMut(&str),
+    {
+        self.stream_into(&mut CallbackSink(on_token)).await
+    }
+
+    // Streams the response into `sink` and assembles the completion.
+    async fn stream_into<S: TokenSink>(&self, sink: &mut S) -> Result<Completion, OairsError> {
+        self.check()?;
+        #[cfg(feature = "tokenizers")]
+        spend(self.budget.as_ref(), || self.estimated_tokens()).await?;
+        let mut json = self.to_request_json();
+        json["stream"] = serde_json::Value::Bool(true);
+        let cancel = self.cancel.as_ref();
+        let request = handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None);
+        let res = until_cancelled(cancel, request).await?;
+
+        let mut state = CompletionStreamState::default();
+        match read_stream(res, &mut state, sink, false, cancel).await? {
+            StreamEnd::Done => (),
+            StreamEnd::Cancelled => return Err(cancelled(Some(state.text(0).to_string()))),
+            StreamEnd::Interrupted(reason) => {
+                return Err(stream_interrupted(state.text(0).to_string(), &reason))
+            }
+        }
+
+        #[cfg(feature = "tokenizers")]
+        let completion = {
+            let tokenizer = self.model.tokenizer();
+            let count_tokens = |text: &str| tokenize(text, tokenizer).map_or(0, |t| t.len());
+            state.into_completion(self.count_prompt_tokens()?, count_tokens)
+        };
+        #[cfg(not(feature = "tokenizers"))]
+        let completion = state.into_completion(0, |_| 0);
+        Ok(completion)
+    }
+}
+
+/// The `prompt` parameter of the completions endpoint, which accepts either text or token ids,
+/// for one or more prompts. Set with [`CompletionBuilder::prompt`],
+/// [`CompletionBuilder::prompts`], [`CompletionBuilder::prompt_tokens`], or
+/// [`CompletionBuilder::prompts_tokens`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Prompt {
+    Texts(Vec<String>),
+    Tokens(Vec<usize>),
+    TokenBatches(Vec<Vec<usize>>),
+}
+
+impl Prompt {
+    /// The number of prompts.
+    pub fn len(&self) -> usize {
+        match self {
+            Prompt::Texts(texts) => texts.len(),
+            Prompt::Tokens(_) => 1,
+            Prompt::TokenBatches(batches) => batches.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
@@ -445,3 +804,112 @@ impl std::fmt::Display for LogitBias {
         write!(f, "{{\"{}\": {}}}", self.token, self.value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_serialization() {
+        let mut builder = CompletionBuilder::create("key", CompletionModel::TextDavinci003);
+
+        builder.prompt("This is a test.");
+        let json = serde_json::to_value(&builder).unwrap();
+        assert_eq!(json["prompt"], serde_json::json!(["This is a test."]));
+
+        builder.prompt_tokens(vec![1212, 318, 257, 1332, 13]);
+        let json = serde_json::to_value(&builder).unwrap();
+        assert_eq!(
+            json["prompt"],
+            serde_json::json!([1212, 318, 257, 1332, 13])
+        );
+
+        builder.prompts_tokens(vec![vec![1212, 318], vec![257, 1332]]);
+        let json = serde_json::to_value(&builder).unwrap();
+        assert_eq!(
+            json["prompt"],
+            serde_json::json!([[1212, 318], [257, 1332]])
+        );
+
+        let prompt: Prompt = serde_json::from_value(json["prompt"].clone()).unwrap();
+        assert_eq!(prompt.len(), 2);
+    }
+
+    #[test]
+    fn completion_request_json() {
+        let mut builder = CompletionBuilder::create("key", CompletionModel::TextDavinci003);
+        builder
+            .prompt("Say this is a test")
+            .max_tokens(7)
+            .stop(StopSequences::try_from("\n").unwrap());
+
+        assert_eq!(
+            builder.to_request_json(),
+            serde_json::json!({
+                "model": "text-davinci-003",
+                "prompt": ["Say this is a test"],
+                "max_tokens": 7,
+                "stop": ["\n"]
+            })
+        );
+
+        builder.n(1).stream(false).presence_penalty(0.0);
+        let json = builder.to_request_json();
+        assert_eq!(json["n"], 1);
+        assert_eq!(json["stream"], false);
+        assert_eq!(json["presence_penalty"], 0.0);
+        assert!(json.get("best_of").is_none());
+    }
+
+    #[test]
+    fn stop_sequences() {
+        let stop = StopSequences::try_from(vec!["\n", "###"]).unwrap();
+        assert_eq!(stop.as_slice(), ["\n", "###"]);
+
+        let too_many = vec!["a", "b", "c", "d", "e"];
+        for invalid in [too_many, vec![], vec!["a", ""]] {
+            let error = StopSequences::try_from(invalid).unwrap_err();
+            assert_eq!(error.param.as_deref(), Some("stop"));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use super::*;
+    use crate::test_util::{fixtures::COMPLETION_STREAM, MockApi};
+
+    #[tokio::test]
+    async fn stream_to_writer() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock_stream("/completions", COMPLETION_STREAM).await;
+
+        let mut builder = client.completion(CompletionModel::TextDavinci003);
+        builder.prompt("Say this is a test");
+        let mut written = Vec::new();
+        let completion = builder.stream_to_writer(&mut written).await.unwrap();
+        assert_eq!(completion.text(), Some("\n\nThis is indeed a test."));
+        assert_eq!(completion.choices[0].finish_reason, "stop");
+        assert_eq!(written, b"\n\nThis is indeed a test.");
+        let requests = api.requests("/completions").await;
+        let request: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(request["stream"], true);
+
+        // Without `data: [DONE]`.
+        let api = MockApi::start().await;
+        let truncated: String = COMPLETION_STREAM.split_inclusive("\n\n").take(1).collect();
+        api.mock_stream("/completions", &truncated).await;
+        let mut tokens = Vec::new();
+        let error = api
+            .client()
+            .completion(CompletionModel::TextDavinci003)
+            .prompt("Say this is a test")
+            .on_token(|text| tokens.push(text.to_string()))
+            .await
+            .unwrap_err();
+        assert!(error.is_stream_interrupted());
+        assert_eq!(error.partial(), Some("\n\nThis is"));
+        assert_eq!(tokens, ["\n\nThis is"]);
+    }
+}
@@ -0,0 +1,153 @@
+//! Helpers for consuming a streamed completion, shared by the methods of
+//! [`CompletionBuilder`] that stream the response.
+
+use super::{
+    chat_stream::{Delta, StreamState},
+    response::{Choice, Completion},
+    *,
+};
+
+#[derive(Debug, Deserialize)]
+struct RawChunk {
+    id: String,
+    created: u64,
+    model: String,
+    choices: Vec<RawChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawChoice {
+    text: String,
+    index: usize,
+    finish_reason: Option<String>,
+}
+
+/// Accumulates the chunks of a streamed completion so that a [`Completion`] can be assembled
+/// once the stream is done.
+#[derive(Debug, Default)]
+pub(crate) struct CompletionStreamState {
+    id: String,
+    created: u64,
+    model: String,
+    choices: Vec<(String, Option<String>)>,
+}
+
+impl CompletionStreamState {
+    /// The text received so far for the choice at `index`.
+    pub(crate) fn text(&self, index: usize) -> &str {
+        self.choices
+            .get(index)
+            .map_or("", |(text, _)| text.as_str())
+    }
+
+    /// Assembles the [`Completion`], with the `usage` calculated by `count_tokens` since the
+    /// API doesn't return it for a streamed response.
+    pub(crate) fn into_completion<F>(self, prompt_tokens: usize, count_tokens: F) -> Completion
+    where
+        F: Fn(&str) -> usize,
+    {
+        let mut completion_tokens = 0;
+        let choices = self
+            .choices
+            .into_iter()
+            .enumerate()
+            .map(|(index, (text, finish_reason))| {
+                completion_tokens += count_tokens(&text);
+                Choice {
+                    text,
+                    index: index as u32,
+                    logprobs: None,
+                    finish_reason: finish_reason.unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Completion {
+            id: self.id,
+            object: "text_completion".to_string(),
+            created: self.created,
+            model: self.model,
+            choices,
+            usage: Usage {
+                prompt_tokens,
+                completion_tokens: Some(completion_tokens),
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+        }
+    }
+}
+
+impl StreamState for CompletionStreamState {
+    fn push(&mut self, data: &[u8]) -> Result<Option<Vec<Delta>>, OairsError> {
+        if data == b"[DONE]" {
+            return Ok(None);
+        }
+        let chunk = serde_json::from_slice::<RawChunk>(data).map_err(|e| {
+            OairsError::new(
+                format!(
+                    "Unable to deserialize completion chunk: {}. Chunk: {}",
+                    e,
+                    String::from_utf8_lossy(data)
+                ),
+                ErrorType::DeserializationError,
+                None,
+                None,
+            )
+        })?;
+        if self.id.is_empty() {
+            self.id = chunk.id;
+            self.created = chunk.created;
+            self.model = chunk.model;
+        }
+
+        let mut deltas = Vec::new();
+        for choice in chunk.choices {
+            if self.choices.len() <= choice.index {
+                self.choices.resize(choice.index + 1, (String::new(), None));
+            }
+            let (text, finish_reason) = &mut self.choices[choice.index];
+            text.push_str(&choice.text);
+            if choice.finish_reason.is_some() {
+                *finish_reason = choice.finish_reason;
+            }
+            if !choice.text.is_empty() {
+                deltas.push(Delta::Content {
+                    index: choice.index,
+                    content: choice.text,
+                });
+            }
+        }
+        Ok(Some(deltas))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion_stream_state() {
+        let events: [&[u8]; 3] = [
+            br#"{"id":"cmpl-1","object":"text_completion","created":1,"model":"text-davinci-003","choices":[{"text":"Hello","index":0,"logprobs":null,"finish_reason":null}]}"#,
+            br#"{"id":"cmpl-1","object":"text_completion","created":1,"model":"text-davinci-003","choices":[{"text":" there","index":0,"logprobs":null,"finish_reason":"stop"}]}"#,
+            b"[DONE]",
+        ];
+
+        let mut state = CompletionStreamState::default();
+        let mut deltas = Vec::new();
+        for data in events {
+            match state.push(data).unwrap() {
+                Some(d) => deltas.extend(d),
+                None => break,
+            }
+        }
+        assert_eq!(state.text(0), "Hello there");
+        let completion = state.into_completion(3, |text| text.split(' ').count());
+
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(completion.text(), Some("Hello there"));
+        assert_eq!(completion.choices[0].finish_reason, "stop");
+        assert_eq!(completion.usage.completion_tokens, Some(2));
+        assert_eq!(completion.usage.total_tokens, 5);
+    }
+}
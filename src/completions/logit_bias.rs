@@ -0,0 +1,153 @@
+//! Contains the [`LogitBiasBuilder`], for building the `logit_bias` parameter from words
+//! rather than token ids.
+
+use std::collections::HashSet;
+
+use crate::tokenizers::{tokenize_batch, Tokenizer};
+
+use super::*;
+
+/// Builds the `HashMap<String, f32>` expected by [`ChatBuilder::logit_bias`] and
+/// [`CompletionBuilder::logit_bias`] from words or phrases.
+///
+/// The same word is usually several different tokens depending on whether it follows a space
+/// and how it is capitalized (e.g., "dog", " dog", "Dog", and " Dog"). By default, [`bias`]
+/// adds all of these forms. Use [`bias_exact`] to only add the text as given.
+///
+/// If a word is split into more than one token, every token of the word is biased, which will
+/// also affect other words that share those tokens.
+///
+/// # Example
+/// ```rust,no_run
+/// let model = ChatModel::default();
+/// let logit_bias = LogitBiasBuilder::for_chat_model(model)
+///     .bias("dog", -100.0)
+///     .bias("cat", 5.0)
+///     .build()
+///     .unwrap();
+///
+/// let res = client
+///     .chat_completion(model, &messages)
+///     .logit_bias(logit_bias)
+///     .send()
+///     .await;
+/// ```
+///
+/// [`bias`]: LogitBiasBuilder::bias
+/// [`bias_exact`]: LogitBiasBuilder::bias_exact
+#[derive(Debug, Clone, Default)]
+pub struct LogitBiasBuilder {
+    tokenizer: Tokenizer,
+    entries: Vec<(String, f32)>,
+}
+
+impl LogitBiasBuilder {
+    pub fn new(tokenizer: Tokenizer) -> Self {
+        LogitBiasBuilder {
+            tokenizer,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Uses the [`Tokenizer`] of the given chat model.
+    pub fn for_chat_model(model: ChatModel) -> Self {
+        Self::new(model.tokenizer())
+    }
+
+    /// Uses the [`Tokenizer`] of the given completion model.
+    pub fn for_completion_model(model: &CompletionModel) -> Self {
+        Self::new(model.tokenizer())
+    }
+
+    /// Bias `word` along with its leading-space and capitalization variants. The bias should
+    /// be between `-100.0` and `100.0`.
+    pub fn bias<S: Into<String>>(&mut self, word: S, bias: f32) -> &mut Self {
+        for variant in variants(&word.into()) {
+            self.entries.push((variant, bias));
+        }
+        self
+    }
+
+    /// Bias the tokens of `text` exactly as given. The bias should be between `-100.0` and `100.0`.
+    pub fn bias_exact<S: Into<String>>(&mut self, text: S, bias: f32) -> &mut Self {
+        self.entries.push((text.into(), bias));
+        self
+    }
+
+    /// Tokenizes every entry and returns a map of token id (as a string) to bias. If the same
+    /// token is biased more than once, the last bias added is used. Returns an error if a bias
+    /// is out of range or the tokenizer fails to load.
+    pub fn build(&self) -> Result<HashMap<String, f32>, OairsError> {
+        if let Some((text, bias)) = self
+            .entries
+            .iter()
+            .find(|(_, bias)| !(-100.0..=100.0).contains(bias))
+        {
+            return Err(OairsError::new(
+                format!("Bias for {text:?} must be between -100.0 and 100.0, got {bias}"),
+                ErrorType::ParamError,
+                Some("logit_bias".to_string()),
+                None,
+            ));
+        }
+
+        let texts: Vec<&str> = self.entries.iter().map(|(t, _)| t.as_str()).collect();
+        let tokens = tokenize_batch(&texts, self.tokenizer);
+
+        let mut logit_bias = HashMap::new();
+        for (tokens, (_, bias)) in tokens.iter().zip(self.entries.iter()) {
+            for token in tokens {
+                logit_bias.insert(token.to_string(), *bias);
+            }
+        }
+
+        Ok(logit_bias)
+    }
+}
+
+// The word as given, lowercase, and capitalized, each with and without a leading space.
+fn variants(word: &str) -> Vec<String> {
+    let word = word.trim();
+    let lower = word.to_lowercase();
+    let mut chars = lower.chars();
+    let capitalized = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+
+    let mut seen = HashSet::new();
+    [word.to_string(), lower, capitalized]
+        .into_iter()
+        .filter(|w| !w.is_empty())
+        .flat_map(|w| [format!(" {w}"), w])
+        .filter(|w| seen.insert(w.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tokenizers::tokenize;
+
+    use super::*;
+
+    #[test]
+    fn logit_bias_variants() {
+        let logit_bias = LogitBiasBuilder::new(Tokenizer::CL100KBase)
+            .bias("dog", -100.0)
+            .build()
+            .unwrap();
+
+        for word in ["dog", " dog", "Dog", " Dog"] {
+            for token in tokenize(word, Tokenizer::CL100KBase).unwrap() {
+                assert_eq!(logit_bias.get(&token.to_string()), Some(&-100.0));
+            }
+        }
+    }
+
+    #[test]
+    fn logit_bias_out_of_range() {
+        let res = LogitBiasBuilder::default().bias_exact("dog", 101.0).build();
+
+        assert!(res.is_err());
+    }
+}
@@ -11,12 +11,27 @@
 // Either way, I don't see it as a major issue. I decided to combine them into
 // one module for now.
 
+mod batch;
 mod chat_builder;
+mod chat_stream;
 mod completion_builder;
+mod completion_stream;
+#[cfg(feature = "tokenizers")]
+mod logit_bias;
+mod render;
 pub mod response;
+mod safe_chat;
+mod transcript;
+mod validation;
 
+pub use self::batch::*;
 pub use self::chat_builder::*;
 pub use self::completion_builder::*;
+#[cfg(feature = "tokenizers")]
+pub use self::logit_bias::*;
+pub use self::safe_chat::*;
+pub use self::transcript::*;
+pub use self::validation::*;
 
 use super::*;
 
@@ -48,3 +63,67 @@ impl TopP {
         TopP(top_p)
     }
 }
+
+/// Up to 4 sequences where the API will stop generating further tokens, for the `stop`
+/// parameter of [`ChatBuilder`] and [`CompletionBuilder`]. The API rejects more than 4, so
+/// [`try_from`](StopSequences::try_from) returns a `ParamError` instead of the request failing
+/// when it's sent.
+///
+/// # Example
+/// ```rust,no_run
+/// let stop = StopSequences::try_from(vec!["\n", "###"])?;
+/// client.completion(CompletionModel::TextDavinci003).prompt("Q: ...").stop(stop);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StopSequences(Vec<String>);
+
+impl StopSequences {
+    /// The most sequences the API accepts.
+    pub const MAX: usize = 4;
+
+    pub fn as_slice(&self) -> &[String] {
+        &self.0
+    }
+}
+
+/// Returns a `ParamError` if there are no sequences, more than [`StopSequences::MAX`], or any
+/// of them is empty.
+impl<S: Into<String>> TryFrom<Vec<S>> for StopSequences {
+    type Error = OairsError;
+
+    fn try_from(sequences: Vec<S>) -> Result<Self, Self::Error> {
+        let sequences: Vec<String> = sequences.into_iter().map(Into::into).collect();
+        let message = if sequences.is_empty() {
+            Some("At least one stop sequence is required".to_string())
+        } else if sequences.len() > StopSequences::MAX {
+            Some(format!(
+                "At most {} stop sequences are allowed, got {}",
+                StopSequences::MAX,
+                sequences.len()
+            ))
+        } else if sequences.iter().any(|s| s.is_empty()) {
+            Some("Stop sequences can't be empty".to_string())
+        } else {
+            None
+        };
+
+        match message {
+            Some(message) => Err(OairsError::new(
+                message,
+                ErrorType::ParamError,
+                Some("stop".to_string()),
+                None,
+            )),
+            None => Ok(StopSequences(sequences)),
+        }
+    }
+}
+
+impl TryFrom<&str> for StopSequences {
+    type Error = OairsError;
+
+    fn try_from(sequence: &str) -> Result<Self, Self::Error> {
+        StopSequences::try_from(vec![sequence])
+    }
+}
@@ -0,0 +1,182 @@
+//! Renders [`Messages`] and [`ChatCompletion`]s as Markdown or HTML, for human-readable logs of
+//! conversations.
+
+use super::{response::ChatCompletion, *};
+
+impl Messages {
+    /// The conversation as Markdown: a `### Role` header for each message (with the function's
+    /// name for a function message), followed by its content as is, so code blocks and other
+    /// Markdown in the content are kept.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let messages = Messages::new(vec![
+    ///     Msg::system("You are a helpful assistant."),
+    ///     Msg::user("Write hello world in Rust."),
+    /// ]);
+    /// std::fs::write("chat.md", messages.to_markdown())?;
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        let sections: Vec<String> = self.data.iter().map(markdown_section).collect();
+        sections.join("\n")
+    }
+
+    /// The conversation as an HTML fragment: a `<div class="message {role}">` for each message,
+    /// with an `<h3>` header like [`to_markdown`](Self::to_markdown)'s. Fenced code blocks in
+    /// the content become `<pre><code>` blocks (with a `language-{lang}` class if the fence
+    /// names one), and the rest becomes paragraphs. All text is escaped.
+    pub fn to_html(&self) -> String {
+        let sections: Vec<String> = self.data.iter().map(html_section).collect();
+        sections.join("\n")
+    }
+}
+
+impl ChatCompletion {
+    /// Each choice's message as Markdown, as [`Messages::to_markdown`] renders it (numbered if
+    /// there's more than one choice), followed by the model and the tokens used.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        for choice in &self.choices {
+            match self.choices.len() {
+                1 => markdown.push_str(&markdown_section(&choice.message)),
+                _ => markdown.push_str(&format!(
+                    "### {} (choice {})\n\n{}\n",
+                    header(&choice.message),
+                    choice.index,
+                    choice.message.content()
+                )),
+            }
+            markdown.push('\n');
+        }
+        let tokens = self.usage.total_tokens;
+        match &self.model {
+            Some(model) => markdown.push_str(&format!("_{model}, {tokens} tokens_\n")),
+            None => markdown.push_str(&format!("_{tokens} tokens_\n")),
+        }
+        markdown
+    }
+}
+
+// E.g. "User", or "Function `get_weather`".
+fn header(message: &Msg) -> String {
+    let role = message.role_as_str();
+    let mut header = role[..1].to_uppercase() + &role[1..];
+    if let Some(name) = message.name() {
+        header.push_str(&format!(" `{name}`"));
+    }
+    header
+}
+
+fn markdown_section(message: &Msg) -> String {
+    format!("### {}\n\n{}\n", header(message), message.content())
+}
+
+fn html_section(message: &Msg) -> String {
+    let mut html = format!(
+        "<div class=\"message {}\">\n<h3>{}</h3>\n",
+        message.role_as_str(),
+        escape_html(&header(message).replace('`', ""))
+    );
+
+    let mut paragraph: Vec<&str> = Vec::new();
+    // The language and lines of the code block being read, if in one.
+    let mut code: Option<(&str, Vec<&str>)> = None;
+    for line in message.content().lines() {
+        let fence = line.trim_start().strip_prefix("```");
+        match (&mut code, fence) {
+            (Some((lang, lines)), Some(_)) => {
+                push_code(&mut html, lang, lines);
+                code = None;
+            }
+            (Some((_, lines)), None) => lines.push(line),
+            (None, Some(lang)) => {
+                push_paragraph(&mut html, &mut paragraph);
+                code = Some((lang.trim(), Vec::new()));
+            }
+            (None, None) if line.trim().is_empty() => push_paragraph(&mut html, &mut paragraph),
+            (None, None) => paragraph.push(line),
+        }
+    }
+    // An unclosed code block runs to the end of the content.
+    if let Some((lang, lines)) = &code {
+        push_code(&mut html, lang, lines);
+    }
+    push_paragraph(&mut html, &mut paragraph);
+
+    html.push_str("</div>");
+    html
+}
+
+fn push_paragraph(html: &mut String, lines: &mut Vec<&str>) {
+    if lines.is_empty() {
+        return;
+    }
+    let escaped: Vec<String> = lines.iter().map(|line| escape_html(line)).collect();
+    html.push_str(&format!("<p>{}</p>\n", escaped.join("<br>\n")));
+    lines.clear();
+}
+
+fn push_code(html: &mut String, lang: &str, lines: &[&str]) {
+    let class = match lang {
+        "" => String::new(),
+        lang => format!(" class=\"language-{}\"", escape_html(lang)),
+    };
+    html.push_str(&format!(
+        "<pre><code{class}>{}</code></pre>\n",
+        escape_html(&lines.join("\n"))
+    ));
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_messages() {
+        let messages = Messages::new(vec![
+            Msg::user("Print <hi> in Rust."),
+            Msg::assistant("Like this:\n\n```rust\nprintln!(\"<hi>\");\n```\nDone."),
+        ]);
+
+        assert_eq!(
+            messages.to_markdown(),
+            "### User\n\nPrint <hi> in Rust.\n\n### Assistant\n\nLike this:\n\n```rust\n\
+             println!(\"<hi>\");\n```\nDone.\n"
+        );
+        assert_eq!(
+            messages.to_html(),
+            "<div class=\"message user\">\n<h3>User</h3>\n<p>Print &lt;hi&gt; in Rust.</p>\n\
+             </div>\n<div class=\"message assistant\">\n<h3>Assistant</h3>\n<p>Like this:</p>\n\
+             <pre><code class=\"language-rust\">println!(&quot;&lt;hi&gt;&quot;);</code></pre>\n\
+             <p>Done.</p>\n</div>"
+        );
+
+        let completion = ChatCompletion::from_json_str(
+            r#"{"id": "chatcmpl-123", "object": "chat.completion", "created": 1679423103,
+                "model": "gpt-3.5-turbo-0301",
+                "usage": {"prompt_tokens": 13, "completion_tokens": 7, "total_tokens": 20},
+                "choices": [{"message": {"role": "assistant", "content": "Hi."},
+                             "finish_reason": "stop", "index": 0}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            completion.to_markdown(),
+            "### Assistant\n\nHi.\n\n_gpt-3.5-turbo-0301, 20 tokens_\n"
+        );
+    }
+}
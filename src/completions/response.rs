@@ -1,16 +1,43 @@
+#[cfg(feature = "dataframes")]
+use polars::{
+    prelude::{DataFrame, NamedFrom},
+    series::Series,
+};
+
 use super::*;
+use crate::pricing::Money;
 
 pub use chat_response::*;
 pub use completion_response::*;
 
 /// Used by the ChatCompletion, `Completion` and `Embedding` structs.
-#[derive(Debug, Serialize, Clone, Copy, Deserialize, SaveJson)]
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Deserialize, SaveJson)]
 pub struct Usage {
     pub prompt_tokens: usize,
     pub completion_tokens: Option<usize>,
     pub total_tokens: usize,
 }
 
+impl Usage {
+    /// What the tokens cost with `model`'s [`price`](crate::pricing::price), e.g.
+    /// `usage.cost(ChatModel::Gpt4.to_str())`. `None` if the model has no price.
+    pub fn cost(&self, model: &str) -> Option<Money> {
+        crate::pricing::price(model).map(|price| price.cost(self))
+    }
+}
+
+/// Sums the usage of several requests. `completion_tokens` is `None` if it's `None` for either.
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.total_tokens += other.total_tokens;
+        self.completion_tokens = match (self.completion_tokens, other.completion_tokens) {
+            (Some(sum), Some(tokens)) => Some(sum + tokens),
+            _ => None,
+        };
+    }
+}
+
 mod completion_response {
     use super::*;
 
@@ -35,7 +62,7 @@ mod completion_response {
     ///    Err(e) => panic!("Error: {}", e),
     /// }=
     /// ```
-    #[derive(Debug, Serialize, Deserialize, SaveJson)]
+    #[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
     pub struct Completion {
         pub id: String,
         pub object: String,
@@ -45,6 +72,71 @@ mod completion_response {
         pub usage: Usage,
     }
 
+    impl Completion {
+        /// The text of the first choice, or `None` if there are no choices.
+        pub fn text(&self) -> Option<&str> {
+            self.choices.first().map(|c| c.text.as_str())
+        }
+
+        /// The text of each choice, in order.
+        pub fn all_texts(&self) -> Vec<&str> {
+            self.choices.iter().map(|c| c.text.as_str()).collect()
+        }
+
+        /// The `finish_reason` of the first choice, e.g. `"stop"` or `"length"`.
+        pub fn finish_reason(&self) -> Option<&str> {
+            self.choices.first().map(|c| c.finish_reason.as_str())
+        }
+
+        /// Whether the first choice was cut off by `max_tokens` (its `finish_reason` is
+        /// `"length"`).
+        pub fn is_truncated(&self) -> bool {
+            self.finish_reason() == Some("length")
+        }
+
+        /// What the request cost, from its [`Usage`] and the price of its `model` (see
+        /// [`pricing`](crate::pricing)). `None` if the model has no price.
+        pub fn estimated_cost(&self) -> Option<Money> {
+            self.usage.cost(&self.model)
+        }
+
+        /// A Polars `DataFrame` with a row for each choice and the columns `index`, `text`,
+        /// `finish_reason`, and `logprobs`. `logprobs` is the sum of the log probabilities of
+        /// the sampled tokens (null if `logprobs` wasn't requested), i.e. the log probability
+        /// of the whole text.
+        #[cfg(feature = "dataframes")]
+        pub fn choices_to_df(&self) -> Result<DataFrame, OairsError> {
+            let index: Vec<u32> = self.choices.iter().map(|c| c.index).collect();
+            let text: Vec<&str> = self.choices.iter().map(|c| c.text.as_str()).collect();
+            let finish_reason: Vec<&str> = self
+                .choices
+                .iter()
+                .map(|c| c.finish_reason.as_str())
+                .collect();
+            let logprobs: Vec<Option<f32>> = self
+                .choices
+                .iter()
+                .map(|c| c.logprobs.as_ref().map(|l| l.token_logprobs.iter().sum()))
+                .collect();
+
+            DataFrame::new(vec![
+                Series::new("index", index),
+                Series::new("text", text),
+                Series::new("finish_reason", finish_reason),
+                Series::new("logprobs", logprobs),
+            ])
+            .map_err(|e| OairsError::new(e.to_string(), ErrorType::PolarsError, None, None))
+        }
+
+        /// Converts the choices into a Polars DataFrame (see
+        /// [`choices_to_df`](Self::choices_to_df)) and saves it as a parquet file.
+        #[cfg(feature = "dataframes")]
+        pub fn save_parquet(&self, path: &str) -> Result<u64, OairsError> {
+            let mut df = self.choices_to_df()?;
+            write_parquet(&mut df, path)
+        }
+    }
+
     /// Substruct of the [`Completion`] struct, used for deserializing the `choices` field in a response from the completions endpoint.
     #[derive(Debug, Serialize, Deserialize, SaveJson)]
     pub struct Choice {
@@ -54,6 +146,9 @@ mod completion_response {
         pub finish_reason: String,
     }
 
+    /// The log probabilities of a [`Choice`], if `logprobs` was requested: for each sampled
+    /// token, its log probability, the `logprobs` most likely tokens at its position, and its
+    /// offset in the text.
     #[derive(Debug, Serialize, Deserialize, SaveJson)]
     pub struct LogProbsResult {
         pub tokens: Vec<String>,
@@ -61,16 +156,88 @@ mod completion_response {
         pub top_logprobs: Vec<HashMap<String, f32>>,
         pub text_offset: Vec<u32>,
     }
+
+    impl LogProbsResult {
+        /// The number of sampled tokens.
+        pub fn len(&self) -> usize {
+            self.tokens.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.tokens.is_empty()
+        }
+
+        /// The most likely tokens at position `i` with their log probabilities, most likely
+        /// first. Empty if `i` is out of range.
+        pub fn top_alternatives(&self, i: usize) -> Vec<(&str, f32)> {
+            let mut alternatives: Vec<(&str, f32)> = match self.top_logprobs.get(i) {
+                Some(top) => top.iter().map(|(t, lp)| (t.as_str(), *lp)).collect(),
+                None => Vec::new(),
+            };
+            alternatives.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+            alternatives
+        }
+
+        /// The entropy (in nats) at each position, computed from the top alternatives. Since
+        /// the rest of the vocabulary isn't returned, it's a lower bound: low values mean the
+        /// model was confident in the token it sampled.
+        pub fn token_entropy(&self) -> Vec<f32> {
+            self.top_logprobs
+                .iter()
+                .map(|top| top.values().map(|lp| -lp.exp() * lp).sum())
+                .collect()
+        }
+
+        /// The perplexity of the sampled tokens, `exp` of their mean negative log probability,
+        /// or `None` if there are none. 1.0 means every token had a probability of 1.
+        pub fn perplexity(&self) -> Option<f32> {
+            if self.token_logprobs.is_empty() {
+                return None;
+            }
+            let mean = self.token_logprobs.iter().sum::<f32>() / self.token_logprobs.len() as f32;
+            Some((-mean).exp())
+        }
+
+        /// The characters of the choice's text that token `i` spans, or `None` if `i` is out of
+        /// range. The API's `text_offset` counts from the start of the prompt, so the offsets
+        /// are made relative to the first token, which starts the text unless the prompt was
+        /// echoed.
+        pub fn char_range(&self, i: usize) -> Option<std::ops::Range<usize>> {
+            let start = *self.text_offset.get(i)? as usize;
+            let first = *self.text_offset.first()? as usize;
+            let len = self.tokens.get(i)?.chars().count();
+            Some(start - first..start - first + len)
+        }
+
+        /// A Polars `DataFrame` with a row for each sampled token and the columns `position`,
+        /// `token`, `logprob`, `entropy` (see [`token_entropy`](Self::token_entropy)), and
+        /// `text_offset`.
+        #[cfg(feature = "dataframes")]
+        pub fn to_df(&self) -> Result<DataFrame, OairsError> {
+            let position: Vec<u32> = (0..self.len() as u32).collect();
+            let tokens: Vec<&str> = self.tokens.iter().map(String::as_str).collect();
+
+            DataFrame::new(vec![
+                Series::new("position", position),
+                Series::new("token", tokens),
+                Series::new("logprob", &self.token_logprobs),
+                Series::new("entropy", self.token_entropy()),
+                Series::new("text_offset", &self.text_offset),
+            ])
+            .map_err(|e| OairsError::new(e.to_string(), ErrorType::PolarsError, None, None))
+        }
+    }
 }
 
 mod chat_response {
+    #[cfg(feature = "tokenizers")]
     use crate::tokenizers::{tokenize, Tokenizer};
 
     use super::*;
 
     /// For representing a successful response from the `...chat/completions`
     /// endpoint.
-    #[derive(Debug, Serialize, Deserialize, SaveJson)]
+    #[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
     pub struct ChatCompletion {
         pub input: Option<String>,
         pub id: String,
@@ -82,6 +249,19 @@ mod chat_response {
     }
 
     impl ChatCompletion {
+        /// The `model` that generated the completion, e.g. [`ChatModel::Gpt40314`]. `None` if
+        /// the response has no `model`, or it isn't a `ChatModel` variant.
+        pub fn chat_model(&self) -> Option<ChatModel> {
+            self.model.as_deref()?.parse().ok()
+        }
+
+        /// What the request cost, from its [`Usage`] and the price of its `model` (see
+        /// [`pricing`](crate::pricing)). `None` if the response has no `model`, or the model
+        /// has no price.
+        pub fn estimated_cost(&self) -> Option<Money> {
+            self.usage.cost(self.model.as_deref()?)
+        }
+
         /// Returns only the first response message.
         pub fn response_message(&self) -> Msg {
             self.choices.iter().take(1).next().unwrap().message.clone()
@@ -94,6 +274,75 @@ mod chat_response {
                 .map(|choice| choice.message.clone())
                 .collect()
         }
+
+        /// The content of the first choice's message, or `None` if there are no choices.
+        /// Unlike [`response_message`](Self::response_message), doesn't clone or panic.
+        pub fn text(&self) -> Option<&str> {
+            self.choices.first().map(|c| c.message.content())
+        }
+
+        /// The content of each choice's message, in order.
+        pub fn all_texts(&self) -> Vec<&str> {
+            self.choices.iter().map(|c| c.message.content()).collect()
+        }
+
+        /// The `finish_reason` of the first choice, e.g. `"stop"`, `"length"`, or
+        /// `"function_call"`. `None` if there are no choices or the API didn't give one.
+        pub fn finish_reason(&self) -> Option<&str> {
+            self.choices.first()?.finish_reason.as_deref()
+        }
+
+        /// Whether the first choice was cut off by `max_tokens` (its `finish_reason` is
+        /// `"length"`).
+        pub fn is_truncated(&self) -> bool {
+            self.finish_reason() == Some("length")
+        }
+
+        /// A Polars `DataFrame` with a row for each choice and the columns `index`, `role`,
+        /// `content`, and `finish_reason`. (The chat completions endpoint doesn't return
+        /// log probabilities.)
+        #[cfg(feature = "dataframes")]
+        pub fn to_df(&self) -> Result<DataFrame, OairsError> {
+            let index: Vec<u32> = self.choices.iter().map(|c| c.index as u32).collect();
+            let role: Vec<&str> = self
+                .choices
+                .iter()
+                .map(|c| c.message.role_as_str())
+                .collect();
+            let content: Vec<&str> = self.choices.iter().map(|c| c.message.content()).collect();
+            let finish_reason: Vec<Option<&str>> = self
+                .choices
+                .iter()
+                .map(|c| c.finish_reason.as_deref())
+                .collect();
+
+            DataFrame::new(vec![
+                Series::new("index", index),
+                Series::new("role", role),
+                Series::new("content", content),
+                Series::new("finish_reason", finish_reason),
+            ])
+            .map_err(|e| OairsError::new(e.to_string(), ErrorType::PolarsError, None, None))
+        }
+
+        /// Converts the choices into a Polars DataFrame (see [`to_df`](Self::to_df)) and
+        /// saves it as a parquet file.
+        #[cfg(feature = "dataframes")]
+        pub fn save_parquet(&self, path: &str) -> Result<u64, OairsError> {
+            let mut df = self.to_df()?;
+            write_parquet(&mut df, path)
+        }
+    }
+
+    /// A chat completion request paired with its response, as written by
+    /// [`ChatBuilder::archive_to`](crate::completions::ChatBuilder::archive_to). The `request` is
+    /// the JSON payload that was sent, and `timestamp` is the time the response was received, in
+    /// milliseconds since the Unix epoch.
+    #[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
+    pub struct ChatArchiveRecord {
+        pub timestamp: u128,
+        pub request: serde_json::Value,
+        pub response: ChatCompletion,
     }
 
     #[derive(Debug, Serialize, Deserialize)]
@@ -150,6 +399,7 @@ mod chat_response {
         /// message.content field in a `chat.completion` object.
         /// * prompt - The original prompt (message) that was used to generate the
         /// `chat.completion.chunk` object.
+        #[cfg(feature = "tokenizers")]
         pub fn to_chat_response(
             self,
             response_message_content: String,
@@ -233,3 +483,101 @@ mod chat_response {
         pub msg: Option<String>,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_text_accessors() {
+        let completion = Completion::from_json_str(
+            r#"{"id":"cmpl-1","object":"text_completion","created":1,"model":"text-davinci-003","choices":[{"text":" Yes","index":0,"logprobs":null,"finish_reason":"length"},{"text":" No","index":1,"logprobs":null,"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":2,"total_tokens":3}}"#,
+        )
+        .unwrap();
+        assert_eq!(completion.text(), Some(" Yes"));
+        assert_eq!(completion.all_texts(), vec![" Yes", " No"]);
+        assert!(completion.is_truncated());
+
+        let mut chat = ChatCompletion::from_json_str(
+            r#"{"id":"chatcmpl-1","object":"chat.completion","created":1,"model":"gpt-3.5-turbo-0301","choices":[{"index":0,"message":{"role":"assistant","content":"Hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+        )
+        .unwrap();
+        assert_eq!(chat.text(), Some("Hi"));
+        assert_eq!(chat.all_texts(), vec!["Hi"]);
+        assert_eq!(chat.finish_reason(), Some("stop"));
+        assert!(!chat.is_truncated());
+
+        chat.choices.clear();
+        assert_eq!(chat.text(), None);
+        assert_eq!(chat.finish_reason(), None);
+    }
+
+    #[test]
+    fn logprobs_analysis() {
+        let logprobs: LogProbsResult = serde_json::from_str(
+            r#"{"tokens":[" Yes","!"],"token_logprobs":[0.0,-2.0],"top_logprobs":[{" Yes":0.0},{"!":-2.0,".":-0.5}],"text_offset":[12,16]}"#,
+        )
+        .unwrap();
+        assert_eq!(logprobs.top_alternatives(1), vec![(".", -0.5), ("!", -2.0)]);
+        assert!(logprobs.top_alternatives(2).is_empty());
+
+        let entropy = logprobs.token_entropy();
+        assert_eq!(entropy[0], 0.0);
+        assert!(entropy[1] > 0.0);
+        assert!((logprobs.perplexity().unwrap() - 1f32.exp()).abs() < 1e-6);
+
+        assert_eq!(logprobs.char_range(0), Some(0..4));
+        assert_eq!(logprobs.char_range(1), Some(4..5));
+        assert_eq!(logprobs.char_range(2), None);
+
+        #[cfg(feature = "dataframes")]
+        assert_eq!(logprobs.to_df().unwrap().shape(), (2, 5));
+    }
+
+    #[cfg(feature = "dataframes")]
+    #[test]
+    fn choices_to_df() {
+        let completion: Completion = serde_json::from_str(
+            r#"{"id":"cmpl-1","object":"text_completion","created":1,"model":"text-davinci-003","choices":[{"text":" Yes","index":0,"logprobs":{"tokens":[" Yes"],"token_logprobs":[-0.5],"top_logprobs":[{" Yes":-0.5}],"text_offset":[0]},"finish_reason":"stop"},{"text":" No","index":1,"logprobs":null,"finish_reason":"length"}],"usage":{"prompt_tokens":1,"completion_tokens":2,"total_tokens":3}}"#,
+        )
+        .unwrap();
+        let df = completion.choices_to_df().unwrap();
+
+        assert_eq!(df.shape(), (2, 4));
+        assert_eq!(df.column("logprobs").unwrap().null_count(), 1);
+
+        let chat: ChatCompletion = serde_json::from_str(
+            r#"{"id":"chatcmpl-1","object":"chat.completion","created":1,"model":"gpt-3.5-turbo-0301","choices":[{"index":0,"message":{"role":"assistant","content":"Hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+        )
+        .unwrap();
+        let df = chat.to_df().unwrap();
+
+        assert_eq!(
+            df.get_column_names(),
+            vec!["index", "role", "content", "finish_reason"]
+        );
+        let content = df.column("content").unwrap().utf8().unwrap();
+        assert_eq!(content.into_iter().next(), Some(Some("Hi")));
+    }
+
+    #[test]
+    fn chat_model_round_trip() {
+        for model in ChatModel::ALL {
+            let json = serde_json::to_string(&model).unwrap();
+            assert_eq!(serde_json::from_str::<ChatModel>(&json).unwrap(), model);
+        }
+        let completion: ChatCompletion = serde_json::from_str(
+            r#"{"id":"chatcmpl-1","object":"chat.completion","created":1,"model":"gpt-4-0314","choices":[],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+        )
+        .unwrap();
+        assert_eq!(completion.chat_model(), Some(ChatModel::Gpt40314));
+
+        let model: ChatModel = serde_json::from_str(r#""gpt-4-32k-0901""#).unwrap();
+        assert_eq!(model, ChatModel::Other("gpt-4-32k-0901".to_string()));
+        assert_eq!(
+            serde_json::to_string(&model).unwrap(),
+            r#""gpt-4-32k-0901""#
+        );
+        assert_eq!(model.max_context_tokens(), 32768);
+    }
+}
@@ -0,0 +1,225 @@
+//! Contains the [`SafeChatBuilder`], which runs a chat completion request past the moderations
+//! endpoint before (and optionally after) sending it. See
+//! [`Client::safe_chat_completion`](crate::client::Client::safe_chat_completion).
+
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    client::Client,
+    error::{moderation_blocked, ModerationBlocked, ModerationStage},
+};
+
+use super::{response::ChatCompletion, *};
+
+/// A [`ChatBuilder`] whose [`send`](SafeChatBuilder::send) first runs the latest user messages
+/// (those after the last assistant message) through the moderations endpoint, and returns a
+/// `ModerationBlocked` error without calling the chat endpoint if any are flagged. With
+/// [`moderate_reply`](SafeChatBuilder::moderate_reply), the model's reply is checked as well.
+///
+/// The chat request is configured through the `ChatBuilder` it derefs to.
+///
+/// # Example
+/// ```rust,no_run
+/// let mut chat = client.safe_chat_completion(ChatModel::default(), &messages);
+/// chat.max_tokens(200);
+/// match chat.moderate_reply(true).send().await {
+///     Ok(completion) => messages.push_response(&completion),
+///     Err(e) if e.is_moderation_blocked() => {
+///         let blocked = e.moderation_blocked().unwrap();
+///         println!("Blocked for {:?}", blocked.categories);
+///     }
+///     Err(e) => return Err(e),
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SafeChatBuilder<'a> {
+    client: &'a Client<Keyed>,
+    chat: ChatBuilder<Sendable>,
+    prompt: Vec<String>,
+    moderate_reply: bool,
+}
+
+impl<'a> SafeChatBuilder<'a> {
+    pub(crate) fn new(
+        client: &'a Client<Keyed>,
+        chat: ChatBuilder<Sendable>,
+        msgs: &Messages,
+    ) -> SafeChatBuilder<'a> {
+        SafeChatBuilder {
+            client,
+            chat,
+            prompt: latest_user_messages(msgs),
+            moderate_reply: false,
+        }
+    }
+
+    /// If `true`, the content of each choice is also run through the moderations endpoint, and
+    /// a `ModerationBlocked` error is returned instead of the completion if any is flagged.
+    /// Defaults to `false`.
+    pub fn moderate_reply(&mut self, truth_value: bool) -> &mut Self {
+        self.moderate_reply = truth_value;
+        self
+    }
+
+    /// The user messages that are moderated before the request is sent.
+    pub fn moderated_messages(&self) -> &[String] {
+        &self.prompt
+    }
+
+    /// Moderates the latest user messages, sends the chat request with
+    /// [`ChatBuilder::send_parsed`], and, if set, moderates the reply.
+    pub async fn send(&self) -> Result<ChatCompletion, OairsError> {
+        self.moderate(self.prompt.clone(), ModerationStage::Prompt)
+            .await?;
+        let completion = self.chat.send_parsed().await?;
+        if self.moderate_reply {
+            let reply = completion
+                .all_texts()
+                .into_iter()
+                .map(|text| text.to_string())
+                .collect();
+            self.moderate(reply, ModerationStage::Reply).await?;
+        }
+        Ok(completion)
+    }
+
+    async fn moderate(
+        &self,
+        inputs: Vec<String>,
+        stage: ModerationStage,
+    ) -> Result<(), OairsError> {
+        let inputs: Vec<String> = inputs.into_iter().filter(|i| !i.is_empty()).collect();
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        let result = self
+            .client
+            .create_moderations(inputs.clone())
+            .send_parsed()
+            .await?;
+        let mut flagged = Vec::new();
+        let mut categories = Vec::new();
+        for (input, moderation) in inputs.into_iter().zip(&result.results) {
+            if !moderation.flagged {
+                continue;
+            }
+            flagged.push(input);
+            for category in moderation.flagged_categories() {
+                if !categories.contains(&category) {
+                    categories.push(category);
+                }
+            }
+        }
+
+        match flagged.is_empty() {
+            true => Ok(()),
+            false => Err(moderation_blocked(ModerationBlocked {
+                stage,
+                flagged,
+                categories,
+            })),
+        }
+    }
+}
+
+impl Deref for SafeChatBuilder<'_> {
+    type Target = ChatBuilder<Sendable>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.chat
+    }
+}
+
+impl DerefMut for SafeChatBuilder<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.chat
+    }
+}
+
+/// The content of the user messages after the last assistant message.
+fn latest_user_messages(msgs: &Messages) -> Vec<String> {
+    let start = msgs
+        .data
+        .iter()
+        .rposition(|msg| msg.role() == Role::Assistant)
+        .map_or(0, |i| i + 1);
+    msgs.data[start..]
+        .iter()
+        .filter(|msg| msg.role() == Role::User)
+        .map(|msg| msg.content().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_user_messages_after_reply() {
+        let messages = Messages::new(vec![
+            Msg::system("Be brief."),
+            Msg::user("Hi"),
+            Msg::assistant("Hello"),
+            Msg::user("First"),
+            Msg::function("f", "{}"),
+            Msg::user("Second"),
+        ]);
+        assert_eq!(latest_user_messages(&messages), vec!["First", "Second"]);
+        assert_eq!(
+            latest_user_messages(&Messages::new(vec![Msg::user("Hi")])),
+            vec!["Hi"]
+        );
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        client::HttpMethod,
+        completions::{Messages, Msg},
+        test_util::{fixtures::MODERATION_RESULT, MockApi},
+    };
+
+    #[tokio::test]
+    async fn safe_chat_completion() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let messages = Messages::new(vec![Msg::system("Be brief."), Msg::user("Hi")]);
+
+        let completion = client
+            .safe_chat_completion(crate::ChatModel::default(), &messages)
+            .moderate_reply(true)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(completion.text(), Some("This is a test response."));
+        let moderated: Vec<serde_json::Value> = api
+            .requests("/moderations")
+            .await
+            .iter()
+            .map(|r| serde_json::from_slice(&r.body).unwrap())
+            .collect();
+        assert_eq!(moderated[0]["input"], serde_json::json!(["Hi"]));
+        assert_eq!(
+            moderated[1]["input"],
+            serde_json::json!(["This is a test response."])
+        );
+
+        let flagged = MODERATION_RESULT
+            .replacen("\"violence\": false", "\"violence\": true", 1)
+            .replace("\"flagged\": false", "\"flagged\": true");
+        api.mock(HttpMethod::Post, "/moderations", &flagged).await;
+        let error = client
+            .safe_chat_completion(crate::ChatModel::default(), &messages)
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error.is_moderation_blocked());
+        let blocked = error.moderation_blocked().unwrap();
+        assert_eq!(blocked.stage, crate::error::ModerationStage::Prompt);
+        assert_eq!(blocked.flagged, vec!["Hi".to_string()]);
+        assert_eq!(blocked.categories, vec!["violence"]);
+        assert_eq!(api.requests("/chat/completions").await.len(), 1);
+    }
+}
@@ -0,0 +1,199 @@
+//! Contains the [`Transcript`] format used by [`Messages::save_transcript`] and
+//! [`Messages::load_transcript`], and the [`Turn`]s yielded by [`Messages::replay`].
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{chat_stream::count_tokens, response::Usage, *};
+
+/// The version of the [`Transcript`] format written by [`Messages::save_transcript`].
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// When a message was added to [`Messages`] and, if it came from a response (see
+/// [`Messages::push_response`]), the model and usage of that response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MsgMeta {
+    /// Unix timestamp in seconds.
+    pub created_at: u64,
+    pub model: Option<String>,
+    pub usage: Option<Usage>,
+}
+
+impl MsgMeta {
+    pub(super) fn now() -> MsgMeta {
+        MsgMeta {
+            created_at: unix_now(),
+            ..MsgMeta::default()
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// A conversation saved by [`Messages::save_transcript`].
+#[derive(Debug, Clone, Serialize, Deserialize, SaveJson, LoadJson)]
+pub struct Transcript {
+    pub version: u32,
+    /// Unix timestamp in seconds.
+    pub saved_at: u64,
+    /// The usage of every response in the conversation, summed.
+    pub usage: Usage,
+    pub messages: Vec<TranscriptEntry>,
+}
+
+/// A message of a [`Transcript`], with its [`MsgMeta`] and number of tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub role: Role,
+    pub content: String,
+    /// The number of tokens of the content, or 0 without the `tokenizers` feature.
+    pub tokens: usize,
+    #[serde(flatten)]
+    pub meta: MsgMeta,
+}
+
+/// A message of [`Messages`], yielded by [`Messages::replay`].
+#[derive(Debug, Clone, Copy)]
+pub struct Turn<'a> {
+    /// The index of the message.
+    pub index: usize,
+    pub message: &'a Msg,
+    /// `None` if the messages were deserialized rather than built or loaded from a transcript.
+    pub meta: Option<&'a MsgMeta>,
+    /// The messages before this one.
+    pub history: &'a [Msg],
+}
+
+impl Messages {
+    /// The messages with their [`MsgMeta`] and token counts, along with the total usage of the
+    /// responses. See [`save_transcript`](Messages::save_transcript).
+    pub fn to_transcript(&self) -> Result<Transcript, OairsError> {
+        let mut usage = Usage {
+            prompt_tokens: 0,
+            completion_tokens: Some(0),
+            total_tokens: 0,
+        };
+        let mut messages = Vec::with_capacity(self.data.len());
+        for turn in self.replay() {
+            let meta = turn.meta.cloned().unwrap_or_default();
+            if let Some(response_usage) = meta.usage {
+                usage.prompt_tokens += response_usage.prompt_tokens;
+                usage.total_tokens += response_usage.total_tokens;
+                usage.completion_tokens = usage
+                    .completion_tokens
+                    .zip(response_usage.completion_tokens)
+                    .map(|(sum, tokens)| sum + tokens);
+            }
+            messages.push(TranscriptEntry {
+                role: turn.message.role(),
+                content: turn.message.content().to_string(),
+                tokens: count_tokens(turn.message.content()),
+                meta,
+            });
+        }
+
+        Ok(Transcript {
+            version: TRANSCRIPT_VERSION,
+            saved_at: unix_now(),
+            usage,
+            messages,
+        })
+    }
+
+    /// Saves the conversation as a [`Transcript`] to `path`, recording when each message was
+    /// added, its number of tokens, and, for responses added with
+    /// [`push_response`](Messages::push_response), the model and usage. Load it with
+    /// [`load_transcript`](Messages::load_transcript) to resume the conversation.
+    ///
+    /// As with [`SaveJson::save_json`], ".json" is appended to the path if needed, and the path
+    /// written to is returned.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_transcript<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, OairsError> {
+        Ok(self.to_transcript()?.save_json(path)?)
+    }
+
+    /// Loads a conversation saved with [`save_transcript`](Messages::save_transcript),
+    /// keeping each message's [`MsgMeta`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_transcript<P: AsRef<Path>>(path: P) -> Result<Messages, OairsError> {
+        Ok(Messages::from(Transcript::load_json(path)?))
+    }
+
+    /// Iterates over the messages in order, each with its [`MsgMeta`] and the messages before
+    /// it.
+    pub fn replay(&self) -> impl Iterator<Item = Turn<'_>> {
+        self.data.iter().enumerate().map(|(index, message)| Turn {
+            index,
+            message,
+            meta: self.meta.get(index),
+            history: &self.data[..index],
+        })
+    }
+}
+
+impl From<Transcript> for Messages {
+    fn from(transcript: Transcript) -> Self {
+        let mut messages = Messages::default();
+        for entry in transcript.messages {
+            messages.data.push(Msg::new(entry.role, entry.content));
+            messages.meta.push(entry.meta);
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{response::ChatCompletion, *};
+
+    const CHAT_COMPLETION: &str = r#"{
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "created": 1679423103,
+        "model": "gpt-3.5-turbo-0301",
+        "usage": {"prompt_tokens": 13, "completion_tokens": 7, "total_tokens": 20},
+        "choices": [{
+            "message": {"role": "assistant", "content": "This is a test response."},
+            "finish_reason": "stop",
+            "index": 0
+        }]
+    }"#;
+
+    #[test]
+    fn transcript_round_trip() {
+        let response = ChatCompletion::from_json_str(CHAT_COMPLETION).unwrap();
+        let mut messages = Messages::new(vec![
+            Msg::System("You are a test.".to_string()),
+            Msg::User("Say something.".to_string()),
+        ]);
+        messages.push_response(&response);
+
+        let path = std::env::temp_dir().join(format!("oairs-transcript-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        messages.save_transcript(path).unwrap();
+        let loaded = Messages::load_transcript(&format!("{path}.json")).unwrap();
+        std::fs::remove_file(format!("{path}.json")).unwrap();
+
+        let transcript = loaded.to_transcript().unwrap();
+        assert_eq!(transcript.version, TRANSCRIPT_VERSION);
+        assert_eq!(transcript.usage.total_tokens, response.usage.total_tokens);
+        #[cfg(feature = "tokenizers")]
+        assert_eq!(transcript.messages[1].tokens, 3);
+
+        let turns: Vec<Turn> = loaded.replay().collect();
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[2].history.len(), 2);
+        assert_eq!(turns[2].message.content(), "This is a test response.");
+        assert_eq!(
+            turns[2].meta.unwrap().model.as_deref(),
+            Some("gpt-3.5-turbo-0301")
+        );
+        assert!(turns[0].meta.unwrap().usage.is_none());
+    }
+}
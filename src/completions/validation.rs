@@ -0,0 +1,239 @@
+//! Contains [`Messages::validate`] and the [`MessageIssue`]s it reports, as well as
+//! the [`ParamIssue`]s reported by [`CompletionBuilder::validate`].
+
+use super::*;
+
+/// A common mistake in [`Messages`], found by [`Messages::validate`]. Each issue but
+/// `NoUserMessage` holds the index of the offending message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageIssue {
+    /// The content is empty or only whitespace.
+    EmptyContent(usize),
+    /// None of the messages are from the user.
+    NoUserMessage,
+    /// A system message after the first message.
+    SystemNotFirst(usize),
+    /// The content contains control characters other than newlines, carriage returns, and
+    /// tabs, e.g. from text extracted from a PDF.
+    ControlCharacters(usize),
+    /// A function message with an empty name.
+    MissingFunctionName(usize),
+}
+
+impl std::fmt::Display for MessageIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageIssue::EmptyContent(i) => write!(f, "message {} has no content", i),
+            MessageIssue::NoUserMessage => write!(f, "there is no user message"),
+            MessageIssue::SystemNotFirst(i) => {
+                write!(
+                    f,
+                    "message {} is a system message but not the first message",
+                    i
+                )
+            }
+            MessageIssue::ControlCharacters(i) => {
+                write!(f, "message {} contains control characters", i)
+            }
+            MessageIssue::MissingFunctionName(i) => {
+                write!(f, "message {} is a function message without a name", i)
+            }
+        }
+    }
+}
+
+impl MessageIssue {
+    /// The index of the offending message, if the issue is with a single message.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            MessageIssue::EmptyContent(i)
+            | MessageIssue::SystemNotFirst(i)
+            | MessageIssue::ControlCharacters(i)
+            | MessageIssue::MissingFunctionName(i) => Some(*i),
+            MessageIssue::NoUserMessage => None,
+        }
+    }
+}
+
+impl Messages {
+    /// Checks the messages for common mistakes that the API would reject with an opaque `400`,
+    /// or that quietly degrade the response: empty content, no user message, system messages
+    /// after the first position, control characters, and function messages without a name.
+    /// Returns the issues in the order of the messages, or an empty `Vec` if there are none.
+    ///
+    /// See also [`ChatBuilder::validate_messages`].
+    pub fn validate(&self) -> Vec<MessageIssue> {
+        let mut issues = Vec::new();
+        for (i, msg) in self.data.iter().enumerate() {
+            let content = msg.content();
+            // A function call has no content.
+            if content.trim().is_empty() && msg.function_call().is_none() {
+                issues.push(MessageIssue::EmptyContent(i));
+            }
+            if msg.role() == Role::System && i > 0 {
+                issues.push(MessageIssue::SystemNotFirst(i));
+            }
+            if content
+                .chars()
+                .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+            {
+                issues.push(MessageIssue::ControlCharacters(i));
+            }
+            if msg.name() == Some("") {
+                issues.push(MessageIssue::MissingFunctionName(i));
+            }
+        }
+        if !self.data.iter().any(|msg| msg.role() == Role::User) {
+            issues.push(MessageIssue::NoUserMessage);
+        }
+        issues
+    }
+
+    /// [`validate`](Self::validate), as a `ParamError` listing the issues.
+    pub(crate) fn check(&self) -> Result<(), OairsError> {
+        let issues = self.validate();
+        if issues.is_empty() {
+            return Ok(());
+        }
+        let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        Err(OairsError::new(
+            format!("Invalid messages: {}", issues.join("; ")),
+            ErrorType::ParamError,
+            Some("messages".to_string()),
+            None,
+        ))
+    }
+}
+
+/// A combination of completion parameters that the API rejects, found by
+/// [`CompletionBuilder::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamIssue {
+    /// `best_of` is less than `n`.
+    BestOfLessThanN { best_of: u32, n: u8 },
+    /// `n` or `best_of` is 0.
+    ZeroChoices,
+    /// `logprobs` is greater than 5.
+    LogprobsTooHigh(u8),
+    /// `stream` is set with a `best_of` greater than 1.
+    StreamWithBestOf(u32),
+    /// `suffix` is set with `echo`.
+    SuffixWithEcho,
+    /// `suffix` is set with more than one prompt. Holds the number of prompts.
+    SuffixWithMultiplePrompts(usize),
+    /// `presence_penalty` is not between -2.0 and 2.0.
+    PresencePenalty(f32),
+    /// `frequency_penalty` is not between -2.0 and 2.0.
+    FrequencyPenalty(f32),
+}
+
+impl std::fmt::Display for ParamIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamIssue::BestOfLessThanN { best_of, n } => {
+                write!(f, "best_of ({}) must be at least n ({})", best_of, n)
+            }
+            ParamIssue::ZeroChoices => write!(f, "n and best_of must be at least 1"),
+            ParamIssue::LogprobsTooHigh(logprobs) => {
+                write!(f, "logprobs ({}) must be at most 5", logprobs)
+            }
+            ParamIssue::StreamWithBestOf(best_of) => {
+                write!(f, "stream can't be used with best_of ({}) > 1", best_of)
+            }
+            ParamIssue::SuffixWithEcho => write!(f, "suffix can't be used with echo"),
+            ParamIssue::SuffixWithMultiplePrompts(prompts) => {
+                write!(f, "suffix can't be used with {} prompts", prompts)
+            }
+            ParamIssue::PresencePenalty(penalty) => {
+                write!(f, "presence_penalty ({}) must be between -2 and 2", penalty)
+            }
+            ParamIssue::FrequencyPenalty(penalty) => {
+                write!(
+                    f,
+                    "frequency_penalty ({}) must be between -2 and 2",
+                    penalty
+                )
+            }
+        }
+    }
+}
+
+impl ParamIssue {
+    /// The parameter at fault. For an incompatible pair, the one that was likely set last.
+    pub fn param(&self) -> &'static str {
+        match self {
+            ParamIssue::BestOfLessThanN { .. } | ParamIssue::StreamWithBestOf(_) => "best_of",
+            ParamIssue::ZeroChoices => "n",
+            ParamIssue::LogprobsTooHigh(_) => "logprobs",
+            ParamIssue::SuffixWithEcho | ParamIssue::SuffixWithMultiplePrompts(_) => "suffix",
+            ParamIssue::PresencePenalty(_) => "presence_penalty",
+            ParamIssue::FrequencyPenalty(_) => "frequency_penalty",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_validate() {
+        let messages = Messages::new(vec![Msg::system("Be brief."), Msg::user("Hi")]);
+        assert!(messages.validate().is_empty());
+        assert!(messages.check().is_ok());
+
+        let messages = Messages::new(vec![
+            Msg::assistant(" "),
+            Msg::system("Be brief.\u{0}"),
+            Msg::new(Role::Function, "22"),
+        ]);
+        assert_eq!(
+            messages.validate(),
+            vec![
+                MessageIssue::EmptyContent(0),
+                MessageIssue::SystemNotFirst(1),
+                MessageIssue::ControlCharacters(1),
+                MessageIssue::MissingFunctionName(2),
+                MessageIssue::NoUserMessage,
+            ]
+        );
+        let error = messages.check().unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("messages"));
+        assert!(error
+            .message
+            .contains("message 1 contains control characters"));
+    }
+
+    #[test]
+    fn completion_validate() {
+        let mut builder = CompletionBuilder::create("key", CompletionModel::TextDavinci003);
+        builder
+            .prompt("Hi")
+            .n(2)
+            .best_of(3)
+            .logprobs(LogProbs::Five);
+        assert!(builder.validate().is_empty());
+        assert!(builder.check().is_ok());
+
+        builder
+            .prompts(vec!["Hi".to_string(), "Bye".to_string()])
+            .suffix("!")
+            .echo(true)
+            .stream(true)
+            .n(4);
+        assert_eq!(
+            builder.validate(),
+            vec![
+                ParamIssue::BestOfLessThanN { best_of: 3, n: 4 },
+                ParamIssue::StreamWithBestOf(3),
+                ParamIssue::SuffixWithEcho,
+                ParamIssue::SuffixWithMultiplePrompts(2),
+            ]
+        );
+        let error = builder.dry_run().unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("best_of"));
+        assert!(error
+            .message
+            .contains("suffix can't be used with 2 prompts"));
+    }
+}
@@ -0,0 +1,261 @@
+//! Contains [`ApiKey`], for loading an API key from the environment or a file, and [`KeyRing`],
+//! for spreading requests across multiple keys.
+//!
+//! # Example
+//! ```rust,no_run
+//! use oairs::credentials::{ApiKey, KeyRing, Rotation};
+//!
+//! // A single key
+//! let client = Client::new(ApiKey::from_env().unwrap());
+//!
+//! // Several keys, switching to the next one whenever a request is rate limited
+//! let keys = vec![ApiKey::from_file("keys/org_a").unwrap(), ApiKey::from_file("keys/org_b").unwrap()];
+//! let ring = KeyRing::new(keys, Rotation::OnRateLimit).unwrap();
+//! let client = Client::with_key_ring(ring);
+//!
+//! let res = client.chat_completion(model, &messages).send().await;
+//! if let Err(e) = &res {
+//!     client.rotate_on_error(e);
+//! }
+//! ```
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use super::*;
+
+/// The environment variable read by [`ApiKey::from_env`].
+pub const API_KEY_ENV_VAR: &str = "OPENAI_API_KEY";
+
+/// An OpenAI API key. The `Debug` implementation doesn't print the key, so it won't end up in
+/// logs by accident.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey(String);
+
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ApiKey({})", redact(&self.0))
+    }
+}
+
+impl From<ApiKey> for String {
+    fn from(key: ApiKey) -> Self {
+        key.0
+    }
+}
+
+impl ApiKey {
+    pub fn new<K: Into<String>>(key: K) -> ApiKey {
+        ApiKey(key.into())
+    }
+
+    /// Reads the key from the `OPENAI_API_KEY` environment variable.
+    pub fn from_env() -> Result<ApiKey, OairsError> {
+        ApiKey::from_env_var(API_KEY_ENV_VAR)
+    }
+
+    /// Reads the key from the given environment variable.
+    pub fn from_env_var(var: &str) -> Result<ApiKey, OairsError> {
+        match std::env::var(var) {
+            Ok(key) => ApiKey::parse(&key, var),
+            Err(e) => Err(OairsError::new(
+                format!("Unable to read API key from environment: {e}"),
+                ErrorType::Authentication,
+                Some(var.to_string()),
+                None,
+            )),
+        }
+    }
+
+    /// Reads the key from a file. Blank lines and lines starting with `#` are ignored, and the
+    /// first remaining line is used as the key.
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<ApiKey, OairsError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| OairsError {
+            param: Some(path.display().to_string()),
+            ..e.into()
+        })?;
+        let key = content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or_default();
+
+        ApiKey::parse(key, &path.display().to_string())
+    }
+
+    fn parse(key: &str, source: &str) -> Result<ApiKey, OairsError> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(OairsError::new(
+                "No API key found".to_string(),
+                ErrorType::Authentication,
+                Some(source.to_string()),
+                None,
+            ));
+        }
+        Ok(ApiKey(key.to_string()))
+    }
+
+    /// The key itself.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// How a [`KeyRing`] chooses the key for the next request.
+/// * `RoundRobin` - Each request uses the next key in turn. (Default)
+/// * `OnRateLimit` - Keep using the same key until a request is rate limited (see
+///   [`KeyRing::rotate_on_error`]), then switch to the next one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    RoundRobin,
+    OnRateLimit,
+}
+
+/// A set of API keys that are rotated between, so long-running batch jobs can spread load across
+/// the keys of several organizations. Cloning a `KeyRing` is cheap, and the clones share their
+/// position in the rotation. Normally you would pass it to [`Client::with_key_ring`](crate::client::Client::with_key_ring).
+#[derive(Clone)]
+pub struct KeyRing {
+    keys: Arc<[ApiKey]>,
+    current: Arc<AtomicUsize>,
+    rotation: Rotation,
+}
+
+impl std::fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("keys", &self.keys)
+            .field("current", &self.current.load(Ordering::Relaxed))
+            .field("rotation", &self.rotation)
+            .finish()
+    }
+}
+
+impl KeyRing {
+    /// Returns an `OairsError` if `keys` is empty.
+    pub fn new(keys: Vec<ApiKey>, rotation: Rotation) -> Result<KeyRing, OairsError> {
+        if keys.is_empty() {
+            return Err(OairsError::new(
+                "A KeyRing needs at least one key".to_string(),
+                ErrorType::ParamError,
+                Some("keys".to_string()),
+                None,
+            ));
+        }
+        Ok(KeyRing {
+            keys: keys.into(),
+            current: Arc::new(AtomicUsize::new(0)),
+            rotation,
+        })
+    }
+
+    /// Reads a comma-separated list of keys from the given environment variable
+    /// (e.g., `OPENAI_API_KEYS=sk-...,sk-...`).
+    pub fn from_env_var(var: &str, rotation: Rotation) -> Result<KeyRing, OairsError> {
+        let keys = ApiKey::from_env_var(var)?;
+        let keys = keys
+            .as_str()
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(ApiKey::new)
+            .collect();
+        KeyRing::new(keys, rotation)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// The key to use for the next request. With [`Rotation::RoundRobin`] this advances the
+    /// rotation.
+    pub fn next_key(&self) -> &ApiKey {
+        let index = match self.rotation {
+            Rotation::RoundRobin => self.current.fetch_add(1, Ordering::Relaxed),
+            Rotation::OnRateLimit => self.current.load(Ordering::Relaxed),
+        };
+        &self.keys[index % self.keys.len()]
+    }
+
+    /// Switch to the next key.
+    pub fn rotate(&self) {
+        self.current.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Switch to the next key if `error` is a rate limit error and the rotation is
+    /// [`Rotation::OnRateLimit`]. Returns whether the key was switched.
+    pub fn rotate_on_error(&self, error: &OairsError) -> bool {
+        if self.rotation == Rotation::OnRateLimit && error.is_rate_limit() {
+            self.rotate();
+            return true;
+        }
+        false
+    }
+}
+
+// Keeps enough of the key to tell keys apart, e.g. "sk-...wxyz".
+pub(crate) fn redact(key: &str) -> String {
+    let chars = key.chars().count();
+    if chars <= 8 {
+        return "***".to_string();
+    }
+    let tail: String = key.chars().skip(chars - 4).collect();
+    format!("{}...{}", key.chars().take(3).collect::<String>(), tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<ApiKey> {
+        vec![ApiKey::new("sk-aaaaaaaa"), ApiKey::new("sk-bbbbbbbb")]
+    }
+
+    #[test]
+    fn key_ring_rotation() {
+        let ring = KeyRing::new(keys(), Rotation::RoundRobin).unwrap();
+        let used: Vec<&str> = (0..3).map(|_| ring.next_key().as_str()).collect();
+        assert_eq!(used, vec!["sk-aaaaaaaa", "sk-bbbbbbbb", "sk-aaaaaaaa"]);
+
+        let ring = KeyRing::new(keys(), Rotation::OnRateLimit).unwrap();
+        let rate_limit = OairsError::new(
+            "429".to_string(),
+            ErrorType::RateLimit,
+            None,
+            Some("429 Too Many Requests".to_string()),
+        );
+        let other = OairsError::new("".to_string(), ErrorType::Other, None, None);
+
+        assert_eq!(ring.next_key().as_str(), "sk-aaaaaaaa");
+        assert!(!ring.rotate_on_error(&other));
+        assert_eq!(ring.next_key().as_str(), "sk-aaaaaaaa");
+        assert!(ring.rotate_on_error(&rate_limit));
+        assert_eq!(ring.next_key().as_str(), "sk-bbbbbbbb");
+
+        assert!(KeyRing::new(Vec::new(), Rotation::RoundRobin).is_err());
+    }
+
+    #[test]
+    fn api_key_from_file() {
+        let path = std::env::temp_dir().join(format!("oairs-key-{}", std::process::id()));
+        std::fs::write(&path, "# org a\n\n  sk-abcdefgh1234  \n").unwrap();
+        let key = ApiKey::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(key.as_str(), "sk-abcdefgh1234");
+        assert_eq!(format!("{key:?}"), "ApiKey(sk-...1234)");
+    }
+}
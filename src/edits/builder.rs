@@ -1,25 +1,56 @@
-use crate::client::{handle_request, HttpMethod};
+#[cfg(feature = "tokenizers")]
+use crate::{
+    budget::{spend, TokenBudget},
+    tokenizers::{tokenize, Tokenizer},
+};
+use crate::{
+    client::{bearer, handle_request, HttpMethod, PreparedBody, PreparedRequest},
+    completions::{ChatBuilder, Messages, Msg},
+    meta::{deserialize_response, with_meta, ResponseMeta},
+    utils::raw::tee_raw,
+};
 
-use super::*;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{completions::response::ChatCompletion, meta::TimingStart};
+
+use super::{response::Edit, *};
+
+/// The system message of an edit sent through the chat completions endpoint with
+/// [`EditBuilder::via_chat`].
+const VIA_CHAT_SYSTEM: &str = "You edit text. Apply the instruction to the text and reply with \
+    only the edited text, without quotes, comments, or explanations.";
 
 // TODO: Clean up type-state pattern!
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditBuilder<State = Buildable> {
     #[serde(skip)]
     key: String,
     #[serde(skip)]
-    url: &'static str,
+    url: Cow<'static, str>,
     model: EditModel,
     #[serde(skip_serializing_if = "Option::is_none")]
     input: Option<String>,
     instruction: String,
-    n: usize,
+    /// Defaults to 1 if `n` is not specified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<Temperature>,
     #[serde(skip_serializing_if = "Option::is_none")]
     top_p: Option<TopP>,
     #[serde(skip)]
+    raw_dir: Option<std::path::PathBuf>,
+    #[serde(skip)]
+    via_chat: Option<ChatModel>,
+    #[serde(skip)]
+    chat_url: Cow<'static, str>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    budget: Option<TokenBudget>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+    #[serde(skip)]
     state: std::marker::PhantomData<State>,
 }
 
@@ -31,26 +62,67 @@ impl EditBuilder<Buildable> {
     {
         EditBuilder {
             key: key.into(),
-            url: URL.get(&Uri::Edits).unwrap(),
+            url: Endpoints::default().edits(),
             model,
             input: None,
             instruction: instruction.into(),
-            n: 1,
+            n: None,
             temperature: None,
             top_p: None,
+            raw_dir: None,
+            via_chat: None,
+            chat_url: Endpoints::default().chat_completions(),
+            #[cfg(feature = "tokenizers")]
+            budget: None,
+            extra: serde_json::Map::new(),
             state: std::marker::PhantomData,
         }
     }
 }
 
 impl EditBuilder<Sendable> {
+    /// The tokens the request is estimated to use, as spent from a [`TokenBudget`]: the tokens
+    /// of the instruction and input, plus those of the input again for each of the `n` edits.
+    #[cfg(feature = "tokenizers")]
+    pub fn estimated_tokens(&self) -> Result<usize, OairsError> {
+        let instruction = tokenize(&self.instruction, Tokenizer::P50KEdit)?.len();
+        let input = match &self.input {
+            Some(input) => tokenize(input, Tokenizer::P50KEdit)?.len(),
+            None => 0,
+        };
+        Ok(instruction + input * (1 + self.n.unwrap_or(1)))
+    }
+
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
+    pub(crate) fn set_chat_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.chat_url = url;
+        self
+    }
+
+    #[cfg(feature = "tokenizers")]
+    pub(crate) fn set_budget(&mut self, budget: Option<TokenBudget>) -> &mut Self {
+        self.budget = budget;
+        self
+    }
+
     pub fn input<S: Into<String>>(&mut self, input: S) -> &mut Self {
         self.input = Some(input.into());
         self
     }
 
     pub fn n(&mut self, n: usize) -> &mut Self {
-        self.n = n;
+        self.n = Some(n);
         self
     }
 
@@ -64,8 +136,208 @@ impl EditBuilder<Sendable> {
         self
     }
 
+    /// Send the edit to the chat completions endpoint with `model` instead of to the edits
+    /// endpoint, which is being retired. The instruction and input are sent as messages asking
+    /// for only the edited text, and the chat completion is mapped back into an [`Edit`], so
+    /// the response of [`send`](Self::send) still deserializes into one. `n`, `temperature`,
+    /// and `top_p` are passed on to the chat request.
+    ///
+    /// The [`blocking`](crate::blocking) client sends the same chat request, but its response
+    /// is the chat completion itself. Not available for `wasm32`, where reqwest can't rebuild
+    /// the response.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn via_chat(&mut self, model: ChatModel) -> &mut Self {
+        self.via_chat = Some(model);
+        self
+    }
+
+    /// The chat request an edit is sent as with [`via_chat`](Self::via_chat).
+    fn chat_builder(&self, model: &ChatModel) -> ChatBuilder<Sendable> {
+        let text = self.input.as_deref().unwrap_or_default();
+        let messages = Messages::new(vec![
+            Msg::system(VIA_CHAT_SYSTEM),
+            Msg::user(format!(
+                "Instruction: {}\n\nText:\n{text}",
+                self.instruction
+            )),
+        ]);
+        let mut chat = ChatBuilder::create(&self.key, model.clone(), &messages);
+        chat.set_url(self.chat_url.clone());
+        if let Some(n) = self.n {
+            chat.n(n.min(u8::MAX as usize) as u8);
+        }
+        if let Some(temperature) = &self.temperature {
+            chat.temperature(temperature.clone());
+        }
+        if let Some(top_p) = &self.top_p {
+            chat.top_p(top_p.clone());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(dir) = &self.raw_dir {
+            chat.save_raw_to(dir);
+        }
+        #[cfg(feature = "tokenizers")]
+        chat.set_budget(self.budget.clone());
+        for (key, value) in &self.extra {
+            chat.extra_param(key.clone(), value.clone());
+        }
+        chat
+    }
+
+    /// Adds `key` to the request body with `value`, as with
+    /// [`ChatBuilder::extra_param`](crate::completions::ChatBuilder::extra_param). The field is
+    /// passed on to the chat request if [`via_chat`](Self::via_chat) was set.
+    pub fn extra_param<K: Into<String>>(&mut self, key: K, value: serde_json::Value) -> &mut Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// The JSON body that [`send`](Self::send) sends (to the chat completions endpoint if
+    /// [`via_chat`](Self::via_chat) was set).
+    pub fn to_request_json(&self) -> serde_json::Value {
+        match &self.via_chat {
+            Some(model) => self.chat_builder(model).to_request_json(),
+            None => serde_json::to_value(self).unwrap(),
+        }
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        if let Some(model) = &self.via_chat {
+            return self.chat_builder(model).dry_run();
+        }
+        Ok(PreparedRequest::new(
+            &self.key,
+            &self.url,
+            HttpMethod::Post,
+            PreparedBody::Json(self.to_request_json()),
+        ))
+    }
+
+    /// Executes the `POST` request. With [`via_chat`](Self::via_chat), the request is sent to
+    /// the chat completions endpoint, and the returned `reqwest::Response` is rebuilt with the
+    /// status and headers of the chat response and the chat completion mapped into an [`Edit`].
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let json = serde_json::to_value(self).unwrap();
-        handle_request(&self.key, self.url, HttpMethod::Post, Some(json), None).await
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(model) = &self.via_chat {
+            return self.send_via_chat(model).await;
+        }
+        #[cfg(feature = "tokenizers")]
+        spend(self.budget.as_ref(), || self.estimated_tokens()).await?;
+        let json = self.to_request_json();
+        let res = handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None).await?;
+        tee_raw(self.raw_dir.as_deref(), "edit", res).await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn send_via_chat(&self, model: &ChatModel) -> Result<reqwest::Response, OairsError> {
+        let res = self.chat_builder(model).send().await?;
+        let status = res.status();
+        let mut headers = res.headers().clone();
+        let timing = res.extensions().get::<TimingStart>().copied();
+        let completion: ChatCompletion = deserialize_response(res).await?;
+
+        let body = match serde_json::to_vec(&Edit::from_chat(completion)) {
+            Ok(body) => body,
+            Err(e) => {
+                return Err(OairsError::new(
+                    e.to_string(),
+                    ErrorType::SerializationError,
+                    None,
+                    None,
+                ))
+            }
+        };
+        headers.remove(reqwest::header::CONTENT_LENGTH);
+        let mut rebuilt = ::http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.headers_mut() = headers;
+        if let Some(timing) = timing {
+            rebuilt.extensions_mut().insert(timing);
+        }
+        Ok(reqwest::Response::from(rebuilt))
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into an [`Edit`], with the
+    /// status and the start of the body in the error if it doesn't match.
+    pub async fn send_parsed(&self) -> Result<Edit, OairsError> {
+        deserialize_response(self.send().await?).await
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into an [`Edit`] and returns it
+    /// with the [`ResponseMeta`] read from the response's headers, e.g. the request id.
+    pub async fn send_with_meta(&self) -> Result<(Edit, ResponseMeta), OairsError> {
+        with_meta(self.send().await?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_request_json() {
+        let mut builder = EditBuilder::create(
+            "key",
+            EditModel::TextDavinciEdit001,
+            "Fix the spelling mistakes",
+        );
+        builder.input("What day of the wek is it?");
+
+        assert_eq!(
+            builder.to_request_json(),
+            serde_json::json!({
+                "model": "text-davinci-edit-001",
+                "input": "What day of the wek is it?",
+                "instruction": "Fix the spelling mistakes"
+            })
+        );
+
+        builder.via_chat(ChatModel::GptTurbo).n(2);
+        let json = builder.to_request_json();
+        assert_eq!(json["model"], "gpt-3.5-turbo");
+        assert_eq!(json["n"], 2);
+        assert_eq!(json["messages"][0]["role"], "system");
+        assert_eq!(
+            json["messages"][1]["content"],
+            "Instruction: Fix the spelling mistakes\n\nText:\nWhat day of the wek is it?"
+        );
+        assert!(builder
+            .dry_run()
+            .unwrap()
+            .url
+            .ends_with("/chat/completions"));
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::test_util::MockApi;
+
+    #[tokio::test]
+    async fn edit_via_chat() {
+        let api = MockApi::start().await;
+        let client = api.client();
+
+        let edit = client
+            .create_edit(
+                crate::EditModel::TextDavinciEdit001,
+                "Fix the spelling mistakes",
+            )
+            .input("What day of the wek is it?")
+            .via_chat(crate::ChatModel::default())
+            .send_parsed()
+            .await
+            .unwrap();
+        assert_eq!(edit.first(), Some("This is a test response."));
+        assert_eq!(edit.usage().total_tokens, 20);
+
+        let sent: serde_json::Value =
+            serde_json::from_slice(&api.requests("/chat/completions").await[0].body).unwrap();
+        assert!(sent["messages"][1]["content"]
+            .as_str()
+            .unwrap()
+            .ends_with("What day of the wek is it?"));
+        assert!(api.requests("/edits").await.is_empty());
     }
 }
@@ -0,0 +1,171 @@
+//! A simple line or word diff between two texts, e.g. the input of an edit and the text returned
+//! by the model. See [`Edit::diff_against`](super::response::Edit::diff_against).
+
+/// A single operation of a [`TextDiff`]. Applying the operations in order to the old text, keeping
+/// `Equal` and `Insert` and dropping `Delete`, gives the new text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp<'a> {
+    Equal(&'a str),
+    Insert(&'a str),
+    Delete(&'a str),
+}
+
+/// Whether a [`TextDiff`] compares lines or words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Line,
+    Word,
+}
+
+/// The difference between two texts, as the shortest sequence of [`DiffOp`]s (computed from the
+/// longest common subsequence of their lines or words).
+///
+/// The `Display` implementation prints a line diff with a `-`, `+`, or ` ` prefix on each line,
+/// and a word diff inline, marking deleted words as `[-word-]` and inserted words as `{+word+}`.
+///
+/// # Example
+/// ```rust
+/// use oairs::edits::TextDiff;
+///
+/// let diff = TextDiff::words("What day of the wek is it?", "What day of the week is it?");
+/// assert_eq!(diff.to_string(), "What day of the [-wek-]{+week+} is it?");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextDiff<'a> {
+    granularity: Granularity,
+    ops: Vec<DiffOp<'a>>,
+}
+
+impl<'a> TextDiff<'a> {
+    /// Compare the texts line by line.
+    pub fn lines(old: &'a str, new: &'a str) -> TextDiff<'a> {
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+        TextDiff {
+            granularity: Granularity::Line,
+            ops: diff(&old, &new),
+        }
+    }
+
+    /// Compare the texts word by word. Runs of whitespace are compared as words of their own,
+    /// so changes to spacing are kept.
+    pub fn words(old: &'a str, new: &'a str) -> TextDiff<'a> {
+        TextDiff {
+            granularity: Granularity::Word,
+            ops: diff(&split_words(old), &split_words(new)),
+        }
+    }
+
+    pub fn granularity(&self) -> Granularity {
+        self.granularity
+    }
+
+    pub fn ops(&self) -> &[DiffOp<'a>] {
+        &self.ops
+    }
+
+    /// Whether the texts differ.
+    pub fn has_changes(&self) -> bool {
+        self.ops.iter().any(|op| !matches!(op, DiffOp::Equal(_)))
+    }
+
+    /// The inserted lines or words.
+    pub fn insertions(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.ops.iter().filter_map(|op| match op {
+            DiffOp::Insert(s) => Some(*s),
+            _ => None,
+        })
+    }
+
+    /// The deleted lines or words.
+    pub fn deletions(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.ops.iter().filter_map(|op| match op {
+            DiffOp::Delete(s) => Some(*s),
+            _ => None,
+        })
+    }
+}
+
+impl std::fmt::Display for TextDiff<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for op in &self.ops {
+            match (self.granularity, op) {
+                (Granularity::Line, DiffOp::Equal(s)) => writeln!(f, "  {s}")?,
+                (Granularity::Line, DiffOp::Insert(s)) => writeln!(f, "+ {s}")?,
+                (Granularity::Line, DiffOp::Delete(s)) => writeln!(f, "- {s}")?,
+                (Granularity::Word, DiffOp::Equal(s)) => write!(f, "{s}")?,
+                (Granularity::Word, DiffOp::Insert(s)) => write!(f, "{{+{s}+}}")?,
+                (Granularity::Word, DiffOp::Delete(s)) => write!(f, "[-{s}-]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Splits into alternating runs of whitespace and non-whitespace.
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (i, c) in text.char_indices() {
+        let space = c.is_whitespace();
+        if in_space.is_some_and(|s| s != space) {
+            words.push(&text[start..i]);
+            start = i;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+fn diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffOp<'a>> {
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(DiffOp::Equal(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(DiffOp::Delete(old[i]));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|s| DiffOp::Delete(s)));
+    ops.extend(new[j..].iter().map(|s| DiffOp::Insert(s)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_diff() {
+        let diff = TextDiff::words("What day of the wek is it?", "What day of the week is it?");
+        assert_eq!(diff.to_string(), "What day of the [-wek-]{+week+} is it?");
+        assert_eq!(diff.insertions().collect::<Vec<_>>(), vec!["week"]);
+
+        let diff = TextDiff::lines("one\ntwo\nthree\n", "one\n2\nthree\nfour\n");
+        assert_eq!(diff.to_string(), "  one\n- two\n+ 2\n  three\n+ four\n");
+
+        assert!(!TextDiff::lines("same", "same").has_changes());
+    }
+}
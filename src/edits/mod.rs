@@ -1,6 +1,8 @@
 mod builder;
+mod diff;
 pub mod response;
 
 pub use self::builder::*;
+pub use self::diff::*;
 
 use super::*;
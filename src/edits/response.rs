@@ -1,11 +1,83 @@
 use super::*;
 
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+use super::diff::TextDiff;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::completions::response::ChatCompletion;
+
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
 pub struct Edit {
-    object: String,
-    created: u64,
-    choices: Vec<EditChoice>,
-    usage: Usage,
+    pub object: String,
+    pub created: u64,
+    pub choices: Vec<EditChoice>,
+    pub usage: Usage,
+}
+
+impl Edit {
+    /// Maps a chat completion into an `Edit`, for [`EditBuilder::via_chat`](super::EditBuilder::via_chat).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) fn from_chat(completion: ChatCompletion) -> Edit {
+        Edit {
+            object: "edit".to_string(),
+            created: completion.created,
+            choices: completion
+                .choices
+                .into_iter()
+                .map(|choice| EditChoice {
+                    index: choice.index,
+                    text: choice.message.content().to_string(),
+                    finish_reason: choice.finish_reason,
+                })
+                .collect(),
+            usage: completion.usage,
+        }
+    }
+
+    /// The edited text of each choice, in order.
+    pub fn texts(&self) -> Vec<&str> {
+        self.choices
+            .iter()
+            .map(|choice| choice.text.as_str())
+            .collect()
+    }
+
+    /// The edited text of the first choice.
+    pub fn first(&self) -> Option<&str> {
+        self.choices.first().map(|choice| choice.text.as_str())
+    }
+
+    pub fn choices(&self) -> &[EditChoice] {
+        &self.choices
+    }
+
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// A line diff from `input` (the text that was sent to be edited) to the first choice,
+    /// showing what the model changed. `None` if there are no choices. For a word diff use
+    /// [`TextDiff::words`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let input = "What day of the wek is it?";
+    /// let edit = client
+    ///     .create_edit(EditModel::TextDavinciEdit001, "Fix the spelling mistakes")
+    ///     .input(input)
+    ///     .send()
+    ///     .await?
+    ///     .json::<Edit>()
+    ///     .await?;
+    /// if let Some(diff) = edit.diff_against(input) {
+    ///     print!("{diff}");
+    /// }
+    /// ```
+    pub fn diff_against<'a>(&'a self, input: &'a str) -> Option<TextDiff<'a>> {
+        self.first().map(|text| TextDiff::lines(input, text))
+    }
 }
 
 // TODO: Use serde to collapse some of these choice structs in various modules.
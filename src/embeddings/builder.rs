@@ -1,26 +1,61 @@
 //! Contains the [`EmbeddingBuilder`] struct.
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::cache::{send_cached, ResponseCache};
+#[cfg(target_arch = "wasm32")]
 use crate::client::handle_request;
+#[cfg(feature = "tokenizers")]
+use crate::{
+    budget::{spend, TokenBudget},
+    text::redact::Redactor,
+    tokenizers::tokenize_batch,
+};
+use crate::{
+    client::{bearer, HttpMethod, PreparedBody, PreparedRequest},
+    meta::{deserialize_response, with_meta, ResponseMeta},
+    utils::raw::tee_raw,
+};
 
-use super::*;
+#[cfg(not(target_arch = "wasm32"))]
+use super::response::EmbeddingObject;
+use super::{response::Embedding, *};
 
 // TODO: Clean up type-state pattern!
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingBuilder<State = Sendable> {
     #[serde(skip)]
     key: String,
     #[serde(skip)]
-    url: &'static str,
+    url: Cow<'static, str>,
     model: EmbeddingModel,
     input: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<String>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    validate_lengths: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    cache: Option<ResponseCache>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    embedding_cache: Option<EmbeddingCache>,
+    #[serde(skip)]
+    raw_dir: Option<std::path::PathBuf>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    budget: Option<TokenBudget>,
+    #[cfg(feature = "tokenizers")]
+    #[serde(skip)]
+    redactor: Option<Redactor>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
     #[serde(skip)]
     state: std::marker::PhantomData<State>,
 }
 
-impl<'a, Sendable> EmbeddingBuilder<Sendable> {
+impl<'a> EmbeddingBuilder<Sendable> {
     pub fn new<K, T>(key: K, model: EmbeddingModel, inputs: &'a [T]) -> EmbeddingBuilder<Sendable>
     where
         K: Into<String>,
@@ -28,28 +63,346 @@ impl<'a, Sendable> EmbeddingBuilder<Sendable> {
     {
         Self {
             key: key.into(),
-            url: URL.get(&Uri::Embeddings).unwrap(),
+            url: Endpoints::default().embeddings(),
             model,
             input: inputs.iter().map(|i| i.to_string()).collect(),
             user: None,
+            #[cfg(feature = "tokenizers")]
+            validate_lengths: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            embedding_cache: None,
+            raw_dir: None,
+            #[cfg(feature = "tokenizers")]
+            budget: None,
+            #[cfg(feature = "tokenizers")]
+            redactor: None,
+            extra: serde_json::Map::new(),
             state: std::marker::PhantomData,
         }
     }
 
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_cache(&mut self, cache: Option<ResponseCache>) -> &mut Self {
+        self.cache = cache;
+        self
+    }
+
+    #[cfg(feature = "tokenizers")]
+    pub(crate) fn set_budget(&mut self, budget: Option<TokenBudget>) -> &mut Self {
+        self.budget = budget;
+        self
+    }
+
     pub fn user<U: Into<String> + std::fmt::Debug>(&mut self, user: U) -> &mut Self {
         self.user = Some(user.into());
         self
     }
 
-    pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let json = serde_json::to_value(self).unwrap();
-        handle_request(
-            &self.key,
-            self.url,
-            client::HttpMethod::Post,
-            Some(json),
+    /// Consult `cache` before sending: inputs it has an embedding of (by this builder's model)
+    /// are left out of the request, and the embeddings of the rest are added to it. The
+    /// response has an embedding for every input, in the order of the inputs, and the `usage`
+    /// of the inputs that were sent. No request is made if every input is cached. (The
+    /// [`blocking`](crate::blocking) client doesn't consult the cache.) Not available for
+    /// `wasm32`, where reqwest can't rebuild the response.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn embedding_cache(&mut self, cache: &EmbeddingCache) -> &mut Self {
+        self.embedding_cache = Some(cache.clone());
+        self
+    }
+
+    /// Save the body of each response to a file in `dir`, as with
+    /// [`ChatBuilder::save_raw_to`](crate::completions::ChatBuilder::save_raw_to). A response
+    /// served from the [`ResponseCache`] is saved again. Not available for `wasm32`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_raw_to<P: Into<std::path::PathBuf>>(&mut self, dir: P) -> &mut Self {
+        self.raw_dir = Some(dir.into());
+        self
+    }
+
+    /// If `true`, [`send`](Self::send) first tokenizes the inputs with the model's tokenizer
+    /// and returns a `ParamError` listing the inputs that exceed
+    /// [`EmbeddingModel::max_input_tokens`], instead of sending a request that the API would
+    /// reject. Defaults to `false`.
+    #[cfg(feature = "tokenizers")]
+    pub fn validate_lengths(&mut self, truth_value: bool) -> &mut Self {
+        self.validate_lengths = truth_value;
+        self
+    }
+
+    /// Returns a `ParamError` naming the index and token count of each input that exceeds the
+    /// model's token limit.
+    #[cfg(feature = "tokenizers")]
+    pub fn check_lengths(&self) -> Result<(), OairsError> {
+        let limit = self.model.max_input_tokens();
+        let inputs: Vec<&str> = self.input.iter().map(|i| i.as_str()).collect();
+        let too_long: Vec<String> = tokenize_batch(&inputs, self.model.tokenizer())
+            .iter()
+            .enumerate()
+            .filter(|(_, tokens)| tokens.len() > limit)
+            .map(|(index, tokens)| format!("{} ({} tokens)", index, tokens.len()))
+            .collect();
+
+        if too_long.is_empty() {
+            return Ok(());
+        }
+        Err(OairsError::new(
+            format!(
+                "Inputs exceed the {} token limit of {}: {}",
+                limit,
+                self.model,
+                too_long.join(", ")
+            ),
+            ErrorType::ParamError,
+            Some("input".to_string()),
             None,
-        )
-        .await
+        ))
+    }
+
+    /// The tokens the request is estimated to use, as spent from a [`TokenBudget`]: the tokens
+    /// of the inputs.
+    #[cfg(feature = "tokenizers")]
+    pub fn estimated_tokens(&self) -> Result<usize, OairsError> {
+        let inputs: Vec<&str> = self.input.iter().map(|i| i.as_str()).collect();
+        let tokens = tokenize_batch(&inputs, self.model.tokenizer());
+        Ok(tokens.iter().map(|t| t.len()).sum())
+    }
+
+    /// Adds `key` to the request body with `value`, as with
+    /// [`ChatBuilder::extra_param`](crate::completions::ChatBuilder::extra_param).
+    pub fn extra_param<K: Into<String>>(&mut self, key: K, value: serde_json::Value) -> &mut Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Redact the inputs with `redactor` before sending, e.g. to keep emails or phone numbers
+    /// out of the request. The placeholders are recorded in `redactor`'s map (clones share it),
+    /// so the response can be re-hydrated with [`Redactor::rehydrate`]. The request is redacted
+    /// as [`to_request_json`](Self::to_request_json) builds it, so [`dry_run`](Self::dry_run)
+    /// shows what would be sent.
+    #[cfg(feature = "tokenizers")]
+    pub fn redact_with(&mut self, redactor: &Redactor) -> &mut Self {
+        self.redactor = Some(redactor.clone());
+        self
+    }
+
+    /// The JSON body that [`send`](Self::send) sends. It's also what the
+    /// [`ResponseCache`] hashes to recognize a duplicate request.
+    pub fn to_request_json(&self) -> serde_json::Value {
+        #[allow(unused_mut)]
+        let mut json = serde_json::to_value(self).unwrap();
+        #[cfg(feature = "tokenizers")]
+        if let Some(redactor) = &self.redactor {
+            redactor.redact_json(&mut json["input"]);
+        }
+        json
+    }
+
+    /// Checks the inputs' lengths (see [`check_lengths`](Self::check_lengths)), whether or not
+    /// [`validate_lengths`](Self::validate_lengths) is set, and returns the request that
+    /// [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        #[cfg(feature = "tokenizers")]
+        self.check_lengths()?;
+        Ok(PreparedRequest::new(
+            &self.key,
+            &self.url,
+            HttpMethod::Post,
+            PreparedBody::Json(self.to_request_json()),
+        ))
+    }
+
+    /// Executes the `POST` request. With an [`embedding_cache`](Self::embedding_cache), only
+    /// the inputs that aren't cached are sent, and the returned `reqwest::Response` is rebuilt
+    /// with the embeddings of all of the inputs.
+    pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = &self.embedding_cache {
+            return self.send_with_embedding_cache(cache).await;
+        }
+        self.send_uncached().await
+    }
+
+    async fn send_uncached(&self) -> Result<reqwest::Response, OairsError> {
+        #[cfg(feature = "tokenizers")]
+        if self.validate_lengths {
+            self.check_lengths()?;
+        }
+        #[cfg(feature = "tokenizers")]
+        spend(self.budget.as_ref(), || self.estimated_tokens()).await?;
+        let json = self.to_request_json();
+        #[cfg(not(target_arch = "wasm32"))]
+        let res = send_cached(self.cache.as_ref(), &self.key, &self.url, json).await?;
+        #[cfg(target_arch = "wasm32")]
+        let res = handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None).await?;
+        tee_raw(self.raw_dir.as_deref(), "embedding", res).await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn send_with_embedding_cache(
+        &self,
+        cache: &EmbeddingCache,
+    ) -> Result<reqwest::Response, OairsError> {
+        let mut embeddings: Vec<Option<Vec<f64>>> = self
+            .input
+            .iter()
+            .map(|input| cache.get(&self.model, input))
+            .collect();
+        // The uncached inputs, without duplicates, in the order they first appear.
+        let mut seen = std::collections::HashSet::new();
+        let missing: Vec<String> = self
+            .input
+            .iter()
+            .zip(&embeddings)
+            .filter(|(input, embedding)| embedding.is_none() && seen.insert(*input))
+            .map(|(input, _)| input.clone())
+            .collect();
+
+        let mut status = reqwest::StatusCode::OK;
+        let mut headers = reqwest::header::HeaderMap::new();
+        let mut model = self.model.to_string();
+        let mut usage = Usage {
+            prompt_tokens: 0,
+            completion_tokens: None,
+            total_tokens: 0,
+        };
+        if !missing.is_empty() {
+            let mut uncached = self.clone();
+            uncached.input = missing.clone();
+            uncached.embedding_cache = None;
+            let res = uncached.send_uncached().await?;
+            status = res.status();
+            headers = res.headers().clone();
+            let mut fresh: Embedding = deserialize_response(res).await?;
+            fresh.data.sort_by_key(|e| e.index);
+            let fresh_by_input: HashMap<&str, Vec<f64>> = missing
+                .iter()
+                .map(|input| input.as_str())
+                .zip(fresh.data.into_iter().map(|e| e.embedding))
+                .collect();
+            for (input, embedding) in &fresh_by_input {
+                cache.insert(&self.model, input, embedding.clone());
+            }
+            for (input, embedding) in self.input.iter().zip(embeddings.iter_mut()) {
+                if embedding.is_none() {
+                    *embedding = fresh_by_input.get(input.as_str()).cloned();
+                }
+            }
+            model = fresh.model;
+            usage = fresh.usage;
+        }
+
+        let data = embeddings
+            .into_iter()
+            .enumerate()
+            .map(|(index, embedding)| match embedding {
+                Some(embedding) => Ok(EmbeddingObject {
+                    input: None,
+                    embedding,
+                    index: index as u32,
+                    object: "embedding".to_string(),
+                }),
+                None => Err(OairsError::new(
+                    format!("The response has no embedding for input {index}"),
+                    ErrorType::DeserializationError,
+                    Some("input".to_string()),
+                    None,
+                )),
+            })
+            .collect::<Result<Vec<_>, OairsError>>()?;
+        let merged = Embedding {
+            data,
+            model,
+            object: "list".to_string(),
+            usage,
+        };
+
+        let body = match serde_json::to_vec(&merged) {
+            Ok(body) => body,
+            Err(e) => {
+                return Err(OairsError::new(
+                    e.to_string(),
+                    ErrorType::SerializationError,
+                    None,
+                    None,
+                ))
+            }
+        };
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        );
+        headers.remove(reqwest::header::CONTENT_LENGTH);
+        let mut rebuilt = ::http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.headers_mut() = headers;
+        Ok(reqwest::Response::from(rebuilt))
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into an [`Embedding`], with
+    /// the status and the start of the body in the error if it doesn't match.
+    pub async fn send_parsed(&self) -> Result<Embedding, OairsError> {
+        deserialize_response(self.send().await?).await
+    }
+
+    /// Like [`send`](Self::send), but deserializes the response into an [`Embedding`] and returns it
+    /// with the [`ResponseMeta`] read from the response's headers, e.g. the request id. A response served
+    /// from the [`ResponseCache`] has the headers of the response that was cached.
+    pub async fn send_with_meta(&self) -> Result<(Embedding, ResponseMeta), OairsError> {
+        with_meta(self.send().await?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "tokenizers")]
+    #[test]
+    fn embedding_check_lengths() {
+        let long = "word ".repeat(3000);
+        let inputs = vec!["short", long.as_str(), "short"];
+
+        let builder =
+            EmbeddingBuilder::<Sendable>::new("key", EmbeddingModel::TextEmbeddingAda002, &inputs);
+        assert!(builder.check_lengths().is_ok());
+
+        let builder =
+            EmbeddingBuilder::<Sendable>::new("key", EmbeddingModel::TextAdaEmbedding001, &inputs);
+        let error = builder.check_lengths().unwrap_err();
+        assert_eq!(error.error_type, ErrorType::ParamError.to_string());
+        assert!(error.message.contains(": 1 ("));
+        assert!(!error.message.contains(", "));
+    }
+
+    #[test]
+    fn embedding_request_json() {
+        let mut builder =
+            EmbeddingBuilder::<Sendable>::new("key", EmbeddingModel::default(), &["a", "b"]);
+        builder.user("user-1");
+        #[cfg(feature = "tokenizers")]
+        builder.validate_lengths(true);
+
+        assert_eq!(
+            builder.to_request_json(),
+            serde_json::json!({
+                "model": "text-embedding-ada-002",
+                "input": ["a", "b"],
+                "user": "user-1"
+            })
+        );
     }
 }
@@ -0,0 +1,271 @@
+//! Contains the [`EmbeddingCache`], which lets an [`EmbeddingBuilder`] skip the inputs it has
+//! already embedded. See [`EmbeddingBuilder::embedding_cache`].
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::{collections::BTreeMap, sync::Arc};
+
+use parking_lot::Mutex;
+
+use super::*;
+
+/// A bounded, in-memory cache of embeddings, keyed by model and input. When it's full, the least
+/// recently used embedding is evicted.
+///
+/// Unlike the [`ResponseCache`](crate::cache::ResponseCache), which only recognizes a request
+/// identical to one already sent, the `EmbeddingCache` works per input: a request for
+/// `["a", "b"]` after one for `["a"]` only sends `"b"`.
+///
+/// Cloning an `EmbeddingCache` is cheap, and the clones share their entries. It can be saved
+/// with [`save`](EmbeddingCache::save) and loaded with [`load`](EmbeddingCache::load) to keep
+/// it across runs.
+///
+/// # Example
+/// ```rust,no_run
+/// let cache = EmbeddingCache::load("cache/embeddings.json", 10_000)
+///     .unwrap_or_else(|_| EmbeddingCache::new(10_000));
+/// let embedding = client
+///     .create_embeddings(EmbeddingModel::default(), &inputs)
+///     .embedding_cache(&cache)
+///     .send_parsed()
+///     .await?;
+/// cache.save("cache/embeddings.json")?;
+/// ```
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    capacity: usize,
+    entries: Arc<Mutex<LruEntries>>,
+}
+
+impl std::fmt::Debug for EmbeddingCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbeddingCache")
+            .field("capacity", &self.capacity)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+type Key = (String, String);
+
+#[derive(Default)]
+struct LruEntries {
+    // Each entry's embedding and the tick it was last used at.
+    map: HashMap<Key, (u64, Vec<f64>)>,
+    // The keys by the tick they were last used at, oldest first.
+    order: BTreeMap<u64, Key>,
+    tick: u64,
+}
+
+impl LruEntries {
+    fn touch(&mut self, key: &Key) -> Option<Vec<f64>> {
+        self.tick += 1;
+        let (used, embedding) = self.map.get_mut(key)?;
+        let key = self.order.remove(used)?;
+        *used = self.tick;
+        self.order.insert(self.tick, key);
+        Some(embedding.clone())
+    }
+
+    fn insert(&mut self, key: Key, embedding: Vec<f64>, capacity: usize) {
+        self.tick += 1;
+        if let Some((used, _)) = self.map.insert(key.clone(), (self.tick, embedding)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, key);
+        while self.map.len() > capacity {
+            match self.order.pop_first() {
+                Some((_, oldest)) => self.map.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+}
+
+/// An entry of a saved [`EmbeddingCache`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEmbedding {
+    model: String,
+    input: String,
+    embedding: Vec<f64>,
+}
+
+/// A saved [`EmbeddingCache`], with the entries from least to most recently used.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
+struct SavedCache {
+    entries: Vec<CachedEmbedding>,
+}
+
+impl EmbeddingCache {
+    /// A cache that holds up to `capacity` embeddings.
+    pub fn new(capacity: usize) -> EmbeddingCache {
+        EmbeddingCache {
+            capacity,
+            entries: Arc::new(Mutex::new(LruEntries::default())),
+        }
+    }
+
+    /// Loads a cache saved with [`save`](EmbeddingCache::save), keeping the `capacity` most
+    /// recently used embeddings.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load<P: AsRef<Path>>(path: P, capacity: usize) -> Result<EmbeddingCache, OairsError> {
+        let saved = SavedCache::load_json(path)?;
+        let cache = EmbeddingCache::new(capacity);
+        {
+            let mut entries = cache.entries.lock();
+            for entry in saved.entries {
+                entries.insert((entry.model, entry.input), entry.embedding, capacity);
+            }
+        }
+        Ok(cache)
+    }
+
+    /// Saves the cache as JSON to `path` (with ".json" appended if needed), in the order the
+    /// entries were used, so that [`load`](EmbeddingCache::load) keeps the most recent ones.
+    /// Returns the path written to.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, OairsError> {
+        let path = path.as_ref();
+        let saved = {
+            let entries = self.entries.lock();
+            let entries = entries
+                .order
+                .values()
+                .filter_map(|key| {
+                    let (_, embedding) = entries.map.get(key)?;
+                    Some(CachedEmbedding {
+                        model: key.0.clone(),
+                        input: key.1.clone(),
+                        embedding: embedding.clone(),
+                    })
+                })
+                .collect();
+            SavedCache { entries }
+        };
+
+        saved.save_json(path).map_err(|e| {
+            OairsError::new(
+                format!("Unable to save the embedding cache: {e}"),
+                ErrorType::FileError,
+                Some(path.display().to_string()),
+                None,
+            )
+        })
+    }
+
+    /// The cached embedding of `input` by `model`, marking it as recently used.
+    pub fn get(&self, model: &EmbeddingModel, input: &str) -> Option<Vec<f64>> {
+        self.entries
+            .lock()
+            .touch(&(model.to_string(), input.to_string()))
+    }
+
+    /// Caches the embedding of `input` by `model`, evicting the least recently used embedding
+    /// if the cache is full.
+    pub fn insert(&self, model: &EmbeddingModel, input: &str, embedding: Vec<f64>) {
+        self.entries.lock().insert(
+            (model.to_string(), input.to_string()),
+            embedding,
+            self.capacity,
+        );
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every cached embedding.
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock();
+        entries.map.clear();
+        entries.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedding_cache_lru() {
+        let model = EmbeddingModel::default();
+        let cache = EmbeddingCache::new(2);
+        cache.insert(&model, "a", vec![0.1]);
+        cache.insert(&model, "b", vec![0.2]);
+        assert_eq!(cache.get(&model, "a"), Some(vec![0.1]));
+        cache.insert(&model, "c", vec![0.3]);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&model, "b"), None);
+        assert_eq!(cache.get(&EmbeddingModel::TextAdaEmbedding001, "a"), None);
+
+        let path =
+            std::env::temp_dir().join(format!("oairs-embedding-cache-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        cache.save(path).unwrap();
+        let loaded = EmbeddingCache::load(&format!("{path}.json"), 1).unwrap();
+        std::fs::remove_file(format!("{path}.json")).unwrap();
+
+        // "c" was used after "a", so it's the one kept.
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.get(&model, "c"), Some(vec![0.3]));
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        embeddings::response::Embedding,
+        test_util::{fixtures::EMBEDDING, MockApi},
+        LoadJson,
+    };
+
+    #[tokio::test]
+    async fn embedding_cache() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        let model = crate::EmbeddingModel::default();
+        let cache = crate::embeddings::EmbeddingCache::new(10);
+        cache.insert(&model, "a", vec![0.5]);
+
+        let embedding = client
+            .create_embeddings(model.clone(), &["a", "b", "a"])
+            .embedding_cache(&cache)
+            .send_parsed()
+            .await
+            .unwrap();
+        let fresh = Embedding::from_json_str(EMBEDDING).unwrap().data[0]
+            .embedding
+            .clone();
+        assert_eq!(
+            embedding.extract_embeddings(),
+            vec![vec![0.5], fresh.clone(), vec![0.5]]
+        );
+        assert_eq!(embedding.usage.total_tokens, 8);
+        assert_eq!(cache.get(&model, "b"), Some(fresh));
+
+        let cached = client
+            .create_embeddings(model, &["b", "a"])
+            .embedding_cache(&cache)
+            .send_parsed()
+            .await
+            .unwrap();
+        assert_eq!(cached.data.len(), 2);
+        assert_eq!(cached.usage.total_tokens, 0);
+
+        let requests = api.requests("/embeddings").await;
+        assert_eq!(requests.len(), 1);
+        let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(sent["input"], serde_json::json!(["b"]));
+    }
+}
@@ -1,6 +1,12 @@
 mod builder;
+mod cache;
 pub mod response;
+#[cfg(feature = "dataframes")]
+mod store;
 
 pub use self::builder::*;
+pub use self::cache::*;
+#[cfg(feature = "dataframes")]
+pub use self::store::*;
 
 use super::*;
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 
+#[cfg(feature = "dataframes")]
 use polars::{
     prelude::{DataFrame, NamedFrom},
     series::Series,
@@ -9,7 +10,7 @@ use serde::ser::SerializeMap;
 use super::*;
 
 /// Struct for deserializing a successful call to the embeddings endpoint.
-#[derive(Debug, Serialize, Clone, Deserialize, SaveJson)]
+#[derive(Debug, Serialize, Clone, Deserialize, SaveJson, LoadJson)]
 pub struct Embedding {
     pub data: Vec<EmbeddingObject>,
     pub model: String,
@@ -46,12 +47,14 @@ impl Embedding {
     /// saves the `Embedding` to a JSON file at the specified `path`. Note that
     /// the order of the inputs must match the order from which the embeddings
     /// were created, otherwise the output will be incorrect!
-    pub fn save_with_inputs<'a, T>(
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_with_inputs<'a, P, T>(
         &mut self,
-        path: &str,
+        path: P,
         inputs: Vec<T>,
-    ) -> Result<(), std::io::Error>
+    ) -> Result<std::path::PathBuf, std::io::Error>
     where
+        P: AsRef<std::path::Path>,
         T: Into<Cow<'a, str>>,
     {
         self.append_input(inputs);
@@ -63,6 +66,7 @@ impl Embedding {
     /// each `EmbeddingObject` (in the `data` field of `Embedding`). Consumes
     /// self. For a non-consuming method, you can use `extract_embeddings` and
     /// then  convert the `Vec<Vec<f64>>` to a `DataFrame`.
+    #[cfg(feature = "dataframes")]
     pub fn embeddings_to_df(self) -> Result<DataFrame, OairsError> {
         let series = self
             .data
@@ -82,6 +86,7 @@ impl Embedding {
 
     /// Converts EmbeddingResponse into a Polars DataFrame and saves it as a
     /// parquet file.
+    #[cfg(feature = "dataframes")]
     pub fn save_parquet(self, path: &str) -> Result<u64, OairsError> {
         let mut df = self.embeddings_to_df()?;
         write_parquet(&mut df, path)
@@ -91,6 +96,7 @@ impl Embedding {
     /// converts the `Embedding` into a Polars DataFrame and saves it as a
     /// parquet file. The inputs will be the column names for the respective
     /// embedding.
+    #[cfg(feature = "dataframes")]
     pub fn save_parquet_with_input(
         mut self,
         path: &str,
@@ -134,6 +140,7 @@ impl Serialize for EmbeddingObject {
 }
 
 impl EmbeddingObject {
+    #[cfg(feature = "dataframes")]
     pub fn to_series(self, name: Option<String>) -> Series {
         let name = name.unwrap_or_else(|| format!("{}", self.index));
         Series::new(name.as_ref(), self.embedding)
@@ -0,0 +1,307 @@
+//! Contains the [`EmbeddingStore`], for saving an [`Embedding`] to a directory along with what
+//! a parquet file alone would lose (the model and usage), and loading it back.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use polars::{
+    prelude::{DataFrame, NamedFrom, ParquetReader, SerReader},
+    series::Series,
+};
+
+use super::{
+    response::{Embedding, EmbeddingObject},
+    *,
+};
+use crate::utils::write_parquet;
+
+const EMBEDDINGS_FILE: &str = "embeddings.parquet";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Describes the embeddings saved by an [`EmbeddingStore`]. Saved as `manifest.json` next to
+/// the parquet file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SaveJson, LoadJson)]
+pub struct EmbeddingManifest {
+    /// The model as returned by the API, e.g. `text-embedding-ada-002-v2`.
+    pub model: String,
+    /// When the embeddings were saved, in seconds since the Unix epoch.
+    pub created: u64,
+    /// The length of each embedding.
+    pub dimensions: usize,
+    /// The number of embeddings.
+    pub count: usize,
+    /// A hash of the inputs, in order, if every embedding had its input appended (see
+    /// [`Embedding::append_input`]).
+    pub input_hash: Option<String>,
+    pub usage: Usage,
+}
+
+impl EmbeddingManifest {
+    /// Whether the saved embeddings were created from `inputs`, in the same order. `false` if
+    /// the inputs weren't saved.
+    pub fn matches_inputs<S: AsRef<str>>(&self, inputs: &[S]) -> bool {
+        self.input_hash.as_deref() == Some(hash_inputs(inputs.iter().map(|i| i.as_ref())).as_str())
+    }
+
+    /// Whether the embeddings were created by `model`. The API returns a versioned id (e.g.
+    /// `text-embedding-ada-002-v2` for [`EmbeddingModel::TextEmbeddingAda002`]), so a suffix
+    /// after the model's id is allowed.
+    pub fn is_model(&self, model: &EmbeddingModel) -> bool {
+        let id = model.to_string();
+        self.model == id || self.model.starts_with(&format!("{id}-"))
+    }
+}
+
+/// A directory holding an [`Embedding`] as `embeddings.parquet`, with a row (`index`, `input`,
+/// `embedding`) for each embedding, and an [`EmbeddingManifest`] as `manifest.json`.
+///
+/// # Example
+/// ```rust,no_run
+/// let store = EmbeddingStore::new("embeddings/faq");
+/// let mut embedding = client.create_embeddings(model, inputs.clone()).send_parsed().await?;
+/// embedding.append_input(inputs);
+/// store.save(&embedding)?;
+///
+/// // Later
+/// let embedding = store.load(&EmbeddingModel::TextEmbeddingAda002)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct EmbeddingStore {
+    dir: PathBuf,
+}
+
+impl EmbeddingStore {
+    pub fn new<P: AsRef<Path>>(dir: P) -> EmbeddingStore {
+        EmbeddingStore {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes `embedding` and its manifest to the directory, creating it if needed and
+    /// replacing anything saved there before. Returns the manifest.
+    pub fn save(&self, embedding: &Embedding) -> Result<EmbeddingManifest, OairsError> {
+        let dimensions = embedding.data.first().map_or(0, |e| e.embedding.len());
+        if let Some(e) = embedding
+            .data
+            .iter()
+            .find(|e| e.embedding.len() != dimensions)
+        {
+            return Err(OairsError::new(
+                format!(
+                    "Embedding {} has {} dimensions, but the first has {dimensions}",
+                    e.index,
+                    e.embedding.len()
+                ),
+                ErrorType::ParamError,
+                Some("embedding".to_string()),
+                None,
+            ));
+        }
+
+        let inputs: Option<Vec<&str>> = embedding.data.iter().map(|e| e.input.as_deref()).collect();
+        let manifest = EmbeddingManifest {
+            model: embedding.model.clone(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            dimensions,
+            count: embedding.data.len(),
+            input_hash: inputs.map(|inputs| hash_inputs(inputs.into_iter())),
+            usage: embedding.usage,
+        };
+
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| self.file_error(format!("Unable to create the store: {e}"), None))?;
+        let index: Vec<u32> = embedding.data.iter().map(|e| e.index).collect();
+        let input: Vec<Option<&str>> = embedding.data.iter().map(|e| e.input.as_deref()).collect();
+        let vectors: Vec<Series> = embedding
+            .data
+            .iter()
+            .map(|e| Series::new("", &e.embedding))
+            .collect();
+        let mut df = DataFrame::new(vec![
+            Series::new("index", index),
+            Series::new("input", input),
+            Series::new("embedding", vectors),
+        ])
+        .map_err(polars_error)?;
+        write_parquet(&mut df, &self.path(EMBEDDINGS_FILE))?;
+        manifest
+            .save_json(self.path(MANIFEST_FILE))
+            .map_err(|e| self.file_error(e.to_string(), Some(MANIFEST_FILE)))?;
+
+        Ok(manifest)
+    }
+
+    /// The manifest of the saved embeddings.
+    pub fn manifest(&self) -> Result<EmbeddingManifest, OairsError> {
+        EmbeddingManifest::load_json(self.path(MANIFEST_FILE))
+    }
+
+    /// Loads the saved embeddings as a `DataFrame` with the columns `index`, `input`, and
+    /// `embedding` (a list of floats), after checking that they were created by `model` and
+    /// match the manifest.
+    pub fn load_df(&self, model: &EmbeddingModel) -> Result<DataFrame, OairsError> {
+        self.load_checked(model).map(|(df, _)| df)
+    }
+
+    /// Loads the saved embeddings back into an [`Embedding`], after checking that they were
+    /// created by `model` and match the manifest. Returns a `ParamError` if the model doesn't
+    /// match.
+    pub fn load(&self, model: &EmbeddingModel) -> Result<Embedding, OairsError> {
+        let (df, manifest) = self.load_checked(model)?;
+
+        let index = df.column("index").map_err(polars_error)?;
+        let input = df.column("input").map_err(polars_error)?;
+        let embedding = df.column("embedding").map_err(polars_error)?;
+        let data = index
+            .u32()
+            .map_err(polars_error)?
+            .into_iter()
+            .zip(input.utf8().map_err(polars_error)?)
+            .zip(embedding.list().map_err(polars_error)?)
+            .map(|((index, input), embedding)| {
+                let embedding = match embedding {
+                    Some(s) => s.f64().map_err(polars_error)?.into_no_null_iter().collect(),
+                    None => Vec::new(),
+                };
+                Ok(EmbeddingObject {
+                    input: input.map(|i| i.to_string()),
+                    embedding,
+                    index: index.unwrap_or_default(),
+                    object: "embedding".to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, OairsError>>()?;
+
+        Ok(Embedding {
+            data,
+            model: manifest.model,
+            object: "list".to_string(),
+            usage: manifest.usage,
+        })
+    }
+
+    fn load_checked(
+        &self,
+        model: &EmbeddingModel,
+    ) -> Result<(DataFrame, EmbeddingManifest), OairsError> {
+        let manifest = self.manifest()?;
+        if !manifest.is_model(model) {
+            return Err(OairsError::new(
+                format!(
+                    "The store holds embeddings from {}, not {model}",
+                    manifest.model
+                ),
+                ErrorType::ParamError,
+                Some("model".to_string()),
+                None,
+            ));
+        }
+
+        let path = self.dir.join(EMBEDDINGS_FILE);
+        let file = std::fs::File::open(&path)
+            .map_err(|e| self.file_error(e.to_string(), Some(EMBEDDINGS_FILE)))?;
+        let df = ParquetReader::new(file).finish().map_err(polars_error)?;
+
+        let dimensions = match df.column("embedding").map_err(polars_error)?.list() {
+            Ok(list) => list.into_iter().flatten().map(|s| s.len()).max(),
+            Err(e) => return Err(polars_error(e)),
+        };
+        if df.height() != manifest.count || dimensions.unwrap_or(0) != manifest.dimensions {
+            return Err(self.file_error(
+                format!(
+                    "{EMBEDDINGS_FILE} doesn't match the manifest: expected {} embeddings with {} dimensions",
+                    manifest.count, manifest.dimensions
+                ),
+                Some(EMBEDDINGS_FILE),
+            ));
+        }
+
+        Ok((df, manifest))
+    }
+
+    fn path(&self, file: &str) -> String {
+        self.dir.join(file).to_string_lossy().to_string()
+    }
+
+    fn file_error(&self, message: String, file: Option<&str>) -> OairsError {
+        let path = match file {
+            Some(file) => self.dir.join(file),
+            None => self.dir.clone(),
+        };
+        OairsError::new(
+            message,
+            ErrorType::FileError,
+            Some(path.display().to_string()),
+            None,
+        )
+    }
+}
+
+fn polars_error(e: polars::error::PolarsError) -> OairsError {
+    OairsError::new(e.to_string(), ErrorType::PolarsError, None, None)
+}
+
+/// A 64-bit FNV-1a hash of the inputs, as hex. Unlike `DefaultHasher`, it's the same across
+/// Rust versions, so it can be compared with a hash saved by an older build.
+fn hash_inputs<'a>(inputs: impl Iterator<Item = &'a str>) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for input in inputs {
+        // Each input is followed by a 0xff byte, which can't appear in UTF-8, so that
+        // `["ab", "c"]` and `["a", "bc"]` hash differently.
+        for byte in input.bytes().chain(std::iter::once(0xff)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedding_store_round_trip() {
+        let mut embedding = Embedding::from_json_str(
+            r#"{"object": "list", "model": "text-embedding-ada-002-v2",
+                "data": [
+                    {"object": "embedding", "index": 0, "embedding": [0.1, -0.2, 0.3]},
+                    {"object": "embedding", "index": 1, "embedding": [0.4, 0.5, -0.6]}
+                ],
+                "usage": {"prompt_tokens": 4, "total_tokens": 4}}"#,
+        )
+        .unwrap();
+        let inputs = vec!["a, b".to_string(), "c".to_string()];
+        embedding.append_input(inputs.clone());
+
+        let dir = std::env::temp_dir().join(format!("oairs-store-{}", std::process::id()));
+        let store = EmbeddingStore::new(&dir);
+        let manifest = store.save(&embedding).unwrap();
+        let loaded = store.load(&EmbeddingModel::TextEmbeddingAda002);
+        let wrong_model = store.load(&EmbeddingModel::TextAdaEmbedding001);
+        let df = store.load_df(&EmbeddingModel::TextEmbeddingAda002).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(manifest.dimensions, 3);
+        assert_eq!(manifest.count, 2);
+        assert!(manifest.matches_inputs(&inputs));
+        assert!(!manifest.matches_inputs(&["a", ", bc"]));
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.model, embedding.model);
+        assert_eq!(loaded.usage, embedding.usage);
+        assert_eq!(loaded.extract_embeddings(), embedding.extract_embeddings());
+        assert_eq!(loaded.data[0].input.as_deref(), Some("a, b"));
+        assert!(wrong_model.is_err());
+        assert_eq!(df.get_column_names(), vec!["index", "input", "embedding"]);
+    }
+}
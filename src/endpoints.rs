@@ -0,0 +1,233 @@
+//! Contains the [`Endpoints`] struct, which is owned by the [`Client`](crate::client::Client) and
+//! builds the url for each endpoint of the OpenAI API.
+//!
+//! Endpoints without path params are returned as `Cow::Borrowed` static strings, so only the
+//! endpoints that need to format a path param (e.g., a file id) allocate.
+
+use std::borrow::Cow;
+
+// A macro rather than a const so that it can be used with `concat!`.
+macro_rules! default_base {
+    () => {
+        "https://api.openai.com/v1"
+    };
+}
+
+/// The base url of the OpenAI API used when no other base url has been set.
+pub const DEFAULT_BASE_URL: &str = default_base!();
+
+// Returns the static url when using the default base url, and only formats a new url otherwise.
+macro_rules! fixed {
+    ($self:ident, $path:literal) => {
+        match &$self.base {
+            None => Cow::Borrowed(concat!(default_base!(), $path)),
+            Some(base) => Cow::Owned(format!("{}{}", base, $path)),
+        }
+    };
+}
+
+/// Builds the url for each endpoint of the OpenAI API.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    // `None` for the default base url.
+    base: Option<String>,
+}
+
+impl Endpoints {
+    /// Builds the urls on `base_url` instead of the OpenAI API, e.g. for a proxy, an
+    /// API-compatible server, or a mock server in tests. The base url should include the
+    /// version (e.g., `http://127.0.0.1:8080/v1`). A trailing "/" is ignored.
+    pub fn new<B: Into<String>>(base_url: B) -> Endpoints {
+        let mut endpoints = Endpoints::default();
+        endpoints.set_base_url(base_url);
+        endpoints
+    }
+
+    pub fn set_base_url<B: Into<String>>(&mut self, base_url: B) -> &mut Self {
+        let base_url = base_url.into();
+        let base_url = base_url.trim_end_matches('/');
+        self.base = match base_url == DEFAULT_BASE_URL {
+            true => None,
+            false => Some(base_url.to_string()),
+        };
+        self
+    }
+
+    /// The base url that the endpoint paths are appended to.
+    pub fn base_url(&self) -> &str {
+        self.base.as_deref().unwrap_or(DEFAULT_BASE_URL)
+    }
+
+    pub fn chat_completions(&self) -> Cow<'static, str> {
+        fixed!(self, "/chat/completions")
+    }
+
+    pub fn completions(&self) -> Cow<'static, str> {
+        fixed!(self, "/completions")
+    }
+
+    pub fn edits(&self) -> Cow<'static, str> {
+        fixed!(self, "/edits")
+    }
+
+    pub fn embeddings(&self) -> Cow<'static, str> {
+        fixed!(self, "/embeddings")
+    }
+
+    /// Used to list files and to upload a file.
+    pub fn files(&self) -> Cow<'static, str> {
+        fixed!(self, "/files")
+    }
+
+    /// Used to retrieve and to delete a file.
+    pub fn file(&self, file_id: &str) -> Cow<'static, str> {
+        Cow::Owned(format!("{}/files/{}", self.base_url(), file_id))
+    }
+
+    pub fn file_content(&self, file_id: &str) -> Cow<'static, str> {
+        Cow::Owned(format!("{}/files/{}/content", self.base_url(), file_id))
+    }
+
+    /// Used to list fine-tunes and to create a fine-tune.
+    pub fn fine_tunes(&self) -> Cow<'static, str> {
+        fixed!(self, "/fine-tunes")
+    }
+
+    pub fn fine_tune(&self, fine_tune_id: &str) -> Cow<'static, str> {
+        Cow::Owned(format!("{}/fine-tunes/{}", self.base_url(), fine_tune_id))
+    }
+
+    pub fn cancel_fine_tune(&self, fine_tune_id: &str) -> Cow<'static, str> {
+        Cow::Owned(format!(
+            "{}/fine-tunes/{}/cancel",
+            self.base_url(),
+            fine_tune_id
+        ))
+    }
+
+    pub fn fine_tune_events(&self, fine_tune_id: &str) -> Cow<'static, str> {
+        Cow::Owned(format!(
+            "{}/fine-tunes/{}/events",
+            self.base_url(),
+            fine_tune_id
+        ))
+    }
+
+    pub fn image_generations(&self) -> Cow<'static, str> {
+        fixed!(self, "/images/generations")
+    }
+
+    pub fn image_edits(&self) -> Cow<'static, str> {
+        fixed!(self, "/images/edits")
+    }
+
+    pub fn image_variations(&self) -> Cow<'static, str> {
+        fixed!(self, "/images/variations")
+    }
+
+    pub fn models(&self) -> Cow<'static, str> {
+        fixed!(self, "/models")
+    }
+
+    /// Used to retrieve a model and to delete a fine-tuned model.
+    pub fn model(&self, model: &str) -> Cow<'static, str> {
+        Cow::Owned(format!("{}/models/{}", self.base_url(), model))
+    }
+
+    pub fn moderations(&self) -> Cow<'static, str> {
+        fixed!(self, "/moderations")
+    }
+
+    /// Any other endpoint. `path` may include or omit a leading "/" or "/v1/". A full url
+    /// (starting with "http") is returned as is.
+    pub fn custom(&self, path: &str) -> Cow<'static, str> {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            return Cow::Owned(path.to_string());
+        }
+        let path = path.trim_start_matches('/');
+        let path = path.strip_prefix("v1/").unwrap_or(path);
+        Cow::Owned(format!("{}/{}", self.base_url(), path))
+    }
+}
+
+#[cfg(test)]
+mod url_test {
+    use super::*;
+
+    #[test]
+    fn url_custom_base() {
+        let endpoints = Endpoints::new("http://127.0.0.1:8080/v1/");
+        assert_eq!(
+            endpoints.chat_completions(),
+            "http://127.0.0.1:8080/v1/chat/completions"
+        );
+        assert_eq!(
+            endpoints.file("file-abc"),
+            "http://127.0.0.1:8080/v1/files/file-abc"
+        );
+        assert_eq!(Endpoints::new(DEFAULT_BASE_URL), Endpoints::default());
+    }
+
+    #[test]
+    fn url_static_is_borrowed() {
+        let url = Endpoints::default().chat_completions();
+
+        assert!(matches!(url, Cow::Borrowed(_)));
+        assert_eq!(url, "https://api.openai.com/v1/chat/completions");
+    }
+
+    #[test]
+    fn url_cancel_ft() {
+        let url = Endpoints::default().cancel_fine_tune("ft_id");
+
+        let expected = "https://api.openai.com/v1/fine-tunes/ft_id/cancel";
+
+        assert_eq!(url, expected);
+    }
+
+    #[test]
+    fn url_file_delete() {
+        let url = Endpoints::default().file("file_id");
+
+        let expected = "https://api.openai.com/v1/files/file_id";
+
+        assert_eq!(url, expected);
+    }
+
+    #[test]
+    fn url_file_retrieve_content() {
+        let url = Endpoints::default().file_content("file_id");
+
+        let expected = "https://api.openai.com/v1/files/file_id/content";
+
+        assert_eq!(url, expected);
+    }
+
+    #[test]
+    fn url_ft_create() {
+        let url = Endpoints::default().fine_tunes();
+
+        let expected = "https://api.openai.com/v1/fine-tunes";
+
+        assert_eq!(url, expected);
+    }
+
+    #[test]
+    fn url_custom() {
+        let endpoints = Endpoints::default();
+        let expected = "https://api.openai.com/v1/assistants";
+
+        assert_eq!(endpoints.custom("assistants"), expected);
+        assert_eq!(endpoints.custom("/v1/assistants"), expected);
+        assert_eq!(endpoints.custom(expected), expected);
+    }
+
+    #[test]
+    fn url_ft_list_events() {
+        let url = Endpoints::default().fine_tune_events("ft_id");
+
+        let expected = "https://api.openai.com/v1/fine-tunes/ft_id/events";
+
+        assert_eq!(url, expected);
+    }
+}
@@ -1,6 +1,10 @@
 // TODO: Need to look closer at what's considered idiomatic Rust error handling. May need a lot of refactoring?
 
-use reqwest::StatusCode;
+use std::time::Duration;
+
+use reqwest::{header::HeaderMap, StatusCode};
+
+use crate::meta::BODY_SNIPPET_LEN;
 
 use super::*;
 
@@ -11,6 +15,23 @@ pub struct OairsError {
     pub error_type: String,
     pub param: Option<String>,
     pub code: Option<String>,
+    /// The HTTP status code of the response, for errors returned by the API.
+    #[serde(skip)]
+    pub status: Option<u16>,
+    /// The API's error code, e.g. [`ApiErrorCode::ContextLengthExceeded`].
+    #[serde(skip)]
+    pub error_code: Option<ApiErrorCode>,
+    // The fields only some errors have, boxed so that every `Result<_, OairsError>` doesn't
+    // pay for them.
+    #[serde(skip)]
+    pub(crate) details: Option<Box<ErrorDetails>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ErrorDetails {
+    retry_after: Option<Duration>,
+    partial: Option<String>,
+    moderation: Option<ModerationBlocked>,
 }
 
 impl std::fmt::Display for OairsError {
@@ -32,6 +53,75 @@ impl From<std::io::Error> for OairsError {
 }
 
 impl OairsError {
+    /// Whether the error is likely to be resolved by retrying the request: rate limits, timeouts,
+    /// connection issues, and server errors (5xx).
+    pub fn is_transient(&self) -> bool {
+        let transient = [
+            ErrorType::RateLimit,
+            ErrorType::Timeout,
+            ErrorType::APIConnection,
+            ErrorType::ServiceUnavailable,
+            ErrorType::StreamInterrupted,
+        ];
+        if transient.iter().any(|t| self.error_type == t.to_str()) {
+            return true;
+        }
+        matches!(&self.code, Some(code) if code.starts_with('5'))
+    }
+
+    /// Whether the request was rejected for exceeding a rate limit (status code 429).
+    pub fn is_rate_limit(&self) -> bool {
+        self.error_type == ErrorType::RateLimit.to_str()
+            || self.status == Some(429)
+            || matches!(&self.code, Some(code) if code.starts_with("429"))
+    }
+
+    /// Whether a streamed response ended before it was done, e.g. because the connection
+    /// dropped. What was received before it ended is available from
+    /// [`partial`](OairsError::partial).
+    pub fn is_stream_interrupted(&self) -> bool {
+        self.error_type == ErrorType::StreamInterrupted.to_str()
+    }
+
+    /// For a `StreamInterrupted` error, or a `Cancelled` error from a stream, the content
+    /// received before the stream ended.
+    pub fn partial(&self) -> Option<&str> {
+        self.details.as_ref()?.partial.as_deref()
+    }
+
+    /// Whether the request (or stream) was stopped by its
+    /// [`CancellationToken`](crate::CancellationToken). If a stream was cancelled, what was
+    /// received before is available from [`partial`](OairsError::partial).
+    pub fn is_cancelled(&self) -> bool {
+        self.error_type == ErrorType::Cancelled.to_str()
+    }
+
+    /// Whether a request was refused because the moderations endpoint flagged its input (or
+    /// the model's reply), as by [`SafeChatBuilder`](crate::completions::SafeChatBuilder).
+    pub fn is_moderation_blocked(&self) -> bool {
+        self.error_type == ErrorType::ModerationBlocked.to_str()
+    }
+
+    /// For a `ModerationBlocked` error, what was flagged and in which categories.
+    pub fn moderation_blocked(&self) -> Option<&ModerationBlocked> {
+        self.details.as_ref()?.moderation.as_ref()
+    }
+
+    /// How long to wait before retrying, from the `retry-after` header or, when the rate limit
+    /// is exhausted, the `x-ratelimit-reset-*` headers.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.details.as_ref()?.retry_after
+    }
+
+    pub(crate) fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        if retry_after.is_some() {
+            self.details
+                .get_or_insert_with(Default::default)
+                .retry_after = retry_after;
+        }
+        self
+    }
+
     pub fn new(
         message: String,
         error_type: ErrorType,
@@ -43,10 +133,80 @@ impl OairsError {
             error_type: error_type.to_string(),
             param,
             code,
+            status: None,
+            error_code: None,
+            details: None,
         }
     }
 }
 
+/// Whether a [`ModerationBlocked`] error was for the messages sent to the model or the model's
+/// reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationStage {
+    Prompt,
+    Reply,
+}
+
+/// The details of a `ModerationBlocked` error. See [`OairsError::moderation_blocked`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModerationBlocked {
+    pub stage: ModerationStage,
+    /// The inputs that were flagged.
+    pub flagged: Vec<String>,
+    /// The categories any of them were flagged in, e.g. "hate/threatening", without duplicates.
+    pub categories: Vec<&'static str>,
+}
+
+/// The `code` of an error returned by the API, for branching on the cause of an error. See
+/// [`OairsError::error_code`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorCode {
+    ContextLengthExceeded,
+    InsufficientQuota,
+    RateLimitExceeded,
+    InvalidApiKey,
+    ModelNotFound,
+    ServerError,
+    /// A code without its own variant. The code itself is part of [`OairsError::code`].
+    Other,
+}
+
+impl ApiErrorCode {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            ApiErrorCode::ContextLengthExceeded => "context_length_exceeded",
+            ApiErrorCode::InsufficientQuota => "insufficient_quota",
+            ApiErrorCode::RateLimitExceeded => "rate_limit_exceeded",
+            ApiErrorCode::InvalidApiKey => "invalid_api_key",
+            ApiErrorCode::ModelNotFound => "model_not_found",
+            ApiErrorCode::ServerError => "server_error",
+            ApiErrorCode::Other => "other",
+        }
+    }
+}
+
+impl From<&str> for ApiErrorCode {
+    fn from(code: &str) -> Self {
+        match code {
+            "context_length_exceeded" => ApiErrorCode::ContextLengthExceeded,
+            "insufficient_quota" => ApiErrorCode::InsufficientQuota,
+            "rate_limit_exceeded" => ApiErrorCode::RateLimitExceeded,
+            "invalid_api_key" => ApiErrorCode::InvalidApiKey,
+            "model_not_found" => ApiErrorCode::ModelNotFound,
+            "server_error" => ApiErrorCode::ServerError,
+            _ => ApiErrorCode::Other,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
 #[non_exhaustive]
 #[derive(Debug)]
 pub enum ErrorType {
@@ -69,6 +229,9 @@ pub enum ErrorType {
     ParseError,
     SaveError,
     ParamError,
+    StreamInterrupted,
+    Cancelled,
+    ModerationBlocked,
     // Catch-all that should be factored out as more specific errors are added
     Other,
 }
@@ -92,6 +255,9 @@ impl ErrorType {
             ErrorType::Tokenizer => "Tokenizer Error",
             ErrorType::PolarsError => "Polars Error",
             ErrorType::ParamError => "Parameter Error",
+            ErrorType::StreamInterrupted => "Stream Interrupted",
+            ErrorType::Cancelled => "Cancelled",
+            ErrorType::ModerationBlocked => "Moderation Blocked",
             ErrorType::Other => "Other Error",
         }
     }
@@ -120,13 +286,19 @@ impl std::fmt::Display for InvalidRequest {
 pub(crate) fn handle_request_fail(body: &str, status_code: StatusCode) -> OairsError {
     match serde_json::from_str::<InvalidRequest>(body) {
         Ok(ir) => {
-            if ir.error.code.is_none() {
+            let error_code = api_error_code(&ir.error);
+            let error = OairsError {
+                status: Some(status_code.as_u16()),
+                error_code,
+                ..ir.error
+            };
+            if error.code.is_none() {
                 OairsError {
                     code: Some(status_code.as_u16().to_string()),
-                    ..ir.error
+                    ..error
                 }
             } else {
-                ir.error
+                error
             }
         }
         Err(e) => OairsError::new(
@@ -138,6 +310,59 @@ pub(crate) fn handle_request_fail(body: &str, status_code: StatusCode) -> OairsE
     }
 }
 
+/// A `StreamInterrupted` error carrying the `partial` content received before the stream ended.
+pub(crate) fn stream_interrupted(partial: String, reason: &str) -> OairsError {
+    OairsError {
+        details: Some(Box::new(ErrorDetails {
+            partial: Some(partial),
+            ..Default::default()
+        })),
+        ..OairsError::new(
+            format!("The stream ended before it was done: {reason}"),
+            ErrorType::StreamInterrupted,
+            None,
+            None,
+        )
+    }
+}
+
+/// A `Cancelled` error, carrying the `partial` content of a stream if there was one.
+pub(crate) fn cancelled(partial: Option<String>) -> OairsError {
+    OairsError {
+        details: partial.map(|partial| {
+            Box::new(ErrorDetails {
+                partial: Some(partial),
+                ..Default::default()
+            })
+        }),
+        ..OairsError::new(
+            "The request was cancelled".to_string(),
+            ErrorType::Cancelled,
+            None,
+            None,
+        )
+    }
+}
+
+/// A `ModerationBlocked` error carrying what was `blocked`.
+pub(crate) fn moderation_blocked(blocked: ModerationBlocked) -> OairsError {
+    let stage = match blocked.stage {
+        ModerationStage::Prompt => "The messages were",
+        ModerationStage::Reply => "The model's reply was",
+    };
+    let message = format!(
+        "{stage} flagged by the moderations endpoint for: {}",
+        blocked.categories.join(", ")
+    );
+    OairsError {
+        details: Some(Box::new(ErrorDetails {
+            moderation: Some(blocked),
+            ..Default::default()
+        })),
+        ..OairsError::new(message, ErrorType::ModerationBlocked, None, None)
+    }
+}
+
 pub(crate) fn builder_error(e: reqwest::Error) -> OairsError {
     let status_code = if e.status().is_some() {
         Some(e.status().unwrap().to_string())
@@ -168,7 +393,68 @@ pub(crate) async fn parse_api_error(
 ) -> OairsError {
     let headers = response.headers().clone();
 
-    let invalid_request = response.json::<InvalidRequest>().await.unwrap();
+    match response.text().await {
+        Ok(body) => parse_api_error_body(&body, status_code, &headers),
+        Err(e) => parse_reqwest_error(e),
+    }
+}
+
+/// The same as [`parse_api_error`], for a body that has already been read, as by the
+/// [`blocking`](crate::blocking) client and [`http::parse_error`](crate::http::parse_error).
+pub(crate) fn parse_api_error_body(
+    body: &str,
+    status_code: reqwest::StatusCode,
+    headers: &HeaderMap,
+) -> OairsError {
+    match serde_json::from_str::<InvalidRequest>(body) {
+        Ok(invalid_request) => from_invalid_request(invalid_request, status_code, headers),
+        Err(_) => unparsed_api_error(body, status_code, headers),
+    }
+}
+
+// The error for a body that isn't one of the API's errors, e.g. the HTML page of a proxy's 502
+// or the empty body of a 500. The type comes from the status code alone, so that a 429, 503,
+// 504, or any other 5xx is still transient and retried.
+fn unparsed_api_error(body: &str, status_code: StatusCode, headers: &HeaderMap) -> OairsError {
+    let error_type = match status_code {
+        StatusCode::TOO_MANY_REQUESTS => ErrorType::RateLimit,
+        StatusCode::SERVICE_UNAVAILABLE => ErrorType::ServiceUnavailable,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => ErrorType::Timeout,
+        _ => ErrorType::Other,
+    };
+    let snippet: String = body.trim().chars().take(BODY_SNIPPET_LEN).collect();
+    let message = match snippet.is_empty() {
+        true => format!("{status_code} with an empty body"),
+        false => format!("{status_code} with a body that isn't an API error: {snippet}"),
+    };
+    OairsError {
+        status: Some(status_code.as_u16()),
+        ..OairsError::new(message, error_type, None, Some(status_code.to_string()))
+    }
+    .with_retry_after(retry_after(headers))
+}
+
+fn from_invalid_request(
+    invalid_request: InvalidRequest,
+    status_code: reqwest::StatusCode,
+    headers: &HeaderMap,
+) -> OairsError {
+    let error_code = api_error_code(&invalid_request.error);
+    let error = api_error(invalid_request, status_code, headers);
+
+    OairsError {
+        status: Some(status_code.as_u16()),
+        error_code,
+        ..error
+    }
+    .with_retry_after(retry_after(headers))
+}
+
+fn api_error(
+    invalid_request: InvalidRequest,
+    status_code: reqwest::StatusCode,
+    headers: &HeaderMap,
+) -> OairsError {
     let message = invalid_request.error.message;
     let param = invalid_request.error.param;
     let api_code = if invalid_request.error.code.is_some() {
@@ -185,11 +471,10 @@ pub(crate) async fn parse_api_error(
                 let remaining = remaining.to_str().unwrap();
                 let remaining = remaining.parse::<u32>().unwrap();
                 if remaining == 0 {
-                    let reset = headers.get("x-ratelimit-reset-requests").unwrap();
                     OairsError::new(
                         format!("Rate limit exceeded. API message: {}", message),
                         ErrorType::RateLimit,
-                        Some(reset.to_str().unwrap().to_string()),
+                        param,
                         Some(code),
                     )
                 } else {
@@ -234,3 +519,114 @@ pub(crate) async fn parse_api_error(
         _ => OairsError::new(message, ErrorType::Other, param, Some(code)),
     }
 }
+
+// The API puts the code in `code`, except for some errors (e.g. `insufficient_quota`) where it's
+// only in `type`.
+fn api_error_code(error: &OairsError) -> Option<ApiErrorCode> {
+    match &error.code {
+        Some(code) if !code.is_empty() => Some(ApiErrorCode::from(code.as_str())),
+        _ if error.error_type == "insufficient_quota" => Some(ApiErrorCode::InsufficientQuota),
+        _ => None,
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(seconds) = header("retry-after").and_then(|s| s.trim().parse::<f64>().ok()) {
+        return Some(Duration::from_secs_f64(seconds.max(0.0)));
+    }
+    ["requests", "tokens"]
+        .iter()
+        .filter(|limit| header(&format!("x-ratelimit-remaining-{limit}")) == Some("0"))
+        .filter_map(|limit| header(&format!("x-ratelimit-reset-{limit}")))
+        .filter_map(parse_reset_duration)
+        .max()
+}
+
+// Parses the durations of the `x-ratelimit-reset-*` headers, e.g. "20ms", "1s", or "6m0.5s".
+pub(crate) fn parse_reset_duration(s: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut number = String::new();
+    let mut chars = s.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let value: f64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'h' => value * 3600.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                value / 1000.0
+            }
+            'm' => value * 60.0,
+            's' => value,
+            _ => return None,
+        };
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_error_fields() {
+        let body = r#"{"error": {"message": "This model's maximum context length is 4097 tokens.", "type": "invalid_request_error", "param": "messages", "code": "context_length_exceeded"}}"#;
+        let error = handle_request_fail(body, StatusCode::BAD_REQUEST);
+        assert_eq!(error.status, Some(400));
+        assert_eq!(error.error_code, Some(ApiErrorCode::ContextLengthExceeded));
+        assert_eq!(error.param.as_deref(), Some("messages"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining-requests", "0".parse().unwrap());
+        headers.insert("x-ratelimit-reset-requests", "6m0.5s".parse().unwrap());
+        headers.insert("x-ratelimit-remaining-tokens", "100".parse().unwrap());
+        headers.insert("x-ratelimit-reset-tokens", "20ms".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs_f64(360.5)));
+
+        headers.insert("retry-after", "2".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+
+        assert_eq!(
+            parse_reset_duration("1h2m3s"),
+            Some(Duration::from_secs(3723))
+        );
+        assert_eq!(
+            parse_reset_duration("20ms"),
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(parse_reset_duration("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn non_json_error_body() {
+        let body = "<html><head><title>502 Bad Gateway</title></head></html>";
+        let response = ::http::Response::builder()
+            .status(502)
+            .body(body.to_string())
+            .unwrap();
+        let error = parse_api_error(response.into(), StatusCode::BAD_GATEWAY).await;
+        assert_eq!(error.status, Some(502));
+        assert_eq!(error.code.as_deref(), Some("502 Bad Gateway"));
+        assert!(error.message.contains("<title>502 Bad Gateway</title>"));
+        assert!(error.is_transient());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", "3".parse().unwrap());
+        let error = parse_api_error_body("", StatusCode::TOO_MANY_REQUESTS, &headers);
+        assert!(error.is_rate_limit());
+        assert!(error.is_transient());
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(3)));
+
+        let error = parse_api_error_body("Not Found", StatusCode::NOT_FOUND, &HeaderMap::new());
+        assert!(!error.is_transient());
+    }
+}
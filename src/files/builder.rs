@@ -0,0 +1,624 @@
+//! Contains the [`ListFilesBuilder`], [`DownloadFileBuilder`], and [`DeleteFilesBuilder`]
+//! structs.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures::stream::{self, Stream, StreamExt};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{
+    client::{bearer, handle_request, HttpMethod, PreparedBody, PreparedRequest},
+    meta::deserialize_response,
+};
+
+use super::{
+    response::{DeleteResponse, FileInfo, FileList},
+    *,
+};
+
+/// The sort order for the `order` query parameter of the list files endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+impl std::fmt::Display for Order {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+impl Order {
+    pub fn to_str(&self) -> &str {
+        match self {
+            Order::Asc => "asc",
+            Order::Desc => "desc",
+        }
+    }
+}
+
+/// Builder for a request to list files. The optional parameters are sent as
+/// query parameters. Normally you would not construct this directly, but would
+/// get it from [`Client::list_files`](crate::client::Client::list_files).
+#[derive(Debug, Clone, Default)]
+pub struct ListFilesBuilder<State = Buildable> {
+    key: String,
+    url: Cow<'static, str>,
+    purpose: Option<Purpose>,
+    after: Option<String>,
+    limit: Option<u32>,
+    order: Option<Order>,
+    state: PhantomData<State>,
+}
+
+impl ListFilesBuilder<Buildable> {
+    pub(crate) fn create<K: Into<String>>(key: K) -> ListFilesBuilder<Sendable> {
+        ListFilesBuilder {
+            key: key.into(),
+            url: Endpoints::default().files(),
+            ..Default::default()
+        }
+    }
+}
+
+impl ListFilesBuilder<Sendable> {
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
+    /// Only return files with the given [`Purpose`].
+    pub fn purpose(&mut self, purpose: Purpose) -> &mut Self {
+        self.purpose = Some(purpose);
+        self
+    }
+
+    /// A cursor for pagination. Pass the id of the last file from a previous
+    /// page to get the files that follow it.
+    pub fn after<S: Into<String>>(&mut self, file_id: S) -> &mut Self {
+        self.after = Some(file_id.into());
+        self
+    }
+
+    /// The maximum number of files to return in a single page.
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sort order of the files by their `created_at` timestamp.
+    pub fn order(&mut self, order: Order) -> &mut Self {
+        self.order = Some(order);
+        self
+    }
+
+    fn query(&self) -> Vec<(&str, String)> {
+        let mut query = Vec::new();
+        if let Some(purpose) = &self.purpose {
+            query.push(("purpose", purpose.to_string()));
+        }
+        if let Some(after) = &self.after {
+            query.push(("after", after.clone()));
+        }
+        if let Some(limit) = self.limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(order) = self.order {
+            query.push(("order", order.to_string()));
+        }
+        query
+    }
+
+    /// The full url, including any query parameters that have been set.
+    pub(crate) fn url_with_query(&self) -> Result<String, OairsError> {
+        let query = self.query();
+        if query.is_empty() {
+            return Ok(self.url.to_string());
+        }
+        match reqwest::Url::parse_with_params(&self.url, query) {
+            Ok(url) => Ok(url.to_string()),
+            Err(e) => Err(OairsError::new(
+                e.to_string(),
+                ErrorType::ParamError,
+                Some(self.url.to_string()),
+                None,
+            )),
+        }
+    }
+
+    /// Executes the `GET` request for a single page. Returns a `Result` with either a
+    /// `reqwest::Response` or an `OairsError`. A successful response can be
+    /// deserialized into a [`FileList`].
+    pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
+        let url = self.url_with_query()?;
+        handle_request(&self.key, &url, HttpMethod::Get, None, None).await
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        let url = self.url_with_query()?;
+        Ok(PreparedRequest::new(
+            &self.key,
+            &url,
+            HttpMethod::Get,
+            PreparedBody::Empty,
+        ))
+    }
+
+    /// Returns a [`Stream`] of every [`FileInfo`], requesting the next page (using the
+    /// page's `last_id`, or else the id of the last file received, as the `after` cursor)
+    /// for as long as the API
+    /// reports `has_more`. If the API doesn't paginate, the stream simply ends
+    /// after the first page.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use futures::{pin_mut, StreamExt};
+    ///
+    /// let stream = client.list_files().limit(20).into_stream();
+    /// pin_mut!(stream);
+    /// while let Some(file) = stream.next().await {
+    ///     let file = file.unwrap();
+    ///     println!("{}: {}", file.id, file.filename);
+    /// }
+    /// ```
+    pub fn into_stream(&self) -> impl Stream<Item = Result<FileInfo, OairsError>> {
+        let builder = self.clone();
+        // (builder for the next page, buffered files from the current page, whether
+        // another page should be requested)
+        let init = (builder, Vec::<FileInfo>::new().into_iter(), true);
+
+        stream::unfold(init, |(mut builder, mut buffer, more)| async move {
+            if let Some(file) = buffer.next() {
+                return Some((Ok(file), (builder, buffer, more)));
+            }
+            if !more {
+                return None;
+            }
+
+            let page = match builder.send().await {
+                Ok(r) => match deserialize_response::<FileList>(r).await {
+                    Ok(list) => list,
+                    Err(e) => return Some((Err(e), (builder, buffer, false))),
+                },
+                Err(e) => return Some((Err(e), (builder, buffer, false))),
+            };
+
+            let more = page.has_more() && !page.data.is_empty();
+            // Prefer the cursor returned by the API, if any.
+            builder.after = match page.last_id() {
+                Some(id) => Some(id.to_string()),
+                None => page.data.last().map(|last| last.id.clone()),
+            };
+            let mut buffer = page.data.into_iter();
+            buffer
+                .next()
+                .map(|file| (Ok(file), (builder, buffer, more)))
+        })
+    }
+}
+
+/// Callback for reporting download progress. Called with the number of bytes written so
+/// far and the total number of bytes expected.
+pub type ProgressCallback = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+/// Builder for downloading the content of a file straight to disk. Normally you would not
+/// construct this directly, but would get it from
+/// [`Client::download_file`](crate::client::Client::download_file). Not available for `wasm32`,
+/// which has no file system.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Default)]
+pub struct DownloadFileBuilder<State = Buildable> {
+    key: String,
+    file_id: String,
+    path: PathBuf,
+    progress: Option<ProgressCallback>,
+    endpoints: Endpoints,
+    state: PhantomData<State>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<State> std::fmt::Debug for DownloadFileBuilder<State> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadFileBuilder")
+            .field("file_id", &self.file_id)
+            .field("path", &self.path)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DownloadFileBuilder<Buildable> {
+    pub(crate) fn create<K, I, P>(key: K, file_id: I, path: P) -> DownloadFileBuilder<Sendable>
+    where
+        K: Into<String>,
+        I: Into<String>,
+        P: Into<PathBuf>,
+    {
+        DownloadFileBuilder {
+            key: key.into(),
+            file_id: file_id.into(),
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DownloadFileBuilder<Sendable> {
+    pub(crate) fn set_endpoints(&mut self, endpoints: &Endpoints) -> &mut Self {
+        self.endpoints = endpoints.clone();
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
+    /// Set a callback that is called after each chunk is written, with the number of
+    /// bytes written so far and the total size of the file.
+    pub fn on_progress<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Retrieves the [`FileInfo`] for the file, then streams its content to the given path.
+    /// Returns the number of bytes written. If the number of bytes received doesn't match
+    /// [`FileInfo::bytes`], the partially written file is removed and an `OairsError` is
+    /// returned.
+    pub async fn send(&self) -> Result<u64, OairsError> {
+        let info = handle_request(
+            &self.key,
+            &self.endpoints.file(&self.file_id),
+            HttpMethod::Get,
+            None,
+            None,
+        )
+        .await?;
+        let info = deserialize_response::<FileInfo>(info).await?;
+
+        let res = handle_request(
+            &self.key,
+            &self.endpoints.file_content(&self.file_id),
+            HttpMethod::Get,
+            None,
+            None,
+        )
+        .await?;
+
+        let written = match self.write_body(res, info.bytes).await {
+            Ok(written) => written,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&self.path).await;
+                return Err(e);
+            }
+        };
+
+        if written != info.bytes {
+            let _ = tokio::fs::remove_file(&self.path).await;
+            return Err(OairsError::new(
+                format!(
+                    "Downloaded {} bytes, but file {} should be {} bytes",
+                    written, self.file_id, info.bytes
+                ),
+                ErrorType::FileError,
+                Some(self.path.display().to_string()),
+                None,
+            ));
+        }
+
+        Ok(written)
+    }
+
+    async fn write_body(&self, res: reqwest::Response, total: u64) -> Result<u64, OairsError> {
+        let mut file = File::create(&self.path).await?;
+        let mut body = res.bytes_stream();
+        let mut written = 0;
+
+        while let Some(chunk) = body.next().await {
+            let chunk = match chunk {
+                Ok(c) => c,
+                Err(e) => return Err(parse_reqwest_error(e)),
+            };
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+            if let Some(progress) = &self.progress {
+                progress(written, total);
+            }
+        }
+        file.flush().await?;
+
+        Ok(written)
+    }
+}
+
+/// Callback for choosing the files to delete with
+/// [`Client::delete_files_where`](crate::client::Client::delete_files_where).
+pub type FileFilter = Arc<dyn Fn(&FileInfo) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+enum FileSelection {
+    Ids(Vec<String>),
+    Where(ListFilesBuilder<Sendable>, FileFilter),
+}
+
+/// Builder for deleting many files, up to [`max_concurrency`](Self::max_concurrency) at a time.
+/// Normally you would not construct this directly, but would get it from
+/// [`Client::delete_files`](crate::client::Client::delete_files) or
+/// [`Client::delete_files_where`](crate::client::Client::delete_files_where).
+#[derive(Clone)]
+pub struct DeleteFilesBuilder<State = Buildable> {
+    key: String,
+    endpoints: Endpoints,
+    selection: FileSelection,
+    max_concurrency: usize,
+    state: PhantomData<State>,
+}
+
+impl<State> std::fmt::Debug for DeleteFilesBuilder<State> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let selection = match &self.selection {
+            FileSelection::Ids(ids) => format!("{:?}", ids),
+            FileSelection::Where(..) => "where".to_string(),
+        };
+        f.debug_struct("DeleteFilesBuilder")
+            .field("selection", &selection)
+            .field("max_concurrency", &self.max_concurrency)
+            .finish()
+    }
+}
+
+impl DeleteFilesBuilder<Buildable> {
+    pub(crate) fn create<K: Into<String>>(
+        key: K,
+        file_ids: Vec<String>,
+    ) -> DeleteFilesBuilder<Sendable> {
+        DeleteFilesBuilder::with_selection(key.into(), FileSelection::Ids(file_ids))
+    }
+
+    pub(crate) fn create_where<K: Into<String>>(
+        key: K,
+        list: ListFilesBuilder<Sendable>,
+        filter: FileFilter,
+    ) -> DeleteFilesBuilder<Sendable> {
+        DeleteFilesBuilder::with_selection(key.into(), FileSelection::Where(list, filter))
+    }
+
+    fn with_selection(key: String, selection: FileSelection) -> DeleteFilesBuilder<Sendable> {
+        DeleteFilesBuilder {
+            key,
+            endpoints: Endpoints::default(),
+            selection,
+            max_concurrency: 4,
+            state: PhantomData,
+        }
+    }
+}
+
+impl DeleteFilesBuilder<Sendable> {
+    pub(crate) fn set_endpoints(&mut self, endpoints: &Endpoints) -> &mut Self {
+        self.endpoints = endpoints.clone();
+        self
+    }
+
+    /// Send the requests, including the one listing the files to delete, with `key` instead
+    /// of the client's key, e.g. to delete another project's files. See
+    /// [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        if let FileSelection::Where(list, _) = &mut self.selection {
+            list.api_key(self.key.as_str());
+        }
+        self
+    }
+
+    /// The maximum number of delete requests in flight at once. Defaults to 4. Returns a
+    /// `ParamError` from [`send`](Self::send) if 0.
+    pub fn max_concurrency(&mut self, max_concurrency: usize) -> &mut Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    /// The ids of the files to delete. For [`delete_files_where`](crate::client::Client::delete_files_where),
+    /// this lists every file and applies the filter.
+    pub async fn file_ids(&self) -> Result<Vec<String>, OairsError> {
+        match &self.selection {
+            FileSelection::Ids(ids) => Ok(ids.clone()),
+            FileSelection::Where(list, filter) => {
+                let files: Vec<FileInfo> = list
+                    .into_stream()
+                    .collect::<Vec<_>>()
+                    .await
+                    .into_iter()
+                    .collect::<Result<_, _>>()?;
+                Ok(files
+                    .into_iter()
+                    .filter(|file| filter(file))
+                    .map(|file| file.id)
+                    .collect())
+            }
+        }
+    }
+
+    /// The `DELETE` request that [`send`](Self::send) would make for each file, without
+    /// deleting anything. (Choosing the files for
+    /// [`delete_files_where`](crate::client::Client::delete_files_where) still lists them.)
+    pub async fn dry_run(&self) -> Result<Vec<PreparedRequest>, OairsError> {
+        let requests = self
+            .file_ids()
+            .await?
+            .iter()
+            .map(|id| {
+                PreparedRequest::new(
+                    &self.key,
+                    &self.endpoints.file(id),
+                    HttpMethod::Delete,
+                    PreparedBody::Empty,
+                )
+            })
+            .collect();
+        Ok(requests)
+    }
+
+    /// Deletes the files, up to `max_concurrency` at a time, and returns the result of each
+    /// deletion. A failed deletion doesn't stop the others; an `OairsError` is only returned if
+    /// the files to delete couldn't be listed.
+    pub async fn send(&self) -> Result<DeleteResults, OairsError> {
+        if self.max_concurrency == 0 {
+            return Err(OairsError::new(
+                "max_concurrency must be greater than 0".to_string(),
+                ErrorType::ParamError,
+                Some("max_concurrency".to_string()),
+                None,
+            ));
+        }
+
+        let file_ids = self.file_ids().await?;
+        let results = stream::iter(file_ids.iter())
+            .map(|id| self.delete(id))
+            .buffered(self.max_concurrency)
+            .collect()
+            .await;
+
+        Ok(DeleteResults { file_ids, results })
+    }
+
+    async fn delete(&self, file_id: &str) -> Result<DeleteResponse, OairsError> {
+        let url = self.endpoints.file(file_id);
+        let res = handle_request(&self.key, &url, HttpMethod::Delete, None, None).await?;
+        deserialize_response::<DeleteResponse>(res).await
+    }
+}
+
+/// The result of each deletion made by a [`DeleteFilesBuilder`], in the order of the file ids.
+#[derive(Debug)]
+pub struct DeleteResults {
+    file_ids: Vec<String>,
+    results: Vec<Result<DeleteResponse, OairsError>>,
+}
+
+impl DeleteResults {
+    pub fn file_ids(&self) -> &[String] {
+        &self.file_ids
+    }
+
+    pub fn results(&self) -> &[Result<DeleteResponse, OairsError>] {
+        &self.results
+    }
+
+    /// Pairs each file id with the result of its deletion.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Result<DeleteResponse, OairsError>)> {
+        self.file_ids
+            .iter()
+            .map(|id| id.as_str())
+            .zip(self.results.iter())
+    }
+
+    /// The ids of the files that were deleted.
+    pub fn deleted(&self) -> impl Iterator<Item = &str> {
+        self.iter()
+            .filter(|(_, r)| matches!(r, Ok(response) if response.deleted))
+            .map(|(id, _)| id)
+    }
+
+    /// The errors of the deletions that failed, with the id of their file.
+    pub fn errors(&self) -> impl Iterator<Item = (&str, &OairsError)> {
+        self.iter()
+            .filter_map(|(id, r)| r.as_ref().err().map(|e| (id, e)))
+    }
+
+    pub fn all_succeeded(&self) -> bool {
+        self.results
+            .iter()
+            .all(|r| matches!(r, Ok(response) if response.deleted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_files_query() {
+        let mut builder = ListFilesBuilder::create("key");
+        builder
+            .purpose(Purpose::FineTune)
+            .limit(10)
+            .after("file-abc")
+            .order(Order::Desc);
+
+        let url = builder.url_with_query().unwrap();
+        let expected =
+            "https://api.openai.com/v1/files?purpose=fine-tune&after=file-abc&limit=10&order=desc";
+
+        assert_eq!(url, expected);
+    }
+
+    #[test]
+    fn list_files_no_query() {
+        let builder = ListFilesBuilder::create("key");
+        let url = builder.url_with_query().unwrap();
+
+        assert_eq!(url, "https://api.openai.com/v1/files");
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        client::HttpMethod,
+        files::response::FileInfo,
+        test_util::{fixtures::RATE_LIMIT_ERROR, MockApi},
+    };
+
+    #[tokio::test]
+    async fn delete_files() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock_error(HttpMethod::Delete, "/files/file-bad", 429, RATE_LIMIT_ERROR)
+            .await;
+
+        let results = client
+            .delete_files(["file-abc", "file-bad", "file-def"])
+            .max_concurrency(2)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(results.file_ids(), ["file-abc", "file-bad", "file-def"]);
+        assert_eq!(results.deleted().count(), 2);
+        let errors: Vec<_> = results.errors().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "file-bad");
+        assert!(!results.all_succeeded());
+
+        let builder = client.delete_files_where(|file: &FileInfo| file.purpose == "fine-tune");
+        let requests = builder.dry_run().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0]
+            .url
+            .ends_with("/v1/files/file-XjGxS3KTG0uNmNOK362iJua3"));
+        let endpoint = "/files/file-XjGxS3KTG0uNmNOK362iJua3";
+        assert!(api.requests(endpoint).await.is_empty());
+        assert!(builder.send().await.unwrap().all_succeeded());
+        assert_eq!(api.requests(endpoint).await.len(), 1);
+    }
+}
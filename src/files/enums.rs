@@ -1,10 +1,21 @@
 use super::*;
 
-// Currently the only available purpose in the API, but if more are added later...
+/// The intended use of an uploaded file, sent as the `purpose` of an upload and used to filter
+/// [`list_files`](crate::client::Client::list_files) and
+/// [`FileList::by_purpose`](crate::files::response::FileList). `Other` is an escape hatch for
+/// purposes the API accepts that don't have a variant yet.
 #[non_exhaustive]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Purpose {
     FineTune,
+    /// Files the API creates with the results of a fine-tune.
+    FineTuneResults,
+    Assistants,
+    AssistantsOutput,
+    Batch,
+    BatchOutput,
+    Vision,
+    Other(String),
 }
 
 impl std::default::Default for Purpose {
@@ -15,9 +26,44 @@ impl std::default::Default for Purpose {
 
 impl std::fmt::Display for Purpose {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Purpose::FineTune => write!(f, "fine-tune"),
-        }
+        write!(f, "{}", self.to_str())
+    }
+}
+
+impl std::str::FromStr for Purpose {
+    type Err = OairsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let purpose = match s {
+            "fine-tune" => Purpose::FineTune,
+            "fine-tune-results" => Purpose::FineTuneResults,
+            "assistants" => Purpose::Assistants,
+            "assistants_output" => Purpose::AssistantsOutput,
+            "batch" => Purpose::Batch,
+            "batch_output" => Purpose::BatchOutput,
+            "vision" => Purpose::Vision,
+            _ => Purpose::Other(s.to_string()),
+        };
+        Ok(purpose)
+    }
+}
+
+impl Serialize for Purpose {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        serializer.serialize_str(self.to_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Purpose {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -25,6 +71,35 @@ impl Purpose {
     pub fn to_str(&self) -> &str {
         match self {
             Purpose::FineTune => "fine-tune",
+            Purpose::FineTuneResults => "fine-tune-results",
+            Purpose::Assistants => "assistants",
+            Purpose::AssistantsOutput => "assistants_output",
+            Purpose::Batch => "batch",
+            Purpose::BatchOutput => "batch_output",
+            Purpose::Vision => "vision",
+            Purpose::Other(purpose) => purpose,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purpose_strings() {
+        for purpose in [Purpose::FineTune, Purpose::BatchOutput] {
+            let json = serde_json::to_string(&purpose).unwrap();
+            assert_eq!(json, format!("\"{}\"", purpose));
+            assert_eq!(serde_json::from_str::<Purpose>(&json).unwrap(), purpose);
         }
+        assert_eq!(
+            "user_data".parse::<Purpose>().unwrap(),
+            Purpose::Other("user_data".to_string())
+        );
+        assert_eq!(
+            Purpose::Other("user_data".to_string()).to_str(),
+            "user_data"
+        );
     }
 }
@@ -1,6 +1,8 @@
+mod builder;
 mod enums;
 pub mod response;
 
+pub use self::builder::*;
 pub use self::enums::*;
 
 use super::*;
@@ -1,24 +1,24 @@
-use std::str::FromStr;
+use std::{collections::HashSet, str::FromStr};
 
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::{fs::OpenOptions, io::AsyncWriteExt};
 
+use crate::list::List;
+
 use super::*;
 
 /// In response to a delete file request or a delete fine-tune model request.
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
 pub struct DeleteResponse {
     pub id: String,
     pub object: String,
     pub deleted: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
-pub struct FileList {
-    data: Vec<FileInfo>,
-    object: String,
-}
+/// Response from a request to list files. See [`List`].
+pub type FileList = List<FileInfo>;
 
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
 pub struct FileInfo {
     pub object: String, // "file"
     pub id: String,
@@ -40,6 +40,42 @@ impl FileInfo {
     }
 }
 
+impl FileList {
+    /// The files with the given [`Purpose`].
+    pub fn by_purpose(&self, purpose: &Purpose) -> Vec<&FileInfo> {
+        self.iter()
+            .filter(|file| file.purpose == purpose.to_str())
+            .collect()
+    }
+
+    /// The first file named `filename`. Filenames aren't unique, so there may be others; see
+    /// [`newest`](Self::newest) for picking the latest upload.
+    pub fn find_by_filename(&self, filename: &str) -> Option<&FileInfo> {
+        self.iter().find(|file| file.filename == filename)
+    }
+
+    /// The most recently created file.
+    pub fn newest(&self) -> Option<&FileInfo> {
+        self.iter().max_by_key(|file| file.created_at)
+    }
+
+    /// The combined size of the files, in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.iter().map(|file| file.bytes).sum()
+    }
+
+    /// Sorts the files by their `created_at` timestamp, in the given [`Order`].
+    pub fn sort_by_created_at(&mut self, order: Order) -> &mut Self {
+        match order {
+            Order::Asc => self.data.sort_by_key(|file| file.created_at),
+            Order::Desc => self
+                .data
+                .sort_by_key(|file| std::cmp::Reverse(file.created_at)),
+        }
+        self
+    }
+}
+
 /// Struct for retrieving file content of a fine-tune training file.
 #[derive(Debug, Serialize)]
 pub struct FineTuneFC {
@@ -75,6 +111,10 @@ impl FineTuneFC {
         Ok(Self::from_string(s))
     }
 
+    /// Saves the pairs as JSONL, one pair per line, ready to upload as a training (or
+    /// validation) file. ".jsonl" is appended to `path` if needed, and an existing file is
+    /// overwritten.
+    #[cfg(not(target_arch = "wasm32"))]
     pub async fn save_jsonl(&self, path: &str) -> Result<(), OairsError> {
         let path = if !path.ends_with(".jsonl") {
             format!("{}.jsonl", path)
@@ -84,19 +124,94 @@ impl FineTuneFC {
 
         let mut file = OpenOptions::new()
             .create(true)
-            .append(true)
+            .write(true)
+            .truncate(true)
             .open(path)
             .await?;
         for line in &self.data {
-            let s = serde_json::to_string(line).unwrap();
+            let mut s = serde_json::to_string(line).unwrap();
+            s.push('\n');
             file.write_all(s.as_bytes()).await?;
         }
+        // A tokio file may still be writing the last line when it's dropped.
+        file.flush().await?;
         Ok(())
     }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Removes the pairs whose prompt and completion are both identical to an earlier pair's,
+    /// keeping the order of the rest. Returns the number of pairs removed.
+    pub fn dedupe(&mut self) -> usize {
+        let before = self.data.len();
+        let mut seen = HashSet::new();
+        self.data
+            .retain(|pair| seen.insert((pair.prompt.clone(), pair.completion.clone())));
+        before - self.data.len()
+    }
+
+    /// Shuffles the pairs in place. The same `seed` always gives the same order (for the same
+    /// pairs), so a split made after shuffling can be reproduced.
+    pub fn shuffle(&mut self, seed: u64) -> &mut Self {
+        let mut rng = SplitMix64(seed);
+        // Fisher-Yates.
+        for i in (1..self.data.len()).rev() {
+            let j = (rng.next() % (i as u64 + 1)) as usize;
+            self.data.swap(i, j);
+        }
+        self
+    }
+
+    /// Splits the pairs into a training set with the first `ratio` of them (rounded to the
+    /// nearest pair) and a validation set with the rest, in order. Shuffle first (see
+    /// [`shuffle`](Self::shuffle)) for a random split. Returns a `ParamError` if `ratio` is not
+    /// between 0.0 and 1.0.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let mut content = FineTuneFC::from_string(std::fs::read_to_string("data.jsonl")?);
+    /// content.dedupe();
+    /// content.shuffle(42);
+    /// let (train, validation) = content.split(0.8)?;
+    /// train.save_jsonl("train.jsonl").await?;
+    /// validation.save_jsonl("validation.jsonl").await?;
+    /// ```
+    pub fn split(mut self, ratio: f64) -> Result<(FineTuneFC, FineTuneFC), OairsError> {
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(OairsError::new(
+                format!("ratio must be between 0.0 and 1.0, got {ratio}"),
+                ErrorType::ParamError,
+                Some("ratio".into()),
+                None,
+            ));
+        }
+        let at = (self.data.len() as f64 * ratio).round() as usize;
+        let validation = self.data.split_off(at);
+        Ok((self, FineTuneFC { data: validation }))
+    }
+}
+
+// A small seeded PRNG for `FineTuneFC::shuffle`, so it doesn't need a `rand` dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
 }
 
 /// Represents a single prompt-response in a `jsonl` fine-tune training file.
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, SaveJson)]
 pub struct PromptCompletion {
     pub prompt: String,
     pub completion: String,
@@ -138,14 +253,38 @@ impl FromStr for PromptCompletion {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+/// Struct for retrieving file content of a fine-tune results file. Each field is a column
+/// of the `CSV` file. The validation columns will only have values at the steps where
+/// validation was run (and not at all if no validation file was provided), so the metric
+/// columns are all `Option`s.
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
 pub struct FineTuneResultsFC {
     pub step: Vec<u64>,
     pub elapsed_tokens: Vec<u64>,
     pub elapsed_examples: Vec<u64>,
-    pub training_loss: Vec<f64>,
-    pub training_sequence_accuracy: Vec<f64>,
-    pub training_token_accuracy: Vec<f64>,
+    pub training_loss: Vec<Option<f64>>,
+    pub training_sequence_accuracy: Vec<Option<f64>>,
+    pub training_token_accuracy: Vec<Option<f64>>,
+    pub validation_loss: Vec<Option<f64>>,
+    pub validation_sequence_accuracy: Vec<Option<f64>>,
+    pub validation_token_accuracy: Vec<Option<f64>>,
+}
+
+// A single row of a fine-tune results file.
+#[derive(Debug, Deserialize)]
+struct FineTuneResultsRow {
+    step: u64,
+    elapsed_tokens: u64,
+    elapsed_examples: u64,
+    training_loss: Option<f64>,
+    training_sequence_accuracy: Option<f64>,
+    training_token_accuracy: Option<f64>,
+    #[serde(default)]
+    validation_loss: Option<f64>,
+    #[serde(default)]
+    validation_sequence_accuracy: Option<f64>,
+    #[serde(default)]
+    validation_token_accuracy: Option<f64>,
 }
 
 impl Default for FineTuneResultsFC {
@@ -163,55 +302,175 @@ impl FineTuneResultsFC {
             training_loss: Vec::new(),
             training_sequence_accuracy: Vec::new(),
             training_token_accuracy: Vec::new(),
+            validation_loss: Vec::new(),
+            validation_sequence_accuracy: Vec::new(),
+            validation_token_accuracy: Vec::new(),
         }
     }
 
-    fn from_string(s: String) -> Self {
-        let mut step = Vec::new();
-        let mut elapsed_tokens = Vec::new();
-        let mut elapsed_examples = Vec::new();
-        let mut training_loss = Vec::new();
-        let mut training_sequence_accuracy = Vec::new();
-        let mut training_token_accuracy = Vec::new();
+    fn from_string(s: &str) -> Result<Self, OairsError> {
+        let mut results = Self::new();
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(s.as_bytes());
 
-        let mut in_header_row = true;
-        for line in s.lines() {
-            if in_header_row {
-                in_header_row = false;
-                continue;
-            }
-            let mut iter = line.split(',');
-            let s = match iter.next() {
-                Some(s) => s.parse::<u64>().unwrap(),
-                None => continue,
+        for (i, row) in reader.deserialize::<FineTuneResultsRow>().enumerate() {
+            let row = match row {
+                Ok(r) => r,
+                Err(e) => {
+                    return Err(OairsError::new(
+                        format!("Unable to parse row {} of fine-tune results: {}", i + 1, e),
+                        ErrorType::ParseError,
+                        None,
+                        None,
+                    ))
+                }
             };
-            let et = iter.next().unwrap().parse::<u64>().unwrap();
-            let ee = iter.next().unwrap().parse::<u64>().unwrap();
-            let tl = iter.next().unwrap().parse::<f64>().unwrap();
-            let tsa = iter.next().unwrap().parse::<f64>().unwrap();
-            let tta = iter.next().unwrap().parse::<f64>().unwrap();
-
-            step.push(s);
-            elapsed_tokens.push(et);
-            elapsed_examples.push(ee);
-            training_loss.push(tl);
-            training_sequence_accuracy.push(tsa);
-            training_token_accuracy.push(tta);
-        }
 
-        FineTuneResultsFC {
-            step,
-            elapsed_tokens,
-            elapsed_examples,
-            training_loss,
-            training_sequence_accuracy,
-            training_token_accuracy,
+            results.step.push(row.step);
+            results.elapsed_tokens.push(row.elapsed_tokens);
+            results.elapsed_examples.push(row.elapsed_examples);
+            results.training_loss.push(row.training_loss);
+            results
+                .training_sequence_accuracy
+                .push(row.training_sequence_accuracy);
+            results
+                .training_token_accuracy
+                .push(row.training_token_accuracy);
+            results.validation_loss.push(row.validation_loss);
+            results
+                .validation_sequence_accuracy
+                .push(row.validation_sequence_accuracy);
+            results
+                .validation_token_accuracy
+                .push(row.validation_token_accuracy);
         }
+
+        Ok(results)
     }
 
     /// Convert a `reqwest::Response` to a `FineTuneResultsFC` struct.
     pub async fn from_response(response: reqwest::Response) -> Result<Self, OairsError> {
-        let s = response.text().await.unwrap();
-        Ok(Self::from_string(s))
+        let s = match response.text().await {
+            Ok(s) => s,
+            Err(e) => return Err(parse_reqwest_error(e)),
+        };
+        Self::from_string(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fine_tune_content_split() {
+        let pair = |i: usize| format!(r#"{{"prompt": "p{i} ->", "completion": " c{i}"}}"#);
+        let lines: Vec<String> = (0..10).chain([3, 7]).map(pair).collect();
+        let mut content = FineTuneFC::from_string(lines.join("\n"));
+
+        assert_eq!(content.dedupe(), 2);
+        assert_eq!(content.len(), 10);
+
+        let original = content.data.clone();
+        content.shuffle(7);
+        assert_ne!(content.data, original);
+        let mut again = FineTuneFC {
+            data: original.clone(),
+        };
+        assert_eq!(again.shuffle(7).data, content.data);
+
+        for ratio in [-0.1, 1.5, f64::NAN] {
+            let data = content.data.clone();
+            let error = FineTuneFC { data }.split(ratio).unwrap_err();
+            assert_eq!(error.error_type, ErrorType::ParamError.to_string());
+            assert_eq!(error.param.as_deref(), Some("ratio"));
+        }
+        let (train, validation) = content.split(0.75).unwrap();
+        assert_eq!((train.len(), validation.len()), (8, 2));
+
+        let path = std::env::temp_dir().join(format!("oairs-split-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        validation.save_jsonl(path).await.unwrap();
+        validation.save_jsonl(path).await.unwrap();
+        let saved = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(FineTuneFC::from_string(saved).data, validation.data);
+    }
+
+    #[test]
+    fn fine_tune_results_with_validation() {
+        let s = "step,elapsed_tokens,elapsed_examples,training_loss,training_sequence_accuracy,\
+            training_token_accuracy,validation_loss,validation_sequence_accuracy,validation_token_accuracy\n\
+            1,25,1,1.5,0.0,0.5,,,\n\
+            2,50,2,1.2,0.0,0.6,1.3,0.0,0.55\n";
+
+        let results = FineTuneResultsFC::from_string(s).unwrap();
+
+        assert_eq!(results.step, vec![1, 2]);
+        assert_eq!(results.training_loss, vec![Some(1.5), Some(1.2)]);
+        assert_eq!(results.validation_loss, vec![None, Some(1.3)]);
+        assert_eq!(results.validation_token_accuracy, vec![None, Some(0.55)]);
+    }
+
+    #[test]
+    fn fine_tune_results_without_validation() {
+        let s = "step,elapsed_tokens,elapsed_examples,training_loss,training_sequence_accuracy,training_token_accuracy\n\
+            1,25,1,1.5,0.0,0.5\n";
+
+        let results = FineTuneResultsFC::from_string(s).unwrap();
+
+        assert_eq!(results.training_token_accuracy, vec![Some(0.5)]);
+        assert_eq!(results.validation_loss, vec![None]);
+    }
+
+    #[test]
+    fn fine_tune_results_parse_error() {
+        let s = "step,elapsed_tokens,elapsed_examples,training_loss,training_sequence_accuracy,training_token_accuracy\n\
+            one,25,1,1.5,0.0,0.5\n";
+
+        let err = FineTuneResultsFC::from_string(s).unwrap_err();
+
+        assert_eq!(err.error_type, ErrorType::ParseError.to_string());
+    }
+
+    #[test]
+    fn file_list_queries() {
+        let file = |id: &str, purpose: &str, created_at: u64| FileInfo {
+            object: "file".to_string(),
+            id: id.to_string(),
+            purpose: purpose.to_string(),
+            filename: format!("{purpose}.jsonl"),
+            bytes: 100,
+            created_at,
+            status: "processed".to_string(),
+            status_destails: None,
+        };
+        let mut files = FileList::from(vec![
+            file("file-1", "fine-tune", 20),
+            file("file-2", "fine-tune-results", 30),
+            file("file-3", "fine-tune", 10),
+        ]);
+
+        let ids = |files: Vec<&FileInfo>| files.iter().map(|f| f.id.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            ids(files.by_purpose(&Purpose::FineTune)),
+            ["file-1", "file-3"]
+        );
+        assert_eq!(ids(files.by_purpose(&Purpose::FineTuneResults)), ["file-2"]);
+        assert!(files.by_purpose(&Purpose::Batch).is_empty());
+
+        assert_eq!(
+            files.find_by_filename("fine-tune.jsonl").unwrap().id,
+            "file-1"
+        );
+        assert!(files.find_by_filename("batch.jsonl").is_none());
+        assert_eq!(files.newest().unwrap().id, "file-2");
+        assert_eq!(files.total_bytes(), 300);
+
+        files.sort_by_created_at(Order::Asc);
+        assert_eq!(ids(files.iter().collect()), ["file-3", "file-1", "file-2"]);
+        files.sort_by_created_at(Order::Desc);
+        assert_eq!(ids(files.iter().collect()), ["file-2", "file-1", "file-3"]);
     }
 }
@@ -1,15 +1,18 @@
 // TODO: Clean up type-state pattern!
 
-use crate::client::{handle_request, HttpMethod};
+use crate::{
+    client::{bearer, handle_request, HttpMethod, PreparedBody, PreparedRequest},
+    meta::deserialize_response,
+};
 
-use super::*;
+use super::{response::EventList, *};
 
-#[derive(Default, Serialize)]
+#[derive(Clone, Default, Serialize)]
 pub struct FineTunesBuilder<'a, State = Buildable> {
     #[serde(skip)]
     key: String,
     #[serde(skip)]
-    url: String,
+    url: Cow<'static, str>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     training_file: Option<&'a str>,
@@ -17,14 +20,8 @@ pub struct FineTunesBuilder<'a, State = Buildable> {
     validation_file: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
     model: Option<&'a str>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    n_epochs: Option<u16>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    batch_size: Option<u16>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    learning_rate_multiplier: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    prompt_loss_weight: Option<f32>,
+    #[serde(flatten)]
+    hyperparameters: Hyperparameters,
     #[serde(skip_serializing_if = "Option::is_none")]
     compute_classification_metrics: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -35,6 +32,8 @@ pub struct FineTunesBuilder<'a, State = Buildable> {
     classification_betas: Option<Vec<f32>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     suffix: Option<&'a str>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 
     #[serde(skip)]
     state: PhantomData<State>,
@@ -91,7 +90,7 @@ impl<'a> FineTunesBuilder<'a, Buildable> {
     ) -> FineTunesBuilder<'a, Sendable> {
         FineTunesBuilder {
             key: key.into(),
-            url: create_ft_url().to_string(),
+            url: Endpoints::default().fine_tunes(),
             training_file: Some(training_file_id),
             ..Default::default()
         }
@@ -99,6 +98,18 @@ impl<'a> FineTunesBuilder<'a, Buildable> {
 }
 
 impl<'a> FineTunesBuilder<'a, Sendable> {
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
     /// The id of a file *that has already been uploaded* to OpenAI's servers (cf. the files endpoint).
     ///
     /// "If you provide this file, the data is used to generate validation metrics periodically during
@@ -128,7 +139,7 @@ impl<'a> FineTunesBuilder<'a, Sendable> {
     /// training dataset." -
     /// [OpenAI API docs](https://platform.openai.com/docs/api-reference/fine-tunes/create#fine-tunes/create-n_epochs)
     pub fn n_epochs(&mut self, n: u16) -> &mut Self {
-        self.n_epochs = Some(n);
+        self.hyperparameters.n_epochs = Some(n.into());
         self
     }
 
@@ -140,7 +151,7 @@ impl<'a> FineTunesBuilder<'a, Sendable> {
     /// work better for larger datasets." -
     /// [OpenAI API docs](https://platform.openai.com/docs/api-reference/fine-tunes/create#fine-tunes/create-batch_size)
     pub fn batch_size(&mut self, batch_size: u16) -> &mut Self {
-        self.batch_size = Some(batch_size);
+        self.hyperparameters.batch_size = Some(batch_size.into());
         self
     }
 
@@ -152,7 +163,7 @@ impl<'a> FineTunesBuilder<'a, Sendable> {
     /// experimenting with values in the range `0.02` to `0.2` to see what produces the best results." -
     /// [OpenAI API docs](https://platform.openai.com/docs/api-reference/fine-tunes/create#fine-tunes/create-learning_rate_multiplier)
     pub fn learning_rate_multiplier(&mut self, learning_rate: f32) -> &mut Self {
-        self.learning_rate_multiplier = Some(learning_rate);
+        self.hyperparameters.learning_rate_multiplier = Some(learning_rate);
         self
     }
 
@@ -164,7 +175,14 @@ impl<'a> FineTunesBuilder<'a, Sendable> {
     /// so as to avoid over-prioritizing learning the prompt." -
     /// [OpenAI API docs](https://platform.openai.com/docs/api-reference/fine-tunes/create#fine-tunes/create-prompt_loss_weight)
     pub fn prompt_loss_weight(&mut self, loss_weight: f32) -> &mut Self {
-        self.prompt_loss_weight = Some(loss_weight);
+        self.hyperparameters.prompt_loss_weight = Some(loss_weight);
+        self
+    }
+
+    /// Set `n_epochs`, `batch_size`, `learning_rate_multiplier`, and `prompt_loss_weight` at
+    /// once, replacing any that were already set. See [`Hyperparameters`].
+    pub fn hyperparameters(&mut self, hyperparameters: Hyperparameters) -> &mut Self {
+        self.hyperparameters = hyperparameters;
         self
     }
 
@@ -226,20 +244,50 @@ impl<'a> FineTunesBuilder<'a, Sendable> {
         self
     }
 
+    /// Adds `key` to the request body with `value`, as with
+    /// [`ChatBuilder::extra_param`](crate::completions::ChatBuilder::extra_param).
+    pub fn extra_param<K: Into<String>>(&mut self, key: K, value: serde_json::Value) -> &mut Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// The JSON body that [`send`](Self::send) sends, including the hyperparameters, which
+    /// aren't validated here.
+    pub fn to_request_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    /// Validates the hyperparameters and returns the request that [`send`](Self::send) would
+    /// make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        self.hyperparameters.validate()?;
+        Ok(PreparedRequest::new(
+            &self.key,
+            &self.url,
+            HttpMethod::Post,
+            PreparedBody::Json(self.to_request_json()),
+        ))
+    }
+
+    /// Executes the `POST` request. Returns a `ParamError` without sending the request if any
+    /// of the hyperparameters is out of range (see [`Hyperparameters::validate`]).
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let json = serde_json::to_value(self).unwrap();
+        self.hyperparameters.validate()?;
+        let json = self.to_request_json();
         handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None).await
     }
 }
 
-#[derive(Default, Serialize)]
+#[derive(Clone, Default, Serialize)]
 pub struct ListEventsBuilder<State = Buildable> {
     #[serde(skip)]
     key: String,
     #[serde(skip)]
-    url: String,
+    url: Cow<'static, str>,
     stream: bool,
     #[serde(skip)]
+    after: Option<u64>,
+    #[serde(skip)]
     state: PhantomData<State>,
 }
 
@@ -247,18 +295,115 @@ impl ListEventsBuilder<Buildable> {
     pub fn new(key: &str, ft_id: &str) -> ListEventsBuilder<Sendable> {
         ListEventsBuilder {
             key: key.to_string(),
-            url: list_ft_events_url(ft_id),
+            url: Endpoints::default().fine_tune_events(ft_id),
             stream: false,
+            after: None,
             state: PhantomData::<Sendable>,
         }
     }
 }
 
 impl ListEventsBuilder<Sendable> {
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self
+    }
+
     pub fn stream(&mut self, stream: bool) -> &mut Self {
         self.stream = stream;
         self
     }
+
+    /// Only keep the events created after `timestamp` (in seconds since the Unix epoch, like
+    /// an event's `created_at`) in the list returned by [`send_parsed`](Self::send_parsed). The
+    /// API always returns every event of the fine-tune, so when polling a long job, pass the
+    /// `created_at` of the last event seen to get only the new ones. [`send`](Self::send)
+    /// returns the response as is.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let mut last_seen = 0;
+    /// loop {
+    ///     let events = client.list_fine_tune_events(id).after(last_seen).send_parsed().await?;
+    ///     for event in &events {
+    ///         println!("[{}] {}", event.level, event.message);
+    ///     }
+    ///     last_seen = events.latest_created_at().unwrap_or(last_seen);
+    ///     tokio::time::sleep(Duration::from_secs(30)).await;
+    /// }
+    /// ```
+    pub fn after(&mut self, timestamp: u64) -> &mut Self {
+        self.after = Some(timestamp);
+        self
+    }
+
+    /// Sends the request and deserializes the response into an [`EventList`], without the
+    /// events at or before the [`after`](Self::after) timestamp, if set.
+    pub async fn send_parsed(&self) -> Result<EventList, OairsError> {
+        let mut events: EventList = deserialize_response(self.send().await?).await?;
+        if let Some(timestamp) = self.after {
+            events.data.retain(|event| event.created_at > timestamp);
+        }
+        Ok(events)
+    }
 }
 
 impl_get!(ListEventsBuilder<Sendable>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fine_tunes_request_json() {
+        let model = FineTuneModel::Curie;
+        let mut builder = FineTunesBuilder::create("key", "file-abc");
+        builder.model(&model).n_epochs(2).suffix("custom");
+
+        assert_eq!(
+            builder.to_request_json(),
+            serde_json::json!({
+                "training_file": "file-abc",
+                "model": "curie",
+                "n_epochs": 2,
+                "suffix": "custom"
+            })
+        );
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::test_util::MockApi;
+
+    #[tokio::test]
+    async fn fine_tune_events_after() {
+        let api = MockApi::start().await;
+        let client = api.client();
+
+        let events = client
+            .list_fine_tune_events("ft-abc")
+            .after(1679423500)
+            .send_parsed()
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.data[0].message, "Job started.");
+
+        let all = client
+            .list_fine_tune_events("ft-abc")
+            .send_parsed()
+            .await
+            .unwrap();
+        assert_eq!(all.since(1679423500).len(), 2);
+        assert_eq!(all.latest_created_at(), Some(1679423600));
+        assert!(all.errors_only().is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Contains [`estimate_cost`], for checking what a fine-tune will cost before creating it.
+
+use crate::{files::response::FineTuneFC, tokenizers::tokenize_batch};
+
+use super::*;
+
+/// The estimated cost of training a fine-tune, as returned by [`estimate_cost`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SaveJson)]
+pub struct CostEstimate {
+    pub model: FineTuneModel,
+    pub n_examples: usize,
+    pub n_epochs: u32,
+    /// The number of prompt and completion tokens in the training file.
+    pub tokens_per_epoch: usize,
+    /// `tokens_per_epoch * n_epochs`
+    pub training_tokens: usize,
+    pub price_per_1k_tokens: f64,
+    /// The estimated cost in USD.
+    pub cost: f64,
+}
+
+impl std::fmt::Display for CostEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} examples x {} epochs = {} tokens on {} at ${}/1K tokens: ${:.2}",
+            self.n_examples,
+            self.n_epochs,
+            self.training_tokens,
+            self.model,
+            self.price_per_1k_tokens,
+            self.cost
+        )
+    }
+}
+
+/// Estimate the cost of fine-tuning `model` on `training_file` for `n_epochs` (the API's default
+/// is 4), by tokenizing every prompt and completion with the model's tokenizer and multiplying
+/// by [`FineTuneModel::training_price_per_1k_tokens`]. The actual number of tokens billed may
+/// differ slightly, so treat the estimate as a guard against launching an unexpectedly
+/// expensive job rather than an exact price.
+///
+/// Returns a `ParamError` for an [`Other`](FineTuneModel::Other) model, which has no known price.
+///
+/// # Example
+/// ```rust,no_run
+/// let training_file = FineTuneFC::from_string(std::fs::read_to_string("train.jsonl")?);
+/// let estimate = estimate_cost(&training_file, FineTuneModel::Davinci, 4)?;
+/// println!("{estimate}");
+/// if estimate.cost > 10.0 {
+///     return Ok(());
+/// }
+/// ```
+pub fn estimate_cost(
+    training_file: &FineTuneFC,
+    model: FineTuneModel,
+    n_epochs: u32,
+) -> Result<CostEstimate, OairsError> {
+    let price_per_1k_tokens = match model.training_price_per_1k_tokens() {
+        Some(price) => price,
+        None => {
+            return Err(OairsError::new(
+                format!("No training price for {model}"),
+                ErrorType::ParamError,
+                Some("model".to_string()),
+                None,
+            ))
+        }
+    };
+    let texts: Vec<&str> = training_file
+        .data
+        .iter()
+        .flat_map(|pc| [pc.prompt.as_str(), pc.completion.as_str()])
+        .collect();
+    let tokens_per_epoch = tokenize_batch(&texts, model.tokenizer())
+        .iter()
+        .map(|tokens| tokens.len())
+        .sum::<usize>();
+
+    let training_tokens = tokens_per_epoch * n_epochs as usize;
+
+    Ok(CostEstimate {
+        n_examples: training_file.data.len(),
+        n_epochs,
+        tokens_per_epoch,
+        training_tokens,
+        price_per_1k_tokens,
+        cost: training_tokens as f64 / 1000.0 * price_per_1k_tokens,
+        model,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizers::{tokenize, Tokenizer};
+
+    #[test]
+    fn fine_tune_cost_estimate() {
+        let training_file = FineTuneFC::from_string(
+            "{\"prompt\": \"What is 2 + 2? ->\", \"completion\": \" 4\\n\"}\n".repeat(10),
+        );
+        let per_example = tokenize("What is 2 + 2? ->", Tokenizer::R50KBase)
+            .unwrap()
+            .len()
+            + tokenize(" 4\n", Tokenizer::R50KBase).unwrap().len();
+
+        let estimate = estimate_cost(&training_file, FineTuneModel::Davinci, 4).unwrap();
+
+        assert_eq!(estimate.n_examples, 10);
+        assert_eq!(estimate.tokens_per_epoch, per_example * 10);
+        assert_eq!(estimate.training_tokens, per_example * 40);
+        assert!((estimate.cost - per_example as f64 * 40.0 / 1000.0 * 0.03).abs() < 1e-9);
+    }
+}
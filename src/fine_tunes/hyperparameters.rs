@@ -0,0 +1,129 @@
+//! Contains the [`Hyperparameters`] struct, used both to set the hyperparameters of a fine-tune
+//! and to deserialize the `hyperparams` of a [`FineTuneInfo`](super::response::FineTuneInfo).
+
+use super::*;
+
+/// The hyperparameters of a fine-tune. Any that are `None` are left for the API to choose (and
+/// are filled in on the [`FineTuneInfo`](super::response::FineTuneInfo) once it has).
+///
+/// Set them all at once with [`FineTunesBuilder::hyperparameters`], or one at a time with the
+/// builder's setters. They are checked with [`validate`](Hyperparameters::validate) before the
+/// request is sent.
+///
+/// # Example
+/// ```rust,no_run
+/// let hp = Hyperparameters::new()
+///     .n_epochs(2)
+///     .learning_rate_multiplier(0.1);
+/// let response = client
+///     .create_fine_tune(training_file_id)
+///     .hyperparameters(hp)
+///     .send()
+///     .await;
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Hyperparameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_epochs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub learning_rate_multiplier: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_loss_weight: Option<f32>,
+}
+
+impl Hyperparameters {
+    pub fn new() -> Hyperparameters {
+        Hyperparameters::default()
+    }
+
+    /// Must be at least 1.
+    pub fn n_epochs(mut self, n_epochs: u32) -> Self {
+        self.n_epochs = Some(n_epochs);
+        self
+    }
+
+    /// Must be at least 1.
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Must be greater than 0. OpenAI recommends values from 0.02 to 0.2.
+    pub fn learning_rate_multiplier(mut self, multiplier: f32) -> Self {
+        self.learning_rate_multiplier = Some(multiplier);
+        self
+    }
+
+    /// Must be between 0.0 and 1.0.
+    pub fn prompt_loss_weight(mut self, weight: f32) -> Self {
+        self.prompt_loss_weight = Some(weight);
+        self
+    }
+
+    /// Returns a `ParamError` naming the first hyperparameter that is out of range.
+    pub fn validate(&self) -> Result<(), OairsError> {
+        let error = |param: &str, range: &str| {
+            Err(OairsError::new(
+                format!("{param} must be {range}"),
+                ErrorType::ParamError,
+                Some(param.to_string()),
+                None,
+            ))
+        };
+
+        if self.n_epochs == Some(0) {
+            return error("n_epochs", "at least 1");
+        }
+        if self.batch_size == Some(0) {
+            return error("batch_size", "at least 1");
+        }
+        if let Some(lr) = self.learning_rate_multiplier {
+            if !(lr.is_finite() && lr > 0.0) {
+                return error("learning_rate_multiplier", "greater than 0.0");
+            }
+        }
+        if let Some(weight) = self.prompt_loss_weight {
+            if !(0.0..=1.0).contains(&weight) {
+                return error("prompt_loss_weight", "between 0.0 and 1.0");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hyperparameters_validate() {
+        assert!(Hyperparameters::new().validate().is_ok());
+        assert!(Hyperparameters::new()
+            .n_epochs(4)
+            .batch_size(8)
+            .learning_rate_multiplier(0.1)
+            .prompt_loss_weight(0.01)
+            .validate()
+            .is_ok());
+
+        let error = Hyperparameters::new().n_epochs(0).validate().unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("n_epochs"));
+        let error = Hyperparameters::new()
+            .learning_rate_multiplier(-0.1)
+            .validate()
+            .unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("learning_rate_multiplier"));
+    }
+
+    #[test]
+    fn hyperparameters_round_trip() {
+        let json = r#"{"batch_size":4,"learning_rate_multiplier":0.1,"n_epochs":4,"prompt_loss_weight":0.1}"#;
+        let hp: Hyperparameters = serde_json::from_str(json).unwrap();
+        assert_eq!(hp.n_epochs, Some(4));
+
+        let hp = Hyperparameters::new().n_epochs(2);
+        assert_eq!(serde_json::to_string(&hp).unwrap(), r#"{"n_epochs":2}"#);
+    }
+}
@@ -1,6 +1,12 @@
 mod builder;
+#[cfg(feature = "tokenizers")]
+mod cost;
+mod hyperparameters;
 pub mod response;
 
 pub use self::builder::*;
+#[cfg(feature = "tokenizers")]
+pub use self::cost::*;
+pub use self::hyperparameters::*;
 
 use super::*;
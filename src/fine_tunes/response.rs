@@ -1,11 +1,16 @@
-use crate::files::response::FileInfo;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "dataframes")]
+use polars::prelude::{DataFrame, DataType, NamedFrom, Series, TimeUnit};
+
+use crate::{files::response::FileInfo, list::List};
 
 use super::*;
 
 /// Describes a fine-tune object, which can be a response
 /// from a request to fine-tune a model or part of a response from a list
 /// fine-tunes or list fine-tune events request.
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
 pub struct FineTuneInfo {
     pub object: String,
     pub id: String,
@@ -16,7 +21,7 @@ pub struct FineTuneInfo {
     pub events: Option<Vec<Event>>,
     // Can be null if failed
     pub fine_tuned_model: Option<String>,
-    pub hyperparams: Option<Hyperparams>,
+    pub hyperparams: Option<Hyperparameters>,
     pub organization_id: String,
     pub status: String,
     pub training_files: Vec<FileInfo>,
@@ -25,7 +30,44 @@ pub struct FineTuneInfo {
     pub updated_at: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl FineTuneInfo {
+    /// Prepends the fine-tune's id, base model, and hyperparameters to `results`, the metrics
+    /// in the fine-tune's result file (one row per step, with columns such as `step`,
+    /// `training_loss`, and `validation_loss`), so the metrics of several fine-tunes can be
+    /// concatenated and charted against their hyperparameters. Hyperparameters the API hasn't
+    /// reported are null.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let result_file = &fine_tune.result_files.as_ref().unwrap()[0];
+    /// let response = client.retrieve_file_content(&result_file.id).send().await?;
+    /// let results = df_from_response(response).await?;
+    /// let metrics = fine_tune.metrics_df(&results)?;
+    /// ```
+    #[cfg(feature = "dataframes")]
+    pub fn metrics_df(&self, results: &DataFrame) -> Result<DataFrame, OairsError> {
+        let rows = results.height();
+        let hp = self.hyperparams.unwrap_or_default();
+
+        let mut columns = vec![
+            Series::new("fine_tune_id", vec![self.id.as_str(); rows]),
+            Series::new("model", vec![self.model.as_str(); rows]),
+            Series::new("n_epochs", vec![hp.n_epochs; rows]),
+            Series::new("batch_size", vec![hp.batch_size; rows]),
+            Series::new(
+                "learning_rate_multiplier",
+                vec![hp.learning_rate_multiplier; rows],
+            ),
+            Series::new("prompt_loss_weight", vec![hp.prompt_loss_weight; rows]),
+        ];
+        columns.extend(results.get_columns().iter().cloned());
+
+        DataFrame::new(columns)
+            .map_err(|e| OairsError::new(e.to_string(), ErrorType::PolarsError, None, None))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Event {
     pub object: String,
     pub created_at: u64,
@@ -33,41 +75,242 @@ pub struct Event {
     pub message: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Hyperparams {
-    pub n_epochs: u32,
-    pub batch_size: Option<u32>,
-    pub prompt_loss_weight: f32,
-    pub learning_rate_multiplier: Option<f32>,
-}
+/// Kept for backwards compatibility, this is the same as [`Hyperparameters`].
+pub type Hyperparams = Hyperparameters;
 
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
-pub struct FineTunesList {
-    pub object: String, // Will be "list"
-    pub data: Vec<FineTuneInfo>,
-}
+/// Response from a request to list fine-tunes. See [`List`].
+pub type FineTunesList = List<FineTuneInfo>;
 
 impl FineTunesList {
+    /// Kept for backwards compatibility, this is the same as [`LoadJson::load_json`].
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn load_from_file(path: &str) -> Result<Self, OairsError> {
-        let file = std::fs::File::open(path)?;
-        let reader = std::io::BufReader::new(file);
-        let list = match serde_json::from_reader(reader) {
-            Ok(list) => list,
-            Err(e) => Err(OairsError::new(
-                e.to_string(),
-                ErrorType::DeserializationError,
-                Some(path.to_string()),
-                None,
-            ))?,
+        Self::load_json(path)
+    }
+
+    /// The fine-tunes with the given `status`, e.g. `"succeeded"`, `"failed"`, `"pending"`, or
+    /// `"running"`, in the order of the list.
+    pub fn by_status<'a>(&'a self, status: &'a str) -> impl Iterator<Item = &'a FineTuneInfo> {
+        self.data.iter().filter(move |ft| ft.status == status)
+    }
+
+    /// The most recently created fine-tune.
+    pub fn latest(&self) -> Option<&FineTuneInfo> {
+        self.data.iter().max_by_key(|ft| ft.created_at)
+    }
+
+    /// The fine-tune with the given id.
+    pub fn get(&self, fine_tune_id: &str) -> Option<&FineTuneInfo> {
+        self.data.iter().find(|ft| ft.id == fine_tune_id)
+    }
+
+    /// The names of the fine-tuned models, for the fine-tunes that have produced one.
+    pub fn fine_tuned_models(&self) -> impl Iterator<Item = &str> {
+        self.data
+            .iter()
+            .filter_map(|ft| ft.fine_tuned_model.as_deref())
+    }
+
+    /// The number of fine-tunes per status and per base model.
+    pub fn summary(&self) -> FineTunesSummary {
+        let mut summary = FineTunesSummary {
+            total: self.data.len(),
+            ..Default::default()
         };
-        Ok(list)
+        for ft in &self.data {
+            *summary.by_status.entry(ft.status.clone()).or_default() += 1;
+            *summary.by_model.entry(ft.model.clone()).or_default() += 1;
+        }
+        summary
+    }
+}
+
+/// The number of fine-tunes in a [`FineTunesList`] per status and per base model, from
+/// [`FineTunesList::summary`]. The maps are sorted by key.
+///
+/// # Example
+/// ```rust,no_run
+/// let list = client.fine_tunes().list().send().await?.json::<FineTunesList>().await?;
+/// let summary = list.summary();
+/// println!("{} of {} fine-tunes failed", summary.status("failed"), summary.total);
+/// for (model, count) in &summary.by_model {
+///     println!("{model}: {count}");
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FineTunesSummary {
+    pub total: usize,
+    pub by_status: BTreeMap<String, usize>,
+    pub by_model: BTreeMap<String, usize>,
+}
+
+impl FineTunesSummary {
+    /// The number of fine-tunes with the given status, 0 if there are none.
+    pub fn status(&self, status: &str) -> usize {
+        self.by_status.get(status).copied().unwrap_or(0)
+    }
+
+    /// The number of fine-tunes of the given base model, 0 if there are none.
+    pub fn model(&self, model: &str) -> usize {
+        self.by_model.get(model).copied().unwrap_or(0)
     }
 }
 
 /// Struct for deserializing a [`Response`](reqwest::Response) from a request to list
 /// fine-tune events.
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
-pub struct EventList {
-    pub object: String, // Will be "list"
-    pub data: Vec<Event>,
+/// Response from a request to list fine-tune events. See [`List`].
+pub type EventList = List<Event>;
+
+impl EventList {
+    /// The events created at or after `created_at` (in seconds since the Unix epoch), in the
+    /// order of the list.
+    pub fn since(&self, created_at: u64) -> EventList {
+        self.filtered(|event| event.created_at >= created_at)
+    }
+
+    /// The events with the level `"error"`, in the order of the list.
+    pub fn errors_only(&self) -> EventList {
+        self.filtered(|event| event.level == "error")
+    }
+
+    /// The `created_at` of the newest event, to pass to
+    /// [`ListEventsBuilder::after`](crate::fine_tunes::ListEventsBuilder::after) when polling
+    /// for new events. `None` if the list is empty.
+    pub fn latest_created_at(&self) -> Option<u64> {
+        self.data.iter().map(|event| event.created_at).max()
+    }
+
+    fn filtered<F: Fn(&Event) -> bool>(&self, keep: F) -> EventList {
+        EventList::from(
+            self.data
+                .iter()
+                .filter(|event| keep(event))
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Converts the events into a Polars DataFrame with the columns `timestamp` (a datetime,
+    /// from the events' `created_at`), `level`, and `message`, in the order of the list.
+    #[cfg(feature = "dataframes")]
+    pub fn to_df(&self) -> Result<DataFrame, OairsError> {
+        let polars_error = |e: polars::error::PolarsError| {
+            OairsError::new(e.to_string(), ErrorType::PolarsError, None, None)
+        };
+
+        // `created_at` is in seconds.
+        let millis: Vec<i64> = self
+            .data
+            .iter()
+            .map(|e| e.created_at as i64 * 1000)
+            .collect();
+        let timestamp = Series::new("timestamp", millis)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .map_err(polars_error)?;
+        let level: Vec<&str> = self.data.iter().map(|e| e.level.as_str()).collect();
+        let message: Vec<&str> = self.data.iter().map(|e| e.message.as_str()).collect();
+
+        DataFrame::new(vec![
+            timestamp,
+            Series::new("level", level),
+            Series::new("message", message),
+        ])
+        .map_err(polars_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "dataframes")]
+    use polars::{df, prelude::AnyValue};
+
+    use super::*;
+
+    #[test]
+    fn fine_tunes_summary() {
+        let fine_tune = |id: &str, model: &str, status: &str, created_at: u64| {
+            let fine_tuned_model = match status {
+                "succeeded" => format!("\"{model}:ft-{id}\""),
+                _ => "null".to_string(),
+            };
+            FineTuneInfo::from_json_str(&format!(
+                r#"{{"object": "fine-tune", "id": "{id}", "model": "{model}",
+                    "created_at": {created_at}, "fine_tuned_model": {fine_tuned_model},
+                    "organization_id": "org-123", "status": "{status}", "training_files": [],
+                    "updated_at": {created_at}}}"#
+            ))
+            .unwrap()
+        };
+        let list = FineTunesList::from(vec![
+            fine_tune("a", "curie", "succeeded", 3),
+            fine_tune("b", "davinci", "failed", 5),
+            fine_tune("c", "curie", "succeeded", 4),
+            fine_tune("d", "curie", "running", 1),
+        ]);
+
+        let succeeded: Vec<&str> = list
+            .by_status("succeeded")
+            .map(|ft| ft.id.as_str())
+            .collect();
+        assert_eq!(succeeded, ["a", "c"]);
+        assert_eq!(list.latest().unwrap().id, "b");
+        assert_eq!(list.get("d").unwrap().status, "running");
+        assert_eq!(
+            list.fine_tuned_models().collect::<Vec<_>>(),
+            ["curie:ft-a", "curie:ft-c"]
+        );
+
+        let summary = list.summary();
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.status("succeeded"), 2);
+        assert_eq!(summary.status("cancelled"), 0);
+        assert_eq!(summary.model("curie"), 3);
+        assert_eq!(summary.by_model.len(), 2);
+    }
+
+    #[cfg(feature = "dataframes")]
+    #[test]
+    fn fine_tune_dataframes() {
+        let events = EventList::from_json_str(
+            r#"{"object": "list", "data": [
+                {"object": "fine-tune-event", "created_at": 1614807352, "level": "info", "message": "Job enqueued."},
+                {"object": "fine-tune-event", "created_at": 1614807356, "level": "info", "message": "Job started."}
+            ]}"#,
+        )
+        .unwrap();
+        let df = events.to_df().unwrap();
+        assert_eq!(df.shape(), (2, 3));
+        assert_eq!(
+            df.column("timestamp").unwrap().dtype(),
+            &DataType::Datetime(TimeUnit::Milliseconds, None)
+        );
+        assert_eq!(
+            df.column("message").unwrap().get(1).unwrap(),
+            AnyValue::Utf8("Job started.")
+        );
+
+        let fine_tune = FineTuneInfo::from_json_str(
+            r#"{"object": "fine-tune", "id": "ft-abc", "model": "curie", "created_at": 1614807352,
+                "events": null, "fine_tuned_model": null,
+                "hyperparams": {"n_epochs": 4, "batch_size": 1},
+                "organization_id": "org-123", "status": "succeeded", "training_files": [],
+                "validation_files": [], "result_files": [], "updated_at": 1614807865}"#,
+        )
+        .unwrap();
+        let results = df!("step" => [1, 2, 3], "training_loss" => [0.9, 0.6, 0.4]).unwrap();
+        let df = fine_tune.metrics_df(&results).unwrap();
+        assert_eq!(df.shape(), (3, 8));
+        assert_eq!(
+            df.column("n_epochs").unwrap().get(2).unwrap(),
+            AnyValue::UInt32(4)
+        );
+        assert_eq!(
+            df.column("learning_rate_multiplier").unwrap().null_count(),
+            3
+        );
+        assert!(df
+            .column("training_loss")
+            .unwrap()
+            .series_equal(&results["training_loss"]));
+    }
 }
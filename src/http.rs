@@ -0,0 +1,255 @@
+//! The request plumbing that this crate's builders are built on, for crates that add builders
+//! for endpoints it doesn't cover yet.
+//!
+//! A builder that implements [`OairsRequest`] is sent like the crate's own: through the shared
+//! connection pool, with the API key as a bearer token, and with the API's error responses
+//! returned as an [`OairsError`] (so [`OairsError::is_transient`] and
+//! [`OairsError::retry_after`] work as usual). It also implements [`SendRequest`], so it can be
+//! sent with a [`RetryPolicy`](crate::retry::RetryPolicy) or by any other code that takes a
+//! `SendRequest`, and it's recorded or replayed inside a
+//! [`RecordReplay`](crate::record_replay::RecordReplay).
+//!
+//! # Example
+//! ```rust,no_run
+//! use oairs::http::{self, HttpMethod, OairsRequest};
+//!
+//! struct CreateAssistant {
+//!     key: String,
+//!     url: String,
+//!     body: serde_json::Value,
+//! }
+//!
+//! impl CreateAssistant {
+//!     fn new(client: &Client<Keyed>, model: &str) -> CreateAssistant {
+//!         CreateAssistant {
+//!             key: client.next_key(),
+//!             url: client.endpoints().custom("assistants").to_string(),
+//!             body: serde_json::json!({ "model": model }),
+//!         }
+//!     }
+//! }
+//!
+//! impl OairsRequest for CreateAssistant {
+//!     fn api_key(&self) -> &str {
+//!         &self.key
+//!     }
+//!
+//!     fn url(&self) -> &str {
+//!         &self.url
+//!     }
+//!
+//!     fn json(&self) -> Option<serde_json::Value> {
+//!         Some(self.body.clone())
+//!     }
+//! }
+//!
+//! let request = CreateAssistant::new(&client, "gpt-4");
+//! let res = RetryPolicy::new().send(&request).await?;
+//! let assistant: Assistant = http::parse_json(res).await?;
+//! ```
+
+use serde::de::DeserializeOwned;
+
+pub use crate::client::{HttpMethod, PreparedBody, PreparedRequest, SendRequest};
+use crate::{
+    client::{bearer, handle_request},
+    meta::deserialize_response,
+};
+
+use super::*;
+
+/// A request to an endpoint, described by its key, url, method, and body. Everything else,
+/// e.g. the headers, connection, and error handling, is the crate's. Implementing it also
+/// implements [`SendRequest`], whose `send` sends the request with [`send_request`].
+pub trait OairsRequest {
+    /// The API key, e.g. from [`Client::next_key`](crate::client::Client::next_key). It's sent
+    /// as a bearer token, with or without a `Bearer ` prefix.
+    fn api_key(&self) -> &str;
+
+    /// The url of the endpoint, e.g. from
+    /// [`Endpoints::custom`](crate::endpoints::Endpoints::custom) of the client's
+    /// [`endpoints`](crate::client::Client::endpoints), so the client's base url is used.
+    fn url(&self) -> &str;
+
+    /// Defaults to `POST`.
+    fn method(&self) -> HttpMethod {
+        HttpMethod::Post
+    }
+
+    /// The JSON body, if the request has one. Defaults to none.
+    fn json(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// The multipart form to send instead of a JSON body, e.g. to upload a file. It's built
+    /// for each attempt, since a form can only be sent once. Defaults to none.
+    fn form(&self) -> Result<Option<reqwest::multipart::Form>, OairsError> {
+        Ok(None)
+    }
+
+    /// Whether sending the request again after a failed attempt can't change or cost anything
+    /// the first attempt didn't, as for [`SendRequest::is_idempotent`]. Defaults to `true` for
+    /// `GET` and `DELETE` requests.
+    fn idempotent(&self) -> bool {
+        matches!(self.method(), HttpMethod::Get | HttpMethod::Delete)
+    }
+
+    /// The request that sending it would make, with the key redacted, e.g. for logging. A
+    /// form is shown without its fields, since they can't be read back.
+    fn dry_run(&self) -> PreparedRequest {
+        let body = match self.json() {
+            Some(json) => PreparedBody::Json(json),
+            None => PreparedBody::Empty,
+        };
+        PreparedRequest::new(&bearer(self.api_key()), self.url(), self.method(), body)
+    }
+}
+
+impl<R: OairsRequest + Sync> SendRequest for R {
+    async fn send(&self) -> Result<reqwest::Response, OairsError> {
+        let form = self.form()?;
+        send_request(self.api_key(), self.url(), self.method(), self.json(), form).await
+    }
+
+    fn is_idempotent(&self) -> bool {
+        self.idempotent()
+    }
+}
+
+/// Sends a request as the crate's builders do: with `api_key` as a bearer token (with or
+/// without a `Bearer ` prefix), and with a JSON body or a multipart form, if given. A response
+/// with a status other than `200 OK` is returned as an [`OairsError`] parsed from the API's
+/// error body.
+pub async fn send_request(
+    api_key: &str,
+    url: &str,
+    method: HttpMethod,
+    json: Option<serde_json::Value>,
+    form: Option<reqwest::multipart::Form>,
+) -> Result<reqwest::Response, OairsError> {
+    handle_request(&bearer(api_key), url, method, json, form).await
+}
+
+/// Deserializes the body of a successful response into `T`. Unlike `res.json::<T>()`, the
+/// error for a body that doesn't match `T` includes the status, the name of `T`, and the start
+/// of the body.
+pub async fn parse_json<T: DeserializeOwned>(res: reqwest::Response) -> Result<T, OairsError> {
+    deserialize_response(res).await
+}
+
+/// The [`OairsError`] for an error response, e.g. one received by sending a request without
+/// [`send_request`], parsed from the API's error body and headers (for
+/// [`retry_after`](OairsError::retry_after)).
+pub async fn parse_error(res: reqwest::Response) -> OairsError {
+    let status = res.status();
+    let headers = res.headers().clone();
+    match res.text().await {
+        Ok(body) => parse_api_error_body(&body, status, &headers),
+        Err(e) => parse_reqwest_error(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ListAssistants {
+        key: String,
+        url: String,
+    }
+
+    impl OairsRequest for ListAssistants {
+        fn api_key(&self) -> &str {
+            &self.key
+        }
+
+        fn url(&self) -> &str {
+            &self.url
+        }
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Get
+        }
+    }
+
+    #[test]
+    fn oairs_request() {
+        let client = crate::client::Client::new("sk-abcdefghijklmnop");
+        let request = ListAssistants {
+            key: client.next_key(),
+            url: client.endpoints().custom("assistants").to_string(),
+        };
+
+        assert!(SendRequest::is_idempotent(&request));
+        let prepared = request.dry_run();
+        assert_eq!(prepared.url, "https://api.openai.com/v1/assistants");
+        assert_eq!(prepared.body, PreparedBody::Empty);
+        assert!(prepared
+            .headers
+            .contains(&("Authorization".to_string(), "Bearer sk-...mnop".to_string())));
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        client::HttpMethod,
+        test_util::{fixtures::RATE_LIMIT_ERROR, MockApi, TEST_API_KEY},
+    };
+
+    #[tokio::test]
+    async fn third_party_request() {
+        use crate::http::{self, OairsRequest, SendRequest};
+
+        struct CreateAssistant {
+            key: String,
+            url: String,
+        }
+
+        impl OairsRequest for CreateAssistant {
+            fn api_key(&self) -> &str {
+                &self.key
+            }
+
+            fn url(&self) -> &str {
+                &self.url
+            }
+
+            fn json(&self) -> Option<serde_json::Value> {
+                Some(serde_json::json!({"model": "gpt-4"}))
+            }
+        }
+
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock(HttpMethod::Post, "/assistants", r#"{"id": "asst_abc"}"#)
+            .await;
+        let request = CreateAssistant {
+            key: client.next_key(),
+            url: client.endpoints().custom("assistants").to_string(),
+        };
+        let res = request.send().await.unwrap();
+        let assistant: serde_json::Value = http::parse_json(res).await.unwrap();
+        assert_eq!(assistant["id"], "asst_abc");
+
+        let received = &api.requests("/assistants").await[0];
+        let authorization = received.headers.get(&"authorization".into()).unwrap();
+        assert_eq!(
+            authorization.last().as_str(),
+            format!("Bearer {TEST_API_KEY}")
+        );
+        assert_eq!(
+            received.body_json::<serde_json::Value>().unwrap()["model"],
+            "gpt-4"
+        );
+
+        api.mock_error(HttpMethod::Post, "/threads", 429, RATE_LIMIT_ERROR)
+            .await;
+        let request = CreateAssistant {
+            key: client.next_key(),
+            url: client.endpoints().custom("threads").to_string(),
+        };
+        let error = request.send().await.unwrap_err();
+        assert!(error.is_rate_limit());
+    }
+}
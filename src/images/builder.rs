@@ -1,21 +1,28 @@
 // TODO: Clean up type-state pattern!
 
-use reqwest::multipart::Form;
+use reqwest::multipart::{Form, Part};
 
 use crate::{
-    client::{get_file_part, handle_request, HttpMethod},
-    images::response::ResponseFormat,
+    client::{
+        bearer, check_file, get_file_part, handle_request, HttpMethod, PreparedBody,
+        PreparedRequest,
+    },
+    images::{
+        response::ResponseFormat,
+        validation::{max_n, max_prompt_chars, supports_size, ImageIssue},
+    },
+    pricing::{self, Money},
 };
 
 use super::*;
 
 #[doc(hidden)]
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 struct ImageRequest {
     #[serde(skip)]
     key: String,
     #[serde(skip)]
-    url: String,
+    url: Cow<'static, str>,
 
     // Required for:
     //      .../images/generations
@@ -27,6 +34,8 @@ struct ImageRequest {
 
     // Following group is optional for all .../images/... endpoints
     #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<ImageModel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     n: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     size: Option<ImageSize>,
@@ -34,6 +43,8 @@ struct ImageRequest {
     response_format: Option<ResponseFormat>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<String>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 // impl_post!(ImageRequest);
@@ -44,25 +55,142 @@ impl ImageRequest {
     }
 }
 
+/// An image to upload with an edit or variation: the path of a PNG file, or a PNG image in
+/// memory.
+///
+/// Converts from a path (`&str` or `String`), from the bytes of a PNG image (`Vec<u8>` or
+/// `&[u8]`), and, with the `image` feature, from an `image::DynamicImage`, which is encoded to
+/// PNG. The API only accepts `RGBA`, `LA`, and `L` images, so a `DynamicImage` of any other
+/// color type is converted to `RGBA` first.
+///
+/// # Example
+/// ```rust,no_run
+/// let img = image::open("otter.png")?.resize(512, 512, FilterType::Lanczos3);
+/// let variation = client.create_image_variation(img).send().await?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ImageInput {
+    /// The path of a PNG file, which is read when the request is sent.
+    Path(String),
+    /// The bytes of a PNG image.
+    Png(Vec<u8>),
+}
+
+impl Default for ImageInput {
+    fn default() -> Self {
+        ImageInput::Path(String::new())
+    }
+}
+
+impl ImageInput {
+    pub fn is_in_memory(&self) -> bool {
+        matches!(self, ImageInput::Png(_))
+    }
+
+    // Checks that a file exists, for a dry run.
+    fn check(&self) -> Result<(), OairsError> {
+        match self {
+            ImageInput::Path(path) => check_file(path),
+            ImageInput::Png(_) => Ok(()),
+        }
+    }
+
+    // The value shown for the image in the form of a dry run.
+    fn form_value(&self) -> String {
+        match self {
+            ImageInput::Path(path) => path.clone(),
+            ImageInput::Png(bytes) => format!("<{} bytes of PNG>", bytes.len()),
+        }
+    }
+
+    fn part(&self, name: &str) -> Result<Part, OairsError> {
+        match self {
+            ImageInput::Path(path) => get_file_part(path),
+            ImageInput::Png(bytes) => Part::bytes(bytes.clone())
+                .file_name(format!("{name}.png"))
+                .mime_str("image/png")
+                .map_err(|e| OairsError::new(e.to_string(), ErrorType::ParamError, None, None)),
+        }
+    }
+}
+
+impl From<&str> for ImageInput {
+    fn from(path: &str) -> Self {
+        ImageInput::Path(path.to_string())
+    }
+}
+
+impl From<String> for ImageInput {
+    fn from(path: String) -> Self {
+        ImageInput::Path(path)
+    }
+}
+
+impl From<&String> for ImageInput {
+    fn from(path: &String) -> Self {
+        ImageInput::Path(path.clone())
+    }
+}
+
+impl From<Vec<u8>> for ImageInput {
+    fn from(png: Vec<u8>) -> Self {
+        ImageInput::Png(png)
+    }
+}
+
+impl From<&[u8]> for ImageInput {
+    fn from(png: &[u8]) -> Self {
+        ImageInput::Png(png.to_vec())
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::DynamicImage> for ImageInput {
+    fn from(img: image::DynamicImage) -> Self {
+        ImageInput::from(&img)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<&image::DynamicImage> for ImageInput {
+    fn from(img: &image::DynamicImage) -> Self {
+        use image::{ColorType, DynamicImage, ImageOutputFormat};
+
+        let rgba;
+        let img = match img.color() {
+            ColorType::L8 | ColorType::La8 | ColorType::Rgba8 => img,
+            _ => {
+                rgba = DynamicImage::ImageRgba8(img.to_rgba8());
+                &rgba
+            }
+        };
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageOutputFormat::Png)
+            .expect("an 8-bit L, LA, or RGBA image should encode to PNG in memory");
+        ImageInput::Png(png.into_inner())
+    }
+}
+
 // ========================== //
 //     Type State Trackers    //
 // ========================== //
 #[doc(hidden)]
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ImageGen {}
 #[doc(hidden)]
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ImageEdit {
     // file
-    image: String,
+    image: ImageInput,
     prompt: String,
     mask: Option<String>,
 }
 #[doc(hidden)]
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ImageVariation {
     // file
-    image: String,
+    image: ImageInput,
 }
 #[doc(hidden)]
 #[derive(Debug, Default, Serialize)]
@@ -78,10 +206,10 @@ impl BuildableImage for ImageVariation {}
 //        ImageBuilder        //
 // ========================== //
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct ImageBuilder<S> {
     key: String,
-    url: String,
+    url: Cow<'static, str>,
     // Fields common to all of the .../images/... endpoints
     // are stored here.
     state: Box<ImageRequest>,
@@ -89,16 +217,37 @@ pub struct ImageBuilder<S> {
     // other required and optional fields by .../edits and .../variations need
     // to be sent as a form, so we can store them in the state_data field
     state_data: S,
+    max_cost: Option<Money>,
 }
 
 #[allow(dead_code)]
 impl<S> ImageBuilder<S> {
-    /// The number of images to generate. Must be between `1` and `10`.
-    /// Panics if `n` is not in range.
+    // The url is stored on the `ImageRequest` for generations, which are sent as json, and on
+    // the builder for edits and variations, which are sent as a form.
+    pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+        self.state.url = url.clone();
+        self.url = url;
+        self
+    }
+
+    /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+    /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+    pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+        self.key = bearer(&key.into());
+        self.state.key = self.key.clone();
+        self
+    }
+
+    /// The [`ImageModel`] to use. If not set, the API uses its default, `dall-e-2`.
+    pub fn model(&mut self, model: ImageModel) -> &mut Self {
+        self.state.model = Some(model);
+        self
+    }
+
+    /// The number of images to generate: between `1` and `10`, or only `1` for `dall-e-3`.
+    /// An `n` out of range is reported by `validate`, and returned as a `ParamError` by `send`
+    /// and `dry_run`.
     pub fn n(&mut self, n: usize) -> &mut Self {
-        if !(1..=10).contains(&n) {
-            panic!("n must be between 1 and 10");
-        }
         self.state.n = Some(n);
         self
     }
@@ -127,6 +276,85 @@ impl<S> ImageBuilder<S> {
         self.state.user = Some(user);
         self
     }
+
+    /// Adds `key` to the request with `value`, as with
+    /// [`ChatBuilder::extra_param`](crate::completions::ChatBuilder::extra_param). Edits and
+    /// variations are sent as forms, so for them a string is sent as is, and any other value
+    /// as JSON.
+    pub fn extra_param<K: Into<String>>(&mut self, key: K, value: serde_json::Value) -> &mut Self {
+        self.state.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Refuse to send the request if its [`estimated_cost`](Self::estimated_cost) is over
+    /// `max_cost`, e.g. to guard against a large `n` by mistake. A request whose cost can't be
+    /// estimated is sent.
+    pub fn max_cost(&mut self, max_cost: Money) -> &mut Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    /// What the request is estimated to cost: the price of an image of its model and size in
+    /// the [`pricing`] table (`dall-e-2` and `1024x1024` if not set, as for the API), times
+    /// `n`. `None` if the table has no price for the model and size.
+    pub fn estimated_cost(&self) -> Option<Money> {
+        let model = self.state.model.clone().unwrap_or_default().to_string();
+        let size = self.state.size.clone().unwrap_or_default();
+        let price = pricing::image_price(&model, size.to_str())?;
+        Some(Money::from_usd(
+            price.usd() * self.state.n.unwrap_or(1) as f64,
+        ))
+    }
+
+    // The issues with the parameters, and with `prompt`, for a request that has one.
+    fn issues(&self, prompt: Option<&str>) -> Vec<ImageIssue> {
+        let model = self.state.model.as_ref();
+        let mut issues = Vec::new();
+        if let Some(prompt) = prompt {
+            let chars = prompt.chars().count();
+            if prompt.trim().is_empty() {
+                issues.push(ImageIssue::EmptyPrompt);
+            } else if let Some(max) = max_prompt_chars(model).filter(|&max| chars > max) {
+                issues.push(ImageIssue::PromptTooLong { chars, max });
+            }
+        }
+        if let Some(n) = self.state.n {
+            let max = max_n(model);
+            if !(1..=max).contains(&n) {
+                issues.push(ImageIssue::N { n, max });
+            }
+        }
+        if let Some(size) = &self.state.size {
+            if !supports_size(model, size) {
+                issues.push(ImageIssue::UnsupportedSize {
+                    size: size.clone(),
+                    model: model.map(|m| m.to_string()).unwrap_or_default(),
+                });
+            }
+        }
+        if let (Some(cost), Some(max)) = (self.estimated_cost(), self.max_cost) {
+            if cost > max {
+                issues.push(ImageIssue::OverMaxCost { cost, max });
+            }
+        }
+        issues
+    }
+
+    // The issues, as a `ParamError` listing them, with the parameter of the first.
+    fn check(&self, prompt: Option<&str>) -> Result<(), OairsError> {
+        let issues = self.issues(prompt);
+        let param = match issues.first() {
+            Some(issue) => issue.param().to_string(),
+            None => return Ok(()),
+        };
+        let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        Err(OairsError::new(
+            format!("Invalid parameters: {}", issues.join("; ")),
+            ErrorType::ParamError,
+            Some(param),
+            None,
+        ))
+    }
 }
 
 impl ImageBuilder<Keyed> {
@@ -138,7 +366,7 @@ impl ImageBuilder<Keyed> {
         ImageBuilder {
             state: Box::new(ImageRequest {
                 key: key.into(),
-                url: img_create_url(),
+                url: Endpoints::default().image_generations(),
                 prompt: Some(prompt.into()),
                 ..Default::default()
             }),
@@ -149,12 +377,12 @@ impl ImageBuilder<Keyed> {
     pub fn create_edit<K, I, P>(key: K, image: I, prompt: P) -> ImageBuilder<ImageEdit>
     where
         K: Into<String>,
-        I: Into<String>,
+        I: Into<ImageInput>,
         P: Into<String>,
     {
         ImageBuilder {
             key: key.into(),
-            url: img_edit_url(),
+            url: Endpoints::default().image_edits(),
             state_data: ImageEdit {
                 image: image.into(),
                 prompt: prompt.into(),
@@ -167,11 +395,11 @@ impl ImageBuilder<Keyed> {
     pub fn create_variation<K, I>(key: K, image: I) -> ImageBuilder<ImageVariation>
     where
         K: Into<String>,
-        I: Into<String>,
+        I: Into<ImageInput>,
     {
         ImageBuilder {
             key: key.into(),
-            url: img_variation_url(),
+            url: Endpoints::default().image_variations(),
             state_data: ImageVariation {
                 image: image.into(),
             },
@@ -181,38 +409,129 @@ impl ImageBuilder<Keyed> {
 }
 
 impl ImageBuilder<ImageGen> {
+    /// The JSON body that [`send`](Self::send) sends. Image edits and variations are sent as
+    /// multipart forms, so they have no JSON body.
+    pub fn to_request_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.state).unwrap()
+    }
+
+    /// Checks the prompt's length, `n`, the size, and the estimated cost against the model's
+    /// limits and the [`max_cost`](Self::max_cost), returning every issue at once, or an empty
+    /// `Vec` if there are none. [`send`](Self::send) and [`dry_run`](Self::dry_run) return the
+    /// issues as a `ParamError` before making the request.
+    pub fn validate(&self) -> Vec<ImageIssue> {
+        self.issues(self.state.prompt.as_deref())
+    }
+
+    /// The request that [`send`](Self::send) would make, without sending it.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        self.check(self.state.prompt.as_deref())?;
+        Ok(PreparedRequest::new(
+            &self.state.key,
+            &self.state.url,
+            HttpMethod::Post,
+            PreparedBody::Json(self.to_request_json()),
+        ))
+    }
+
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
+        self.check(self.state.prompt.as_deref())?;
         self.state.send().await
     }
 }
 
+impl<S> ImageBuilder<S> {
+    // The optional text fields of an edit or variation form.
+    fn form_fields(&self) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        if let Some(m) = self.state.model.clone() {
+            fields.push(("model", m.to_string()));
+        }
+        if let Some(n) = self.state.n {
+            fields.push(("n", n.to_string()));
+        }
+        if let Some(s) = self.state.size.clone() {
+            fields.push(("size", s.to_string()));
+        }
+        if let Some(r) = self.state.response_format.clone() {
+            fields.push(("response_format", r.to_string()));
+        }
+        if let Some(u) = self.state.user.clone() {
+            fields.push(("user", u));
+        }
+        let mut fields: Vec<(String, String)> = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        for (key, value) in &self.state.extra {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            fields.push((key.clone(), value));
+        }
+        fields
+    }
+
+    fn prepare_form(&self, fields: Vec<(&'static str, String)>) -> PreparedRequest {
+        let mut fields: Vec<(String, String)> = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        fields.extend(self.form_fields());
+        PreparedRequest::new(
+            &self.key,
+            &self.url,
+            HttpMethod::Post,
+            PreparedBody::Form(fields),
+        )
+    }
+}
+
 impl ImageBuilder<ImageEdit> {
+    /// The image to edit.
+    pub fn image(&self) -> &ImageInput {
+        &self.state_data.image
+    }
+
+    /// Checks the prompt's length, `n`, and the estimated cost, as for a generation (see
+    /// [`ImageBuilder::<ImageGen>::validate`]).
+    pub fn validate(&self) -> Vec<ImageIssue> {
+        self.issues(Some(&self.state_data.prompt))
+    }
+
+    /// Checks the parameters (see [`validate`](Self::validate)) and that the image (and mask,
+    /// if set) exists, and returns the request that [`send`](Self::send) would make, without
+    /// reading the files or sending anything. An in-memory image is shown by its size.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        self.check(Some(&self.state_data.prompt))?;
+        self.state_data.image.check()?;
+        let mut fields = vec![
+            ("image", self.state_data.image.form_value()),
+            ("prompt", self.state_data.prompt.clone()),
+        ];
+        if let Some(m) = self.state_data.mask.clone() {
+            check_file(&m)?;
+            fields.push(("mask", m));
+        }
+        Ok(self.prepare_form(fields))
+    }
+
     /// Executes a `POST` request, submitting a `form` to the API.
     pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let file_part = get_file_part(&self.state_data.image)?;
+        self.check(Some(&self.state_data.prompt))?;
+        let file_part = self.state_data.image.part("image")?;
 
         let mut form = Form::new()
             .part("image", file_part)
             .text("prompt", self.state_data.prompt.clone());
 
-        if let Some(n) = self.state.n {
-            form = form.text("n", n.to_string());
-        }
-
         if let Some(m) = self.state_data.mask.clone() {
             form = form.text("mask", m);
         }
 
-        if let Some(s) = self.state.size.clone() {
-            form = form.text("size", s.to_string());
-        }
-
-        if let Some(r) = self.state.response_format.clone() {
-            form = form.text("response_format", r.to_string());
-        }
-
-        if let Some(u) = self.state.user.clone() {
-            form = form.text("user", u);
+        for (name, value) in self.form_fields() {
+            form = form.text(name, value);
         }
 
         handle_request(&self.key, &self.url, HttpMethod::Post, None, Some(form)).await
@@ -220,33 +539,42 @@ impl ImageBuilder<ImageEdit> {
 }
 
 impl ImageBuilder<ImageVariation> {
-    /// Executes a `POST` request, submitting a `form` to the API.
-    pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-        let file_part = get_file_part(&self.state_data.image)?;
+    /// The image to vary.
+    pub fn image(&self) -> &ImageInput {
+        &self.state_data.image
+    }
 
-        let mut form = Form::new().part("image", file_part);
+    /// Checks `n` and the estimated cost, as for a generation (see
+    /// [`ImageBuilder::<ImageGen>::validate`]).
+    pub fn validate(&self) -> Vec<ImageIssue> {
+        self.issues(None)
+    }
 
-        if let Some(n) = self.state.n {
-            form = form.text("n", n.to_string());
-        }
+    /// Checks the parameters (see [`validate`](Self::validate)) and that the image exists, and
+    /// returns the request that [`send`](Self::send) would make, without reading the file or
+    /// sending anything. An in-memory image is shown by its size.
+    pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+        self.check(None)?;
+        self.state_data.image.check()?;
+        Ok(self.prepare_form(vec![("image", self.state_data.image.form_value())]))
+    }
 
-        if let Some(s) = self.state.size.clone() {
-            form = form.text("size", s.to_string());
-        }
+    /// Executes a `POST` request, submitting a `form` to the API.
+    pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
+        self.check(None)?;
+        let file_part = self.state_data.image.part("image")?;
 
-        if let Some(r) = self.state.response_format.clone() {
-            form = form.text("response_format", r.to_string());
-        }
+        let mut form = Form::new().part("image", file_part);
 
-        if let Some(u) = self.state.user.clone() {
-            form = form.text("user", u);
+        for (name, value) in self.form_fields() {
+            form = form.text(name, value);
         }
 
         handle_request(&self.key, &self.url, HttpMethod::Post, None, Some(form)).await
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
 pub enum ImageSize {
     /// 256x256
     Small,
@@ -281,3 +609,127 @@ impl ImageSize {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_request_json() {
+        let mut builder = ImageBuilder::create_image("key", "A cute baby sea otter");
+        builder.n(2).size(ImageSize::Small);
+
+        assert_eq!(
+            builder.to_request_json(),
+            serde_json::json!({"prompt": "A cute baby sea otter", "n": 2, "size": "256x256"})
+        );
+
+        builder.model(ImageModel::DallE3);
+        assert_eq!(builder.to_request_json()["model"], "dall-e-3");
+        let variation = ImageBuilder::create_variation("key", "otter.png")
+            .model(ImageModel::DallE2)
+            .extra_param("quality", serde_json::json!("hd"))
+            .extra_param("seed", serde_json::json!(7))
+            .prepare_form(vec![]);
+        assert_eq!(
+            variation.body,
+            PreparedBody::Form(vec![
+                ("model".to_string(), "dall-e-2".to_string()),
+                ("quality".to_string(), "hd".to_string()),
+                ("seed".to_string(), "7".to_string()),
+            ])
+        );
+
+        let png: &[u8] = b"\x89PNG\r\n\x1a\n";
+        let variation = ImageBuilder::create_variation("key", png);
+        assert!(variation.image().is_in_memory());
+        assert_eq!(
+            variation.dry_run().unwrap().body,
+            PreparedBody::Form(vec![("image".to_string(), "<8 bytes of PNG>".to_string())])
+        );
+    }
+
+    #[test]
+    fn validate_image_request() {
+        let mut builder = ImageBuilder::create_image("key", "a".repeat(1001));
+        builder.n(12);
+        assert_eq!(
+            builder.validate(),
+            vec![
+                ImageIssue::PromptTooLong {
+                    chars: 1001,
+                    max: 1000
+                },
+                ImageIssue::N { n: 12, max: 10 },
+            ]
+        );
+        let error = builder.dry_run().unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("prompt"));
+        assert_eq!(
+            error.message,
+            "Invalid parameters: the prompt (1001 characters) must be at most 1000 characters; \
+             n (12) must be between 1 and 10"
+        );
+
+        builder
+            .model(ImageModel::DallE3)
+            .n(2)
+            .size(ImageSize::Small);
+        assert_eq!(
+            builder.validate(),
+            vec![
+                ImageIssue::N { n: 2, max: 1 },
+                ImageIssue::UnsupportedSize {
+                    size: ImageSize::Small,
+                    model: "dall-e-3".to_string()
+                },
+            ]
+        );
+
+        let mut builder = ImageBuilder::create_image("key", "A cute baby sea otter");
+        builder.n(3).size(ImageSize::Medium);
+        assert_eq!(builder.estimated_cost().unwrap().to_string(), "$0.054");
+        assert!(builder.dry_run().is_ok());
+        builder.max_cost(Money::from_usd(0.05));
+        assert_eq!(
+            builder.dry_run().unwrap_err().param.as_deref(),
+            Some("max_cost")
+        );
+        builder.model(ImageModel::Other("dall-e-4".to_string()));
+        assert_eq!(builder.estimated_cost(), None);
+        assert!(builder.validate().is_empty());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn dynamic_image_input() {
+        let img = image::DynamicImage::new_rgb8(2, 2);
+        let png = match ImageInput::from(img) {
+            ImageInput::Png(png) => png,
+            other => panic!("{other:?}"),
+        };
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!(decoded.color(), image::ColorType::Rgba8);
+        assert_eq!((decoded.width(), decoded.height()), (2, 2));
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{images::response::Image, test_util::MockApi};
+
+    #[tokio::test]
+    async fn in_memory_image() {
+        let api = MockApi::start().await;
+        let client = api.client();
+
+        let png = b"\x89PNG\r\n\x1a\nnot really a png".to_vec();
+        let variation = client.create_image_variation(png).send().await.unwrap();
+        variation.json::<Image>().await.unwrap();
+
+        let body = String::from_utf8_lossy(&api.requests("/images/variations").await[0].body)
+            .to_lowercase();
+        assert!(body.contains("filename=\"image.png\"\r\ncontent-type: image/png"));
+        assert!(body.contains("not really a png"));
+    }
+}
@@ -1,6 +1,8 @@
 mod builder;
 pub mod response;
+mod validation;
 
 pub use builder::*;
+pub use validation::*;
 
 use super::*;
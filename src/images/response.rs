@@ -33,10 +33,10 @@ impl ResponseFormat {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
 pub struct Image {
-    created: u64,
-    data: Vec<FormattedImage>,
+    pub created: u64,
+    pub data: Vec<FormattedImage>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,85 @@
+//! Contains the [`ImageIssue`]s reported by `ImageBuilder::validate`.
+
+use crate::pricing::Money;
+
+use super::*;
+
+/// A parameter of an image request that the API rejects, or that's over the builder's
+/// [`max_cost`](ImageBuilder::max_cost), found by `ImageBuilder::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageIssue {
+    /// The prompt is empty or only whitespace.
+    EmptyPrompt,
+    /// The prompt is longer than the model accepts: 1,000 characters for `dall-e-2`, and
+    /// 4,000 for `dall-e-3`.
+    PromptTooLong { chars: usize, max: usize },
+    /// `n` is 0, or more than the model generates at once: 10 for `dall-e-2`, and 1 for
+    /// `dall-e-3`.
+    N { n: usize, max: usize },
+    /// The model doesn't generate images of the size, e.g. `dall-e-3` with
+    /// [`ImageSize::Small`].
+    UnsupportedSize { size: ImageSize, model: String },
+    /// The [`estimated_cost`](ImageBuilder::estimated_cost) is over the
+    /// [`max_cost`](ImageBuilder::max_cost).
+    OverMaxCost { cost: Money, max: Money },
+}
+
+impl std::fmt::Display for ImageIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageIssue::EmptyPrompt => write!(f, "the prompt is empty"),
+            ImageIssue::PromptTooLong { chars, max } => write!(
+                f,
+                "the prompt ({chars} characters) must be at most {max} characters"
+            ),
+            ImageIssue::N { n, max: 1 } => write!(f, "n ({n}) must be 1"),
+            ImageIssue::N { n, max } => write!(f, "n ({n}) must be between 1 and {max}"),
+            ImageIssue::UnsupportedSize { size, model } => {
+                write!(f, "{model} doesn't generate {size} images")
+            }
+            ImageIssue::OverMaxCost { cost, max } => {
+                write!(f, "the estimated cost ({cost}) is over max_cost ({max})")
+            }
+        }
+    }
+}
+
+impl ImageIssue {
+    /// The parameter at fault.
+    pub fn param(&self) -> &'static str {
+        match self {
+            ImageIssue::EmptyPrompt | ImageIssue::PromptTooLong { .. } => "prompt",
+            ImageIssue::N { .. } => "n",
+            ImageIssue::UnsupportedSize { .. } => "size",
+            ImageIssue::OverMaxCost { .. } => "max_cost",
+        }
+    }
+}
+
+// The most characters `model` accepts in a prompt, or `None` for a model it doesn't know. The
+// API's default model is `dall-e-2`.
+pub(super) fn max_prompt_chars(model: Option<&ImageModel>) -> Option<usize> {
+    match model {
+        None | Some(ImageModel::DallE2) => Some(1000),
+        Some(ImageModel::DallE3) => Some(4000),
+        Some(ImageModel::Other(_)) => None,
+    }
+}
+
+// The most images `model` generates for one request.
+pub(super) fn max_n(model: Option<&ImageModel>) -> usize {
+    match model {
+        Some(ImageModel::DallE3) => 1,
+        _ => 10,
+    }
+}
+
+pub(super) fn supports_size(model: Option<&ImageModel>, size: &ImageSize) -> bool {
+    !matches!(
+        (model, size),
+        (
+            Some(ImageModel::DallE3),
+            ImageSize::Small | ImageSize::Medium
+        )
+    )
+}
@@ -0,0 +1,126 @@
+//! Contains the generic [`List`] struct for the `{"object": "list", "data": [...]}` responses
+//! of the list endpoints. The list types of each module ([`FileList`](crate::files::response::FileList),
+//! [`ModelsList`](crate::models::response::ModelsList),
+//! [`FineTunesList`](crate::fine_tunes::response::FineTunesList), and
+//! [`EventList`](crate::fine_tunes::response::EventList)) are aliases of it.
+
+use serde::de::DeserializeOwned;
+
+use super::*;
+
+/// A list of objects returned by a list endpoint. The pagination fields (`has_more`, `first_id`,
+/// and `last_id`) are only present when the endpoint is paginated, so they are all optional and
+/// left out when serializing if absent.
+///
+/// # Example
+/// ```rust,no_run
+/// let files = client.list_files().send().await?.json::<FileList>().await?;
+/// for file in &files {
+///     println!("{}: {}", file.id, file.filename);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct List<T> {
+    pub object: String, // Will be "list"
+    pub data: Vec<T>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_id: Option<String>,
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        List {
+            object: "list".to_string(),
+            data: Vec::new(),
+            has_more: None,
+            first_id: None,
+            last_id: None,
+        }
+    }
+}
+
+impl<T> List<T> {
+    /// Whether there is another page to request. `false` if the API didn't include the
+    /// `has_more` field.
+    pub fn has_more(&self) -> bool {
+        self.has_more.unwrap_or(false)
+    }
+
+    /// The id to pass as the `after` cursor to request the next page, if the API included it.
+    pub fn last_id(&self) -> Option<&str> {
+        self.last_id.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+}
+
+impl<T> From<Vec<T>> for List<T> {
+    fn from(data: Vec<T>) -> Self {
+        List {
+            data,
+            ..Default::default()
+        }
+    }
+}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+// Implemented by hand since the derives would require `T: Serialize` / `T: DeserializeOwned`
+// of every `T`.
+impl<T: Serialize> SaveJson for List<T> {}
+
+impl<T: DeserializeOwned> LoadJson for List<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_pagination_fields() {
+        let list: List<u32> = serde_json::from_str(r#"{"object":"list","data":[1,2,3]}"#).unwrap();
+        assert!(!list.has_more());
+        assert_eq!(list.last_id(), None);
+        assert_eq!(
+            serde_json::to_string(&list).unwrap(),
+            r#"{"object":"list","data":[1,2,3]}"#
+        );
+
+        let list: List<u32> = serde_json::from_str(
+            r#"{"object":"list","data":[1],"has_more":true,"first_id":"a","last_id":"b"}"#,
+        )
+        .unwrap();
+        assert!(list.has_more());
+        assert_eq!(list.last_id(), Some("b"));
+        assert_eq!(list.into_iter().sum::<u32>(), 1);
+    }
+}
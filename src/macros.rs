@@ -1,6 +1,5 @@
 use super::*;
 
-// TODO: Remove the following to macros and use `handle_request` method instead.
 #[allow(unused_macros)]
 macro_rules! impl_del {
     ($typ:ident < $( $gen:tt ),+ >) => {
@@ -8,15 +7,14 @@ macro_rules! impl_del {
             /// Executes the `DELETE` request. Returns a `Result` with either a `reqwest::Response` or an
             /// `OairsError`.
             pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-                let client = match build_client(&self.key) {
-                    Ok(c) => c,
-                    Err(e) => return Err(e),
-                };
-
-                match client.delete(self.url.to_string()).send().await {
-                    Ok(r) => Ok(r),
-                    Err(e) => Err(parse_reqwest_error(e)),
-                }
+                crate::client::handle_request(
+                    &self.key,
+                    &self.url,
+                    crate::client::HttpMethod::Delete,
+                    None,
+                    None,
+                )
+                .await
             }
         }
     };
@@ -30,21 +28,24 @@ macro_rules! impl_get {
             /// Executes the `GET` request. Returns a `Result` with either a `reqwest::Response` or an
             /// `OairsError`.
             pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-                let client = match build_client(&self.key) {
-                    Ok(c) => c,
-                    Err(e) => return Err(e),
-                };
-
-                let response = match client.get(self.url.to_string()).send().await {
-                    Ok(r) => r,
-                    Err(e) => return Err(parse_reqwest_error(e)),
-                };
-
-                let sc = response.status();
-                match sc {
-                    reqwest::StatusCode::OK => Ok(response),
-                    _ => Err(parse_api_error(response, sc).await),
-                }
+                crate::client::handle_request(
+                    &self.key,
+                    &self.url,
+                    crate::client::HttpMethod::Get,
+                    None,
+                    None,
+                )
+                .await
+            }
+
+            /// The request that [`send`](Self::send) would make, without sending it.
+            pub fn dry_run(&self) -> Result<crate::client::PreparedRequest, OairsError> {
+                Ok(crate::client::PreparedRequest::new(
+                    &self.key,
+                    &self.url,
+                    crate::client::HttpMethod::Get,
+                    crate::client::PreparedBody::Empty,
+                ))
             }
         }
     };
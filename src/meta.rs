@@ -0,0 +1,393 @@
+//! Contains [`ResponseMeta`], the metadata the API returns in the headers of a response, such
+//! as the request id that OpenAI support asks for when debugging a request.
+
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+
+use super::*;
+
+/// The metadata of a response, read from its headers. Returned alongside the deserialized
+/// response by the `send_with_meta` method of the builders, e.g.
+/// [`ChatBuilder::send_with_meta`](crate::completions::ChatBuilder::send_with_meta).
+///
+/// # Example
+/// ```rust,no_run
+/// let (completion, meta) = client
+///     .chat_completion(model, &messages)
+///     .send_with_meta()
+///     .await?;
+/// println!("{:?} took {:?}ms", meta.request_id, meta.processing_ms);
+/// if let Some(overhead) = meta.overhead() {
+///     println!("{overhead:?} of that wasn't spent by the API");
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseMeta {
+    /// The `x-request-id` header.
+    pub request_id: Option<String>,
+    /// The `openai-processing-ms` header: how long the API took to process the request.
+    pub processing_ms: Option<u64>,
+    /// The `openai-model` header: the model that served the request.
+    pub model_header: Option<String>,
+    pub rate_limits: RateLimits,
+    /// How long the request took, as measured by the client. `None` for a response that wasn't
+    /// sent by this crate, e.g. one returned from a [`ResponseCache`](crate::cache::ResponseCache).
+    pub timing: Option<RequestTiming>,
+    /// The deprecation and warning headers of the response, e.g. for a model that is being
+    /// retired. With the `tracing` feature, they're also logged as each response arrives.
+    #[serde(default)]
+    pub warnings: Vec<ApiWarning>,
+}
+
+/// A deprecation or warning header of a response, read by [`ResponseMeta::from_headers`].
+///
+/// The `deprecation` and `sunset` headers (RFC 8594), the `warning` header (RFC 7234), and any
+/// header ending in `-warning` or `-deprecation`, e.g. `openai-warning`, are read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiWarning {
+    /// The name of the header, in lowercase.
+    pub header: String,
+    /// The value of the header. For a `warning` header, only its quoted text, e.g. `The model
+    /// is deprecated` for `299 - "The model is deprecated"`.
+    pub message: String,
+}
+
+impl ApiWarning {
+    /// Whether the header announces a deprecation or removal (`deprecation`, `sunset`, or a
+    /// `-deprecation` header), rather than a general warning.
+    pub fn is_deprecation(&self) -> bool {
+        self.header == "sunset" || self.header.ends_with("deprecation")
+    }
+
+    /// The warnings among `headers`.
+    pub fn from_headers(headers: &HeaderMap) -> Vec<ApiWarning> {
+        headers
+            .iter()
+            .filter(|(name, _)| {
+                let name = name.as_str();
+                matches!(name, "deprecation" | "sunset" | "warning")
+                    || name.ends_with("-warning")
+                    || name.ends_with("-deprecation")
+            })
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?.trim();
+                let message = match name.as_str() {
+                    "warning" => warning_text(value),
+                    _ => value,
+                };
+                Some(ApiWarning {
+                    header: name.as_str().to_string(),
+                    message: message.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for ApiWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.header, self.message)
+    }
+}
+
+// The text of a `warning` header, e.g. `299 - "text"`, or the whole value if it isn't quoted.
+fn warning_text(value: &str) -> &str {
+    match (value.find('"'), value.rfind('"')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value,
+    }
+}
+
+/// How long the parts of a request took, as measured around the request by the client.
+///
+/// `reqwest` doesn't report when the connection was established, so connecting (including the
+/// TLS handshake) is part of `ttfb`. Compare `ttfb` with
+/// [`ResponseMeta::processing_ms`] (see [`ResponseMeta::overhead`]) to separate the API's
+/// latency from the network's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestTiming {
+    /// How long looking up the API's host took. `None` if there was no lookup, e.g. for a base
+    /// url with an IP address.
+    pub dns: Option<Duration>,
+    /// From sending the request until the headers of the response arrived.
+    pub ttfb: Duration,
+    /// From sending the request until the whole body was read. `None` if the body wasn't read
+    /// when the meta was taken, e.g. with [`ResponseMeta::from_response`].
+    pub total: Option<Duration>,
+}
+
+// Stored in the extensions of a response sent by `handle_request`, for `with_meta` to finish
+// timing once the body is read.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TimingStart {
+    pub(crate) started: Instant,
+    pub(crate) timing: RequestTiming,
+}
+
+/// The `x-ratelimit-*` headers of a response.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    pub limit_requests: Option<u64>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// How long until the requests limit is fully replenished.
+    pub reset_requests: Option<Duration>,
+    /// How long until the tokens limit is fully replenished.
+    pub reset_tokens: Option<Duration>,
+}
+
+impl ResponseMeta {
+    /// The meta of a response returned by a builder's `send`, with its
+    /// [`timing`](Self::timing) up to the headers.
+    pub fn from_response(res: &reqwest::Response) -> ResponseMeta {
+        ResponseMeta {
+            #[cfg(not(target_arch = "wasm32"))]
+            timing: res.extensions().get::<TimingStart>().map(|t| t.timing),
+            ..ResponseMeta::from_headers(res.headers())
+        }
+    }
+
+    /// The part of the time to the response's headers that wasn't spent processing the request
+    /// at the API: the time to first byte less `processing_ms`. `None` without both.
+    pub fn overhead(&self) -> Option<Duration> {
+        let ttfb = self.timing?.ttfb;
+        Some(ttfb.saturating_sub(Duration::from_millis(self.processing_ms?)))
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> ResponseMeta {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let number = |name: &str| header(name).and_then(|s| s.trim().parse::<u64>().ok());
+        let duration = |name: &str| header(name).and_then(parse_reset_duration);
+
+        ResponseMeta {
+            request_id: header("x-request-id").map(String::from),
+            processing_ms: number("openai-processing-ms"),
+            model_header: header("openai-model").map(String::from),
+            rate_limits: RateLimits {
+                limit_requests: number("x-ratelimit-limit-requests"),
+                limit_tokens: number("x-ratelimit-limit-tokens"),
+                remaining_requests: number("x-ratelimit-remaining-requests"),
+                remaining_tokens: number("x-ratelimit-remaining-tokens"),
+                reset_requests: duration("x-ratelimit-reset-requests"),
+                reset_tokens: duration("x-ratelimit-reset-tokens"),
+            },
+            timing: None,
+            warnings: ApiWarning::from_headers(headers),
+        }
+    }
+}
+
+/// How much of the body is included in the error when a response can't be deserialized.
+pub(crate) const BODY_SNIPPET_LEN: usize = 512;
+
+/// Reads the [`ResponseMeta`] of a successful response, then deserializes its body into `T`,
+/// finishing the meta's timing once the body is read.
+pub(crate) async fn with_meta<T: DeserializeOwned>(
+    res: reqwest::Response,
+) -> Result<(T, ResponseMeta), OairsError> {
+    let meta = ResponseMeta::from_headers(res.headers());
+    #[cfg(not(target_arch = "wasm32"))]
+    let start = res.extensions().get::<TimingStart>().copied();
+    let parsed = deserialize_response(res).await?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let meta = ResponseMeta {
+        timing: start.map(|start| RequestTiming {
+            total: Some(start.started.elapsed()),
+            ..start.timing
+        }),
+        ..meta
+    };
+    Ok((parsed, meta))
+}
+
+/// Deserializes the body of `res` into `T`. Unlike `res.json::<T>()`, the error for a body that
+/// doesn't match `T` (e.g. after a change to the API's schema) includes the status, the name of
+/// `T`, and the start of the body, along with serde's message.
+pub(crate) async fn deserialize_response<T: DeserializeOwned>(
+    res: reqwest::Response,
+) -> Result<T, OairsError> {
+    let status = res.status();
+    let body = match res.bytes().await {
+        Ok(body) => body,
+        Err(e) => return Err(parse_reqwest_error(e)),
+    };
+    parse_body(status, &body)
+}
+
+/// Deserializes a response's `body` into `T`, as [`deserialize_response`] does.
+pub(crate) fn parse_body<T: DeserializeOwned>(
+    status: reqwest::StatusCode,
+    body: &[u8],
+) -> Result<T, OairsError> {
+    serde_json::from_slice(body).map_err(|e| {
+        let snippet = String::from_utf8_lossy(&body[..body.len().min(BODY_SNIPPET_LEN)]);
+        let truncated = match body.len() > BODY_SNIPPET_LEN {
+            true => "...",
+            false => "",
+        };
+        let mut error = OairsError::new(
+            format!(
+                "Unable to deserialize {} response into {}: {}. Body: {}{}",
+                status,
+                std::any::type_name::<T>(),
+                e,
+                snippet,
+                truncated
+            ),
+            ErrorType::DeserializationError,
+            None,
+            None,
+        );
+        error.status = Some(status.as_u16());
+        error
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_meta_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "req-123".parse().unwrap());
+        headers.insert("openai-processing-ms", "250".parse().unwrap());
+        headers.insert("openai-model", "gpt-3.5-turbo-0301".parse().unwrap());
+        headers.insert("x-ratelimit-limit-requests", "3500".parse().unwrap());
+        headers.insert("x-ratelimit-remaining-tokens", "89000".parse().unwrap());
+        headers.insert("x-ratelimit-reset-tokens", "6m0.5s".parse().unwrap());
+
+        let meta = ResponseMeta::from_headers(&headers);
+        assert_eq!(meta.request_id.as_deref(), Some("req-123"));
+        assert_eq!(meta.processing_ms, Some(250));
+        assert_eq!(meta.model_header.as_deref(), Some("gpt-3.5-turbo-0301"));
+        assert_eq!(meta.rate_limits.limit_requests, Some(3500));
+        assert_eq!(meta.rate_limits.limit_tokens, None);
+        assert_eq!(meta.rate_limits.remaining_tokens, Some(89000));
+        assert_eq!(
+            meta.rate_limits.reset_tokens,
+            Some(Duration::from_secs_f64(360.5))
+        );
+        assert!(meta.warnings.is_empty());
+        assert_eq!(
+            ResponseMeta::from_headers(&HeaderMap::new()),
+            ResponseMeta::default()
+        );
+    }
+
+    #[test]
+    fn api_warnings() {
+        let mut headers = HeaderMap::new();
+        headers.insert("sunset", "Wed, 04 Jan 2024 00:00:00 GMT".parse().unwrap());
+        headers.insert(
+            "warning",
+            r#"299 - "text-davinci-003 is deprecated""#.parse().unwrap(),
+        );
+        headers.insert(
+            "openai-warning",
+            "Use gpt-3.5-turbo-instruct".parse().unwrap(),
+        );
+        headers.insert("x-request-id", "req-123".parse().unwrap());
+
+        let warnings = ResponseMeta::from_headers(&headers).warnings;
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].is_deprecation());
+        assert_eq!(warnings[1].message, "text-davinci-003 is deprecated");
+        assert!(!warnings[1].is_deprecation());
+        assert_eq!(
+            warnings[2].to_string(),
+            "openai-warning: Use gpt-3.5-turbo-instruct"
+        );
+    }
+
+    #[tokio::test]
+    async fn deserialize_response_error() {
+        let response = |body: String| reqwest::Response::from(::http::Response::new(body));
+
+        let map: HashMap<String, String> = deserialize_response(response(r#"{"a": "b"}"#.into()))
+            .await
+            .unwrap();
+        assert_eq!(map["a"], "b");
+
+        let body = format!(r#"{{"a": 1, "padding": "{}"}}"#, "x".repeat(600));
+        let error = deserialize_response::<HashMap<String, String>>(response(body))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.error_type,
+            ErrorType::DeserializationError.to_string()
+        );
+        assert_eq!(error.status, Some(200));
+        assert!(error
+            .message
+            .contains("200 OK response into std::collections::hash::map::HashMap<"));
+        assert!(error.message.contains(r#"Body: {"a": 1, "padding": "xxx"#));
+        assert!(error.message.ends_with("xxx..."));
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        completions::{Messages, Msg},
+        test_util::{
+            fixtures::CHAT_COMPLETION,
+            wiremock::{
+                matchers::{method, path},
+                Mock, ResponseTemplate,
+            },
+            MockApi,
+        },
+    };
+
+    #[tokio::test]
+    async fn send_with_meta() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(CHAT_COMPLETION.as_bytes(), "application/json")
+                    .insert_header("x-request-id", "req-123")
+                    .insert_header("openai-processing-ms", "250"),
+            )
+            .mount(api.server())
+            .await;
+
+        let dir = std::env::temp_dir().join(format!("oairs-raw-chat-{}", std::process::id()));
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let (completion, meta) = client
+            .chat_completion(crate::ChatModel::default(), &messages)
+            .save_raw_to(&dir)
+            .send_with_meta()
+            .await
+            .unwrap();
+        assert_eq!(
+            completion.response_message().content(),
+            "This is a test response."
+        );
+        assert_eq!(meta.request_id.as_deref(), Some("req-123"));
+        assert_eq!(meta.processing_ms, Some(250));
+        // The mock server is on 127.0.0.1, so there's no DNS lookup.
+        let timing = meta.timing.unwrap();
+        assert_eq!(timing.dns, None);
+        assert!(timing.total.unwrap() >= timing.ttfb);
+        assert_eq!(meta.overhead(), Some(std::time::Duration::ZERO));
+
+        let saved = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+        let raw = std::fs::read_to_string(saved.path()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(saved
+            .file_name()
+            .to_string_lossy()
+            .ends_with("_req-123.json"));
+        assert_eq!(raw, CHAT_COMPLETION);
+    }
+}
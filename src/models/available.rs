@@ -0,0 +1,122 @@
+//! Contains [`AvailableModels`], the models a key can access, sorted by endpoint.
+
+use super::*;
+
+/// The ids of the models a key can access, as listed by the models endpoint, partitioned into
+/// the model enums. An id goes to the first enum (in the order of the fields) with a variant
+/// for it; ids without a variant in any enum, e.g. fine-tuned models or models released after
+/// this version of the crate, are kept as strings in `other`.
+///
+/// Returned by [`Client::available_models`](crate::client::Client::available_models).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AvailableModels {
+    pub chat: Vec<ChatModel>,
+    pub completion: Vec<CompletionModel>,
+    pub edit: Vec<EditModel>,
+    pub embedding: Vec<EmbeddingModel>,
+    pub moderation: Vec<ModerationModel>,
+    pub image: Vec<ImageModel>,
+    pub audio: Vec<AudioModel>,
+    pub other: Vec<String>,
+}
+
+impl AvailableModels {
+    pub fn from_ids<I, S>(ids: I) -> AvailableModels
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut models = AvailableModels::default();
+        for id in ids {
+            let id = id.as_ref();
+            if let Some(model) = ChatModel::ALL.iter().find(|m| m.to_str() == id) {
+                models.chat.push(model.clone());
+            } else if let Some(model) = CompletionModel::ALL.iter().find(|m| m.to_str() == id) {
+                models.completion.push(model.clone());
+            } else if let Some(model) = EditModel::ALL.iter().find(|m| m.to_str() == id) {
+                models.edit.push(model.clone());
+            } else if let Some(model) = EmbeddingModel::ALL.iter().find(|m| m.to_str() == id) {
+                models.embedding.push(model.clone());
+            } else if let Some(model) = ModerationModel::ALL.iter().find(|m| m.to_str() == id) {
+                models.moderation.push(model.clone());
+            } else if let Some(model) = ImageModel::ALL.iter().find(|m| m.to_str() == id) {
+                models.image.push(model.clone());
+            } else if let Some(model) = AudioModel::ALL.iter().find(|m| m.to_str() == id) {
+                models.audio.push(model.clone());
+            } else {
+                models.other.push(id.to_string());
+            }
+        }
+        models
+    }
+
+    /// Whether `id` is among the available models.
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids().any(|available| available == id)
+    }
+
+    /// The ids of all of the available models.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.chat
+            .iter()
+            .map(|m| m.to_str())
+            .chain(self.completion.iter().map(|m| m.to_str()))
+            .chain(self.edit.iter().map(|m| m.to_str()))
+            .chain(self.embedding.iter().map(|m| m.to_str()))
+            .chain(self.moderation.iter().map(|m| m.to_str()))
+            .chain(self.image.iter().map(|m| m.to_str()))
+            .chain(self.audio.iter().map(|m| m.to_str()))
+            .chain(self.other.iter().map(|id| id.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn available_models_from_ids() {
+        let ids = [
+            "gpt-4",
+            "text-davinci-003",
+            "whisper-1",
+            "gpt-4",
+            "curie:ft-personal",
+        ];
+        let models = AvailableModels::from_ids(ids);
+        assert_eq!(models.chat, vec![ChatModel::Gpt4, ChatModel::Gpt4]);
+        assert_eq!(models.completion, vec![CompletionModel::TextDavinci003]);
+        assert_eq!(models.audio, vec![AudioModel::Whisper1]);
+        assert_eq!(models.other, vec!["curie:ft-personal".to_string()]);
+        assert!(models.contains("curie:ft-personal"));
+        assert!(!models.contains("gpt-4-32k"));
+        assert_eq!(models.ids().count(), ids.len());
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        models::{ChatModel, CompletionModel},
+        test_util::MockApi,
+    };
+
+    #[tokio::test]
+    async fn available_models() {
+        let api = MockApi::start().await;
+        let client = api.client();
+
+        let chat = client.available_chat_models().await.unwrap();
+        assert_eq!(chat, vec![ChatModel::GptTurbo]);
+        assert!(client
+            .model_exists(&CompletionModel::TextDavinci003)
+            .await
+            .unwrap());
+        assert!(!client.model_exists(&ChatModel::Gpt4).await.unwrap());
+        assert_eq!(api.requests("/models").await.len(), 1);
+
+        client.clear_available_models();
+        client.available_models().await.unwrap();
+        assert_eq!(api.requests("/models").await.len(), 2);
+    }
+}
@@ -1,6 +1,8 @@
+mod available;
 mod model_enums;
 pub mod response;
 
+pub use available::AvailableModels;
 pub use model_enums::*;
 
 use super::*;
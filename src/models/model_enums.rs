@@ -6,8 +6,40 @@
 //! same thing, but which suggest a different use case conceptually: [`custom_model!`] and
 //! [`ft_model!`]. See the documentation for those macros for more information.
 
+#[cfg(feature = "tokenizers")]
+use crate::tokenizers::Tokenizer;
+
 use super::*;
 
+// The enums are deserialized from the strings the API uses (the same strings they serialize
+// to) by way of their `FromStr` implementations.
+macro_rules! impl_deserialize_from_str {
+    ($($typ:ty),+ $(,)?) => {
+        $(
+            impl<'de> Deserialize<'de> for $typ {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    let s = String::deserialize(deserializer)?;
+                    s.parse().map_err(serde::de::Error::custom)
+                }
+            }
+        )+
+    };
+}
+
+impl_deserialize_from_str!(
+    AudioModel,
+    EditModel,
+    ChatModel,
+    CompletionModel,
+    ModerationModel,
+    EmbeddingModel,
+    FineTuneModel,
+    ImageModel,
+);
+
 // Some of the models have a default, where I think there's an obvious choice. Default is
 // implement instead of deriving it because the enums are non-exhaustive.
 
@@ -16,15 +48,19 @@ use super::*;
 // ========================== //
 
 #[non_exhaustive]
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AudioModel {
     Whisper1,
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
 }
 
 impl std::fmt::Display for AudioModel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
+        match self {
             AudioModel::Whisper1 => write!(f, "whisper-1"),
+            AudioModel::Other(id) => write!(f, "{id}"),
         }
     }
 }
@@ -36,12 +72,7 @@ impl std::str::FromStr for AudioModel {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "whisper-1" => Ok(AudioModel::Whisper1),
-            _ => Err(OairsError::new(
-                format!("No AudioModel variant: {s}"),
-                ErrorType::DeserializationError,
-                Some(s.to_string()),
-                None,
-            )),
+            _ => Ok(AudioModel::Other(s.to_string())),
         }
     }
 }
@@ -59,30 +90,106 @@ impl RetrievableModel for AudioModel {
     fn to_str(&self) -> &str {
         match self {
             AudioModel::Whisper1 => "whisper-1",
+            AudioModel::Other(id) => id,
+        }
+    }
+}
+
+impl AudioModel {
+    /// For convenience of, e.g., iterating over all models: `for m in AudioModel::ALL.iter()`
+    /// or to get vector of all models: `AudioModel::ALL.to_vec()`.
+    pub const ALL: [AudioModel; 1] = [AudioModel::Whisper1];
+}
+
+// ========================== //
+//        ImageModel          //
+// ========================== //
+
+/// For models that can be used by the `.../v1/images/...` endpoints. Default is
+/// `ImageModel::DallE2`, the model the API uses when none is given.
+#[non_exhaustive]
+#[derive(Clone, Default, Debug, PartialEq)]
+pub enum ImageModel {
+    #[default]
+    DallE2,
+    DallE3,
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
+}
+
+impl std::fmt::Display for ImageModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageModel::DallE2 => write!(f, "dall-e-2"),
+            ImageModel::DallE3 => write!(f, "dall-e-3"),
+            ImageModel::Other(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+impl std::str::FromStr for ImageModel {
+    type Err = OairsError;
+
+    #[track_caller]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dall-e-2" => Ok(ImageModel::DallE2),
+            "dall-e-3" => Ok(ImageModel::DallE3),
+            _ => Ok(ImageModel::Other(s.to_string())),
         }
     }
 }
 
+impl Serialize for ImageModel {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        serializer.serialize_str(self.to_str())
+    }
+}
+
+impl RetrievableModel for ImageModel {
+    fn to_str(&self) -> &str {
+        match self {
+            ImageModel::DallE2 => "dall-e-2",
+            ImageModel::DallE3 => "dall-e-3",
+            ImageModel::Other(id) => id,
+        }
+    }
+}
+
+impl ImageModel {
+    /// For convenience of, e.g., iterating over all models: `for m in ImageModel::ALL.iter()`
+    /// or to get vector of all models: `ImageModel::ALL.to_vec()`.
+    pub const ALL: [ImageModel; 2] = [ImageModel::DallE2, ImageModel::DallE3];
+}
+
 // ========================== //
 //        EditModel           //
 // ========================== //
 
 /// For models that can be used by the `.../v1/edits` endpoint.
 #[non_exhaustive]
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EditModel {
     CodeDavinci002, // codex
     TextDavinciEdit001,
     // TODO compatible with edits?
     CodeCushman001, // codex
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
 }
 
 impl std::fmt::Display for EditModel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
+        match self {
             EditModel::CodeDavinci002 => write!(f, "code-davinci-002"),
             EditModel::CodeCushman001 => write!(f, "code-cushman-001"),
             EditModel::TextDavinciEdit001 => write!(f, "text-davinci-edit-001"),
+            EditModel::Other(id) => write!(f, "{id}"),
         }
     }
 }
@@ -96,12 +203,7 @@ impl std::str::FromStr for EditModel {
             "code-davinci-002" => Ok(EditModel::CodeDavinci002),
             "code-cushman-001" => Ok(EditModel::CodeCushman001),
             "text-davinci-edit-001" => Ok(EditModel::TextDavinciEdit001),
-            _ => Err(OairsError::new(
-                format!("No ModelEditsv1 variant: {s}"),
-                ErrorType::DeserializationError,
-                Some(s.to_string()),
-                None,
-            )),
+            _ => Ok(EditModel::Other(s.to_string())),
         }
     }
 }
@@ -117,10 +219,11 @@ impl Serialize for EditModel {
 
 impl RetrievableModel for EditModel {
     fn to_str(&self) -> &str {
-        match *self {
+        match self {
             EditModel::CodeDavinci002 => "code-davinci-002",
             EditModel::CodeCushman001 => "code-cushman-001",
             EditModel::TextDavinciEdit001 => "text-davinci-edit-001",
+            EditModel::Other(id) => id,
         }
     }
 }
@@ -141,12 +244,22 @@ impl EditModel {
 
 /// For models that can be used by the `.../v1/chat/completions` endpoint.
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ChatModel {
     GptTurbo,
     GptTurbo0301,
+    GptTurbo0613,
+    GptTurbo16k,
+    GptTurbo16k0613,
     Gpt4,
     Gpt40314,
+    Gpt40613,
+    Gpt432k,
+    Gpt432k0314,
+    Gpt432k0613,
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
 }
 
 impl std::default::Default for ChatModel {
@@ -157,12 +270,7 @@ impl std::default::Default for ChatModel {
 
 impl std::fmt::Display for ChatModel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ChatModel::GptTurbo => write!(f, "gpt-3.5-turbo"),
-            ChatModel::GptTurbo0301 => write!(f, "gpt-3.5-turbo-0301"),
-            ChatModel::Gpt4 => write!(f, "gpt-4"),
-            ChatModel::Gpt40314 => write!(f, "gpt-4-0314"),
-        }
+        write!(f, "{}", self.to_str())
     }
 }
 
@@ -174,14 +282,16 @@ impl std::str::FromStr for ChatModel {
         match s {
             "gpt-3.5-turbo" => Ok(ChatModel::GptTurbo),
             "gpt-3.5-turbo-0301" => Ok(ChatModel::GptTurbo0301),
+            "gpt-3.5-turbo-0613" => Ok(ChatModel::GptTurbo0613),
+            "gpt-3.5-turbo-16k" => Ok(ChatModel::GptTurbo16k),
+            "gpt-3.5-turbo-16k-0613" => Ok(ChatModel::GptTurbo16k0613),
             "gpt-4" => Ok(ChatModel::Gpt4),
             "gpt-4-0314" => Ok(ChatModel::Gpt40314),
-            _ => Err(OairsError::new(
-                format!("No ModelChatCompletionsv1 variant: {s}"),
-                ErrorType::DeserializationError,
-                Some(s.to_string()),
-                None,
-            )),
+            "gpt-4-0613" => Ok(ChatModel::Gpt40613),
+            "gpt-4-32k" => Ok(ChatModel::Gpt432k),
+            "gpt-4-32k-0314" => Ok(ChatModel::Gpt432k0314),
+            "gpt-4-32k-0613" => Ok(ChatModel::Gpt432k0613),
+            _ => Ok(ChatModel::Other(s.to_string())),
         }
     }
 }
@@ -200,8 +310,16 @@ impl RetrievableModel for ChatModel {
         match self {
             ChatModel::GptTurbo => "gpt-3.5-turbo",
             ChatModel::GptTurbo0301 => "gpt-3.5-turbo-0301",
+            ChatModel::GptTurbo0613 => "gpt-3.5-turbo-0613",
+            ChatModel::GptTurbo16k => "gpt-3.5-turbo-16k",
+            ChatModel::GptTurbo16k0613 => "gpt-3.5-turbo-16k-0613",
             ChatModel::Gpt4 => "gpt-4",
             ChatModel::Gpt40314 => "gpt-4-0314",
+            ChatModel::Gpt40613 => "gpt-4-0613",
+            ChatModel::Gpt432k => "gpt-4-32k",
+            ChatModel::Gpt432k0314 => "gpt-4-32k-0314",
+            ChatModel::Gpt432k0613 => "gpt-4-32k-0613",
+            ChatModel::Other(id) => id,
         }
     }
 }
@@ -209,12 +327,56 @@ impl RetrievableModel for ChatModel {
 impl ChatModel {
     /// For convenience of, e.g., iterating over all models: `for m in ChatModel::ALL.iter()`
     /// or to get vector of all models: `EditModel::ALL.to_vec()`
-    pub const ALL: [ChatModel; 4] = [
+    pub const ALL: [ChatModel; 11] = [
         ChatModel::GptTurbo,
         ChatModel::GptTurbo0301,
+        ChatModel::GptTurbo0613,
+        ChatModel::GptTurbo16k,
+        ChatModel::GptTurbo16k0613,
         ChatModel::Gpt4,
         ChatModel::Gpt40314,
+        ChatModel::Gpt40613,
+        ChatModel::Gpt432k,
+        ChatModel::Gpt432k0314,
+        ChatModel::Gpt432k0613,
     ];
+
+    /// The [`Tokenizer`] used by the model.
+    #[cfg(feature = "tokenizers")]
+    pub fn tokenizer(&self) -> Tokenizer {
+        Tokenizer::CL100KBase
+    }
+
+    /// The maximum number of tokens in a request, counting both the messages and the
+    /// completion (`max_tokens`).
+    pub fn max_context_tokens(&self) -> usize {
+        match self {
+            ChatModel::GptTurbo | ChatModel::GptTurbo0301 | ChatModel::GptTurbo0613 => 4096,
+            ChatModel::GptTurbo16k | ChatModel::GptTurbo16k0613 => 16384,
+            ChatModel::Gpt4 | ChatModel::Gpt40314 | ChatModel::Gpt40613 => 8192,
+            ChatModel::Gpt432k | ChatModel::Gpt432k0314 | ChatModel::Gpt432k0613 => 32768,
+            // Inferred from the id, following the naming of the other variants.
+            ChatModel::Other(id) if id.contains("32k") => 32768,
+            ChatModel::Other(id) if id.contains("16k") => 16384,
+            ChatModel::Other(id) if id.starts_with("gpt-4") => 8192,
+            ChatModel::Other(_) => 4096,
+        }
+    }
+
+    /// Whether the model accepts function definitions. The `0301` and `0314` snapshots predate
+    /// function calling; any other model is assumed to support it.
+    pub fn supports_functions(&self) -> bool {
+        match self {
+            ChatModel::GptTurbo0301 | ChatModel::Gpt40314 | ChatModel::Gpt432k0314 => false,
+            ChatModel::Other(id) => !(id.ends_with("-0301") || id.ends_with("-0314")),
+            _ => true,
+        }
+    }
+
+    /// The month the model's training data ends, as the first day of that month.
+    pub fn training_cutoff(&self) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2021, 9, 1).unwrap()
+    }
 }
 
 // ========================== //
@@ -224,7 +386,7 @@ impl ChatModel {
 /// For models that can be used by the `.../v1/completions` endpoint.
 /// Default is `CompletionModel::TextDavinci003`.
 #[non_exhaustive]
-#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub enum CompletionModel {
     #[default]
     TextDavinci003,
@@ -236,6 +398,9 @@ pub enum CompletionModel {
     Curie,
     Babbage,
     Ada,
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
 }
 
 impl std::fmt::Display for CompletionModel {
@@ -250,6 +415,7 @@ impl std::fmt::Display for CompletionModel {
             CompletionModel::Curie => write!(f, "curie"),
             CompletionModel::Babbage => write!(f, "babbage"),
             CompletionModel::Ada => write!(f, "ada"),
+            CompletionModel::Other(id) => write!(f, "{id}"),
         }
     }
 }
@@ -269,12 +435,7 @@ impl std::str::FromStr for CompletionModel {
             "curie" => Ok(CompletionModel::Curie),
             "babbage" => Ok(CompletionModel::Babbage),
             "ada" => Ok(CompletionModel::Ada),
-            _ => Err(OairsError::new(
-                format!("No ModelCompletionsv1 variant: {s}"),
-                ErrorType::DeserializationError,
-                Some(s.to_string()),
-                None,
-            )),
+            _ => Ok(CompletionModel::Other(s.to_string())),
         }
     }
 }
@@ -300,6 +461,7 @@ impl RetrievableModel for CompletionModel {
             CompletionModel::Curie => "curie",
             CompletionModel::Babbage => "babbage",
             CompletionModel::Ada => "ada",
+            CompletionModel::Other(id) => id,
         }
     }
 }
@@ -317,6 +479,42 @@ impl CompletionModel {
         CompletionModel::Babbage,
         CompletionModel::Ada,
     ];
+
+    /// The [`Tokenizer`] used by the model.
+    #[cfg(feature = "tokenizers")]
+    pub fn tokenizer(&self) -> Tokenizer {
+        match self {
+            CompletionModel::TextDavinci003 | CompletionModel::TextDavinci002 => {
+                Tokenizer::P50KBase
+            }
+            _ => Tokenizer::R50KBase,
+        }
+    }
+
+    /// The maximum number of tokens in a request, counting both the prompt and the completion
+    /// (`max_tokens`).
+    pub fn max_context_tokens(&self) -> usize {
+        match self {
+            CompletionModel::TextDavinci003 | CompletionModel::TextDavinci002 => 4097,
+            _ => 2049,
+        }
+    }
+
+    /// Whether the model accepts function definitions. Always `false`: function calling is
+    /// only available to chat models.
+    pub fn supports_functions(&self) -> bool {
+        false
+    }
+
+    /// The month the model's training data ends, as the first day of that month.
+    pub fn training_cutoff(&self) -> chrono::NaiveDate {
+        match self {
+            CompletionModel::TextDavinci003 | CompletionModel::TextDavinci002 => {
+                chrono::NaiveDate::from_ymd_opt(2021, 6, 1).unwrap()
+            }
+            _ => chrono::NaiveDate::from_ymd_opt(2019, 10, 1).unwrap(),
+        }
+    }
 }
 
 // ========================== //
@@ -326,11 +524,14 @@ impl CompletionModel {
 /// For models that can be used by the `.../v1/moderations` endpoint.
 /// Default is `ModerationModel::TextModerationLatest`.
 #[non_exhaustive]
-#[derive(Clone, Default, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub enum ModerationModel {
     #[default]
     TextModerationLatest,
     TextModerationStable,
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
 }
 
 impl std::fmt::Display for ModerationModel {
@@ -338,6 +539,7 @@ impl std::fmt::Display for ModerationModel {
         match self {
             ModerationModel::TextModerationLatest => write!(f, "text-moderation-latest"),
             ModerationModel::TextModerationStable => write!(f, "text-moderation-stable"),
+            ModerationModel::Other(id) => write!(f, "{id}"),
         }
     }
 }
@@ -350,12 +552,7 @@ impl std::str::FromStr for ModerationModel {
         match s {
             "text-moderation-latest" => Ok(ModerationModel::TextModerationLatest),
             "text-moderation-stable" => Ok(ModerationModel::TextModerationStable),
-            _ => Err(OairsError::new(
-                format!("No ModelModerationsv1 variant: {s}"),
-                ErrorType::DeserializationError,
-                Some(s.to_string()),
-                None,
-            )),
+            _ => Ok(ModerationModel::Other(s.to_string())),
         }
     }
 }
@@ -370,10 +567,18 @@ impl Serialize for ModerationModel {
 }
 
 impl ModerationModel {
+    /// For convenience of, e.g., iterating over all models: `for m in ModerationModel::ALL.iter()`
+    /// or to get vector of all models: `ModerationModel::ALL.to_vec()`.
+    pub const ALL: [ModerationModel; 2] = [
+        ModerationModel::TextModerationLatest,
+        ModerationModel::TextModerationStable,
+    ];
+
     pub fn to_str(&self) -> &str {
         match self {
             ModerationModel::TextModerationLatest => "text-moderation-latest",
             ModerationModel::TextModerationStable => "text-moderation-stable",
+            ModerationModel::Other(id) => id,
         }
     }
 }
@@ -385,7 +590,7 @@ impl ModerationModel {
 /// For models that can be used by the `.../v1/embeddings` endpoint. The default
 /// is `EmbeddingModel::TextEmbeddingAda002`.
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum EmbeddingModel {
     TextEmbeddingAda002,
     // Listed as compatible with the /v1/embeddings endpoint in the
@@ -396,6 +601,9 @@ pub enum EmbeddingModel {
     TextCurieEmbedding001,
     TextBabbageEmbedding001,
     TextAdaEmbedding001,
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
 }
 
 impl std::default::Default for EmbeddingModel {
@@ -413,6 +621,7 @@ impl std::fmt::Display for EmbeddingModel {
             EmbeddingModel::TextCurieEmbedding001 => write!(f, "text-curie-embedding-001"),
             EmbeddingModel::TextBabbageEmbedding001 => write!(f, "text-babbage-embedding-001"),
             EmbeddingModel::TextAdaEmbedding001 => write!(f, "text-ada-embedding-001"),
+            EmbeddingModel::Other(id) => write!(f, "{id}"),
         }
     }
 }
@@ -429,12 +638,7 @@ impl std::str::FromStr for EmbeddingModel {
             "text-curie-embedding-001" => Ok(EmbeddingModel::TextCurieEmbedding001),
             "text-babbage-embedding-001" => Ok(EmbeddingModel::TextBabbageEmbedding001),
             "text-ada-embedding-001" => Ok(EmbeddingModel::TextAdaEmbedding001),
-            _ => Err(OairsError::new(
-                format!("No ModelEmbeddingsv1 variant: {s}"),
-                ErrorType::DeserializationError,
-                Some(s.to_string()),
-                None,
-            )),
+            _ => Ok(EmbeddingModel::Other(s.to_string())),
         }
     }
 }
@@ -448,6 +652,25 @@ impl Serialize for EmbeddingModel {
     }
 }
 
+impl EmbeddingModel {
+    /// The [`Tokenizer`] used by the model.
+    #[cfg(feature = "tokenizers")]
+    pub fn tokenizer(&self) -> Tokenizer {
+        match self {
+            EmbeddingModel::TextEmbeddingAda002 => Tokenizer::CL100KBase,
+            _ => Tokenizer::R50KBase,
+        }
+    }
+
+    /// The maximum number of tokens in a single input.
+    pub fn max_input_tokens(&self) -> usize {
+        match self {
+            EmbeddingModel::TextEmbeddingAda002 => 8191,
+            _ => 2046,
+        }
+    }
+}
+
 impl RetrievableModel for EmbeddingModel {
     fn to_str(&self) -> &str {
         match self {
@@ -457,6 +680,7 @@ impl RetrievableModel for EmbeddingModel {
             EmbeddingModel::TextCurieEmbedding001 => "text-curie-embedding-001",
             EmbeddingModel::TextBabbageEmbedding001 => "text-babbage-embedding-001",
             EmbeddingModel::TextAdaEmbedding001 => "text-ada-embedding-001",
+            EmbeddingModel::Other(id) => id,
         }
     }
 }
@@ -482,12 +706,15 @@ impl EmbeddingModel {
 /// is `Davinci`, as this will generally provide the best restuls. However, note that it is
 /// also the most expensive to run.
 #[non_exhaustive]
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum FineTuneModel {
     Ada,
     Babbage,
     Curie,
     Davinci,
+    /// A model without its own variant, e.g. a snapshot released after this version of the
+    /// crate, identified by its id.
+    Other(String),
 }
 
 impl std::default::Default for FineTuneModel {
@@ -503,6 +730,7 @@ impl std::fmt::Display for FineTuneModel {
             FineTuneModel::Babbage => write!(f, "babbage"),
             FineTuneModel::Curie => write!(f, "curie"),
             FineTuneModel::Davinci => write!(f, "davinci"),
+            FineTuneModel::Other(id) => write!(f, "{id}"),
         }
     }
 }
@@ -517,12 +745,7 @@ impl std::str::FromStr for FineTuneModel {
             "babbage" => Ok(FineTuneModel::Babbage),
             "curie" => Ok(FineTuneModel::Curie),
             "davinci" => Ok(FineTuneModel::Davinci),
-            _ => Err(OairsError::new(
-                format!("No ModelFineTunesv1 variant: {s}"),
-                ErrorType::DeserializationError,
-                Some(s.to_string()),
-                None,
-            )),
+            _ => Ok(FineTuneModel::Other(s.to_string())),
         }
     }
 }
@@ -545,6 +768,25 @@ impl FineTuneModel {
         FineTuneModel::Curie,
         FineTuneModel::Davinci,
     ];
+
+    /// The [`Tokenizer`] used by the model.
+    #[cfg(feature = "tokenizers")]
+    pub fn tokenizer(&self) -> Tokenizer {
+        Tokenizer::R50KBase
+    }
+
+    /// The price, in USD, of training on 1,000 tokens (as listed on OpenAI's pricing page in
+    /// March 2023), or `None` for an [`Other`](FineTuneModel::Other) model. Used by
+    /// [`estimate_cost`](crate::fine_tunes::estimate_cost).
+    pub fn training_price_per_1k_tokens(&self) -> Option<f64> {
+        match self {
+            FineTuneModel::Ada => Some(0.0004),
+            FineTuneModel::Babbage => Some(0.0006),
+            FineTuneModel::Curie => Some(0.003),
+            FineTuneModel::Davinci => Some(0.03),
+            FineTuneModel::Other(_) => None,
+        }
+    }
 }
 
 impl RetrievableModel for FineTuneModel {
@@ -554,6 +796,7 @@ impl RetrievableModel for FineTuneModel {
             FineTuneModel::Babbage => "babbage",
             FineTuneModel::Curie => "curie",
             FineTuneModel::Davinci => "davinci",
+            FineTuneModel::Other(id) => id,
         }
     }
 }
@@ -1,3 +1,5 @@
+use crate::list::List;
+
 use super::*;
 
 /// Convenience struct for deserializing a successful response from a request to the models endpoint.
@@ -12,11 +14,7 @@ use super::*;
 /// let models_list: ModelsList = response.json().await?;
 /// models_list.save_json("models.json")?;
 /// ```
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
-pub struct ModelsList {
-    data: Vec<ModelObject>,
-    object: String,
-}
+pub type ModelsList = List<ModelObject>;
 
 /// Convenience struct for deserializing a successful response from the models retrieve endpoint.
 /// method. Also used as a substruct of the [`ModelsList`].
@@ -30,7 +28,7 @@ pub struct ModelsList {
 /// let model_data: ModelData = response.json().await?;
 /// model_data.save_json("model.json")?;
 /// ```
-#[derive(Debug, Serialize, Deserialize, SaveJson)]
+#[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
 pub struct ModelObject {
     pub id: String,
     pub object: String,
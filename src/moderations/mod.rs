@@ -3,19 +3,37 @@ pub use builder::*;
 use super::*;
 
 mod builder {
+    use futures::{stream, StreamExt, TryStreamExt};
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::cache::{send_cached, ResponseCache};
+    #[cfg(target_arch = "wasm32")]
     use crate::client::handle_request;
+    use crate::{
+        client::{bearer, HttpMethod, PreparedBody, PreparedRequest},
+        meta::{deserialize_response, with_meta, ResponseMeta},
+        retry::RetryPolicy,
+        utils::raw::tee_raw,
+    };
 
-    use super::*;
+    use super::{moderations_response::ModerationResult, *};
 
-    #[derive(Debug, Default, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
     pub struct ModerationBuilder<State = Buildable> {
         #[serde(skip)]
         key: String,
         #[serde(skip)]
-        url: &'static str,
+        url: Cow<'static, str>,
         model: ModerationModel,
         #[serde(alias = "input")]
         input: Vec<String>,
+        #[cfg(not(target_arch = "wasm32"))]
+        #[serde(skip)]
+        cache: Option<ResponseCache>,
+        #[serde(skip)]
+        raw_dir: Option<std::path::PathBuf>,
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
         #[serde(skip)]
         state: std::marker::PhantomData<State>,
     }
@@ -27,7 +45,7 @@ mod builder {
         {
             ModerationBuilder {
                 key: key.into(),
-                url: URL.get(&Uri::Moderations).unwrap(),
+                url: Endpoints::default().moderations(),
                 input: inputs,
                 ..Default::default()
             }
@@ -35,21 +53,159 @@ mod builder {
     }
 
     impl ModerationBuilder<Sendable> {
+        pub(crate) fn set_url(&mut self, url: Cow<'static, str>) -> &mut Self {
+            self.url = url;
+            self
+        }
+
+        /// Send the request with `key` instead of the client's key, e.g. to bill it to another
+        /// project. See [`ChatBuilder::api_key`](crate::completions::ChatBuilder::api_key).
+        pub fn api_key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+            self.key = bearer(&key.into());
+            self
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        pub(crate) fn set_cache(&mut self, cache: Option<ResponseCache>) -> &mut Self {
+            self.cache = cache;
+            self
+        }
+
         pub fn model(&mut self, model: ModerationModel) -> &mut Self {
             self.model = model;
             self
         }
 
-        pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
-            let json = serde_json::to_value(self).unwrap();
-            handle_request(
+        /// Save the body of each response (one per chunk for
+        /// [`send_batched`](Self::send_batched)) to a file in `dir`, as with
+        /// [`ChatBuilder::save_raw_to`](crate::completions::ChatBuilder::save_raw_to).
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn save_raw_to<P: Into<std::path::PathBuf>>(&mut self, dir: P) -> &mut Self {
+            self.raw_dir = Some(dir.into());
+            self
+        }
+
+        /// Adds `key` to the request body with `value`, as with
+        /// [`ChatBuilder::extra_param`](crate::completions::ChatBuilder::extra_param).
+        pub fn extra_param<K: Into<String>>(
+            &mut self,
+            key: K,
+            value: serde_json::Value,
+        ) -> &mut Self {
+            self.extra.insert(key.into(), value);
+            self
+        }
+
+        /// The JSON body that [`send`](Self::send) sends (for the whole of the inputs, rather
+        /// than the chunks sent by [`send_batched`](Self::send_batched)).
+        pub fn to_request_json(&self) -> serde_json::Value {
+            serde_json::to_value(self).unwrap()
+        }
+
+        /// The request that [`send`](Self::send) would make, without sending it.
+        pub fn dry_run(&self) -> Result<PreparedRequest, OairsError> {
+            Ok(PreparedRequest::new(
                 &self.key,
-                self.url,
-                client::HttpMethod::Post,
-                Some(json),
-                None,
-            )
-            .await
+                &self.url,
+                HttpMethod::Post,
+                PreparedBody::Json(self.to_request_json()),
+            ))
+        }
+
+        pub async fn send(&self) -> Result<reqwest::Response, OairsError> {
+            let json = self.to_request_json();
+            #[cfg(not(target_arch = "wasm32"))]
+            let res = send_cached(self.cache.as_ref(), &self.key, &self.url, json).await?;
+            #[cfg(target_arch = "wasm32")]
+            let res =
+                handle_request(&self.key, &self.url, HttpMethod::Post, Some(json), None).await?;
+            tee_raw(self.raw_dir.as_deref(), "moderation", res).await
+        }
+
+        /// Like [`send`](Self::send), but deserializes the response into a
+        /// [`ModerationResult`], with the status and the start of the body in the error if it
+        /// doesn't match.
+        pub async fn send_parsed(&self) -> Result<ModerationResult, OairsError> {
+            deserialize_response(self.send().await?).await
+        }
+
+        /// Like [`send`](Self::send), but deserializes the response into a [`ModerationResult`]
+        /// and returns it with the [`ResponseMeta`] read from the response's headers, e.g. the
+        /// request id. A response served from the [`ResponseCache`] has the headers of the
+        /// response that was cached.
+        pub async fn send_with_meta(&self) -> Result<(ModerationResult, ResponseMeta), OairsError> {
+            with_meta(self.send().await?).await
+        }
+
+        /// Splits the inputs into chunks of `chunk_size`, sending up to `max_concurrency` requests
+        /// at a time, and aggregates the results into a single [`ModerationResult`] with the
+        /// inputs attached, in their original order. The `id` and `model` are taken from the first
+        /// response.
+        ///
+        /// Chunks that fail with a transient error (see [`OairsError::is_transient`]) are retried
+        /// as the default [`RetryPolicy`] allows; moderations are safe to retry. Any other error,
+        /// or running out of retries, is returned.
+        pub async fn send_batched(
+            &self,
+            chunk_size: usize,
+            max_concurrency: usize,
+        ) -> Result<ModerationResult, OairsError> {
+            for (param, value) in [
+                ("chunk_size", chunk_size),
+                ("max_concurrency", max_concurrency),
+            ] {
+                if value == 0 {
+                    return Err(OairsError::new(
+                        format!("{param} must be greater than 0"),
+                        ErrorType::ParamError,
+                        Some(param.to_string()),
+                        None,
+                    ));
+                }
+            }
+
+            let chunks: Vec<ModerationBuilder<Sendable>> = self
+                .input
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let mut builder = self.clone();
+                    builder.input = chunk.to_vec();
+                    builder
+                })
+                .collect();
+
+            let results: Vec<ModerationResult> = stream::iter(chunks.iter())
+                .map(|chunk| chunk.send_with_retries())
+                .buffered(max_concurrency)
+                .try_collect()
+                .await?;
+
+            let mut results = results.into_iter();
+            let mut aggregate = match results.next() {
+                Some(first) => first,
+                None => {
+                    return Err(OairsError::new(
+                        "No inputs to moderate".to_string(),
+                        ErrorType::ParamError,
+                        Some("input".to_string()),
+                        None,
+                    ))
+                }
+            };
+            for result in results {
+                aggregate.results.extend(result.results);
+            }
+            aggregate.with_input(self.input.clone());
+
+            Ok(aggregate)
+        }
+
+        async fn send_with_retries(&self) -> Result<ModerationResult, OairsError> {
+            RetryPolicy::new()
+                .run(true, || async {
+                    deserialize_response::<ModerationResult>(self.send().await?).await
+                })
+                .await
         }
     }
 }
@@ -60,7 +216,7 @@ pub mod moderations_response {
     /// For deserializing the OpenAI API for a Moderation request. The response doesn't
     /// include the input. To get the input as a field, use the `with_input` method.
     /// To save the response with the input, use the `save_with_input` method.
-    #[derive(Debug, Serialize, Deserialize, SaveJson)]
+    #[derive(Debug, Serialize, Deserialize, SaveJson, LoadJson)]
     pub struct ModerationResult {
         pub inputs: Option<Vec<String>>,
         pub id: String,
@@ -74,14 +230,33 @@ pub mod moderations_response {
             self
         }
 
-        pub fn save_with_input(
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn save_with_input<P: AsRef<std::path::Path>>(
             &mut self,
-            path: &str,
+            path: P,
             input: Vec<String>,
-        ) -> Result<(), std::io::Error> {
+        ) -> Result<std::path::PathBuf, std::io::Error> {
             let response = self.with_input(input);
             response.save_json(path)
         }
+
+        /// Whether any of the inputs were flagged.
+        pub fn any_flagged(&self) -> bool {
+            self.results.iter().any(|r| r.flagged)
+        }
+
+        /// Pairs each flagged [`Moderation`] with the input that produced it. Returns `None` if
+        /// the inputs haven't been attached with [`with_input`](Self::with_input).
+        pub fn flagged_inputs(&self) -> Option<Vec<(&str, &Moderation)>> {
+            let inputs = self.inputs.as_ref()?;
+            let flagged = inputs
+                .iter()
+                .zip(self.results.iter())
+                .filter(|(_, r)| r.flagged)
+                .map(|(i, r)| (i.as_str(), r))
+                .collect();
+            Some(flagged)
+        }
     }
 
     #[derive(Debug, Serialize, Deserialize, SaveJson)]
@@ -91,33 +266,150 @@ pub mod moderations_response {
         pub flagged: bool,
     }
 
+    impl Moderation {
+        /// Iterate over every category as `(category_name, flagged, score)`, using the category
+        /// names returned by the API (e.g., "hate/threatening").
+        pub fn iter(&self) -> impl Iterator<Item = (&'static str, bool, f64)> + '_ {
+            self.categories
+                .iter()
+                .zip(self.category_scores.iter())
+                .map(|((name, flagged), (_, score))| (name, flagged, score))
+        }
+
+        /// The names of the categories that were flagged.
+        pub fn flagged_categories(&self) -> Vec<&'static str> {
+            self.categories
+                .iter()
+                .filter(|(_, flagged)| *flagged)
+                .map(|(name, _)| name)
+                .collect()
+        }
+    }
+
     #[derive(Debug, Serialize, Deserialize)]
     pub struct ModerationCategories {
-        hate: bool,
+        pub hate: bool,
         #[serde(rename = "hate/threatening")]
-        hate_threatening: bool,
+        pub hate_threatening: bool,
         #[serde(rename = "self-harm")]
-        self_harm: bool,
-        sexual: bool,
+        pub self_harm: bool,
+        pub sexual: bool,
         #[serde(rename = "sexual/minors")]
-        sexual_minors: bool,
-        violence: bool,
+        pub sexual_minors: bool,
+        pub violence: bool,
         #[serde(rename = "violence/graphic")]
-        violence_graphic: bool,
+        pub violence_graphic: bool,
+    }
+
+    impl ModerationCategories {
+        /// Iterate over `(category_name, flagged)` pairs.
+        pub fn iter(&self) -> impl Iterator<Item = (&'static str, bool)> {
+            [
+                ("hate", self.hate),
+                ("hate/threatening", self.hate_threatening),
+                ("self-harm", self.self_harm),
+                ("sexual", self.sexual),
+                ("sexual/minors", self.sexual_minors),
+                ("violence", self.violence),
+                ("violence/graphic", self.violence_graphic),
+            ]
+            .into_iter()
+        }
     }
 
     #[derive(Debug, Serialize, Deserialize)]
     pub struct ModerationScores {
-        hate: f64,
+        pub hate: f64,
         #[serde(rename = "hate/threatening")]
-        hate_threatening: f64,
+        pub hate_threatening: f64,
         #[serde(rename = "self-harm")]
-        self_harm: f64,
-        sexual: f64,
+        pub self_harm: f64,
+        pub sexual: f64,
         #[serde(rename = "sexual/minors")]
-        sexual_minors: f64,
-        violence: f64,
+        pub sexual_minors: f64,
+        pub violence: f64,
         #[serde(rename = "violence/graphic")]
-        violence_graphic: f64,
+        pub violence_graphic: f64,
+    }
+
+    impl ModerationScores {
+        /// Iterate over `(category_name, score)` pairs.
+        pub fn iter(&self) -> impl Iterator<Item = (&'static str, f64)> {
+            [
+                ("hate", self.hate),
+                ("hate/threatening", self.hate_threatening),
+                ("self-harm", self.self_harm),
+                ("sexual", self.sexual),
+                ("sexual/minors", self.sexual_minors),
+                ("violence", self.violence),
+                ("violence/graphic", self.violence_graphic),
+            ]
+            .into_iter()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn moderation(flagged: bool, violence: bool) -> String {
+            format!(
+                r#"{{
+                    "categories": {{
+                        "hate": false, "hate/threatening": false, "self-harm": false,
+                        "sexual": false, "sexual/minors": false, "violence": {violence},
+                        "violence/graphic": false
+                    }},
+                    "category_scores": {{
+                        "hate": 0.01, "hate/threatening": 0.0, "self-harm": 0.0,
+                        "sexual": 0.0, "sexual/minors": 0.0, "violence": 0.9,
+                        "violence/graphic": 0.1
+                    }},
+                    "flagged": {flagged}
+                }}"#
+            )
+        }
+
+        #[test]
+        fn moderation_flagged_inputs() {
+            let json = format!(
+                r#"{{"id": "modr-1", "model": "text-moderation-004", "results": [{}, {}]}}"#,
+                moderation(false, false),
+                moderation(true, true)
+            );
+            let mut result: ModerationResult = serde_json::from_str(&json).unwrap();
+
+            assert!(result.any_flagged());
+            assert!(result.flagged_inputs().is_none());
+
+            result.with_input(vec!["fine".to_string(), "not fine".to_string()]);
+            let flagged = result.flagged_inputs().unwrap();
+
+            assert_eq!(flagged.len(), 1);
+            assert_eq!(flagged[0].0, "not fine");
+            assert_eq!(flagged[0].1.flagged_categories(), vec!["violence"]);
+            assert!(flagged[0].1.iter().any(|c| c == ("violence", true, 0.9)));
+        }
+
+        #[test]
+        fn moderation_request_json() {
+            let mut builder = ModerationBuilder::create("key", vec!["text".to_string()]);
+            builder.model(ModerationModel::TextModerationStable);
+
+            assert_eq!(
+                builder.to_request_json(),
+                serde_json::json!({"model": "text-moderation-stable", "input": ["text"]})
+            );
+        }
+
+        #[tokio::test]
+        async fn send_batched_zero() {
+            let builder = ModerationBuilder::create("key", vec!["text".to_string()]);
+
+            let error = builder.send_batched(0, 2).await.unwrap_err();
+            assert_eq!(error.param.as_deref(), Some("chunk_size"));
+            let error = builder.send_batched(2, 0).await.unwrap_err();
+            assert_eq!(error.param.as_deref(), Some("max_concurrency"));
+        }
     }
 }
@@ -0,0 +1,250 @@
+//! Contains the table of per-token [`ModelPrice`]s used to work out what a request cost, e.g.
+//! by [`Usage::cost`] and
+//! [`ChatCompletion::estimated_cost`](crate::completions::response::ChatCompletion::estimated_cost),
+//! and the [`Money`] they return, as well as the table of per-image prices used by
+//! [`ImageBuilder::estimated_cost`](crate::images::ImageBuilder::estimated_cost).
+//!
+//! The tables start with the prices listed on OpenAI's pricing page in June 2023 (and for
+//! `dall-e-3`, at its release). Prices change, and fine-tunes or other deployments may be
+//! billed differently, so the tables can be changed at runtime with [`set_price`],
+//! [`remove_price`], [`set_image_price`] and [`reset`]. They're shared by the whole process.
+//!
+//! # Example
+//! ```rust,no_run
+//! use oairs::pricing::{self, ModelPrice};
+//!
+//! pricing::set_price("gpt-4", ModelPrice::per_1k_tokens(0.03, 0.06));
+//! pricing::set_price("my-deployment", ModelPrice::per_1k_tokens(0.001, 0.002));
+//!
+//! let completion = client.chat_completion(ChatModel::Gpt4, &messages).send_parsed().await?;
+//! if let Some(cost) = completion.estimated_cost() {
+//!     println!("That cost {cost}");
+//! }
+//! ```
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use parking_lot::RwLock;
+
+use super::*;
+
+// Model (or model prefix), price in USD of 1,000 input tokens, and of 1,000 output tokens.
+const DEFAULT_PRICES: [(&str, f64, f64); 21] = [
+    ("gpt-4", 0.03, 0.06),
+    ("gpt-4-32k", 0.06, 0.12),
+    ("gpt-3.5-turbo", 0.0015, 0.002),
+    ("gpt-3.5-turbo-16k", 0.003, 0.004),
+    ("text-davinci-003", 0.02, 0.02),
+    ("text-davinci-002", 0.02, 0.02),
+    ("text-curie-001", 0.002, 0.002),
+    ("text-babbage-001", 0.0005, 0.0005),
+    ("text-ada-001", 0.0004, 0.0004),
+    ("davinci", 0.02, 0.02),
+    ("curie", 0.002, 0.002),
+    ("babbage", 0.0005, 0.0005),
+    ("ada", 0.0004, 0.0004),
+    // Using a fine-tuned model costs more than the model it was trained from.
+    ("davinci:ft-", 0.12, 0.12),
+    ("curie:ft-", 0.012, 0.012),
+    ("babbage:ft-", 0.0024, 0.0024),
+    ("ada:ft-", 0.0016, 0.0016),
+    ("text-embedding-ada-002", 0.0001, 0.0),
+    ("text-davinci-edit-001", 0.0, 0.0),
+    ("code-davinci-edit-001", 0.0, 0.0),
+    ("text-moderation-", 0.0, 0.0),
+];
+
+// Model, image size, and price in USD of one image.
+const DEFAULT_IMAGE_PRICES: [(&str, &str, f64); 4] = [
+    ("dall-e-2", "256x256", 0.016),
+    ("dall-e-2", "512x512", 0.018),
+    ("dall-e-2", "1024x1024", 0.02),
+    ("dall-e-3", "1024x1024", 0.04),
+];
+
+/// An amount of money, in USD.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Money {
+    usd: f64,
+}
+
+impl Money {
+    pub fn from_usd(usd: f64) -> Money {
+        Money { usd }
+    }
+
+    pub fn usd(&self) -> f64 {
+        self.usd
+    }
+}
+
+/// Shows the amount in dollars, to as many decimal places as it needs, up to 6 (a single token
+/// can cost a small fraction of a cent), e.g. `$0.0035`.
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let amount = format!("{:.6}", self.usd.abs());
+        let amount = amount.trim_end_matches('0');
+        let (dollars, cents) = amount.split_once('.').unwrap_or((amount, ""));
+        let sign = if self.usd < 0.0 { "-" } else { "" };
+        write!(f, "{sign}${dollars}.{cents:0<2}")
+    }
+}
+
+impl std::ops::Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money::from_usd(self.usd + other.usd)
+    }
+}
+
+impl std::ops::AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.usd += other.usd;
+    }
+}
+
+impl std::iter::Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::default(), |sum, m| sum + m)
+    }
+}
+
+/// What a model charges for the tokens of a request: the prompt's (input) tokens and the
+/// generated (output) tokens are priced separately.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// USD per 1,000 input tokens.
+    pub input_per_1k_tokens: f64,
+    /// USD per 1,000 output tokens.
+    pub output_per_1k_tokens: f64,
+}
+
+impl ModelPrice {
+    /// Prices as listed on OpenAI's pricing page: USD per 1,000 tokens.
+    pub fn per_1k_tokens(input: f64, output: f64) -> ModelPrice {
+        ModelPrice {
+            input_per_1k_tokens: input,
+            output_per_1k_tokens: output,
+        }
+    }
+
+    pub fn input_cost(&self, tokens: usize) -> Money {
+        Money::from_usd(tokens as f64 / 1000.0 * self.input_per_1k_tokens)
+    }
+
+    pub fn output_cost(&self, tokens: usize) -> Money {
+        Money::from_usd(tokens as f64 / 1000.0 * self.output_per_1k_tokens)
+    }
+
+    /// The cost of a request's [`Usage`]. Tokens not counted as `completion_tokens` are priced
+    /// as input.
+    pub fn cost(&self, usage: &Usage) -> Money {
+        let output = usage.completion_tokens.unwrap_or(0);
+        let input = usage.total_tokens.saturating_sub(output);
+        self.input_cost(input) + self.output_cost(output)
+    }
+}
+
+fn table() -> &'static RwLock<HashMap<String, ModelPrice>> {
+    static TABLE: OnceLock<RwLock<HashMap<String, ModelPrice>>> = OnceLock::new();
+    TABLE.get_or_init(|| RwLock::new(default_prices()))
+}
+
+fn default_prices() -> HashMap<String, ModelPrice> {
+    DEFAULT_PRICES
+        .iter()
+        .map(|&(model, input, output)| {
+            (model.to_string(), ModelPrice::per_1k_tokens(input, output))
+        })
+        .collect()
+}
+
+/// The price of `model`. If there's no price for the exact model, the price of the longest
+/// model in the table it starts with is used, so a dated snapshot, e.g. `gpt-4-0613`, is priced
+/// as `gpt-4`, and `gpt-4-32k-0613` as `gpt-4-32k`. `None` if no price matches.
+pub fn price(model: &str) -> Option<ModelPrice> {
+    let table = table().read();
+    if let Some(price) = table.get(model) {
+        return Some(*price);
+    }
+    table
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, price)| *price)
+}
+
+/// Sets the price of `model`, and of the models that start with it and don't have a more
+/// specific price (see [`price`]), replacing any price it had.
+pub fn set_price<M: Into<String>>(model: M, price: ModelPrice) {
+    table().write().insert(model.into(), price);
+}
+
+/// Removes the price of `model`, returning it if it had one.
+pub fn remove_price(model: &str) -> Option<ModelPrice> {
+    table().write().remove(model)
+}
+
+fn image_table() -> &'static RwLock<HashMap<(String, String), Money>> {
+    static TABLE: OnceLock<RwLock<HashMap<(String, String), Money>>> = OnceLock::new();
+    TABLE.get_or_init(|| RwLock::new(default_image_prices()))
+}
+
+fn default_image_prices() -> HashMap<(String, String), Money> {
+    DEFAULT_IMAGE_PRICES
+        .iter()
+        .map(|&(model, size, usd)| ((model.to_string(), size.to_string()), Money::from_usd(usd)))
+        .collect()
+}
+
+/// The price of one image of `size` (e.g. `1024x1024`) generated by `model`, or `None` if the
+/// table has no price for them. Edits and variations are priced as generations.
+pub fn image_price(model: &str, size: &str) -> Option<Money> {
+    image_table()
+        .read()
+        .get(&(model.to_string(), size.to_string()))
+        .copied()
+}
+
+/// Sets the price of one image of `size` generated by `model`, replacing any price it had.
+pub fn set_image_price<M: Into<String>, S: Into<String>>(model: M, size: S, price: Money) {
+    image_table()
+        .write()
+        .insert((model.into(), size.into()), price);
+}
+
+/// Restores the default prices, undoing any [`set_price`], [`remove_price`] or
+/// [`set_image_price`].
+pub fn reset() {
+    *table().write() = default_prices();
+    *image_table().write() = default_image_prices();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_cost() {
+        let usage = Usage {
+            prompt_tokens: 1000,
+            completion_tokens: Some(500),
+            total_tokens: 1500,
+        };
+        assert_eq!(usage.cost("gpt-4").unwrap().usd(), 0.06);
+        assert_eq!(usage.cost("gpt-4-32k-0613"), usage.cost("gpt-4-32k"));
+        let fine_tuned = usage.cost("curie:ft-org-2023-03-01").unwrap();
+        assert!((fine_tuned.usd() - 0.018).abs() < 1e-12);
+        assert!(usage.cost("unknown-model").is_none());
+
+        set_price("unknown-model", ModelPrice::per_1k_tokens(1.0, 2.0));
+        assert_eq!(usage.cost("unknown-model-v2").unwrap().usd(), 2.0);
+        assert!(remove_price("unknown-model").is_some());
+        assert!(usage.cost("unknown-model").is_none());
+
+        assert_eq!(Money::from_usd(0.0035).to_string(), "$0.0035");
+        assert_eq!(Money::from_usd(2.0).to_string(), "$2.00");
+        assert_eq!(Money::from_usd(-0.1).to_string(), "-$0.10");
+    }
+}
@@ -0,0 +1,629 @@
+//! Contains [`PromptTemplate`], for prompts with named placeholders, [`ChatTemplate`], for
+//! rendering a conversation of templates into [`Messages`], and [`PromptLibrary`], for loading
+//! named templates from a TOML or JSON file. [`FewShot`] renders example input/output pairs for
+//! few-shot prompting.
+//!
+//! # Example
+//! ```rust,no_run
+//! // prompts.toml:
+//! //
+//! // translate = "Translate {text} into {language}."
+//! //
+//! // [[summarize]]
+//! // role = "system"
+//! // content = "You are a concise assistant."
+//! //
+//! // [[summarize]]
+//! // role = "user"
+//! // content = "Summarize {document} in {n} bullets."
+//! let library = PromptLibrary::from_toml_file("prompts.toml")?;
+//!
+//! let vars = HashMap::from([("document", document.as_str()), ("n", "3")]);
+//! let messages = library.render("summarize", &vars)?;
+//! let completion = client.chat_completion(ChatModel::default(), &messages).send().await?;
+//! ```
+
+use std::path::Path;
+
+use crate::completions::{Messages, Msg, Role};
+#[cfg(feature = "tokenizers")]
+use crate::tokenizers::{tokenize, Tokenizer};
+
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+/// A prompt with named placeholders, e.g. `"Summarize {document} in {n} bullets"`. A
+/// placeholder's name can contain letters, digits, and underscores. Use `{{` and `}}` for
+/// literal braces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PromptTemplate {
+    template: String,
+    segments: Vec<Segment>,
+}
+
+impl PromptTemplate {
+    /// Parses `template`, returning a `ParamError` if a brace isn't closed or escaped, or a
+    /// placeholder's name is empty or invalid.
+    pub fn new<S: Into<String>>(template: S) -> Result<PromptTemplate, OairsError> {
+        let template = template.into();
+        let segments = parse(&template)?;
+        Ok(PromptTemplate { template, segments })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// The names of the placeholders, in order of first appearance.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Placeholder(name) = segment {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Replaces each placeholder with its value in `vars`. Returns a `ParamError` naming any
+    /// placeholders without a value. Values for names that aren't placeholders are ignored.
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> Result<String, OairsError> {
+        let missing: Vec<&str> = self
+            .placeholders()
+            .into_iter()
+            .filter(|name| !vars.contains_key(name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(OairsError::new(
+                format!("No value for placeholders: {}", missing.join(", ")),
+                ErrorType::ParamError,
+                Some(missing.join(", ")),
+                None,
+            ));
+        }
+
+        let mut rendered = String::with_capacity(self.template.len());
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Placeholder(name) => rendered.push_str(vars[name.as_str()]),
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Renders the template (see [`render`](Self::render)) into a message from `role`.
+    pub fn render_msg(&self, role: Role, vars: &HashMap<&str, &str>) -> Result<Msg, OairsError> {
+        Ok(Msg::new(role, self.render(vars)?))
+    }
+}
+
+impl std::str::FromStr for PromptTemplate {
+    type Err = OairsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PromptTemplate::new(s)
+    }
+}
+
+impl TryFrom<String> for PromptTemplate {
+    type Error = OairsError;
+
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        PromptTemplate::new(template)
+    }
+}
+
+impl From<PromptTemplate> for String {
+    fn from(template: PromptTemplate) -> Self {
+        template.template
+    }
+}
+
+impl std::fmt::Display for PromptTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+fn parse(template: &str) -> Result<Vec<Segment>, OairsError> {
+    let error = |message: String| {
+        OairsError::new(
+            message,
+            ErrorType::ParamError,
+            Some(template.to_string()),
+            None,
+        )
+    };
+
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_alphanumeric() || c == '_' => name.push(c),
+                        Some(c) => {
+                            return Err(error(format!(
+                                "Invalid character {c:?} in placeholder {{{name}"
+                            )))
+                        }
+                        None => return Err(error(format!("Unclosed placeholder {{{name}"))),
+                    }
+                }
+                if name.is_empty() {
+                    return Err(error("Empty placeholder {}".to_string()));
+                }
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Placeholder(name));
+            }
+            '}' => {
+                return Err(error(
+                    "Unmatched '}'. Use '}}' for a literal brace".to_string(),
+                ))
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// A message of a [`ChatTemplate`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateMsg {
+    pub role: Role,
+    pub content: PromptTemplate,
+}
+
+/// A conversation of [`PromptTemplate`]s, rendered into [`Messages`] with the same values.
+///
+/// Deserializes from either a list of `{role, content}` messages or, for a single user
+/// message, just the template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ChatTemplateRepr", into = "Vec<TemplateMsg>")]
+pub struct ChatTemplate {
+    messages: Vec<TemplateMsg>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChatTemplateRepr {
+    User(PromptTemplate),
+    Messages(Vec<TemplateMsg>),
+}
+
+impl From<ChatTemplateRepr> for ChatTemplate {
+    fn from(repr: ChatTemplateRepr) -> Self {
+        match repr {
+            ChatTemplateRepr::User(content) => ChatTemplate {
+                messages: vec![TemplateMsg {
+                    role: Role::User,
+                    content,
+                }],
+            },
+            ChatTemplateRepr::Messages(messages) => ChatTemplate { messages },
+        }
+    }
+}
+
+impl From<ChatTemplate> for Vec<TemplateMsg> {
+    fn from(template: ChatTemplate) -> Self {
+        template.messages
+    }
+}
+
+impl ChatTemplate {
+    pub fn new() -> ChatTemplate {
+        ChatTemplate::default()
+    }
+
+    /// Appends a message from `role`.
+    pub fn push(&mut self, role: Role, content: PromptTemplate) -> &mut Self {
+        self.messages.push(TemplateMsg { role, content });
+        self
+    }
+
+    pub fn system(&mut self, content: PromptTemplate) -> &mut Self {
+        self.push(Role::System, content)
+    }
+
+    pub fn user(&mut self, content: PromptTemplate) -> &mut Self {
+        self.push(Role::User, content)
+    }
+
+    pub fn assistant(&mut self, content: PromptTemplate) -> &mut Self {
+        self.push(Role::Assistant, content)
+    }
+
+    pub fn messages(&self) -> &[TemplateMsg] {
+        &self.messages
+    }
+
+    /// The names of the placeholders of every message, in order of first appearance.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for name in self.messages.iter().flat_map(|m| m.content.placeholders()) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Renders every message (see [`PromptTemplate::render`]). Returns a `ParamError` if any
+    /// placeholder has no value in `vars`.
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> Result<Messages, OairsError> {
+        let msgs = self
+            .messages
+            .iter()
+            .map(|m| m.content.render_msg(m.role.clone(), vars))
+            .collect::<Result<Vec<Msg>, OairsError>>()?;
+        Ok(Messages::new(msgs))
+    }
+}
+
+/// Named [`ChatTemplate`]s, usually loaded from a TOML or JSON file so that prompts can be
+/// managed apart from the code that uses them. In the file, each template is either a string
+/// (a single user message) or a list of `{role, content}` messages. See the
+/// [module documentation](self) for an example.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PromptLibrary {
+    templates: HashMap<String, ChatTemplate>,
+}
+
+impl PromptLibrary {
+    pub fn new() -> PromptLibrary {
+        PromptLibrary::default()
+    }
+
+    pub fn from_toml_str(toml: &str) -> Result<PromptLibrary, OairsError> {
+        toml::from_str(toml).map_err(|e| {
+            OairsError::new(e.to_string(), ErrorType::DeserializationError, None, None)
+        })
+    }
+
+    pub fn from_json_str(json: &str) -> Result<PromptLibrary, OairsError> {
+        serde_json::from_str(json).map_err(|e| {
+            OairsError::new(e.to_string(), ErrorType::DeserializationError, None, None)
+        })
+    }
+
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<PromptLibrary, OairsError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        PromptLibrary::from_toml_str(&content).map_err(|e| OairsError {
+            param: Some(path.display().to_string()),
+            ..e
+        })
+    }
+
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<PromptLibrary, OairsError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        PromptLibrary::from_json_str(&content).map_err(|e| OairsError {
+            param: Some(path.display().to_string()),
+            ..e
+        })
+    }
+
+    pub fn insert<S: Into<String>>(&mut self, name: S, template: ChatTemplate) -> &mut Self {
+        self.templates.insert(name.into(), template);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ChatTemplate> {
+        self.templates.get(name)
+    }
+
+    /// The names of the templates, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.templates.keys().map(|k| k.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Renders the template called `name` (see [`ChatTemplate::render`]). Returns a
+    /// `ParamError` if there's no such template.
+    pub fn render(&self, name: &str, vars: &HashMap<&str, &str>) -> Result<Messages, OairsError> {
+        match self.get(name) {
+            Some(template) => template.render(vars),
+            None => Err(OairsError::new(
+                format!("No prompt template named {name}"),
+                ErrorType::ParamError,
+                Some(name.to_string()),
+                None,
+            )),
+        }
+    }
+}
+
+/// An example input and its expected output, for [`FewShot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Example {
+    pub input: String,
+    pub output: String,
+}
+
+/// Example input/output pairs for few-shot prompting, rendered either as alternating user and
+/// assistant messages (for chat) or as a single prompt (for completions).
+///
+/// With a [`max_tokens`](FewShot::max_tokens) budget, examples are included in the order they
+/// were added until the next one would exceed it. The budget counts the tokens of the rendered
+/// examples only, not of the query or any other messages. Requires the `tokenizers` feature.
+///
+/// # Example
+/// ```rust,no_run
+/// let mut few_shot = FewShot::new();
+/// few_shot
+///     .example("I loved it!", "positive")
+///     .example("Never again.", "negative")
+///     .max_tokens(500);
+///
+/// // For chat:
+/// let mut messages = Messages::new(vec![Msg::system("Classify the sentiment.")]);
+/// messages.extend(few_shot.to_msgs()?);
+/// messages.push(Msg::user("It was fine."));
+///
+/// // For completions:
+/// // "Input: I loved it!\nOutput: positive\n\nInput: Never again.\nOutput: negative\n\n
+/// // Input: It was fine.\nOutput:"
+/// let prompt = few_shot.to_prompt("It was fine.")?;
+/// ```
+#[cfg(feature = "tokenizers")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FewShot {
+    examples: Vec<Example>,
+    max_tokens: Option<usize>,
+    tokenizer: Tokenizer,
+    input_prefix: String,
+    output_prefix: String,
+    separator: String,
+}
+
+#[cfg(feature = "tokenizers")]
+impl Default for FewShot {
+    fn default() -> Self {
+        FewShot {
+            examples: Vec::new(),
+            max_tokens: None,
+            tokenizer: Tokenizer::default(),
+            input_prefix: "Input: ".to_string(),
+            output_prefix: "Output:".to_string(),
+            separator: "\n\n".to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "tokenizers")]
+impl FewShot {
+    pub fn new() -> FewShot {
+        FewShot::default()
+    }
+
+    pub fn example<S: Into<String>>(&mut self, input: S, output: S) -> &mut Self {
+        self.examples.push(Example {
+            input: input.into(),
+            output: output.into(),
+        });
+        self
+    }
+
+    /// The maximum number of tokens of the rendered examples.
+    pub fn max_tokens(&mut self, max_tokens: usize) -> &mut Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// The [`Tokenizer`] used to count tokens for the budget. Defaults to the one used by the
+    /// chat models; use e.g. [`CompletionModel::tokenizer`](crate::models::CompletionModel::tokenizer)
+    /// for completions.
+    pub fn tokenizer(&mut self, tokenizer: Tokenizer) -> &mut Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Precedes each input in [`to_prompt`](FewShot::to_prompt). Defaults to `"Input: "`.
+    pub fn input_prefix<S: Into<String>>(&mut self, prefix: S) -> &mut Self {
+        self.input_prefix = prefix.into();
+        self
+    }
+
+    /// Precedes each output in [`to_prompt`](FewShot::to_prompt), and ends the prompt so the
+    /// model completes the output. Defaults to `"Output:"`.
+    pub fn output_prefix<S: Into<String>>(&mut self, prefix: S) -> &mut Self {
+        self.output_prefix = prefix.into();
+        self
+    }
+
+    /// Separates the examples in [`to_prompt`](FewShot::to_prompt). Defaults to `"\n\n"`.
+    pub fn separator<S: Into<String>>(&mut self, separator: S) -> &mut Self {
+        self.separator = separator.into();
+        self
+    }
+
+    pub fn examples(&self) -> &[Example] {
+        &self.examples
+    }
+
+    /// Renders the examples that fit the budget as alternating user (input) and assistant
+    /// (output) messages. The budget counts the tokens of each message's content.
+    pub fn to_msgs(&self) -> Result<Vec<Msg>, OairsError> {
+        let examples = self.within_budget(|example| {
+            Ok(tokenize(&example.input, self.tokenizer)?.len()
+                + tokenize(&example.output, self.tokenizer)?.len())
+        })?;
+        Ok(examples
+            .iter()
+            .flat_map(|example| {
+                [
+                    Msg::User(example.input.clone()),
+                    Msg::Assistant(example.output.clone()),
+                ]
+            })
+            .collect())
+    }
+
+    /// Renders the examples that fit the budget, each followed by the separator, and then
+    /// `query` as the input of an example whose output the model is left to complete.
+    pub fn to_prompt(&self, query: &str) -> Result<String, OairsError> {
+        let examples = self.within_budget(|example| {
+            let rendered = format!("{}{}", self.render_example(example), self.separator);
+            Ok(tokenize(&rendered, self.tokenizer)?.len())
+        })?;
+        let mut prompt = String::new();
+        for example in examples {
+            prompt.push_str(&self.render_example(example));
+            prompt.push_str(&self.separator);
+        }
+        prompt.push_str(&format!(
+            "{}{query}\n{}",
+            self.input_prefix, self.output_prefix
+        ));
+        Ok(prompt)
+    }
+
+    fn render_example(&self, example: &Example) -> String {
+        let output_prefix = match self.output_prefix.ends_with(char::is_whitespace) {
+            true => self.output_prefix.clone(),
+            false => format!("{} ", self.output_prefix),
+        };
+        format!(
+            "{}{}\n{output_prefix}{}",
+            self.input_prefix, example.input, example.output
+        )
+    }
+
+    // The leading examples whose summed `tokens` fit within `max_tokens`.
+    fn within_budget<F>(&self, tokens: F) -> Result<&[Example], OairsError>
+    where
+        F: Fn(&Example) -> Result<usize, OairsError>,
+    {
+        let max_tokens = match self.max_tokens {
+            Some(max_tokens) => max_tokens,
+            None => return Ok(&self.examples),
+        };
+        let mut total = 0;
+        for (i, example) in self.examples.iter().enumerate() {
+            total += tokens(example)?;
+            if total > max_tokens {
+                return Ok(&self.examples[..i]);
+            }
+        }
+        Ok(&self.examples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_template() {
+        let template = PromptTemplate::new("Summarize {document} in {n} bullets. {{n}}").unwrap();
+        assert_eq!(template.placeholders(), vec!["document", "n"]);
+
+        let vars = HashMap::from([("document", "the report"), ("n", "3"), ("extra", "x")]);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "Summarize the report in 3 bullets. {n}"
+        );
+
+        let error = template.render(&HashMap::from([("n", "3")])).unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("document"));
+
+        for invalid in ["{unclosed", "{}", "a } b", "{not valid}"] {
+            assert!(PromptTemplate::new(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn prompt_library() {
+        let toml = r#"
+            translate = "Translate {text} into {language}."
+
+            [[summarize]]
+            role = "system"
+            content = "You are concise."
+
+            [[summarize]]
+            role = "user"
+            content = "Summarize {document}."
+        "#;
+        let library = PromptLibrary::from_toml_str(toml).unwrap();
+        assert_eq!(library.names(), vec!["summarize", "translate"]);
+
+        let messages = library
+            .render("summarize", &HashMap::from([("document", "this")]))
+            .unwrap();
+        let expected = Messages::new(vec![
+            Msg::System("You are concise.".to_string()),
+            Msg::User("Summarize this.".to_string()),
+        ]);
+        assert_eq!(
+            serde_json::to_value(&messages).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+
+        let json = serde_json::to_string(&library).unwrap();
+        assert_eq!(PromptLibrary::from_json_str(&json).unwrap(), library);
+        assert!(PromptLibrary::from_json_str(r#"{"bad": "{oops"}"#).is_err());
+        assert!(library.render("missing", &HashMap::new()).is_err());
+    }
+
+    #[cfg(feature = "tokenizers")]
+    #[test]
+    fn few_shot() {
+        let mut few_shot = FewShot::new();
+        few_shot
+            .example("I loved it!", "positive")
+            .example("Never again.", "negative");
+
+        assert_eq!(
+            few_shot.to_prompt("It was fine.").unwrap(),
+            "Input: I loved it!\nOutput: positive\n\nInput: Never again.\nOutput: negative\n\n\
+             Input: It was fine.\nOutput:"
+        );
+        assert_eq!(few_shot.to_msgs().unwrap().len(), 4);
+
+        let first = tokenize("I loved it!", Tokenizer::CL100KBase)
+            .unwrap()
+            .len()
+            + tokenize("positive", Tokenizer::CL100KBase).unwrap().len();
+        few_shot.max_tokens(first);
+        assert_eq!(
+            few_shot.to_msgs().unwrap(),
+            vec![
+                Msg::User("I loved it!".to_string()),
+                Msg::Assistant("positive".to_string()),
+            ]
+        );
+        few_shot.max_tokens(0);
+        assert_eq!(few_shot.to_prompt("ok").unwrap(), "Input: ok\nOutput:");
+    }
+}
@@ -0,0 +1,387 @@
+//! Contains the [`RagPipeline`], for answering questions from your own documents
+//! (retrieval-augmented generation): documents are split into chunks and embedded, and the chunks
+//! closest to a question are sent to a chat model along with it.
+
+use crate::{
+    client::Client,
+    completions::{Messages, Msg},
+    embeddings::response,
+};
+
+use super::*;
+
+/// The instruction sent with the sources of each question.
+const RAG_SYSTEM: &str = "Answer the question using only the numbered sources below. Cite the \
+    sources you use by number, e.g. [1]. If the sources don't contain the answer, say so.";
+
+/// The most inputs the embeddings endpoint accepts in one request.
+const MAX_EMBEDDING_INPUTS: usize = 2048;
+
+/// A chunk of an indexed document, with its embedding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedChunk {
+    /// The name the document was indexed under, e.g. its path or url.
+    pub source: String,
+    /// The position of the chunk in the document.
+    pub index: usize,
+    pub text: String,
+    pub embedding: Vec<f64>,
+}
+
+/// A chunk found by [`RagPipeline::search`], with its cosine similarity to the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub source: String,
+    pub index: usize,
+    pub text: String,
+    pub score: f64,
+}
+
+/// The answer to a question from [`RagPipeline::ask`]. The answer cites the `sources` by their
+/// position in the list, starting at 1.
+#[derive(Debug, Clone)]
+pub struct RagAnswer {
+    pub answer: String,
+    pub sources: Vec<Source>,
+    /// The tokens used to embed the question and to answer it.
+    pub usage: Usage,
+}
+
+/// Indexes documents and answers questions from them. Documents are split into chunks of about
+/// [`chunk_size`](Self::chunk_size) characters on paragraph (and, for longer paragraphs, word)
+/// boundaries, and each chunk is embedded. A question is embedded the same way, the
+/// [`top_k`](Self::top_k) chunks with the highest cosine similarity are looked up in memory,
+/// and a chat model is asked to answer from them.
+///
+/// # Example
+/// ```rust,no_run
+/// let mut rag = RagPipeline::new(&client);
+/// rag.chat_model(ChatModel::Gpt4).top_k(3);
+/// rag.index("handbook.md", std::fs::read_to_string("handbook.md")?).await?;
+///
+/// let answer = rag.ask("How many vacation days do I get?").await?;
+/// println!("{}", answer.answer);
+/// for (i, source) in answer.sources.iter().enumerate() {
+///     println!("[{}] {} (chunk {})", i + 1, source.source, source.index);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RagPipeline {
+    client: Client<Keyed>,
+    embedding_model: EmbeddingModel,
+    chat_model: ChatModel,
+    chunk_size: usize,
+    top_k: usize,
+    chunks: Vec<IndexedChunk>,
+}
+
+impl RagPipeline {
+    /// A pipeline that sends its requests with (a clone of) `client`, with the default
+    /// embedding and chat models, chunks of 1000 characters, and the top 4 chunks sent with
+    /// each question.
+    pub fn new(client: &Client<Keyed>) -> RagPipeline {
+        RagPipeline {
+            client: client.clone(),
+            embedding_model: EmbeddingModel::default(),
+            chat_model: ChatModel::default(),
+            chunk_size: 1000,
+            top_k: 4,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// The model that embeds the documents and questions. Set it before indexing: chunks
+    /// embedded by another model can't be compared with the questions.
+    pub fn embedding_model(&mut self, model: EmbeddingModel) -> &mut Self {
+        self.embedding_model = model;
+        self
+    }
+
+    pub fn chat_model(&mut self, model: ChatModel) -> &mut Self {
+        self.chat_model = model;
+        self
+    }
+
+    /// The most characters in a chunk, unless a single word is longer. Applies to documents
+    /// indexed afterwards.
+    pub fn chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// The number of chunks sent with each question.
+    pub fn top_k(&mut self, top_k: usize) -> &mut Self {
+        self.top_k = top_k;
+        self
+    }
+
+    pub fn chunks(&self) -> &[IndexedChunk] {
+        &self.chunks
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Removes every indexed chunk.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+
+    /// Splits `text` into chunks, embeds them, and adds them to the index under `source`.
+    /// Returns the [`Usage`] of the embeddings. Nothing is added if a request fails.
+    pub async fn index<S, T>(&mut self, source: S, text: T) -> Result<Usage, OairsError>
+    where
+        S: Into<String>,
+        T: AsRef<str>,
+    {
+        let chunks = split_chunks(text.as_ref(), self.chunk_size);
+        self.index_chunks(source, chunks).await
+    }
+
+    /// Embeds `chunks` and adds them to the index under `source`, e.g. chunks split by token
+    /// count with [`text::chunk`](crate::text::chunk) instead of by
+    /// [`chunk_size`](Self::chunk_size). Returns the [`Usage`] of the embeddings. Nothing is
+    /// added if a request fails.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let chunks = chunk::by_sentences(&handbook, Tokenizer::CL100KBase, 300)?;
+    /// rag.index_chunks("handbook.md", chunks).await?;
+    /// ```
+    pub async fn index_chunks<S: Into<String>>(
+        &mut self,
+        source: S,
+        texts: Vec<String>,
+    ) -> Result<Usage, OairsError> {
+        let source = source.into();
+        let mut usage = Usage {
+            prompt_tokens: 0,
+            completion_tokens: None,
+            total_tokens: 0,
+        };
+        let mut chunks = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(MAX_EMBEDDING_INPUTS) {
+            let embedding = self.embed(batch).await?;
+            usage.prompt_tokens += embedding.usage.prompt_tokens;
+            usage.total_tokens += embedding.usage.total_tokens;
+
+            let mut data = embedding.data;
+            data.sort_by_key(|e| e.index);
+            for (text, e) in batch.iter().zip(data) {
+                chunks.push(IndexedChunk {
+                    source: source.clone(),
+                    index: chunks.len(),
+                    text: text.clone(),
+                    embedding: e.embedding,
+                });
+            }
+        }
+        self.chunks.extend(chunks);
+        Ok(usage)
+    }
+
+    /// The [`top_k`](Self::top_k) indexed chunks most similar to `query`, most similar first,
+    /// and the [`Usage`] of embedding the query.
+    pub async fn search(&self, query: &str) -> Result<(Vec<Source>, Usage), OairsError> {
+        let embedding = self.embed(&[query.to_string()]).await?;
+        let usage = embedding.usage;
+        let query = match embedding.data.into_iter().next() {
+            Some(e) => e.embedding,
+            None => return Ok((Vec::new(), usage)),
+        };
+
+        let mut sources: Vec<Source> = self
+            .chunks
+            .iter()
+            .map(|chunk| Source {
+                source: chunk.source.clone(),
+                index: chunk.index,
+                text: chunk.text.clone(),
+                score: cosine_similarity(&query, &chunk.embedding),
+            })
+            .collect();
+        sources.sort_by(|a, b| b.score.total_cmp(&a.score));
+        sources.truncate(self.top_k);
+        Ok((sources, usage))
+    }
+
+    /// Answers `question` from the [`top_k`](Self::top_k) chunks most similar to it. Returns a
+    /// `ParamError` if nothing has been indexed.
+    pub async fn ask(&self, question: &str) -> Result<RagAnswer, OairsError> {
+        if self.chunks.is_empty() {
+            return Err(OairsError::new(
+                "No documents have been indexed".to_string(),
+                ErrorType::ParamError,
+                None,
+                None,
+            ));
+        }
+        let (sources, mut usage) = self.search(question).await?;
+
+        let messages = Messages::new(vec![
+            Msg::system(RAG_SYSTEM),
+            Msg::user(question_prompt(question, &sources)),
+        ]);
+        let completion = self
+            .client
+            .chat_completion(self.chat_model.clone(), &messages)
+            .send_parsed()
+            .await?;
+
+        usage.prompt_tokens += completion.usage.prompt_tokens;
+        usage.total_tokens += completion.usage.total_tokens;
+        usage.completion_tokens = completion.usage.completion_tokens;
+        Ok(RagAnswer {
+            answer: completion.response_message().content().to_string(),
+            sources,
+            usage,
+        })
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<response::Embedding, OairsError> {
+        let embedding = self
+            .client
+            .create_embeddings(self.embedding_model.clone(), inputs)
+            .send_parsed()
+            .await?;
+        if embedding.data.len() != inputs.len() {
+            return Err(OairsError::new(
+                format!(
+                    "Expected {} embeddings, but the API returned {}",
+                    inputs.len(),
+                    embedding.data.len()
+                ),
+                ErrorType::DeserializationError,
+                None,
+                None,
+            ));
+        }
+        Ok(embedding)
+    }
+}
+
+/// The cosine similarity of two embeddings, from -1.0 to 1.0. 0.0 if either is all zeros or
+/// they have different lengths.
+pub fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+// The user message of a question: the numbered sources, then the question.
+fn question_prompt(question: &str, sources: &[Source]) -> String {
+    let mut prompt = String::from("Sources:\n");
+    for (i, source) in sources.iter().enumerate() {
+        prompt.push_str(&format!(
+            "\n[{}] ({})\n{}\n",
+            i + 1,
+            source.source,
+            source.text
+        ));
+    }
+    prompt.push_str(&format!("\nQuestion: {question}"));
+    prompt
+}
+
+// Splits `text` into chunks of at most `size` characters, keeping paragraphs together where
+// they fit and splitting longer ones between words.
+fn split_chunks(text: &str, size: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut push = |current: &mut String, piece: &str, sep: &str| {
+        if !current.is_empty() && current.chars().count() + sep.len() + piece.chars().count() > size
+        {
+            chunks.push(std::mem::take(current));
+        }
+        if !current.is_empty() {
+            current.push_str(sep);
+        }
+        current.push_str(piece);
+    };
+
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if paragraph.chars().count() <= size {
+            push(&mut current, paragraph, "\n\n");
+            continue;
+        }
+        for (i, word) in paragraph.split_whitespace().enumerate() {
+            let sep = if i == 0 { "\n\n" } else { " " };
+            push(&mut current, word, sep);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_compare() {
+        let text = "First paragraph.\n\nSecond one.\n\nA third paragraph that is too long to fit";
+        assert_eq!(
+            split_chunks(text, 30),
+            [
+                "First paragraph.\n\nSecond one.",
+                "A third paragraph that is too",
+                "long to fit"
+            ]
+        );
+        assert_eq!(split_chunks(" \n\n ", 30), Vec::<String>::new());
+
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-9);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::test_util::MockApi;
+
+    #[tokio::test]
+    async fn rag_pipeline() {
+        let api = MockApi::start().await;
+        let mut rag = crate::rag::RagPipeline::new(&api.client());
+        assert!(rag.ask("Anything?").await.is_err());
+
+        rag.top_k(1);
+        let usage = rag
+            .index("handbook.md", "Employees get 25 vacation days.")
+            .await
+            .unwrap();
+        assert_eq!(usage.prompt_tokens, 8);
+        assert_eq!(rag.len(), 1);
+
+        let answer = rag.ask("How many vacation days?").await.unwrap();
+        assert_eq!(answer.answer, "This is a test response.");
+        assert_eq!(answer.sources.len(), 1);
+        assert_eq!(answer.sources[0].source, "handbook.md");
+        assert!((answer.sources[0].score - 1.0).abs() < 1e-9);
+        // The question's embedding and the chat completion.
+        assert_eq!(answer.usage.prompt_tokens, 8 + 13);
+        assert_eq!(answer.usage.completion_tokens, Some(7));
+
+        let requests = api.requests("/chat/completions").await;
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let prompt = body["messages"][1]["content"].as_str().unwrap();
+        assert!(prompt.contains("[1] (handbook.md)\nEmployees get 25 vacation days."));
+        assert!(prompt.ends_with("Question: How many vacation days?"));
+    }
+}
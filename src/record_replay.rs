@@ -0,0 +1,346 @@
+//! Contains [`RecordReplay`], which records the API's responses to a directory of "cassettes"
+//! and plays them back without a network, for reproducible tests and offline demos.
+//!
+//! Every request sent inside [`RecordReplay::run`] goes through the layer. In
+//! [`Mode::Record`], it's sent as usual, and the request and response are written to a
+//! cassette: a JSON file named after the request's method, path, and a hash of its body. The
+//! API key, like every other request header, isn't written. In [`Mode::Replay`], nothing is
+//! sent: the response is read from the cassette of the request, or a `FileError` is returned if
+//! there isn't one.
+//!
+//! Requests are matched by method, path, query, and JSON body, not by host, so responses
+//! recorded from the API can be replayed for a client with another base url, e.g. a
+//! [`MockApi`](crate::test_util::MockApi). A multipart request (e.g. a file upload) is matched by
+//! its method and path only. Identical requests are numbered in the order they're sent, so a
+//! request sent twice gets the two responses recorded for it in turn.
+//!
+//! A streamed response is read to the end before it's recorded, so while recording, its
+//! tokens arrive all at once.
+//!
+//! # Example
+//! ```rust,no_run
+//! use oairs::record_replay::RecordReplay;
+//!
+//! // Record once with a real key...
+//! let cassettes = RecordReplay::record("tests/cassettes/summarize");
+//! let summary = cassettes.run(summarize(&client, &document)).await?;
+//!
+//! // ...then replay in CI, without a key or network access.
+//! let cassettes = RecordReplay::replay("tests/cassettes/summarize");
+//! assert_eq!(cassettes.run(summarize(&client, &document)).await?, summary);
+//! ```
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use base64::Engine;
+use parking_lot::Mutex;
+
+use crate::client::HttpMethod;
+
+use super::*;
+
+tokio::task_local! {
+    // The layer of the `RecordReplay::run` the current task is in, if any.
+    static LAYER: RecordReplay;
+}
+
+/// Whether a [`RecordReplay`] writes cassettes or reads them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Send requests, and write each request and its response to a cassette.
+    Record,
+    /// Answer requests with the responses in the cassettes, without sending them.
+    Replay,
+}
+
+/// Records the requests sent inside [`run`](Self::run), and their responses, to a directory of
+/// cassettes, or replays them from one. See the [module docs](self).
+///
+/// Clones share the count of identical requests used to number the cassettes, which starts at
+/// 0 for a new `RecordReplay`.
+#[derive(Debug, Clone)]
+pub struct RecordReplay {
+    dir: PathBuf,
+    mode: Mode,
+    // The number of requests sent so far with each cassette name.
+    counts: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl RecordReplay {
+    /// Records to cassettes in `dir`, creating it if needed. Existing cassettes of the same
+    /// requests are overwritten.
+    pub fn record<P: Into<PathBuf>>(dir: P) -> RecordReplay {
+        RecordReplay::new(dir.into(), Mode::Record)
+    }
+
+    /// Replays the cassettes in `dir`.
+    pub fn replay<P: Into<PathBuf>>(dir: P) -> RecordReplay {
+        RecordReplay::new(dir.into(), Mode::Replay)
+    }
+
+    fn new(dir: PathBuf, mode: Mode) -> RecordReplay {
+        RecordReplay {
+            dir,
+            mode,
+            counts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Runs `fut`, recording or replaying the requests it sends. Requests sent by tasks it
+    /// spawns aren't, except for the task of
+    /// [`ChatBuilder::send_with_channel`](crate::completions::ChatBuilder::send_with_channel).
+    pub async fn run<F: Future>(&self, fut: F) -> F::Output {
+        LAYER.scope(self.clone(), fut).await
+    }
+
+    // The cassette of a request, numbered after the identical requests sent before it.
+    pub(crate) fn cassette(
+        &self,
+        url: &str,
+        http_method: HttpMethod,
+        json: Option<&serde_json::Value>,
+    ) -> Cassette {
+        let method = format!("{http_method:?}").to_uppercase();
+        let path = match reqwest::Url::parse(url) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{query}", url.path()),
+                None => url.path().to_string(),
+            },
+            Err(_) => url.to_string(),
+        };
+        let body = json.map(|json| json.to_string()).unwrap_or_default();
+        let slug: String = path
+            .trim_matches('/')
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_lowercase(),
+                false => '-',
+            })
+            .collect();
+        let hash = fnv1a(format!("{method} {path}\n{body}").as_bytes());
+        let name = format!("{}-{slug}-{hash:016x}", method.to_lowercase());
+
+        let mut counts = self.counts.lock();
+        let count = counts.entry(name.clone()).or_insert(0);
+        let file = self.dir.join(format!("{name}-{count}.json"));
+        *count += 1;
+        Cassette {
+            file,
+            request: RecordedRequest {
+                method,
+                path,
+                body: json.cloned(),
+            },
+        }
+    }
+}
+
+// The layer of the current task, if it's in a `RecordReplay::run`.
+pub(crate) fn current() -> Option<RecordReplay> {
+    LAYER.try_with(|layer| layer.clone()).ok()
+}
+
+// Runs `fut` in `layer`, if there is one, e.g. to keep the layer of the task that spawned `fut`.
+pub(crate) async fn scoped<F: Future>(layer: Option<RecordReplay>, fut: F) -> F::Output {
+    match layer {
+        Some(layer) => layer.run(fut).await,
+        None => fut.await,
+    }
+}
+
+// A stable hash (unlike `DefaultHasher`'s), so cassettes recorded by one build are found by
+// another.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The file of one request and its response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// The body, if it's UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    /// The body in base64, if it isn't UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Cassette {
+    file: PathBuf,
+    request: RecordedRequest,
+}
+
+impl Cassette {
+    // The recorded response, rebuilt as a `reqwest::Response`.
+    pub(crate) async fn replay(&self) -> Result<reqwest::Response, OairsError> {
+        let missing = |detail: String| {
+            OairsError::new(
+                format!(
+                    "No recorded response for {} {} in {}: {detail}",
+                    self.request.method,
+                    self.request.path,
+                    self.file.display()
+                ),
+                ErrorType::FileError,
+                Some(self.file.display().to_string()),
+                None,
+            )
+        };
+        let json = tokio::fs::read(&self.file)
+            .await
+            .map_err(|e| missing(e.to_string()))?;
+        let interaction: Interaction =
+            serde_json::from_slice(&json).map_err(|e| missing(e.to_string()))?;
+        let recorded = interaction.response;
+
+        let body = match (recorded.body, recorded.body_base64) {
+            (Some(body), _) => body.into_bytes(),
+            (None, Some(encoded)) => base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| missing(e.to_string()))?,
+            (None, None) => Vec::new(),
+        };
+        let mut response = ::http::Response::new(body);
+        *response.status_mut() =
+            reqwest::StatusCode::from_u16(recorded.status).map_err(|e| missing(e.to_string()))?;
+        for (name, value) in recorded.headers {
+            if let (Ok(name), Ok(value)) = (
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+                reqwest::header::HeaderValue::from_str(&value),
+            ) {
+                response.headers_mut().append(name, value);
+            }
+        }
+        Ok(reqwest::Response::from(response))
+    }
+
+    // Writes `response` to the cassette, and returns it rebuilt from the body that was read.
+    pub(crate) async fn record(
+        self,
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, OairsError> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = match response.bytes().await {
+            Ok(body) => body.to_vec(),
+            Err(e) => return Err(parse_reqwest_error(e)),
+        };
+
+        let (text, base64) = match std::str::from_utf8(&body) {
+            Ok(text) => (Some(text.to_string()), None),
+            Err(_) => (
+                None,
+                Some(base64::engine::general_purpose::STANDARD.encode(&body)),
+            ),
+        };
+        let interaction = Interaction {
+            request: self.request,
+            response: RecordedResponse {
+                status: status.as_u16(),
+                headers: headers
+                    .iter()
+                    .filter(|(name, _)| *name != reqwest::header::SET_COOKIE)
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+                body: text,
+                body_base64: base64,
+            },
+        };
+        if let Some(dir) = self.file.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let json = serde_json::to_string_pretty(&interaction).map_err(|e| {
+            OairsError::new(e.to_string(), ErrorType::SerializationError, None, None)
+        })?;
+        tokio::fs::write(&self.file, json).await?;
+
+        let mut response = ::http::Response::new(body);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        Ok(reqwest::Response::from(response))
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::{
+        completions::{Messages, Msg},
+        test_util::{MockApi, TEST_API_KEY},
+    };
+
+    #[tokio::test]
+    async fn record_then_replay() {
+        use crate::record_replay::RecordReplay;
+
+        let dir = std::env::temp_dir().join(format!("oairs-cassettes-{}", std::process::id()));
+        let api = MockApi::start().await;
+        let client = api.client();
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let chat = client.chat_completion(crate::ChatModel::default(), &messages);
+
+        let recorded = RecordReplay::record(&dir)
+            .run(async {
+                let first = chat.send_parsed().await.unwrap();
+                let models = client.list_models().send().await.unwrap();
+                (first, models.text().await.unwrap())
+            })
+            .await;
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        drop(api);
+
+        let replay = RecordReplay::replay(&dir);
+        let replayed = replay
+            .run(async {
+                let first = chat.send_parsed().await.unwrap();
+                let models = client.list_models().send().await.unwrap();
+                (first, models.text().await.unwrap())
+            })
+            .await;
+        // The chat was only recorded once.
+        let error = replay.run(chat.send()).await.unwrap_err();
+        let cassettes: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(replayed.0.id, recorded.0.id);
+        assert_eq!(replayed.1, recorded.1);
+        assert_eq!(error.error_type, crate::ErrorType::FileError.to_str());
+        assert!(cassettes.iter().all(|c| !c.contains(TEST_API_KEY)));
+    }
+}
@@ -0,0 +1,707 @@
+//! Contains the [`RequestSet`], for sending a mix of chat, completion, embedding, and moderation
+//! requests concurrently, with a shared concurrency and rate limit, and
+//! [`AdaptiveConcurrency`], for adjusting the concurrency while they run.
+
+use std::{pin::pin, time::Duration};
+
+use futures::{stream, StreamExt};
+use parking_lot::Mutex;
+use tokio::{sync::Notify, time::Instant};
+
+use crate::{
+    completions::{
+        response::{ChatCompletion, Completion},
+        ChatBuilder, CompletionBuilder,
+    },
+    embeddings::{response::Embedding, EmbeddingBuilder},
+    moderations::moderations_response::ModerationResult,
+};
+
+use super::*;
+
+/// A request queued in a [`RequestSet`]. Converts from the builder of each supported endpoint.
+#[derive(Debug, Clone)]
+pub enum QueuedRequest {
+    Chat(ChatBuilder<Sendable>),
+    Completion(CompletionBuilder<Sendable>),
+    Embedding(EmbeddingBuilder<Sendable>),
+    Moderation(ModerationBuilder<Sendable>),
+}
+
+impl From<ChatBuilder<Sendable>> for QueuedRequest {
+    fn from(builder: ChatBuilder<Sendable>) -> Self {
+        QueuedRequest::Chat(builder)
+    }
+}
+
+impl From<CompletionBuilder<Sendable>> for QueuedRequest {
+    fn from(builder: CompletionBuilder<Sendable>) -> Self {
+        QueuedRequest::Completion(builder)
+    }
+}
+
+impl From<EmbeddingBuilder<Sendable>> for QueuedRequest {
+    fn from(builder: EmbeddingBuilder<Sendable>) -> Self {
+        QueuedRequest::Embedding(builder)
+    }
+}
+
+impl From<ModerationBuilder<Sendable>> for QueuedRequest {
+    fn from(builder: ModerationBuilder<Sendable>) -> Self {
+        QueuedRequest::Moderation(builder)
+    }
+}
+
+impl QueuedRequest {
+    async fn send(&self) -> Result<RequestOutput, OairsError> {
+        match self {
+            QueuedRequest::Chat(b) => b.send_parsed().await.map(RequestOutput::Chat),
+            QueuedRequest::Completion(b) => b.send_parsed().await.map(RequestOutput::Completion),
+            QueuedRequest::Embedding(b) => b.send_parsed().await.map(RequestOutput::Embedding),
+            QueuedRequest::Moderation(b) => b.send_parsed().await.map(RequestOutput::Moderation),
+        }
+    }
+}
+
+/// The parsed response to a [`QueuedRequest`], of the same kind.
+#[derive(Debug)]
+pub enum RequestOutput {
+    Chat(ChatCompletion),
+    Completion(Completion),
+    Embedding(Embedding),
+    Moderation(ModerationResult),
+}
+
+impl RequestOutput {
+    pub fn as_chat(&self) -> Option<&ChatCompletion> {
+        match self {
+            RequestOutput::Chat(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn as_completion(&self) -> Option<&Completion> {
+        match self {
+            RequestOutput::Completion(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn as_embedding(&self) -> Option<&Embedding> {
+        match self {
+            RequestOutput::Embedding(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn as_moderation(&self) -> Option<&ModerationResult> {
+        match self {
+            RequestOutput::Moderation(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// The tokens used by the request. `None` for a moderation, which doesn't report usage.
+    pub fn usage(&self) -> Option<&Usage> {
+        match self {
+            RequestOutput::Chat(c) => Some(&c.usage),
+            RequestOutput::Completion(c) => Some(&c.usage),
+            RequestOutput::Embedding(e) => Some(&e.usage),
+            RequestOutput::Moderation(_) => None,
+        }
+    }
+}
+
+/// A queue of requests to different endpoints, sent together by [`run`](RequestSet::run) up to
+/// [`max_concurrency`](RequestSet::max_concurrency) at a time and, if set, no faster than
+/// [`requests_per_minute`](RequestSet::requests_per_minute). With
+/// [`adaptive_concurrency`](RequestSet::adaptive_concurrency), the number in flight is adjusted
+/// while it runs instead.
+///
+/// Each request is sent with its builder's `send_parsed`, so everything set on the builder (a
+/// cache, a budget, `save_raw_to`, etc.) applies. A failed request doesn't stop the others, and
+/// the results are returned in the order the requests were pushed.
+///
+/// # Example
+/// ```rust,no_run
+/// let mut set = RequestSet::new();
+/// let chat = set.push(client.chat_completion(ChatModel::default(), &messages));
+/// let embedding = set.push(client.create_embeddings(EmbeddingModel::default(), inputs));
+/// let moderation = set.push(client.create_moderation(user_input));
+///
+/// let results = set.max_concurrency(8).requests_per_minute(500).run().await?;
+/// let completion = results.get(chat).and_then(|r| r.as_chat());
+/// for (i, error) in results.errors() {
+///     eprintln!("Request {i} failed: {error}");
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestSet {
+    requests: Vec<QueuedRequest>,
+    max_concurrency: usize,
+    requests_per_minute: Option<u32>,
+    adaptive: Option<AdaptiveConcurrency>,
+}
+
+impl Default for RequestSet {
+    fn default() -> Self {
+        RequestSet::new()
+    }
+}
+
+impl RequestSet {
+    pub fn new() -> RequestSet {
+        RequestSet {
+            requests: Vec::new(),
+            max_concurrency: 4,
+            requests_per_minute: None,
+            adaptive: None,
+        }
+    }
+
+    /// Queues a request and returns its index in the results.
+    pub fn push<R: Into<QueuedRequest>>(&mut self, request: R) -> usize {
+        self.requests.push(request.into());
+        self.requests.len() - 1
+    }
+
+    /// The maximum number of requests in flight at once. Defaults to 4. Returns a `ParamError`
+    /// from [`run`](Self::run) if 0.
+    pub fn max_concurrency(&mut self, max_concurrency: usize) -> &mut Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    /// Start the requests evenly spaced, at most `requests_per_minute` a minute. Not limited by
+    /// default. Returns a `ParamError` from [`run`](Self::run) if 0.
+    pub fn requests_per_minute(&mut self, requests_per_minute: u32) -> &mut Self {
+        self.requests_per_minute = Some(requests_per_minute);
+        self
+    }
+
+    /// Adjust the number of requests in flight while the set runs, backing off on rate limits
+    /// and rising latency, instead of using a fixed [`max_concurrency`](Self::max_concurrency).
+    /// See [`AdaptiveConcurrency`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let mut adaptive = AdaptiveConcurrency::new();
+    /// adaptive.max_concurrency(32);
+    ///
+    /// let results = set.adaptive_concurrency(adaptive).run().await?;
+    /// ```
+    pub fn adaptive_concurrency(&mut self, adaptive: AdaptiveConcurrency) -> &mut Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    pub fn requests(&self) -> &[QueuedRequest] {
+        &self.requests
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Sends every request and returns the result of each, in the order they were pushed. An
+    /// `OairsError` is only returned if the limits are invalid.
+    pub async fn run(&self) -> Result<RequestSetResults, OairsError> {
+        if let Some(adaptive) = &self.adaptive {
+            adaptive.validate()?;
+        } else if self.max_concurrency == 0 {
+            return Err(OairsError::new(
+                "max_concurrency must be greater than 0".to_string(),
+                ErrorType::ParamError,
+                Some("max_concurrency".to_string()),
+                None,
+            ));
+        }
+        let pacer = match self.requests_per_minute {
+            Some(0) => {
+                return Err(OairsError::new(
+                    "requests_per_minute must be greater than 0".to_string(),
+                    ErrorType::ParamError,
+                    Some("requests_per_minute".to_string()),
+                    None,
+                ))
+            }
+            Some(rpm) => Some(Pacer::new(Duration::from_secs(60) / rpm)),
+            None => None,
+        };
+
+        let throttle = self.adaptive.map(Throttle::new);
+        let buffer = throttle
+            .as_ref()
+            .map_or(self.max_concurrency, Throttle::max);
+
+        let results = stream::iter(self.requests.iter())
+            .map(|request| async {
+                let mut permit = match &throttle {
+                    Some(throttle) => Some(throttle.acquire().await),
+                    None => None,
+                };
+                if let Some(pacer) = &pacer {
+                    pacer.wait().await;
+                }
+                if let Some(permit) = permit.as_mut() {
+                    permit.mark_sent();
+                }
+                let result = request.send().await;
+                if let Some(permit) = permit {
+                    permit.record(&result);
+                }
+                result
+            })
+            .buffered(buffer)
+            .collect()
+            .await;
+
+        Ok(RequestSetResults { results })
+    }
+}
+
+// How much weight the latest request has in the rolling latency and rate limit averages.
+const SMOOTHING: f64 = 0.1;
+// The concurrency only rises while the rolling share of rate limited requests is below this.
+const RATE_LIMIT_THRESHOLD: f64 = 0.02;
+// How quickly the latency baseline follows a lasting rise in latency, e.g. from longer responses.
+const BASELINE_DRIFT: f64 = 0.01;
+
+/// Settings for adjusting the number of requests in flight while a [`RequestSet`] (see
+/// [`RequestSet::adaptive_concurrency`]) or [`completions::batch_adaptive`] runs, to send as
+/// many as possible at once without being rate limited.
+///
+/// The concurrency starts at [`initial_concurrency`](Self::initial_concurrency) and rises by
+/// about one for every round of requests that succeed. It's multiplied by
+/// [`decrease_factor`](Self::decrease_factor) when a request is rate limited, or when the rolling
+/// average latency rises above [`latency_tolerance`](Self::latency_tolerance) times the lowest
+/// seen, and doesn't rise again until rate limits have become rare. It only falls once for the
+/// requests that were already in flight when it last fell, so a burst of rate limits from the
+/// same round doesn't send it straight to the minimum.
+///
+/// # Example
+/// ```rust,no_run
+/// let mut adaptive = AdaptiveConcurrency::new();
+/// adaptive
+///     .initial_concurrency(2)
+///     .max_concurrency(50)
+///     .latency_tolerance(2.5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveConcurrency {
+    min: usize,
+    max: usize,
+    initial: usize,
+    decrease_factor: f64,
+    latency_tolerance: f64,
+}
+
+impl Default for AdaptiveConcurrency {
+    fn default() -> Self {
+        AdaptiveConcurrency::new()
+    }
+}
+
+impl AdaptiveConcurrency {
+    pub fn new() -> AdaptiveConcurrency {
+        AdaptiveConcurrency {
+            min: 1,
+            max: 64,
+            initial: 4,
+            decrease_factor: 0.5,
+            latency_tolerance: 3.0,
+        }
+    }
+
+    /// The fewest requests kept in flight, however many are rate limited. Defaults to 1.
+    pub fn min_concurrency(&mut self, min: usize) -> &mut Self {
+        self.min = min;
+        self
+    }
+
+    /// The most requests in flight at once. Defaults to 64.
+    pub fn max_concurrency(&mut self, max: usize) -> &mut Self {
+        self.max = max;
+        self
+    }
+
+    /// The number of requests in flight to start with. Defaults to 4, and is kept between the
+    /// minimum and maximum.
+    pub fn initial_concurrency(&mut self, initial: usize) -> &mut Self {
+        self.initial = initial;
+        self
+    }
+
+    /// What the concurrency is multiplied by when it backs off. Must be greater than 0 and less
+    /// than 1. Defaults to 0.5.
+    pub fn decrease_factor(&mut self, decrease_factor: f64) -> &mut Self {
+        self.decrease_factor = decrease_factor;
+        self
+    }
+
+    /// How many times the lowest rolling average latency the latency can rise to before the
+    /// concurrency backs off. Must be at least 1. Defaults to 3.
+    pub fn latency_tolerance(&mut self, latency_tolerance: f64) -> &mut Self {
+        self.latency_tolerance = latency_tolerance;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), OairsError> {
+        let invalid = |message: &str, param: &str| {
+            Err(OairsError::new(
+                message.to_string(),
+                ErrorType::ParamError,
+                Some(param.to_string()),
+                None,
+            ))
+        };
+        if self.min == 0 {
+            return invalid("min_concurrency must be greater than 0", "min_concurrency");
+        }
+        if self.max < self.min {
+            return invalid(
+                "max_concurrency must be at least min_concurrency",
+                "max_concurrency",
+            );
+        }
+        if !(self.decrease_factor > 0.0 && self.decrease_factor < 1.0) {
+            return invalid("decrease_factor must be between 0 and 1", "decrease_factor");
+        }
+        if self.latency_tolerance.is_nan() || self.latency_tolerance < 1.0 {
+            return invalid("latency_tolerance must be at least 1", "latency_tolerance");
+        }
+        Ok(())
+    }
+}
+
+// Limits the requests in flight to a concurrency adjusted by `AdaptiveConcurrency`.
+pub(crate) struct Throttle {
+    config: AdaptiveConcurrency,
+    state: Mutex<ThrottleState>,
+    released: Notify,
+}
+
+struct ThrottleState {
+    limit: f64,
+    in_flight: usize,
+    // Rolling averages, in seconds and as a share of the requests.
+    latency: Option<f64>,
+    baseline: Option<f64>,
+    rate_limited: f64,
+    last_decrease: Option<Instant>,
+}
+
+enum Outcome {
+    Succeeded,
+    RateLimited,
+    Failed,
+}
+
+impl Throttle {
+    pub(crate) fn new(config: AdaptiveConcurrency) -> Throttle {
+        Throttle {
+            config,
+            state: Mutex::new(ThrottleState {
+                limit: config.initial.clamp(config.min, config.max) as f64,
+                in_flight: 0,
+                latency: None,
+                baseline: None,
+                rate_limited: 0.0,
+                last_decrease: None,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    pub(crate) fn max(&self) -> usize {
+        self.config.max
+    }
+
+    #[cfg(test)]
+    fn limit(&self) -> usize {
+        self.state.lock().limit as usize
+    }
+
+    // Waits until fewer requests than the current limit are in flight.
+    pub(crate) async fn acquire(&self) -> Permit<'_> {
+        loop {
+            // Registered before checking, so a release in between isn't missed.
+            let mut released = pin!(self.released.notified());
+            released.as_mut().enable();
+            {
+                let mut state = self.state.lock();
+                if state.in_flight < state.limit as usize {
+                    state.in_flight += 1;
+                    return Permit {
+                        throttle: self,
+                        started: Instant::now(),
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+}
+
+impl ThrottleState {
+    fn record(&mut self, config: &AdaptiveConcurrency, started: Instant, outcome: Outcome) {
+        let now = Instant::now();
+        let rate_limited = matches!(outcome, Outcome::RateLimited) as u8;
+        self.rate_limited += SMOOTHING * (f64::from(rate_limited) - self.rate_limited);
+
+        let congested = match outcome {
+            Outcome::RateLimited => true,
+            Outcome::Failed => return,
+            Outcome::Succeeded => {
+                let secs = now.duration_since(started).as_secs_f64();
+                let latency = self.latency.map_or(secs, |l| l + SMOOTHING * (secs - l));
+                let baseline = self
+                    .baseline
+                    .map_or(latency, |b| latency.min(b + BASELINE_DRIFT * (latency - b)));
+                self.latency = Some(latency);
+                self.baseline = Some(baseline);
+                latency > baseline * config.latency_tolerance
+            }
+        };
+
+        if congested {
+            if self.last_decrease.is_none_or(|last| started > last) {
+                self.limit = (self.limit * config.decrease_factor).max(config.min as f64);
+                self.last_decrease = Some(now);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(limit = self.limit as usize, "decreased concurrency");
+            }
+        } else if self.rate_limited < RATE_LIMIT_THRESHOLD {
+            self.limit = (self.limit + 1.0 / self.limit).min(config.max as f64);
+        }
+    }
+}
+
+// A request in flight. Dropping it makes room for another.
+pub(crate) struct Permit<'a> {
+    throttle: &'a Throttle,
+    started: Instant,
+}
+
+impl Permit<'_> {
+    // Restarts the latency measurement, e.g. after waiting to be paced.
+    pub(crate) fn mark_sent(&mut self) {
+        self.started = Instant::now();
+    }
+
+    // Adjusts the limit for how the request went.
+    pub(crate) fn record<T>(self, result: &Result<T, OairsError>) {
+        let outcome = match result {
+            Ok(_) => Outcome::Succeeded,
+            Err(e) if e.is_rate_limit() => Outcome::RateLimited,
+            Err(_) => Outcome::Failed,
+        };
+        let config = &self.throttle.config;
+        self.throttle
+            .state
+            .lock()
+            .record(config, self.started, outcome);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.throttle.state.lock().in_flight -= 1;
+        self.throttle.released.notify_waiters();
+    }
+}
+
+// Spaces the start of the requests `interval` apart.
+struct Pacer {
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl Pacer {
+    fn new(interval: Duration) -> Pacer {
+        Pacer {
+            interval,
+            next: Mutex::new(None),
+        }
+    }
+
+    async fn wait(&self) {
+        let start = {
+            let mut next = self.next.lock();
+            let now = Instant::now();
+            let start = next.map_or(now, |next| next.max(now));
+            *next = Some(start + self.interval);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+}
+
+/// The result of each request sent by a [`RequestSet`], in the order they were pushed.
+#[derive(Debug)]
+pub struct RequestSetResults {
+    results: Vec<Result<RequestOutput, OairsError>>,
+}
+
+impl RequestSetResults {
+    pub fn results(&self) -> &[Result<RequestOutput, OairsError>] {
+        &self.results
+    }
+
+    pub fn into_results(self) -> Vec<Result<RequestOutput, OairsError>> {
+        self.results
+    }
+
+    /// The response to the request at `index` (as returned by [`RequestSet::push`]), if it
+    /// succeeded.
+    pub fn get(&self, index: usize) -> Option<&RequestOutput> {
+        self.results.get(index)?.as_ref().ok()
+    }
+
+    /// The responses of the requests that succeeded, with their index.
+    pub fn outputs(&self) -> impl Iterator<Item = (usize, &RequestOutput)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().ok().map(|o| (i, o)))
+    }
+
+    /// The errors of the requests that failed, with their index.
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &OairsError)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().err().map(|e| (i, e)))
+    }
+
+    pub fn all_succeeded(&self) -> bool {
+        self.results.iter().all(|r| r.is_ok())
+    }
+
+    /// The [`Usage`] of all the requests that succeeded, summed. `completion_tokens` is the sum
+    /// of the requests that report it (embeddings don't), or `None` if none do.
+    pub fn usage(&self) -> Usage {
+        let mut usage = Usage {
+            prompt_tokens: 0,
+            completion_tokens: None,
+            total_tokens: 0,
+        };
+        for u in self.outputs().filter_map(|(_, output)| output.usage()) {
+            usage.prompt_tokens += u.prompt_tokens;
+            usage.total_tokens += u.total_tokens;
+            if let Some(tokens) = u.completion_tokens {
+                usage.completion_tokens = Some(usage.completion_tokens.unwrap_or(0) + tokens);
+            }
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pacer_spaces_requests() {
+        let pacer = Pacer::new(Duration::from_millis(50));
+        let start = Instant::now();
+        for _ in 0..3 {
+            pacer.wait().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn adaptive_concurrency() {
+        let mut config = AdaptiveConcurrency::new();
+        config.initial_concurrency(2).max_concurrency(3);
+        let throttle = Throttle::new(config);
+        let ok: Result<(), OairsError> = Ok(());
+        let rate_limited: Result<(), OairsError> = Err(OairsError::new(
+            "Rate limited".to_string(),
+            ErrorType::RateLimit,
+            None,
+            None,
+        ));
+
+        // Rises by about one per round of successes, up to the maximum.
+        for _ in 0..10 {
+            let mut permit = throttle.acquire().await;
+            permit.started -= Duration::from_millis(100);
+            permit.record(&ok);
+        }
+        assert_eq!(throttle.limit(), 3);
+
+        // Requests in flight at once only back off once.
+        let permits = [throttle.acquire().await, throttle.acquire().await];
+        for permit in permits {
+            permit.record(&rate_limited);
+        }
+        assert_eq!(throttle.limit(), 1);
+        let permit = throttle.acquire().await;
+        assert!(futures::poll!(pin!(throttle.acquire())).is_pending());
+
+        // Doesn't rise again until rate limits are rare.
+        permit.record(&ok);
+        assert_eq!(throttle.limit(), 1);
+
+        // Backs off when the latency rises well above the lowest seen.
+        let throttle = Throttle::new(config);
+        for secs in [1, 1, 30] {
+            let mut permit = throttle.acquire().await;
+            permit.mark_sent();
+            permit.started -= Duration::from_secs(secs);
+            permit.record(&ok);
+        }
+        assert_eq!(throttle.limit(), 1);
+
+        assert!(config.decrease_factor(1.0).validate().is_err());
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        client::HttpMethod,
+        completions::{Messages, Msg},
+        test_util::{fixtures::RATE_LIMIT_ERROR, MockApi},
+    };
+
+    #[tokio::test]
+    async fn request_set() {
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock_error(HttpMethod::Post, "/moderations", 429, RATE_LIMIT_ERROR)
+            .await;
+
+        let mut set = crate::request_set::RequestSet::new();
+        let messages = Messages::new(vec![Msg::user("Hi")]);
+        let chat = set.push(client.chat_completion(crate::ChatModel::default(), &messages));
+        let moderation = set.push(client.create_moderation("Hi"));
+        let embedding =
+            set.push(client.create_embeddings(crate::EmbeddingModel::default(), &["Hi"]));
+
+        let results = set
+            .max_concurrency(2)
+            .requests_per_minute(6000)
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(results.results().len(), 3);
+        assert_eq!(
+            results.get(chat).and_then(|r| r.as_chat()).unwrap().text(),
+            Some("This is a test response.")
+        );
+        assert!(results.get(embedding).unwrap().as_embedding().is_some());
+        let errors: Vec<usize> = results.errors().map(|(i, _)| i).collect();
+        assert_eq!(errors, vec![moderation]);
+        let usage = results.usage();
+        assert_eq!((usage.total_tokens, usage.completion_tokens), (28, Some(7)));
+
+        assert!(set.max_concurrency(0).run().await.is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Contains [`RetryPolicy`], for retrying requests that fail with a transient error without
+//! paying for the same request twice.
+//!
+//! A request is only retried automatically if sending it again can't change or cost anything
+//! that the first attempt didn't (see [`SendRequest::is_idempotent`]): `GET` and `DELETE`
+//! requests, and embedding and moderation requests, which return the same result for the same
+//! content. A completion, chat, edit, image, or fine-tune request that timed out or lost its
+//! connection may still have been processed and billed, so it's only retried with
+//! [`retry_unsafe`](RetryPolicy::retry_unsafe). A rate limited request is always retried, since
+//! the API rejects it before processing it, unless the 429 is for an exhausted quota
+//! ([`ApiErrorCode::InsufficientQuota`]), which no amount of waiting fixes.
+
+use std::{future::Future, time::Duration};
+
+use crate::client::SendRequest;
+
+use super::*;
+
+/// How many times, and how long to wait before, retrying a request that failed with a transient
+/// error (see [`OairsError::is_transient`]). The wait doubles with each retry, starting at
+/// [`base_delay`](Self::base_delay), unless the error says how long to wait (see
+/// [`OairsError::retry_after`]).
+///
+/// # Example
+/// ```rust,no_run
+/// let mut policy = RetryPolicy::new();
+/// policy.max_retries(5);
+///
+/// // Retried on a timeout, since listing models is a `GET`.
+/// let models = policy.send(&client.list_models()).await?;
+///
+/// // Only retried if rate limited, unless the policy allows unsafe retries.
+/// let chat = client.chat_completion(model, &messages);
+/// let res = policy.retry_unsafe(true).send(&chat).await?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    retry_unsafe: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new()
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            retry_unsafe: false,
+        }
+    }
+
+    /// The most times a request is retried. Defaults to 3.
+    pub fn max_retries(&mut self, max_retries: u32) -> &mut Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// How long to wait before the first retry. Defaults to 500ms.
+    pub fn base_delay(&mut self, base_delay: Duration) -> &mut Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// The longest to wait before a retry. Defaults to 30 seconds.
+    pub fn max_delay(&mut self, max_delay: Duration) -> &mut Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Also retry requests that aren't idempotent, e.g. completions, after a timeout or a lost
+    /// connection. The failed attempt may have been processed, so this risks paying for (and,
+    /// for e.g. a fine-tune, creating) the same request twice. Defaults to `false`.
+    pub fn retry_unsafe(&mut self, retry_unsafe: bool) -> &mut Self {
+        self.retry_unsafe = retry_unsafe;
+        self
+    }
+
+    /// How long to wait before retrying a request that failed with `error`, after `attempt`
+    /// retries, or `None` if it shouldn't be retried.
+    pub fn retry_delay(
+        &self,
+        error: &OairsError,
+        attempt: u32,
+        idempotent: bool,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries || !error.is_transient() {
+            return None;
+        }
+        // Exhausting the quota is also a 429, but it won't succeed however long we wait.
+        if error.error_code == Some(ApiErrorCode::InsufficientQuota) {
+            return None;
+        }
+        if !(idempotent || self.retry_unsafe || error.is_rate_limit()) {
+            return None;
+        }
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        Some(error.retry_after().unwrap_or(backoff).min(self.max_delay))
+    }
+
+    /// Calls `request` until it succeeds, fails with an error that shouldn't be retried, or runs
+    /// out of retries, returning the last result. `idempotent` is whether the request can be
+    /// sent again safely, e.g. `true` for `builder.send_parsed()` on an embedding.
+    pub async fn run<T, F, Fut>(&self, idempotent: bool, mut request: F) -> Result<T, OairsError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, OairsError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Err(e) => match self.retry_delay(&e, attempt, idempotent) {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(e),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends `request`, retrying it as the policy allows for its
+    /// [`is_idempotent`](SendRequest::is_idempotent).
+    pub async fn send<R: SendRequest>(&self, request: &R) -> Result<reqwest::Response, OairsError> {
+        self.run(request.is_idempotent(), || request.send()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay() {
+        let error = |error_type| OairsError::new(String::new(), error_type, None, None);
+        let timeout = error(ErrorType::Timeout);
+        let rate_limit = error(ErrorType::RateLimit);
+        let mut policy = RetryPolicy::new();
+
+        assert_eq!(
+            policy.retry_delay(&timeout, 2, true),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(policy.retry_delay(&timeout, 3, true), None);
+        assert_eq!(policy.retry_delay(&timeout, 0, false), None);
+        assert!(policy
+            .retry_delay(&error(ErrorType::ParamError), 0, true)
+            .is_none());
+
+        let rate_limit = rate_limit.with_retry_after(Some(Duration::from_secs(7)));
+        assert_eq!(
+            policy.retry_delay(&rate_limit, 0, false),
+            Some(Duration::from_secs(7))
+        );
+
+        let mut quota = error(ErrorType::RateLimit);
+        quota.status = Some(429);
+        quota.error_code = Some(ApiErrorCode::InsufficientQuota);
+        assert!(quota.is_transient());
+        assert_eq!(policy.retry_delay(&quota, 0, true), None);
+
+        policy.retry_unsafe(true);
+        assert!(policy.retry_delay(&timeout, 0, false).is_some());
+    }
+}
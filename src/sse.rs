@@ -0,0 +1,181 @@
+//! Re-emits a stream as server-sent events, e.g. to proxy a streamed chat completion from a web
+//! backend to a browser's `EventSource`.
+//!
+//! [`encode`] turns a stream of items into the bytes of an SSE response body: each item as a
+//! `data:` event of its JSON, then `data: [DONE]` (as the API ends its own streams), with a
+//! `: keep-alive` comment whenever no item has arrived for the heartbeat interval, so proxies
+//! and load balancers don't close an idle connection. An error ends the stream with an `error`
+//! event of the API's error body, `{"error": {"message": ..., "type": ..., ...}}`. The stream of
+//! bytes can be returned from an axum or actix-web handler as it is, with a
+//! `Content-Type: text/event-stream` header. For a chat completion,
+//! [`ChatBuilder::send_sse`](crate::completions::ChatBuilder::send_sse) does it all.
+//!
+//! # Example
+//! ```rust,no_run
+//! use axum::{body::Body, http::header, response::IntoResponse};
+//!
+//! async fn chat(State(client): State<Client<Keyed>>, Json(messages): Json<Messages>) -> impl IntoResponse {
+//!     let events = client
+//!         .chat_completion(ChatModel::Gpt4, &messages)
+//!         .send_sse(Duration::from_secs(15));
+//!     let body = Body::from_stream(events.map(Ok::<_, std::convert::Infallible>));
+//!     ([(header::CONTENT_TYPE, oairs::sse::CONTENT_TYPE)], body)
+//! }
+//! ```
+
+use std::{pin::Pin, time::Duration};
+
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+
+use super::*;
+
+/// The `Content-Type` of a server-sent events response.
+pub const CONTENT_TYPE: &str = "text/event-stream";
+
+/// The server-sent events of `items`, as described in the [module docs](self). A heartbeat is
+/// sent after every `heartbeat` without an item, or never if `heartbeat` is zero.
+///
+/// Must be polled inside a Tokio runtime, for the heartbeat's timer.
+pub fn encode<S, T>(items: S, heartbeat: Duration) -> impl Stream<Item = Bytes> + Send
+where
+    S: Stream<Item = Result<T, OairsError>> + Send + 'static,
+    T: Serialize,
+{
+    let items: Pin<Box<dyn Stream<Item = Result<T, OairsError>> + Send>> = Box::pin(items);
+    stream::unfold(Some(items), move |items| async move {
+        let mut items = items?;
+        let next = match heartbeat.is_zero() {
+            true => items.next().await,
+            false => match tokio::time::timeout(heartbeat, items.next()).await {
+                Ok(next) => next,
+                Err(_) => return Some((heartbeat_event(), Some(items))),
+            },
+        };
+        match next {
+            Some(Ok(item)) => match serde_json::to_string(&item) {
+                Ok(json) => Some((data_event(&json), Some(items))),
+                Err(e) => {
+                    let error = OairsError::new(
+                        format!("Unable to serialize server-sent event: {e}"),
+                        ErrorType::SerializationError,
+                        None,
+                        None,
+                    );
+                    Some((error_event(&error), None))
+                }
+            },
+            Some(Err(e)) => Some((error_event(&e), None)),
+            None => Some((data_event("[DONE]"), None)),
+        }
+    })
+}
+
+// A `data:` event. `data` is one line, as JSON serialized by `serde_json::to_string` is.
+fn data_event(data: &str) -> Bytes {
+    Bytes::from(format!("data: {data}\n\n"))
+}
+
+// A comment, which `EventSource` ignores.
+fn heartbeat_event() -> Bytes {
+    Bytes::from_static(b": keep-alive\n\n")
+}
+
+fn error_event(error: &OairsError) -> Bytes {
+    let json = serde_json::json!({ "error": error });
+    Bytes::from(format!("event: error\ndata: {json}\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect<S: Stream<Item = Bytes>>(events: S) -> String {
+        let events: Vec<Bytes> = events.collect().await;
+        events
+            .iter()
+            .map(|event| String::from_utf8_lossy(event).into_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn encode_events() {
+        let items = stream::iter(vec![Ok("Hello"), Ok(",\nworld")]);
+        let events = collect(encode(items, Duration::ZERO)).await;
+        assert_eq!(
+            events,
+            "data: \"Hello\"\n\ndata: \",\\nworld\"\n\ndata: [DONE]\n\n"
+        );
+
+        let error = OairsError::new("Rate limited".to_string(), ErrorType::RateLimit, None, None);
+        let items = stream::iter(vec![Ok("Hi"), Err(error), Ok("unreachable")]);
+        let events = collect(encode(items, Duration::ZERO)).await;
+        assert!(events.starts_with("data: \"Hi\"\n\nevent: error\ndata: {\"error\":{"));
+        assert!(events.contains("\"message\":\"Rate limited\""));
+        assert!(!events.contains("unreachable") && !events.contains("[DONE]"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heartbeat() {
+        let heartbeat = Duration::from_secs(15);
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let slow = stream::once(async { receiver.await.unwrap() });
+        let mut events = Box::pin(encode(slow, heartbeat));
+
+        for _ in 0..2 {
+            assert!(futures::poll!(events.next()).is_pending());
+            tokio::time::advance(heartbeat).await;
+            assert_eq!(events.next().await.unwrap(), ": keep-alive\n\n");
+        }
+        sender.send(Ok::<_, OairsError>("Hi")).unwrap();
+        assert_eq!(collect(events).await, "data: \"Hi\"\n\ndata: [DONE]\n\n");
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod mock_tests {
+    use crate::{
+        client::HttpMethod,
+        completions::{Messages, Msg},
+        test_util::{
+            fixtures::{CHAT_COMPLETION_STREAM, RATE_LIMIT_ERROR},
+            MockApi,
+        },
+    };
+
+    #[tokio::test]
+    async fn stream_sse() {
+        use futures::StreamExt;
+
+        async fn collect(builder: &crate::completions::ChatBuilder<crate::Sendable>) -> String {
+            let events: Vec<bytes::Bytes> = builder
+                .send_sse(std::time::Duration::from_secs(15))
+                .collect()
+                .await;
+            events
+                .iter()
+                .map(|event| String::from_utf8_lossy(event).into_owned())
+                .collect()
+        }
+
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock_stream("/chat/completions", CHAT_COMPLETION_STREAM)
+            .await;
+        let messages = Messages::new(vec![Msg::user("Test")]);
+        let builder = client.chat_completion(crate::ChatModel::default(), &messages);
+        assert_eq!(
+            collect(&builder).await,
+            "data: \"This is a test\"\n\ndata: \" response.\"\n\ndata: [DONE]\n\n"
+        );
+
+        let api = MockApi::start().await;
+        let client = api.client();
+        api.mock_error(HttpMethod::Post, "/chat/completions", 429, RATE_LIMIT_ERROR)
+            .await;
+        let builder = client.chat_completion(crate::ChatModel::default(), &messages);
+        let events = collect(&builder).await;
+        assert!(events.starts_with("event: error\ndata: {\"error\":{"));
+        assert!(!events.contains("[DONE]"));
+    }
+}
@@ -0,0 +1,310 @@
+//! Canned JSON bodies for a successful response from each endpoint, in the shape returned by
+//! the OpenAI API. Each one deserializes into the response struct named in its doc comment.
+
+/// [`ChatCompletion`](crate::completions::response::ChatCompletion)
+pub const CHAT_COMPLETION: &str = r#"{
+  "id": "chatcmpl-6wXGpT2yCg6m4R9cJkFkM1y5Vh0aN",
+  "object": "chat.completion",
+  "created": 1679423103,
+  "model": "gpt-3.5-turbo-0301",
+  "usage": { "prompt_tokens": 13, "completion_tokens": 7, "total_tokens": 20 },
+  "choices": [
+    {
+      "message": { "role": "assistant", "content": "This is a test response." },
+      "finish_reason": "stop",
+      "index": 0
+    }
+  ]
+}"#;
+
+/// The body of a streamed [`ChatCompletion`](crate::completions::response::ChatCompletion), as
+/// server-sent events.
+pub const CHAT_COMPLETION_STREAM: &str = concat!(
+    r#"data: {"id":"chatcmpl-6wXGpT2yCg6m4R9cJkFkM1y5Vh0aN","object":"chat.completion.chunk","created":1679423103,"model":"gpt-3.5-turbo-0301","choices":[{"delta":{"role":"assistant"},"index":0,"finish_reason":null}]}"#,
+    "\n\n",
+    r#"data: {"id":"chatcmpl-6wXGpT2yCg6m4R9cJkFkM1y5Vh0aN","object":"chat.completion.chunk","created":1679423103,"model":"gpt-3.5-turbo-0301","choices":[{"delta":{"content":"This is a test"},"index":0,"finish_reason":null}]}"#,
+    "\n\n",
+    r#"data: {"id":"chatcmpl-6wXGpT2yCg6m4R9cJkFkM1y5Vh0aN","object":"chat.completion.chunk","created":1679423103,"model":"gpt-3.5-turbo-0301","choices":[{"delta":{"content":" response."},"index":0,"finish_reason":null}]}"#,
+    "\n\n",
+    r#"data: {"id":"chatcmpl-6wXGpT2yCg6m4R9cJkFkM1y5Vh0aN","object":"chat.completion.chunk","created":1679423103,"model":"gpt-3.5-turbo-0301","choices":[{"delta":{},"index":0,"finish_reason":"stop"}]}"#,
+    "\n\n",
+    "data: [DONE]\n\n",
+);
+
+/// A streamed [`Completion`](crate::completions::response::Completion), as the
+/// `text/event-stream` body of the response.
+pub const COMPLETION_STREAM: &str = concat!(
+    r#"data: {"id":"cmpl-6wXIY9Yq7xJ8ZbTtSgWQ1kU3sZ2fG","object":"text_completion","created":1679423210,"model":"text-davinci-003","choices":[{"text":"\n\nThis is","index":0,"logprobs":null,"finish_reason":null}]}"#,
+    "\n\n",
+    r#"data: {"id":"cmpl-6wXIY9Yq7xJ8ZbTtSgWQ1kU3sZ2fG","object":"text_completion","created":1679423210,"model":"text-davinci-003","choices":[{"text":" indeed a test.","index":0,"logprobs":null,"finish_reason":"stop"}]}"#,
+    "\n\n",
+    "data: [DONE]\n\n",
+);
+
+/// [`Completion`](crate::completions::response::Completion)
+pub const COMPLETION: &str = r#"{
+  "id": "cmpl-6wXIY9Yq7xJ8ZbTtSgWQ1kU3sZ2fG",
+  "object": "text_completion",
+  "created": 1679423210,
+  "model": "text-davinci-003",
+  "choices": [
+    {
+      "text": "\n\nThis is indeed a test.",
+      "index": 0,
+      "logprobs": null,
+      "finish_reason": "stop"
+    }
+  ],
+  "usage": { "prompt_tokens": 5, "completion_tokens": 8, "total_tokens": 13 }
+}"#;
+
+/// [`Edit`](crate::edits::response::Edit)
+pub const EDIT: &str = r#"{
+  "object": "edit",
+  "created": 1679423300,
+  "choices": [{ "text": "What day of the week is it?", "index": 0 }],
+  "usage": { "prompt_tokens": 25, "completion_tokens": 32, "total_tokens": 57 }
+}"#;
+
+/// [`Embedding`](crate::embeddings::response::Embedding)
+pub const EMBEDDING: &str = r#"{
+  "object": "list",
+  "data": [
+    {
+      "object": "embedding",
+      "embedding": [0.0023064255, -0.009327292, 0.015797347, -0.0077780345],
+      "index": 0
+    }
+  ],
+  "model": "text-embedding-ada-002-v2",
+  "usage": { "prompt_tokens": 8, "total_tokens": 8 }
+}"#;
+
+/// [`FileInfo`](crate::files::response::FileInfo)
+pub const FILE_INFO: &str = r#"{
+  "object": "file",
+  "id": "file-XjGxS3KTG0uNmNOK362iJua3",
+  "purpose": "fine-tune",
+  "filename": "training_data.jsonl",
+  "bytes": 128,
+  "created_at": 1679423400,
+  "status": "processed",
+  "status_details": null
+}"#;
+
+/// [`FileList`](crate::files::response::FileList)
+pub const FILE_LIST: &str = r#"{
+  "object": "list",
+  "data": [
+    {
+      "object": "file",
+      "id": "file-XjGxS3KTG0uNmNOK362iJua3",
+      "purpose": "fine-tune",
+      "filename": "training_data.jsonl",
+      "bytes": 128,
+      "created_at": 1679423400,
+      "status": "processed",
+      "status_details": null
+    }
+  ]
+}"#;
+
+/// The content of the file in [`FILE_INFO`], which can be parsed into a
+/// [`FineTuneFC`](crate::files::response::FineTuneFC).
+pub const FILE_CONTENT: &str = concat!(
+    r#"{"prompt": "What is the capital of France? ->", "completion": " Paris\n"}"#,
+    "\n",
+    r#"{"prompt": "What is 2 + 2? ->", "completion": " 4\n"}"#,
+    "\n",
+);
+
+/// [`DeleteResponse`](crate::files::response::DeleteResponse)
+pub const DELETE_RESPONSE: &str = r#"{
+  "id": "file-XjGxS3KTG0uNmNOK362iJua3",
+  "object": "file",
+  "deleted": true
+}"#;
+
+/// [`FineTuneInfo`](crate::fine_tunes::response::FineTuneInfo)
+pub const FINE_TUNE_INFO: &str = r#"{
+  "object": "fine-tune",
+  "id": "ft-AF1WoRqd3aJAHsqc9NY7iL8F",
+  "model": "curie",
+  "created_at": 1679423500,
+  "events": [
+    {
+      "object": "fine-tune-event",
+      "created_at": 1679423500,
+      "level": "info",
+      "message": "Job enqueued. Waiting for jobs ahead to complete. Queue number: 0."
+    }
+  ],
+  "fine_tuned_model": null,
+  "hyperparams": {
+    "batch_size": 4,
+    "learning_rate_multiplier": 0.1,
+    "n_epochs": 4,
+    "prompt_loss_weight": 0.1
+  },
+  "organization_id": "org-123",
+  "result_files": [],
+  "status": "pending",
+  "validation_files": [],
+  "training_files": [
+    {
+      "object": "file",
+      "id": "file-XjGxS3KTG0uNmNOK362iJua3",
+      "purpose": "fine-tune",
+      "filename": "training_data.jsonl",
+      "bytes": 128,
+      "created_at": 1679423400,
+      "status": "processed",
+      "status_details": null
+    }
+  ],
+  "updated_at": 1679423500
+}"#;
+
+/// [`FineTunesList`](crate::fine_tunes::response::FineTunesList)
+pub const FINE_TUNES_LIST: &str = r#"{
+  "object": "list",
+  "data": [
+    {
+      "object": "fine-tune",
+      "id": "ft-AF1WoRqd3aJAHsqc9NY7iL8F",
+      "model": "curie",
+      "created_at": 1679423500,
+      "fine_tuned_model": null,
+      "hyperparams": {
+        "batch_size": 4,
+        "learning_rate_multiplier": 0.1,
+        "n_epochs": 4,
+        "prompt_loss_weight": 0.1
+      },
+      "organization_id": "org-123",
+      "result_files": [],
+      "status": "pending",
+      "validation_files": [],
+      "training_files": [],
+      "updated_at": 1679423500
+    }
+  ]
+}"#;
+
+/// [`EventList`](crate::fine_tunes::response::EventList)
+pub const EVENT_LIST: &str = r#"{
+  "object": "list",
+  "data": [
+    {
+      "object": "fine-tune-event",
+      "created_at": 1679423500,
+      "level": "info",
+      "message": "Job enqueued. Waiting for jobs ahead to complete. Queue number: 0."
+    },
+    {
+      "object": "fine-tune-event",
+      "created_at": 1679423600,
+      "level": "info",
+      "message": "Job started."
+    }
+  ]
+}"#;
+
+/// [`Image`](crate::images::response::Image)
+pub const IMAGE: &str = r#"{
+  "created": 1679423700,
+  "data": [
+    { "url": "https://example.com/image-1.png" },
+    { "url": "https://example.com/image-2.png" }
+  ]
+}"#;
+
+/// [`ModelObject`](crate::models::response::ModelObject)
+pub const MODEL_OBJECT: &str = r#"{
+  "id": "text-davinci-003",
+  "object": "model",
+  "created": 1669599635,
+  "owned_by": "openai-internal",
+  "permission": [
+    {
+      "id": "modelperm-jepinXYt59ncUQrjQEIUEDyC",
+      "object": "model_permission",
+      "created": 1679423800,
+      "allow_create_engine": false,
+      "allow_sampling": true,
+      "allow_logprobs": true,
+      "allow_search_indices": false,
+      "allow_view": true,
+      "allow_fine_tuning": false,
+      "organization": "*",
+      "group": null,
+      "is_blocking": false
+    }
+  ],
+  "root": "text-davinci-003",
+  "parent": null
+}"#;
+
+/// [`ModelsList`](crate::models::response::ModelsList)
+pub const MODELS_LIST: &str = r#"{
+  "object": "list",
+  "data": [
+    {
+      "id": "text-davinci-003",
+      "object": "model",
+      "created": 1669599635,
+      "owned_by": "openai-internal",
+      "permission": [],
+      "root": "text-davinci-003",
+      "parent": null
+    },
+    {
+      "id": "gpt-3.5-turbo",
+      "object": "model",
+      "created": 1677610602,
+      "owned_by": "openai",
+      "permission": [],
+      "root": "gpt-3.5-turbo",
+      "parent": null
+    }
+  ]
+}"#;
+
+/// [`ModerationResult`](crate::moderations::moderations_response::ModerationResult)
+pub const MODERATION_RESULT: &str = r#"{
+  "id": "modr-6wXNYjP1QZcJ6KXLtfhcjMAm9xQ2o",
+  "model": "text-moderation-004",
+  "results": [
+    {
+      "categories": {
+        "hate": false,
+        "hate/threatening": false,
+        "self-harm": false,
+        "sexual": false,
+        "sexual/minors": false,
+        "violence": false,
+        "violence/graphic": false
+      },
+      "category_scores": {
+        "hate": 0.0000193,
+        "hate/threatening": 1.2e-9,
+        "self-harm": 3.1e-9,
+        "sexual": 0.0000126,
+        "sexual/minors": 2.5e-8,
+        "violence": 0.0000063,
+        "violence/graphic": 4.7e-8
+      },
+      "flagged": false
+    }
+  ]
+}"#;
+
+/// The body of an error response, as returned with a `429` status code.
+pub const RATE_LIMIT_ERROR: &str = r#"{
+  "error": {
+    "message": "Rate limit reached for default-gpt-3.5-turbo in organization org-123 on requests per min.",
+    "type": "requests",
+    "param": null,
+    "code": null
+  }
+}"#;
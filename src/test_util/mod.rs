@@ -0,0 +1,256 @@
+//! Helpers for writing integration tests against a local mock of the OpenAI API, so they don't
+//! need an API key or network access. Only available with the `test-util` feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! oairs = { version = "0.1", features = ["test-util"] }
+//! ```
+//!
+//! [`MockApi::start`] spins up a [`wiremock`] server that answers every endpoint with the
+//! matching body from [`fixtures`], and [`MockApi::client`] returns a [`Client`] whose base url
+//! points at it.
+//!
+//! # Example
+//! ```rust,no_run
+//! use oairs::test_util::MockApi;
+//!
+//! #[tokio::test]
+//! async fn summarizes() {
+//!     let api = MockApi::start().await;
+//!     let client = api.client();
+//!
+//!     let completion = client
+//!         .chat_completion(ChatModel::default(), &messages)
+//!         .send()
+//!         .await
+//!         .unwrap()
+//!         .json::<ChatCompletion>()
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(api.requests("/chat/completions").await.len(), 1);
+//! }
+//! ```
+
+pub mod fixtures;
+
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+
+use crate::{
+    client::{Client, HttpMethod},
+    Keyed,
+};
+
+pub use wiremock;
+
+/// The key used by the [`Client`] returned from [`MockApi::client`].
+pub const TEST_API_KEY: &str = "sk-test";
+
+// The default mocks are mounted with a lower priority (a higher number) than wiremock's default
+// of 5, so a mock mounted by a test takes precedence.
+const FIXTURE_PRIORITY: u8 = 10;
+
+/// A local mock of the OpenAI API. Every endpoint answers with the matching body from
+/// [`fixtures`] until a test mounts its own response with [`mock`](MockApi::mock),
+/// [`mock_error`](MockApi::mock_error), or directly on the [`server`](MockApi::server).
+pub struct MockApi {
+    server: MockServer,
+}
+
+impl std::fmt::Debug for MockApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockApi")
+            .field("base_url", &self.base_url())
+            .finish()
+    }
+}
+
+impl MockApi {
+    /// Starts the server on a random local port and mounts the default responses.
+    pub async fn start() -> MockApi {
+        let api = MockApi {
+            server: MockServer::start().await,
+        };
+        for (http_method, route, body) in default_routes() {
+            let mock = match route.contains("[^/]+") {
+                true => Mock::given(method(method_str(http_method))).and(path_regex(route)),
+                false => Mock::given(method(method_str(http_method))).and(path(route)),
+            };
+            mock.respond_with(json_response(200, body))
+                .with_priority(FIXTURE_PRIORITY)
+                .mount(&api.server)
+                .await;
+        }
+        api
+    }
+
+    /// The base url to pass to [`Client::with_base_url`], e.g. `http://127.0.0.1:40123/v1`.
+    pub fn base_url(&self) -> String {
+        format!("{}/v1", self.server.uri())
+    }
+
+    /// A client that sends its requests to the mock server.
+    pub fn client(&self) -> Client<Keyed> {
+        Client::new(TEST_API_KEY).with_base_url(self.base_url())
+    }
+
+    /// The underlying [`MockServer`], for mounting mocks with any of [`wiremock`]'s matchers.
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Answer requests to `endpoint` (relative to the base url, e.g. `/chat/completions`) with
+    /// `body` and a `200` status code, instead of the default fixture.
+    pub async fn mock(&self, http_method: HttpMethod, endpoint: &str, body: &str) {
+        Mock::given(method(method_str(http_method)))
+            .and(path(format!("/v1{endpoint}")))
+            .respond_with(json_response(200, body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer `POST` requests to `endpoint` with `body` as a `text/event-stream`, e.g.
+    /// [`fixtures::CHAT_COMPLETION_STREAM`], to test streamed responses.
+    pub async fn mock_stream(&self, endpoint: &str, body: &str) {
+        Mock::given(method("POST"))
+            .and(path(format!("/v1{endpoint}")))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(body.as_bytes(), "text/event-stream"),
+            )
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer requests to `endpoint` with an error, e.g. a `429` status code and
+    /// [`fixtures::RATE_LIMIT_ERROR`], to test how errors are handled.
+    pub async fn mock_error(
+        &self,
+        http_method: HttpMethod,
+        endpoint: &str,
+        status: u16,
+        body: &str,
+    ) {
+        Mock::given(method(method_str(http_method)))
+            .and(path(format!("/v1{endpoint}")))
+            .respond_with(json_response(status, body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// The requests received for `endpoint` (relative to the base url), in the order they
+    /// arrived.
+    pub async fn requests(&self, endpoint: &str) -> Vec<Request> {
+        let endpoint = format!("/v1{endpoint}");
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|request| request.url.path() == endpoint)
+            .collect()
+    }
+}
+
+fn method_str(http_method: HttpMethod) -> &'static str {
+    match http_method {
+        HttpMethod::Get => "GET",
+        HttpMethod::Post => "POST",
+        HttpMethod::Delete => "DELETE",
+    }
+}
+
+fn json_response(status: u16, body: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_raw(body.as_bytes(), "application/json")
+}
+
+// (method, path or path regex, body) for every endpoint of the `Client`.
+fn default_routes() -> Vec<(HttpMethod, &'static str, &'static str)> {
+    use fixtures::*;
+    use HttpMethod::*;
+
+    vec![
+        (Post, "/v1/chat/completions", CHAT_COMPLETION),
+        (Post, "/v1/completions", COMPLETION),
+        (Post, "/v1/edits", EDIT),
+        (Post, "/v1/embeddings", EMBEDDING),
+        (Get, "/v1/files", FILE_LIST),
+        (Post, "/v1/files", FILE_INFO),
+        (Get, "^/v1/files/[^/]+/content$", FILE_CONTENT),
+        (Get, "^/v1/files/[^/]+$", FILE_INFO),
+        (Delete, "^/v1/files/[^/]+$", DELETE_RESPONSE),
+        (Get, "/v1/fine-tunes", FINE_TUNES_LIST),
+        (Post, "/v1/fine-tunes", FINE_TUNE_INFO),
+        (Get, "^/v1/fine-tunes/[^/]+/events$", EVENT_LIST),
+        (Post, "^/v1/fine-tunes/[^/]+/cancel$", FINE_TUNE_INFO),
+        (Get, "^/v1/fine-tunes/[^/]+$", FINE_TUNE_INFO),
+        (Post, "/v1/images/generations", IMAGE),
+        (Post, "/v1/images/edits", IMAGE),
+        (Post, "/v1/images/variations", IMAGE),
+        (Get, "/v1/models", MODELS_LIST),
+        (Get, "^/v1/models/[^/]+$", MODEL_OBJECT),
+        (Delete, "^/v1/models/[^/]+$", DELETE_RESPONSE),
+        (Post, "/v1/moderations", MODERATION_RESULT),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixtures::*, *};
+    use crate::{
+        completions::response::{ChatCompletion, Completion},
+        edits::response::Edit,
+        embeddings::response::Embedding,
+        files::response::{DeleteResponse, FileInfo, FileList, FineTuneFC},
+        fine_tunes::response::{EventList, FineTuneInfo, FineTunesList},
+        images::response::Image,
+        models::response::{ModelObject, ModelsList},
+        moderations::moderations_response::ModerationResult,
+        LoadJson,
+    };
+
+    #[test]
+    fn fixtures_deserialize() {
+        ChatCompletion::from_json_str(CHAT_COMPLETION).unwrap();
+        Completion::from_json_str(COMPLETION).unwrap();
+        Edit::from_json_str(EDIT).unwrap();
+        Embedding::from_json_str(EMBEDDING).unwrap();
+        FileInfo::from_json_str(FILE_INFO).unwrap();
+        FileList::from_json_str(FILE_LIST).unwrap();
+        DeleteResponse::from_json_str(DELETE_RESPONSE).unwrap();
+        FineTuneInfo::from_json_str(FINE_TUNE_INFO).unwrap();
+        FineTunesList::from_json_str(FINE_TUNES_LIST).unwrap();
+        EventList::from_json_str(EVENT_LIST).unwrap();
+        Image::from_json_str(IMAGE).unwrap();
+        ModelObject::from_json_str(MODEL_OBJECT).unwrap();
+        ModelsList::from_json_str(MODELS_LIST).unwrap();
+        ModerationResult::from_json_str(MODERATION_RESULT).unwrap();
+        assert_eq!(
+            FineTuneFC::from_string(FILE_CONTENT.to_string()).data.len(),
+            2
+        );
+
+        let info = FileInfo::from_json_str(FILE_INFO).unwrap();
+        assert_eq!(info.bytes, FILE_CONTENT.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn mock_api() {
+        let api = MockApi::start().await;
+        let client = api.client();
+
+        let models = client.list_models().send().await.unwrap();
+        models.json::<ModelsList>().await.unwrap();
+        let info = client.retrieve_file("file-abc").send().await.unwrap();
+        info.json::<FileInfo>().await.unwrap();
+
+        api.mock_error(HttpMethod::Get, "/models", 429, RATE_LIMIT_ERROR)
+            .await;
+        let error = client.list_models().send().await.unwrap_err();
+
+        assert!(error.is_rate_limit());
+        assert_eq!(error.status, Some(429));
+        assert_eq!(api.requests("/models").await.len(), 2);
+    }
+}
@@ -0,0 +1,184 @@
+//! Token-aware splitting of long documents into chunks, e.g. for
+//! [`create_embeddings`](crate::client::Client::create_embeddings) or a
+//! [`RagPipeline`](crate::rag::RagPipeline). Chunks are counted in the tokens of a [`Tokenizer`]
+//! rather than characters, so they can be sized to the model's limit.
+//!
+//! * [`by_tokens`] cuts fixed windows of tokens, each overlapping the last, so that a passage
+//!   split between two chunks is whole in at least one of them.
+//! * [`by_sentences`] packs whole sentences into each chunk, splitting only sentences that are
+//!   too long on their own.
+//!
+//! # Example
+//! ```rust,no_run
+//! let chunks = chunk::by_sentences(&document, Tokenizer::CL100KBase, 500)?;
+//! let embedding = client
+//!     .create_embeddings(EmbeddingModel::TextEmbeddingAda002, &chunks)
+//!     .send_parsed()
+//!     .await?;
+//! ```
+
+use crate::tokenizers::{token_offsets, tokenize, Tokenizer};
+
+use super::*;
+
+/// Splits `text` into windows of `max_tokens` tokens, each starting `overlap` tokens before the
+/// end of the last. The last window may be shorter. Where a character spans two tokens, a
+/// window boundary moves back to the start of the character, so a window can be a token
+/// longer.
+///
+/// Returns a `ParamError` if `max_tokens` is 0 or `overlap` isn't less than `max_tokens`.
+pub fn by_tokens(
+    text: &str,
+    tokenizer: Tokenizer,
+    max_tokens: usize,
+    overlap: usize,
+) -> Result<Vec<String>, OairsError> {
+    if max_tokens == 0 {
+        return Err(param_error(
+            "max_tokens must be greater than 0",
+            "max_tokens",
+        ));
+    }
+    if overlap >= max_tokens {
+        return Err(param_error(
+            "overlap must be less than max_tokens",
+            "overlap",
+        ));
+    }
+
+    let offsets = token_offsets(text, tokenizer);
+    let tokens = offsets.len() - 1;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < tokens {
+        let end = (start + max_tokens).min(tokens);
+        let from = char_boundary(text, offsets[start]);
+        let to = char_boundary(text, offsets[end]);
+        if from < to {
+            chunks.push(text[from..to].to_string());
+        }
+        if end == tokens {
+            break;
+        }
+        start = end - overlap;
+    }
+    Ok(chunks)
+}
+
+/// Splits `text` into chunks of whole sentences, each of at most `max_tokens` tokens. A
+/// sentence ends at a `.`, `!`, or `?` followed by whitespace, or at a blank line. A sentence
+/// longer than `max_tokens` on its own is split with [`by_tokens`], without overlap. The chunks
+/// are trimmed of surrounding whitespace.
+///
+/// Returns a `ParamError` if `max_tokens` is 0.
+pub fn by_sentences(
+    text: &str,
+    tokenizer: Tokenizer,
+    max_tokens: usize,
+) -> Result<Vec<String>, OairsError> {
+    if max_tokens == 0 {
+        return Err(param_error(
+            "max_tokens must be greater than 0",
+            "max_tokens",
+        ));
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+    for sentence in sentences(text) {
+        let tokens = tokenize(sentence, tokenizer)?.len();
+        if current_tokens + tokens > max_tokens && !current.trim().is_empty() {
+            chunks.push(current.trim().to_string());
+            current.clear();
+            current_tokens = 0;
+        }
+        if tokens > max_tokens {
+            chunks.extend(
+                by_tokens(sentence.trim(), tokenizer, max_tokens, 0)?
+                    .into_iter()
+                    .map(|chunk| chunk.trim().to_string()),
+            );
+            continue;
+        }
+        current.push_str(sentence);
+        current_tokens += tokens;
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current.trim().to_string());
+    }
+    Ok(chunks)
+}
+
+/// Splits `text` into sentences, each keeping the whitespace that follows it, so that the
+/// sentences joined are `text`.
+pub fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c);
+        let ends = match c {
+            '.' | '!' | '?' => next.is_none_or(char::is_whitespace),
+            '\n' => next == Some('\n'),
+            _ => false,
+        };
+        if !ends {
+            continue;
+        }
+        // Keep the whitespace after the sentence with it.
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, w)) = chars.peek() {
+            if !w.is_whitespace() {
+                break;
+            }
+            end = j + w.len_utf8();
+            chars.next();
+        }
+        sentences.push(&text[start..end]);
+        start = end;
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+    sentences
+}
+
+// The nearest character boundary at or before `offset`.
+fn char_boundary(text: &str, mut offset: usize) -> usize {
+    offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_text() {
+        let text = "The cat sat. The dog ran! Did it rain?\n\nYes";
+        assert_eq!(
+            sentences(text),
+            ["The cat sat. ", "The dog ran! ", "Did it rain?\n\n", "Yes"]
+        );
+
+        let tokenizer = Tokenizer::CL100KBase;
+        let chunks = by_sentences(text, tokenizer, 10).unwrap();
+        assert_eq!(chunks, ["The cat sat. The dog ran!", "Did it rain?\n\nYes"]);
+        for chunk in &chunks {
+            assert!(tokenize(chunk, tokenizer).unwrap().len() <= 10);
+        }
+
+        let chunks = by_tokens("one two three four five", tokenizer, 3, 1).unwrap();
+        assert_eq!(chunks, ["one two three", " three four five"]);
+        // A character of several tokens isn't split.
+        let chunks = by_tokens("🦀🦀", tokenizer, 1, 0).unwrap();
+        assert_eq!(chunks, ["🦀", "🦀"]);
+
+        assert!(by_tokens(text, tokenizer, 3, 3).is_err());
+        assert!(by_sentences(text, tokenizer, 0).is_err());
+    }
+}
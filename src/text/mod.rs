@@ -0,0 +1,18 @@
+//! Utilities for preparing text to send to the API, e.g. [`chunk`] for splitting long documents
+//! into pieces that fit an embedding model, and [`redact`] for keeping personal information out
+//! of requests.
+
+pub mod chunk;
+pub mod redact;
+
+use super::*;
+
+/// A `ParamError` for an invalid `param`, for the checks of [`chunk`] and [`redact`].
+pub(super) fn param_error(message: &str, param: &str) -> OairsError {
+    OairsError::new(
+        message.to_string(),
+        ErrorType::ParamError,
+        Some(param.to_string()),
+        None,
+    )
+}
@@ -0,0 +1,324 @@
+//! Redaction of personal information from text before it's sent to the API, e.g. emails,
+//! phone numbers, and credit card numbers. A [`Redactor`] replaces what its [`Detector`]s find
+//! with placeholders, e.g. `[EMAIL_1]`, and keeps a [`RedactionMap`] of what each placeholder
+//! stands for, so a response that repeats the placeholders can be re-hydrated.
+//!
+//! A redactor can be set on a [`ChatBuilder`](crate::completions::ChatBuilder),
+//! [`CompletionBuilder`](crate::completions::CompletionBuilder) or
+//! [`EmbeddingBuilder`](crate::embeddings::EmbeddingBuilder) with `redact_with`, to
+//! redact the messages, prompts or inputs of every request the builder sends.
+//!
+//! # Example
+//! ```rust,no_run
+//! use oairs::text::redact::Redactor;
+//!
+//! let redactor = Redactor::pii();
+//! let messages = Messages::new(vec![Msg::user(
+//!     "Write a reply to jane@example.com, whose number is (555) 123-4567.",
+//! )]);
+//! let completion = client
+//!     .chat_completion(ChatModel::default(), &messages)
+//!     .redact_with(&redactor)
+//!     .send_parsed()
+//!     .await?;
+//! // The API only saw `[EMAIL_1]` and `[PHONE_1]`.
+//! let reply = redactor.rehydrate(completion.choices[0].message.content());
+//! ```
+
+use std::sync::{Arc, OnceLock};
+
+use fancy_regex::Regex;
+use parking_lot::Mutex;
+
+use super::*;
+
+const EMAIL: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}";
+// An optional country code, an area code (in parentheses or not), and a 7 digit number.
+const PHONE: &str =
+    r"(?<![\w+])(?:\+\d{1,3}[ .-]?)?(?:\(\d{3}\)|\d{3})[ .-]?\d{3}[ .-]?\d{4}(?![\w])";
+// 13 to 19 digits, optionally grouped by spaces or dashes. Checked with the Luhn algorithm.
+const CREDIT_CARD: &str = r"(?<![\d-])\d(?:[ -]?\d){12,18}(?![\d])";
+
+/// Finds one kind of information to redact. Matches are replaced with `[{LABEL}_{n}]`, where
+/// `n` counts the distinct matches with the label.
+#[derive(Debug, Clone)]
+pub enum Detector {
+    /// Email addresses. Labeled `EMAIL`.
+    Email,
+    /// Phone numbers in the North American format, e.g. `(555) 123-4567` or
+    /// `+1 555.123.4567`. Labeled `PHONE`.
+    Phone,
+    /// Credit card numbers: 13 to 19 digits, optionally grouped by spaces or dashes, that pass
+    /// the Luhn check. Labeled `CREDIT_CARD`.
+    CreditCard,
+    /// Matches of a regular expression, labeled `label`.
+    Pattern { label: String, regex: Regex },
+}
+
+impl Detector {
+    /// A detector of the matches of `pattern`, in the syntax of the `fancy_regex` crate (which
+    /// supports look-around). Returns a `ParamError` if `pattern` isn't a valid regular
+    /// expression or `label` is empty.
+    pub fn pattern<L: Into<String>>(label: L, pattern: &str) -> Result<Detector, OairsError> {
+        let label = label.into();
+        if label.is_empty() {
+            return Err(param_error("the label must not be empty", "label"));
+        }
+        match Regex::new(pattern) {
+            Ok(regex) => Ok(Detector::Pattern { label, regex }),
+            Err(e) => Err(param_error(
+                &format!("invalid pattern {pattern:?}: {e}"),
+                "pattern",
+            )),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Detector::Email => "EMAIL",
+            Detector::Phone => "PHONE",
+            Detector::CreditCard => "CREDIT_CARD",
+            Detector::Pattern { label, .. } => label,
+        }
+    }
+
+    // The byte ranges of the matches in `text`.
+    fn find(&self, text: &str) -> Vec<std::ops::Range<usize>> {
+        let regex = match self {
+            Detector::Email => builtin(&EMAIL_REGEX, EMAIL),
+            Detector::Phone => builtin(&PHONE_REGEX, PHONE),
+            Detector::CreditCard => builtin(&CREDIT_CARD_REGEX, CREDIT_CARD),
+            Detector::Pattern { regex, .. } => regex,
+        };
+        regex
+            .find_iter(text)
+            .filter_map(|m| m.ok())
+            .filter(|m| !matches!(self, Detector::CreditCard) || luhn(m.as_str()))
+            .map(|m| m.start()..m.end())
+            .collect()
+    }
+}
+
+static EMAIL_REGEX: OnceLock<Regex> = OnceLock::new();
+static PHONE_REGEX: OnceLock<Regex> = OnceLock::new();
+static CREDIT_CARD_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn builtin(regex: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    regex.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+// Whether the digits of `number` pass the Luhn check that credit card numbers are made to pass.
+fn luhn(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            0 => d,
+            _ if d * 2 > 9 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// The placeholders a [`Redactor`] has put in place of redacted text, and the text each stands
+/// for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionMap {
+    // Placeholders and the text they replaced, in the order they were made.
+    entries: Vec<(String, String)>,
+}
+
+impl RedactionMap {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The text that `placeholder` replaced, if it's one of the map's placeholders.
+    pub fn get(&self, placeholder: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(p, _)| p == placeholder)
+            .map(|(_, original)| original.as_str())
+    }
+
+    /// The placeholders and the text each replaced, in the order they were made.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(p, o)| (p.as_str(), o.as_str()))
+    }
+
+    /// `text` with each of the map's placeholders replaced by the text it stands for.
+    pub fn rehydrate(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (placeholder, original) in &self.entries {
+            if text.contains(placeholder.as_str()) {
+                text = text.replace(placeholder.as_str(), original);
+            }
+        }
+        text
+    }
+
+    // The placeholder for `original`: the one it already has, or a new one numbered after the
+    // others with the label.
+    fn placeholder(&mut self, label: &str, original: &str) -> String {
+        if let Some((placeholder, _)) = self.entries.iter().find(|(_, o)| o == original) {
+            return placeholder.clone();
+        }
+        let prefix = format!("[{label}_");
+        let n = self
+            .entries
+            .iter()
+            .filter_map(|(p, _)| p.strip_prefix(&prefix)?.strip_suffix(']'))
+            .filter(|n| n.bytes().all(|b| b.is_ascii_digit()))
+            .count();
+        let placeholder = format!("{prefix}{}]", n + 1);
+        self.entries
+            .push((placeholder.clone(), original.to_string()));
+        placeholder
+    }
+}
+
+/// Replaces what its [`Detector`]s find with placeholders, recording them in its
+/// [`RedactionMap`]. The same text gets the same placeholder every time, so a conversation
+/// redacted message by message stays consistent.
+///
+/// Clones share the map, so a redactor set on a builder with `redact_with` records the
+/// placeholders of the requests the builder sends in the map of the original.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    detectors: Vec<Detector>,
+    map: Arc<Mutex<RedactionMap>>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Redactor::pii()
+    }
+}
+
+impl Redactor {
+    /// A redactor with no detectors. Add them with [`detect`](Self::detect).
+    pub fn new() -> Redactor {
+        Redactor {
+            detectors: Vec::new(),
+            map: Arc::new(Mutex::new(RedactionMap::default())),
+        }
+    }
+
+    /// A redactor of emails, phone numbers, and credit card numbers.
+    pub fn pii() -> Redactor {
+        let mut redactor = Redactor::new();
+        redactor
+            .detect(Detector::Email)
+            .detect(Detector::CreditCard)
+            .detect(Detector::Phone);
+        redactor
+    }
+
+    /// Adds `detector`. Where the matches of two detectors overlap, the one that starts first,
+    /// or else the longer one, or else the one added first, is redacted.
+    pub fn detect(&mut self, detector: Detector) -> &mut Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// `text` with what the detectors find replaced by placeholders, which are added to the
+    /// [`mapping`](Self::mapping).
+    pub fn redact(&self, text: &str) -> String {
+        let mut matches: Vec<(std::ops::Range<usize>, &str)> = self
+            .detectors
+            .iter()
+            .flat_map(|d| {
+                d.find(text)
+                    .into_iter()
+                    .map(move |range| (range, d.label()))
+            })
+            .collect();
+        // Stable, so detectors added first win ties.
+        matches.sort_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+
+        let mut map = self.map.lock();
+        let mut redacted = String::with_capacity(text.len());
+        let mut end = 0;
+        for (range, label) in matches {
+            if range.start < end {
+                continue;
+            }
+            redacted.push_str(&text[end..range.start]);
+            redacted.push_str(&map.placeholder(label, &text[range.clone()]));
+            end = range.end;
+        }
+        redacted.push_str(&text[end..]);
+        redacted
+    }
+
+    /// `text` with the placeholders the redactor has made replaced by the text they stand for,
+    /// e.g. to restore a response to a redacted request.
+    pub fn rehydrate(&self, text: &str) -> String {
+        self.map.lock().rehydrate(text)
+    }
+
+    /// A copy of the placeholders the redactor has made so far.
+    pub fn mapping(&self) -> RedactionMap {
+        self.map.lock().clone()
+    }
+
+    // Redacts `value` if it's a string, or the strings in it if it's an array, e.g. a prompt or
+    // the inputs of an embedding request. Anything else, e.g. token ids, is left as is.
+    pub(crate) fn redact_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = self.redact(text),
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|v| self.redact_json(v)),
+            _ => {}
+        }
+    }
+}
+
+/// Redacts emails, phone numbers, and credit card numbers in `text` with a new
+/// [`Redactor::pii`], returning the redacted text and the map to re-hydrate it with.
+pub fn redact(text: &str) -> (String, RedactionMap) {
+    let redactor = Redactor::pii();
+    let redacted = redactor.redact(text);
+    (redacted, redactor.mapping())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_and_rehydrate() {
+        let text =
+            "Mail jane.doe@example.co.uk or call (555) 123-4567. Card: 4111 1111 1111 1111, \
+                    not 4111 1111 1111 1112. Mail jane.doe@example.co.uk again.";
+        let (redacted, map) = redact(text);
+        assert_eq!(
+            redacted,
+            "Mail [EMAIL_1] or call [PHONE_1]. Card: [CREDIT_CARD_1], \
+             not 4111 1111 1111 1112. Mail [EMAIL_1] again."
+        );
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("[PHONE_1]"), Some("(555) 123-4567"));
+        assert_eq!(map.rehydrate(&redacted), text);
+
+        let mut redactor = Redactor::new();
+        redactor.detect(Detector::pattern("ACCOUNT", r"\bACC-\d{6}\b").unwrap());
+        assert_eq!(
+            redactor.redact("ACC-123456 and ACC-654321"),
+            "[ACCOUNT_1] and [ACCOUNT_2]"
+        );
+        let shared = redactor.clone();
+        assert_eq!(shared.redact("ACC-654321"), "[ACCOUNT_2]");
+        assert_eq!(
+            redactor.rehydrate("Closed [ACCOUNT_1]."),
+            "Closed ACC-123456."
+        );
+        assert!(Detector::pattern("BAD", "(").is_err());
+    }
+}
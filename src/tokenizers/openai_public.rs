@@ -7,7 +7,8 @@
 // https://openaipublic.blob.core.windows.net/encodings/cl100k_base.tiktoken
 //
 // These are the sources for the files in ./assets dir, which were originally
-// downloaded by zurawiki version (I believe).
+// downloaded by zurawiki version (I believe). The `.tiktoken` files are gzipped
+// (`gzip -9 -n`) to cut the size of the binary.
 
 pub const ENDOFTEXT: &str = "<|endoftext|>";
 pub const FIM_PREFIX: &str = "<|fim_prefix|>";
@@ -16,17 +17,32 @@ pub const FIM_SUFFIX: &str = "<|fim_suffix|>";
 pub const ENDOFPROMPT: &str = "<|endofprompt|>";
 
 /// Adaptation of the tiktoken crate for use in Rust projects
+use std::io::Read;
+
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine as _};
+use flate2::read::GzDecoder;
 
 use rustc_hash::FxHashMap as HashMap;
 
 use crate::tokenizers::vendor_tiktoken::CoreBPE;
 
+// JREMB: statics so that p50k_base and p50k_edit share one copy of the file.
+static R50K_BASE: &[u8] = include_bytes!("assets/r50k_base.tiktoken.gz");
+static P50K_BASE: &[u8] = include_bytes!("assets/p50k_base.tiktoken.gz");
+static CL100K_BASE: &[u8] = include_bytes!("assets/cl100k_base.tiktoken.gz");
+
+// JREMB: decompresses one of the gzipped files in ./assets.
+fn decompress(gz: &[u8]) -> Result<String> {
+    let mut bpe_file = String::new();
+    GzDecoder::new(gz).read_to_string(&mut bpe_file)?;
+    Ok(bpe_file)
+}
+
 /// Use for GPT-3 models like `davinci`
 /// Initializes and returns a new instance of the r50k_base tokenizer (also known as `gpt2`)
 pub fn r50k_base() -> Result<CoreBPE> {
-    let bpe_file = include_str!("assets/r50k_base.tiktoken");
+    let bpe_file = decompress(R50K_BASE)?;
 
     let mut encoder = HashMap::default();
     for line in bpe_file.lines() {
@@ -50,7 +66,7 @@ pub fn r50k_base() -> Result<CoreBPE> {
 /// Use for Code models, `text-davinci-002`, `text-davinci-003`
 /// Initializes and returns a new instance of the p50k_base tokenizer.
 pub fn p50k_base() -> Result<CoreBPE> {
-    let bpe_file = include_str!("assets/p50k_base.tiktoken");
+    let bpe_file = decompress(P50K_BASE)?;
 
     let mut encoder = HashMap::default();
     for line in bpe_file.lines() {
@@ -75,7 +91,7 @@ pub fn p50k_base() -> Result<CoreBPE> {
 /// Use for edit models like `text-davinci-edit-001`, `code-davinci-edit-001`
 /// Initializes and returns a new instance of the p50k_base tokenizer.
 pub fn p50k_edit() -> Result<CoreBPE> {
-    let bpe_file = include_str!("assets/p50k_base.tiktoken");
+    let bpe_file = decompress(P50K_BASE)?;
 
     let mut encoder = HashMap::default();
     for line in bpe_file.lines() {
@@ -103,7 +119,7 @@ pub fn p50k_edit() -> Result<CoreBPE> {
 /// Use for ChatGPT models, `text-embedding-ada-002`
 /// Initializes and returns a new instance of the cl100k_base tokenizer.
 pub fn cl100k_base() -> Result<CoreBPE> {
-    let cl100k_base = include_str!("assets/cl100k_base.tiktoken");
+    let cl100k_base = decompress(CL100K_BASE)?;
 
     let mut encoder = HashMap::default();
     for line in cl100k_base.lines() {
@@ -1,4 +1,8 @@
-use super::*;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use super::{singletons::*, *};
 
 // The following enums and struct provide an abstraction over the various
 // encoders/decoders and special tokens that, to my mind, make them easier to
@@ -56,6 +60,38 @@ impl Tokenizer {
             _ => SpecialTokens::from_vec(vec![SpecialToken::EndOfText]),
         }
     }
+
+    /// The number of tokens in the vocabulary, including the recognized special tokens.
+    pub fn vocab_size(&self) -> Result<usize, OairsError> {
+        Ok(load_bpe(*self)?.vocab_size())
+    }
+
+    /// The bytes of a single token. Returns an error if `token` isn't in the vocabulary.
+    ///
+    /// Not every token is valid UTF-8 on its own (e.g., part of a multi-byte character), so
+    /// the bytes are returned as is. See also [`detokenize_bytes`].
+    pub fn token_bytes(&self, token: usize) -> Result<Vec<u8>, OairsError> {
+        detokenize_bytes(&[token], *self)
+    }
+
+    /// Every token whose text contains `substring`, as `(token, text)` pairs sorted by token.
+    /// Useful for finding the tokens to bias (see also
+    /// [`LogitBiasBuilder`](crate::completions::LogitBiasBuilder)), since the same word is
+    /// usually several tokens (e.g., `"Hello"`, `" hello"`, and `"hello"` are all different).
+    ///
+    /// Tokens that aren't valid UTF-8 on their own are decoded lossily, i.e., with `\u{FFFD}`
+    /// in place of the invalid bytes.
+    pub fn search_tokens(&self, substring: &str) -> Result<Vec<(usize, String)>, OairsError> {
+        let bpe = load_bpe(*self)?;
+        let mut tokens: Vec<(usize, String)> = bpe
+            .tokens()
+            .map(|(token, bytes)| (token, String::from_utf8_lossy(bytes)))
+            .filter(|(_, text)| text.contains(substring))
+            .map(|(token, text)| (token, text.into_owned()))
+            .collect();
+        tokens.sort_unstable_by_key(|(token, _)| *token);
+        Ok(tokens)
+    }
 }
 
 /// Representations of special tokens for the encoders.
@@ -205,16 +241,39 @@ pub fn tokenize(text: &str, tokenizer: Tokenizer) -> Result<Vec<usize>, OairsErr
     }
 }
 
-pub fn tokenize_batch(
-    texts: Vec<&str>,
-    tokenizer: Tokenizer,
-) -> Result<Vec<Vec<usize>>, OairsError> {
-    match load_bpe(tokenizer) {
-        Ok(bpe) => {
-            let tokens = bpe.encode_ordinary_batch(texts);
-            Ok(tokens)
-        }
-        Err(e) => Err(e),
+/// Tokenizes each of `texts` as [`tokenize`] would, e.g. for checking the lengths of many
+/// embedding inputs at once. With the `parallel` feature (enabled by default), the texts are
+/// tokenized in parallel with `rayon`.
+///
+/// Unlike the other functions of this module, it uses the shared instance of the tokenizer
+/// from [`singletons`] rather than loading a new one, so repeated calls don't pay to load it
+/// again. See `benches/tokenize.rs` for a comparison with calling [`tokenize`] for each text.
+pub fn tokenize_batch(texts: &[&str], tokenizer: Tokenizer) -> Vec<Vec<usize>> {
+    let bpe = bpe_singleton(tokenizer);
+    let bpe = bpe.lock();
+    bpe.encode_ordinary_batch(texts)
+}
+
+/// The byte offset in `text` where each of its tokens starts, followed by the length of
+/// `text`, so the text of token `i` is `text[offsets[i]..offsets[i + 1]]` (unless a character
+/// spans tokens, in which case the offset isn't on a character boundary).
+pub(crate) fn token_offsets(text: &str, tokenizer: Tokenizer) -> Vec<usize> {
+    let bpe = bpe_singleton(tokenizer);
+    let bpe = bpe.lock();
+    let mut offsets = vec![0];
+    for token in bpe.encode_ordinary(text) {
+        let len = bpe.decode_single_token_bytes(token).map_or(0, |b| b.len());
+        offsets.push(offsets[offsets.len() - 1] + len);
+    }
+    offsets
+}
+
+fn bpe_singleton(tokenizer: Tokenizer) -> Arc<Mutex<vendor_tiktoken::CoreBPE>> {
+    match tokenizer {
+        Tokenizer::R50KBase => r50k_base_singleton(),
+        Tokenizer::P50KBase => p50k_base_singleton(),
+        Tokenizer::P50KEdit => p50k_edit_singleton(),
+        Tokenizer::CL100KBase => cl100k_base_singleton(),
     }
 }
 
@@ -269,6 +328,52 @@ pub fn tokenize_with_special(text: &str, tokenizer: Tokenizer) -> Result<Vec<usi
     }
 }
 
+/// The inverse of [`tokenize`]: decodes the token ids back into a `String`. Special tokens
+/// recognized by the tokenizer are decoded to their text (e.g., `<|endoftext|>`).
+///
+/// Returns an error if any token id isn't in the tokenizer's vocabulary or if the decoded
+/// bytes aren't valid UTF-8. A single token won't always decode to valid UTF-8 on its own
+/// (e.g., part of a multi-byte character), so use [`detokenize_bytes`] when decoding tokens
+/// one at a time, such as the per-token logprobs returned by the completions endpoint.
+pub fn detokenize(tokens: &[usize], tokenizer: Tokenizer) -> Result<String, OairsError> {
+    let bytes = detokenize_bytes(tokens, tokenizer)?;
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(e) => Err(OairsError::new(
+            format!("Unable to decode into a valid UTF-8 string: {}", e),
+            ErrorType::Tokenizer,
+            Some(tokenizer.to_str().into()),
+            None,
+        )),
+    }
+}
+
+/// Decodes the token ids into raw bytes. Returns an error if any token id isn't in the
+/// tokenizer's vocabulary.
+pub fn detokenize_bytes(tokens: &[usize], tokenizer: Tokenizer) -> Result<Vec<u8>, OairsError> {
+    let bpe = load_bpe(tokenizer)?;
+
+    let mut bytes = Vec::with_capacity(tokens.len() * 2);
+    for token in tokens {
+        match bpe.decode_single_token_bytes(*token) {
+            Some(b) => bytes.extend(b),
+            None => {
+                return Err(OairsError::new(
+                    format!(
+                        "Token {} is not in the vocabulary of {:?}.",
+                        token, tokenizer
+                    ),
+                    ErrorType::Tokenizer,
+                    Some(tokenizer.to_str().into()),
+                    None,
+                ))
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
 #[cfg(test)]
 mod tokenizer_tests {
     use crate::tokenizers::openai_public::cl100k_base;
@@ -333,7 +438,7 @@ mod tokenizer_tests {
             "hello world",
         ];
 
-        let tokens = tokenize_batch(strings, Tokenizer::CL100KBase).unwrap();
+        let tokens = tokenize_batch(&strings, Tokenizer::CL100KBase);
         let expected = vec![
             vec![
                 2028, 374, 264, 1296, 925, 311, 1518, 1268, 433, 4037, 4861, 13,
@@ -347,4 +452,36 @@ mod tokenizer_tests {
 
         assert_eq!(tokens, expected)
     }
+
+    #[test]
+    fn vocab_introspection() {
+        let tokenizer = Tokenizer::CL100KBase;
+        let hello = tokenize("hello", tokenizer).unwrap();
+        assert_eq!(hello.len(), 1);
+
+        assert_eq!(tokenizer.vocab_size().unwrap(), 100_256 + 5);
+        assert_eq!(tokenizer.token_bytes(hello[0]).unwrap(), b"hello");
+        assert!(tokenizer.token_bytes(usize::MAX).is_err());
+
+        let found = tokenizer.search_tokens("hello").unwrap();
+        assert!(found.contains(&(hello[0], "hello".to_string())));
+        assert!(found.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(found.iter().all(|(_, text)| text.contains("hello")));
+    }
+
+    #[test]
+    fn detokenize_round_trip() {
+        let s = "This is a test string to see how it tokenizes.";
+        let tokens = tokenize(s, Tokenizer::CL100KBase).unwrap();
+
+        assert_eq!(detokenize(&tokens, Tokenizer::CL100KBase).unwrap(), s);
+
+        let tokens = tokenize_with_special("hello<|endoftext|>", Tokenizer::R50KBase).unwrap();
+        assert_eq!(
+            detokenize(&tokens, Tokenizer::R50KBase).unwrap(),
+            "hello<|endoftext|>"
+        );
+
+        assert!(detokenize(&[usize::MAX], Tokenizer::CL100KBase).is_err());
+    }
 }
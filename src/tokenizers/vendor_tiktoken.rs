@@ -7,7 +7,9 @@ use std::thread;
 use anyhow::anyhow;
 use anyhow::Result;
 use fancy_regex::Regex;
-use rayon::prelude::IntoParallelIterator;
+#[cfg(feature = "parallel")]
+use rayon::prelude::IntoParallelRefIterator;
+#[cfg(feature = "parallel")]
 use rayon::prelude::ParallelIterator;
 use rustc_hash::FxHashMap as HashMap;
 
@@ -452,24 +454,25 @@ impl CoreBPE {
         self._encode_ordinary_native(text)
     }
 
-    pub fn encode_ordinary_batch(&self, texts: Vec<&str>) -> Vec<Vec<usize>> {
-        texts
-            .into_par_iter()
-            .map(|t| self._encode_ordinary_native(t))
-            .collect()
+    // jremb :: the batch methods only use `rayon` with the `parallel` feature
+    pub fn encode_ordinary_batch(&self, texts: &[&str]) -> Vec<Vec<usize>> {
+        #[cfg(feature = "parallel")]
+        let texts = texts.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let texts = texts.iter();
+        texts.map(|t| self._encode_ordinary_native(t)).collect()
     }
 
     pub fn encode(&self, text: &str, allowed_special: HashSet<&str>) -> Vec<usize> {
         self._encode_native(text, &allowed_special).0
     }
 
-    pub fn encode_batch(
-        &self,
-        texts: Vec<&str>,
-        allowed_special: HashSet<&str>,
-    ) -> Vec<Vec<usize>> {
+    pub fn encode_batch(&self, texts: &[&str], allowed_special: HashSet<&str>) -> Vec<Vec<usize>> {
+        #[cfg(feature = "parallel")]
+        let texts = texts.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let texts = texts.iter();
         texts
-            .into_par_iter()
             .map(|t| self._encode_native(t, &allowed_special).0)
             .collect()
     }
@@ -603,6 +606,19 @@ impl CoreBPE {
     pub fn token_byte_values(&self) -> Vec<Vec<u8>> {
         self.sorted_token_bytes.to_vec()
     }
+
+    // jremb :: the following are used by the vocabulary methods of `Tokenizer`
+    pub fn vocab_size(&self) -> usize {
+        self.decoder.len() + self.special_tokens_decoder.len()
+    }
+
+    /// Every token, ordinary and special, with its bytes, in no particular order.
+    pub fn tokens(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        self.decoder
+            .iter()
+            .chain(self.special_tokens_decoder.iter())
+            .map(|(token, bytes)| (*token, bytes.as_slice()))
+    }
 }
 
 #[cfg(test)]
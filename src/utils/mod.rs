@@ -1,7 +1,14 @@
+#[cfg(feature = "dataframes")]
 pub mod df;
+pub mod raw;
+pub mod save;
 pub mod stream_parsers;
 
+#[cfg(feature = "dataframes")]
 pub use df::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use raw::save_raw_response;
+pub use save::*;
 pub use stream_parsers::*;
 
 use super::*;
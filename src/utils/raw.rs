@@ -0,0 +1,141 @@
+//! Contains [`save_raw_response`], for keeping the body of a response exactly as it was received,
+//! e.g. to debug a response that fails to deserialize.
+
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bytes::Bytes;
+
+use super::*;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::meta::TimingStart;
+
+/// Reads the body of `response` and writes it to `path` as is, returning the body for further
+/// parsing (e.g., with `serde_json::from_slice` or `std::str::from_utf8`). The path's parent
+/// directories are created if they don't exist.
+///
+/// # Example
+/// ```rust,no_run
+/// let response = client.list_models().send().await?;
+/// let body = save_raw_response(response, "debug/models.json").await?;
+/// let models: ModelsList = serde_json::from_slice(&body)?;
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn save_raw_response<P: AsRef<Path>>(
+    response: reqwest::Response,
+    path: P,
+) -> Result<Bytes, OairsError> {
+    let path = path.as_ref();
+    let body = match response.bytes().await {
+        Ok(body) => body,
+        Err(e) => return Err(parse_reqwest_error(e)),
+    };
+
+    let written = async {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, &body).await
+    };
+    if let Err(e) = written.await {
+        return Err(OairsError::new(
+            format!("Unable to save raw response: {e}"),
+            ErrorType::FileError,
+            Some(path.display().to_string()),
+            None,
+        ));
+    }
+
+    Ok(body)
+}
+
+/// For the `save_raw_to` option of the builders: if there's a `dir`, saves the body of
+/// `response` to a file in it named after the `endpoint`, the time, and the request id (e.g.,
+/// `chat_1679000000000_req-abc123.json`), and returns a response with the same status, headers,
+/// and body.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn tee_raw(
+    dir: Option<&Path>,
+    endpoint: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, OairsError> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => return Ok(response),
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    // The request id is only used in the filename if it's safe to use in one.
+    let filename = match response
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| {
+            id.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }) {
+        Some(id) => format!("{endpoint}_{timestamp}_{id}.json"),
+        None => format!("{endpoint}_{timestamp}.json"),
+    };
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let timing = response.extensions().get::<TimingStart>().copied();
+    let body = save_raw_response(response, dir.join(filename)).await?;
+
+    let mut rebuilt = ::http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+    if let Some(timing) = timing {
+        rebuilt.extensions_mut().insert(timing);
+    }
+    Ok(reqwest::Response::from(rebuilt))
+}
+
+// The builders' `save_raw_to` isn't available for `wasm32`, which has no file system, so there's
+// never a `dir` to save to.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn tee_raw(
+    _dir: Option<&Path>,
+    _endpoint: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, OairsError> {
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tee_raw_response() {
+        let dir = std::env::temp_dir().join(format!("oairs-raw-{}", std::process::id()));
+        let mut response = ::http::Response::new(r#"{"id": "#.to_string());
+        response
+            .headers_mut()
+            .insert("x-request-id", "req-123".parse().unwrap());
+
+        let response = tee_raw(Some(&dir), "edit", reqwest::Response::from(response))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-request-id"], "req-123");
+        assert_eq!(response.text().await.unwrap(), r#"{"id": "#);
+
+        let saved: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        let content = std::fs::read_to_string(&saved[0]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(saved.len(), 1);
+        let filename = saved[0].file_name().unwrap().to_string_lossy().to_string();
+        assert!(filename.starts_with("edit_") && filename.ends_with("_req-123.json"));
+        assert_eq!(content, r#"{"id": "#);
+    }
+}
@@ -0,0 +1,369 @@
+//! Contains the [`SaveJson`] and [`LoadJson`] traits, which are implemented for response structs
+//! by `#[derive(SaveJson)]` and `#[derive(LoadJson)]`.
+
+use std::io::{Read, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+};
+
+#[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::de::DeserializeOwned;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::AsyncWriteExt;
+
+use super::*;
+
+/// Serialize a struct as JSON, either to a file or to any writer. Files are pretty-printed by
+/// [`save_json`](SaveJson::save_json), or written compactly by
+/// [`save_json_compact`](SaveJson::save_json_compact) and, with the `gzip` feature, compressed
+/// by [`save_json_gz`](SaveJson::save_json_gz), e.g. for large embedding dumps. The methods that
+/// write to a path aren't available for `wasm32`, which has no file system.
+///
+/// All methods have default implementations, so `#[derive(SaveJson)]` only has to emit
+/// `impl SaveJson for T {}`. The trait can also be implemented by hand for any type that
+/// implements `Serialize`, or used as a bound: `fn archive<T: SaveJson>(item: &T)`.
+pub trait SaveJson: Serialize {
+    /// Save as pretty-printed JSON to `path`, a `&str`, `Path`, `PathBuf`, etc. Returns the path
+    /// the file was written to.
+    ///
+    /// If the path does not end with ".json" it will be appended. If the path includes parents
+    /// that do not exist it will try to create them; a bare filename like `"results.json"` is
+    /// written to the current directory. The JSON is first written to a temporary file in the
+    /// same directory, which is then renamed to `path`, so an existing file is never left
+    /// partially written if serialization fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, std::io::Error> {
+        let (path, tmp_path) = json_paths(path.as_ref(), ".json")?;
+        write_atomic(path, &tmp_path, |writer| self.save_json_to(writer))
+    }
+
+    /// The same as [`save_json`](SaveJson::save_json), but without the indentation and line
+    /// breaks, which can make up half of a file of numbers like embeddings.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_json_compact<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, std::io::Error> {
+        let (path, tmp_path) = json_paths(path.as_ref(), ".json")?;
+        write_atomic(path, &tmp_path, |writer| {
+            serde_json::to_writer(writer, self).map_err(|e| std::io::Error::other(e.to_string()))
+        })
+    }
+
+    /// The same as [`save_json_compact`](SaveJson::save_json_compact), but gzip-compressed, with
+    /// ".json.gz" appended to the path unless it ends with ".gz". Load it with
+    /// [`LoadJson::load_json_gz`].
+    #[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+    fn save_json_gz<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, std::io::Error> {
+        let (path, tmp_path) = json_paths(path.as_ref(), ".json.gz")?;
+        write_atomic(path, &tmp_path, |writer| {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            serde_json::to_writer(&mut encoder, self)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            encoder.finish()?;
+            Ok(())
+        })
+    }
+
+    /// The same as [`save_json`](SaveJson::save_json), but the file IO is done with `tokio::fs`
+    /// so it doesn't block the executor. The struct is serialized before the returned future is
+    /// first polled, so the future doesn't borrow `self`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_json_async<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> impl Future<Output = Result<PathBuf, std::io::Error>> + Send + 'static {
+        let prepared = json_paths(path.as_ref(), ".json").and_then(|paths| {
+            let json = serde_json::to_vec_pretty(self)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            Ok((paths, json))
+        });
+
+        async move {
+            let ((path, tmp_path), json) = prepared?;
+            create_parent(&path, tokio::fs::create_dir_all(parent_of(&path)).await)?;
+
+            let result = async {
+                let mut file = tokio::fs::File::create(&tmp_path).await?;
+                file.write_all(&json).await?;
+                file.sync_all().await?;
+                tokio::fs::rename(&tmp_path, &path).await
+            }
+            .await;
+
+            match result {
+                Ok(_) => Ok(path),
+                Err(e) => {
+                    let _ = tokio::fs::remove_file(&tmp_path).await;
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// Write pretty-printed JSON to any [`Write`]r, e.g. a `Vec<u8>`, `stdout`, or an
+    /// already open file.
+    fn save_json_to<W: Write>(&self, writer: W) -> Result<(), std::io::Error> {
+        serde_json::to_writer_pretty(writer, self).map_err(|e| std::io::Error::other(e.to_string()))
+    }
+
+    /// Serialize to a pretty-printed JSON `String`.
+    fn to_json_string_pretty(&self) -> Result<String, std::io::Error> {
+        serde_json::to_string_pretty(self).map_err(|e| std::io::Error::other(e.to_string()))
+    }
+}
+
+/// Load a struct back from JSON, e.g. a response that was saved with
+/// [`save_json`](SaveJson::save_json), so saved artifacts can be analyzed as typed structs.
+///
+/// As with [`SaveJson`], all methods have default implementations and the trait is implemented
+/// with `#[derive(LoadJson)]`.
+///
+/// # Example
+/// ```rust,no_run
+/// use oairs::{completions::response::ChatCompletion, LoadJson};
+///
+/// let completion = ChatCompletion::load_json("some/path/chat_completion.json").unwrap();
+/// ```
+pub trait LoadJson: DeserializeOwned {
+    /// Load from the JSON file at `path`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_json<P: AsRef<Path>>(path: P) -> Result<Self, OairsError> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)?;
+        let reader = std::io::BufReader::new(file);
+        Self::load_json_from(reader).map_err(|e| OairsError {
+            param: Some(path.display().to_string()),
+            ..e
+        })
+    }
+
+    /// Load from the gzip-compressed JSON file at `path`, e.g. one saved with
+    /// [`save_json_gz`](SaveJson::save_json_gz).
+    #[cfg(all(feature = "gzip", not(target_arch = "wasm32")))]
+    fn load_json_gz<P: AsRef<Path>>(path: P) -> Result<Self, OairsError> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)?;
+        let reader = std::io::BufReader::new(GzDecoder::new(file));
+        Self::load_json_from(reader).map_err(|e| OairsError {
+            param: Some(path.display().to_string()),
+            ..e
+        })
+    }
+
+    /// Load from any [`Read`]er.
+    fn load_json_from<R: Read>(reader: R) -> Result<Self, OairsError> {
+        match serde_json::from_reader(reader) {
+            Ok(item) => Ok(item),
+            Err(e) => Err(OairsError::new(
+                e.to_string(),
+                ErrorType::DeserializationError,
+                None,
+                None,
+            )),
+        }
+    }
+
+    /// Load from a JSON string.
+    fn from_json_str(s: &str) -> Result<Self, OairsError> {
+        Self::load_json_from(s.as_bytes())
+    }
+}
+
+/// Appends the part of `suffix` (e.g. ".json", or ".json.gz") the path doesn't already end
+/// with, checks the filename, and returns the final path along with the temporary path that is
+/// written to before being renamed.
+#[cfg(not(target_arch = "wasm32"))]
+fn json_paths(path: &Path, suffix: &str) -> Result<(PathBuf, PathBuf), std::io::Error> {
+    let name = path.as_os_str().to_string_lossy();
+    let present = suffix
+        .match_indices('.')
+        .map(|(i, _)| i)
+        .skip(1)
+        .chain([suffix.len()])
+        .filter(|&i| name.ends_with(&suffix[..i]))
+        .max()
+        .unwrap_or(0);
+    let mut path = path.as_os_str().to_owned();
+    path.push(&suffix[present..]);
+    let path = PathBuf::from(path);
+
+    // In my testing, `:` in the filename itself won't be caught as an error by
+    // File::create. However, (on Windows at least) a path like "te:st2.json" will create a
+    // file named `te` and return a handle to a path that doesn't exist, which won't be caught
+    // by serde_json. The user will end up with an empty file named `te` and no indication
+    // that an error has occured.
+    let filename = match path.file_name().and_then(|f| f.to_str()) {
+        Some(f) => f,
+        None => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "path does not have a valid filename",
+            ))
+        }
+    };
+    if filename.contains(':') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "filename contains invalid character: :",
+        ));
+    }
+
+    let tmp_path = parent_of(&path).join(format!(".{}.{}.tmp", filename, std::process::id()));
+    Ok((path, tmp_path))
+}
+
+/// Creates the parents of `path`, calls `write` with a writer to `tmp_path`, and renames
+/// `tmp_path` to `path`, removing it if anything fails.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_atomic<F>(path: PathBuf, tmp_path: &Path, write: F) -> Result<PathBuf, std::io::Error>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), std::io::Error>,
+{
+    create_parent(&path, std::fs::create_dir_all(parent_of(&path)))?;
+
+    let result = std::fs::File::create(tmp_path).and_then(|file| {
+        let mut writer = std::io::BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    });
+
+    match result.and_then(|_| std::fs::rename(tmp_path, &path)) {
+        Ok(_) => Ok(path),
+        Err(e) => {
+            let _ = std::fs::remove_file(tmp_path);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parent_of(path: &Path) -> &Path {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn create_parent(path: &Path, created: Result<(), std::io::Error>) -> Result<(), std::io::Error> {
+    match created {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            // By default, we get the error 'failed to create whole tree', which
+            // probably isn't very helpful to many users when the error will simply
+            // point to the function call.
+            let e = format!(
+                "Path's parent directory, {}, does not exist and could not be created. Possibly invalid characters or non-existent? {}",
+                parent_of(path).display(),
+                e
+            );
+            Err(std::io::Error::other(e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, SaveJson, LoadJson)]
+    struct Saved {
+        id: String,
+    }
+
+    #[test]
+    fn save_json_to_writer() {
+        let saved = Saved {
+            id: "abc".to_string(),
+        };
+        let mut buf = Vec::new();
+        saved.save_json_to(&mut buf).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            saved.to_json_string_pretty().unwrap()
+        );
+    }
+
+    #[test]
+    fn save_json_atomic() {
+        let dir = std::env::temp_dir().join(format!("oairs-save-{}", std::process::id()));
+        let path = dir.join("saved");
+        let saved = Saved {
+            id: "abc".to_string(),
+        };
+        let written = saved.save_json(&path).unwrap();
+        assert_eq!(written, dir.join("saved.json"));
+
+        let content = std::fs::read_to_string(dir.join("saved.json")).unwrap();
+        let entries = std::fs::read_dir(&dir).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(content, saved.to_json_string_pretty().unwrap());
+        assert_eq!(entries, 1);
+    }
+
+    #[test]
+    fn load_json_round_trip() {
+        let saved = Saved {
+            id: "abc".to_string(),
+        };
+        let json = saved.to_json_string_pretty().unwrap();
+
+        assert_eq!(Saved::from_json_str(&json).unwrap(), saved);
+        assert!(Saved::from_json_str("{}").is_err());
+    }
+
+    #[test]
+    fn json_paths_without_parent() {
+        let (path, tmp_path) = json_paths(Path::new("results"), ".json").unwrap();
+        assert_eq!(path, Path::new("results.json"));
+        assert_eq!(tmp_path.parent(), Some(Path::new(".")));
+
+        let (path, _) = json_paths(Path::new("results.json"), ".json").unwrap();
+        assert_eq!(path, Path::new("results.json"));
+        let (path, _) = json_paths(Path::new("results.json"), ".json.gz").unwrap();
+        assert_eq!(path, Path::new("results.json.gz"));
+        let (path, _) = json_paths(Path::new("results.json.gz"), ".json.gz").unwrap();
+        assert_eq!(path, Path::new("results.json.gz"));
+        assert!(json_paths(Path::new("te:st.json"), ".json").is_err());
+    }
+
+    #[test]
+    fn save_json_compact_and_gz() {
+        let dir = std::env::temp_dir().join(format!("oairs-save-compact-{}", std::process::id()));
+        let saved = Saved {
+            id: "abc".to_string(),
+        };
+        let compact = saved.save_json_compact(dir.join("compact")).unwrap();
+        let content = std::fs::read_to_string(&compact).unwrap();
+        assert_eq!(content, r#"{"id":"abc"}"#);
+        assert_eq!(Saved::load_json(&compact).unwrap(), saved);
+
+        #[cfg(feature = "gzip")]
+        {
+            let gz = saved.save_json_gz(dir.join("saved")).unwrap();
+            assert_eq!(gz, dir.join("saved.json.gz"));
+            assert_eq!(Saved::load_json_gz(&gz).unwrap(), saved);
+            assert!(Saved::load_json_gz(&compact).is_err());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn save_json_async() {
+        let dir = std::env::temp_dir().join(format!("oairs-save-async-{}", std::process::id()));
+        let path = dir.join("nested").join("saved.json");
+        let saved = Saved {
+            id: "abc".to_string(),
+        };
+        assert_eq!(saved.save_json_async(&path).await.unwrap(), path);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(content, saved.to_json_string_pretty().unwrap());
+    }
+}
@@ -5,6 +5,39 @@ use nom::{
 
 pub use chat_parsers::*;
 
+/// Removes the first complete server-sent event (terminated by a blank line) from `buffer` and
+/// returns the value of its `data` field. Lines may end with `\n`, `\r\n`, or `\r`, as the spec
+/// allows. Events without a `data` field (e.g., comments used as keep-alives) are skipped.
+/// Returns `None` if there isn't a complete event in the buffer yet.
+pub(crate) fn take_sse_data(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    loop {
+        let (end, len) = event_end(buffer)?;
+        let event: Vec<u8> = buffer.drain(..end + len).collect();
+
+        // Splitting a `\r\n` line ending leaves an empty line, which has no `data` field.
+        let data: Vec<&[u8]> = event[..end]
+            .split(|b| *b == b'\n' || *b == b'\r')
+            .filter_map(|line| line.strip_prefix(b"data:"))
+            .map(|data| data.strip_prefix(b" ").unwrap_or(data))
+            .collect();
+
+        if !data.is_empty() {
+            return Some(data.join(&b'\n'));
+        }
+    }
+}
+
+// The position of the blank line that ends the first event in `buffer`, and its length.
+fn event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    [&b"\r\n\r\n"[..], b"\n\n", b"\r\r"]
+        .iter()
+        .filter_map(|blank| {
+            let end = buffer.windows(blank.len()).position(|w| w == *blank)?;
+            Some((end, blank.len()))
+        })
+        .min_by_key(|(end, _)| *end)
+}
+
 // It may be that much of this is applicable to parsing a completion stream too. In which
 // case, will change mod layout.
 pub mod chat_parsers {
@@ -160,6 +193,34 @@ mod tests {
 
     // endregion
 
+    #[test]
+    fn test_take_sse_data() {
+        let mut buffer = some_delta().to_vec();
+        buffer.extend_from_slice(b": keep-alive\n\ndata: [DONE]\n\ndata: {\"partial\"");
+
+        let first = take_sse_data(&mut buffer).unwrap();
+        assert!(first.starts_with(b"{\"id\""));
+        assert!(first.ends_with(b"}]}"));
+        assert!(take_sse_data(&mut buffer).is_some());
+        assert_eq!(take_sse_data(&mut buffer).unwrap(), b"[DONE]");
+        assert!(take_sse_data(&mut buffer).is_none());
+        assert_eq!(buffer, b"data: {\"partial\"");
+    }
+
+    #[test]
+    fn take_sse_data_crlf() {
+        let mut buffer =
+            b"data: first\r\n\r\n: keep-alive\r\n\r\nevent: x\r\ndata: a\r\ndata: b\r\n\r\n"
+                .to_vec();
+        buffer.extend_from_slice(b"data: cr\r\rdata: [DONE]\r\n");
+
+        assert_eq!(take_sse_data(&mut buffer).unwrap(), b"first");
+        assert_eq!(take_sse_data(&mut buffer).unwrap(), b"a\nb");
+        assert_eq!(take_sse_data(&mut buffer).unwrap(), b"cr");
+        assert!(take_sse_data(&mut buffer).is_none());
+        assert_eq!(buffer, b"data: [DONE]\r\n");
+    }
+
     #[test]
     fn test_nom_chat_completion_chunk() {
         let mut slice = no_delta().as_ref();